@@ -0,0 +1,43 @@
+//! Records a `getTransaction` response as a parser test fixture.
+//!
+//! ```text
+//! SOLANA_RPC_URL=https://api.mainnet-beta.solana.com \
+//!     cargo run --example record_fixture -- <signature> <name>
+//! ```
+//!
+//! The response is written with `json` encoding and its full status meta to
+//! `tests/fixtures/transactions/<name>_<signature>.json`.
+
+use solana_rpc_client::rpc::config::{EndpointConfig, RpcConfig};
+use solana_rpc_client::SolanaRpcClient;
+use std::path::PathBuf;
+
+const DEFAULT_RPC_URL: &str = "https://api.mainnet-beta.solana.com";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let (Some(signature), Some(name)) = (args.next(), args.next()) else {
+        eprintln!("usage: record_fixture <signature> <name>");
+        std::process::exit(2);
+    };
+
+    let config = RpcConfig {
+        endpoints: vec![EndpointConfig {
+            url: std::env::var("SOLANA_RPC_URL").unwrap_or_else(|_| DEFAULT_RPC_URL.to_string()),
+            weight: 1,
+            enabled: true,
+        }],
+        ..Default::default()
+    };
+    let client = SolanaRpcClient::new(config)?;
+    let transaction = client.get_transaction(&signature).await?;
+
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "fixtures", "transactions"]
+        .iter()
+        .collect::<PathBuf>()
+        .join(format!("{}_{}.json", name, signature));
+    std::fs::write(&path, serde_json::to_string_pretty(&transaction)? + "\n")?;
+    println!("Recorded slot {} to {}", transaction.slot, path.display());
+    Ok(())
+}
@@ -0,0 +1,402 @@
+//! Historical balance reconstruction from snapshots and stored changes
+
+use crate::balances::NATIVE_MINT;
+use crate::core::error::{Error, Result};
+use crate::core::metrics::BALANCE_VALIDATION_MISMATCHES_TOTAL;
+use crate::db::Database;
+use crate::models::{BalanceChange, BalanceSnapshot, RawAmount};
+use crate::parsers::token::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::rpc::client::TokenAccountClientTrait;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use metrics::counter;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::StateWithExtensions;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use tokio_postgres::types::ToSql;
+use tracing::warn;
+
+/// Number of replayed changes past which a reconstructed balance is stored
+/// as a snapshot, so later lookups start from it
+const DEFAULT_SNAPSHOT_EVERY: usize = 1_000;
+
+/// A point in the history of a balance, after every change at or before it.
+///
+/// Changes stored without a block time only count towards slot points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalancePoint {
+    Slot(u64),
+    Time(DateTime<Utc>),
+}
+
+impl BalancePoint {
+    /// Column and parameter comparing rows against this point
+    pub(crate) fn sql(&self) -> (&'static str, Box<dyn ToSql + Sync + Send>) {
+        match self {
+            BalancePoint::Slot(slot) => ("slot", Box::new(*slot as i64)),
+            BalancePoint::Time(time) => ("timestamp", Box::new(*time)),
+        }
+    }
+}
+
+/// Storage of balance changes and snapshots
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait BalanceStore: Send + Sync {
+    /// Latest snapshot at or before `at`
+    async fn latest_snapshot(&self, owner: &str, mint: &str, at: BalancePoint) -> Result<Option<BalanceSnapshot>>;
+
+    /// Changes after `after`, if set, up to and including `until`, oldest first
+    async fn changes(
+        &self,
+        owner: &str,
+        mint: &str,
+        after: Option<BalancePoint>,
+        until: BalancePoint,
+    ) -> Result<Vec<BalanceChange>>;
+
+    async fn save_snapshots(&self, snapshots: &[BalanceSnapshot]) -> Result<()>;
+
+    /// Mints `owner` has changes or snapshots of
+    async fn owner_mints(&self, owner: &str) -> Result<Vec<String>>;
+}
+
+#[async_trait]
+impl BalanceStore for Database {
+    async fn latest_snapshot(&self, owner: &str, mint: &str, at: BalancePoint) -> Result<Option<BalanceSnapshot>> {
+        self.latest_balance_snapshot(owner, mint, at)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to load balance snapshot: {}", e)))
+    }
+
+    async fn changes(
+        &self,
+        owner: &str,
+        mint: &str,
+        after: Option<BalancePoint>,
+        until: BalancePoint,
+    ) -> Result<Vec<BalanceChange>> {
+        self.get_balance_changes(owner, mint, after, until)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to load balance changes: {}", e)))
+    }
+
+    async fn save_snapshots(&self, snapshots: &[BalanceSnapshot]) -> Result<()> {
+        self.insert_balance_snapshots(snapshots)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to persist balance snapshots: {}", e)))
+    }
+
+    async fn owner_mints(&self, owner: &str) -> Result<Vec<String>> {
+        self.get_owner_mints(owner)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to load owner mints: {}", e)))
+    }
+}
+
+/// A reconstructed balance that differs from the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceMismatch {
+    pub owner: String,
+    pub mint: String,
+    /// Slot the live balance was read at
+    pub slot: u64,
+    pub reconstructed: RawAmount,
+    pub live: RawAmount,
+}
+
+/// Answers balance queries from the latest snapshot before the queried point
+/// plus the stored changes since.
+///
+/// Balances are summed over every token account of the owner, so accounts
+/// closed and later reopened, or several accounts of the same mint, need no
+/// special handling: closing records the remaining balance as a negative
+/// change and reopening starts from zero. Owners without a snapshot are
+/// replayed from their first stored change, which assumes their history is
+/// fully indexed.
+pub struct BalanceHistory {
+    store: Arc<dyn BalanceStore>,
+    snapshot_every: usize,
+}
+
+impl BalanceHistory {
+    pub fn new(store: Arc<dyn BalanceStore>) -> Self {
+        Self {
+            store,
+            snapshot_every: DEFAULT_SNAPSHOT_EVERY,
+        }
+    }
+
+    /// Store a `replay` snapshot whenever a lookup replays at least `changes`
+    /// changes
+    pub fn with_snapshot_every(mut self, changes: usize) -> Self {
+        self.snapshot_every = changes.max(1);
+        self
+    }
+
+    /// Balance of `owner` in `mint`, in raw units, after every change at or
+    /// before `at`
+    pub async fn balance_at(&self, owner: &str, mint: &str, at: BalancePoint) -> Result<RawAmount> {
+        let snapshot = self.store.latest_snapshot(owner, mint, at).await?;
+        let after = snapshot.as_ref().map(|snapshot| BalancePoint::Slot(snapshot.slot as u64));
+        let changes = self.store.changes(owner, mint, after, at).await?;
+        let balance = snapshot.map_or(RawAmount::ZERO, |snapshot| snapshot.balance)
+            + changes.iter().map(|change| change.delta).sum::<RawAmount>();
+
+        // A change without a block time cannot place the snapshot in time
+        let last = changes.last().filter(|_| changes.len() >= self.snapshot_every);
+        if let Some(&BalanceChange { slot, timestamp: Some(timestamp), .. }) = last {
+            let snapshot = BalanceSnapshot::new(owner.to_string(), mint.to_string(), slot, timestamp, balance, "replay");
+            if let Err(e) = self.store.save_snapshots(&[snapshot]).await {
+                warn!("Failed to store balance snapshot of {} in {}: {}", owner, mint, e);
+            }
+        }
+        Ok(balance)
+    }
+
+    /// Balance of `owner` in `mint` at `range.start()` and every `interval`
+    /// after it, up to `range.end()`
+    pub async fn balance_series(
+        &self,
+        owner: &str,
+        mint: &str,
+        range: RangeInclusive<DateTime<Utc>>,
+        interval: Duration,
+    ) -> Result<Vec<(DateTime<Utc>, RawAmount)>> {
+        if interval <= Duration::zero() {
+            return Err(Error::validation("Balance series interval must be positive"));
+        }
+        let (start, end) = (*range.start(), *range.end());
+        let mut balance = self.balance_at(owner, mint, BalancePoint::Time(start)).await?;
+        let changes = self
+            .store
+            .changes(owner, mint, Some(BalancePoint::Time(start)), BalancePoint::Time(end))
+            .await?;
+
+        let mut changes = changes.iter().peekable();
+        let mut series = Vec::new();
+        let mut time = start;
+        while time <= end {
+            while let Some(change) = changes.next_if(|change| change.timestamp.is_some_and(|timestamp| timestamp <= time)) {
+                balance += change.delta;
+            }
+            series.push((time, balance));
+            time += interval;
+        }
+        Ok(series)
+    }
+
+    /// Compare the reconstructed token balances of `owner` with those
+    /// `getTokenAccountsByOwner` returns for both token programs, at the slot
+    /// the accounts were read at. Native SOL is not covered.
+    ///
+    /// Changes must be indexed up to that slot for the comparison to hold.
+    pub async fn validate(&self, client: &dyn TokenAccountClientTrait, owner: &Pubkey) -> Result<Vec<BalanceMismatch>> {
+        let mut live: BTreeMap<String, RawAmount> = BTreeMap::new();
+        let mut slot = 0;
+        for program_id in [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID] {
+            let response = client
+                .get_token_accounts_by_owner(owner, &program_id)
+                .await
+                .map_err(|e| Error::pipeline(format!("Failed to fetch token accounts of {}: {}", owner, e)))?;
+            slot = slot.max(response.context.slot);
+            for (pubkey, account) in response.value {
+                let (mint, amount) = token_account_balance(&account.data)
+                    .ok_or_else(|| Error::validation(format!("Invalid token account {}", pubkey)))?;
+                *live.entry(mint.to_string()).or_default() += RawAmount::from(amount);
+            }
+        }
+
+        let owner = owner.to_string();
+        let mut mints: BTreeSet<String> = live.keys().cloned().collect();
+        mints.extend(self.store.owner_mints(&owner).await?.into_iter().filter(|mint| mint != NATIVE_MINT));
+        let mut mismatches = Vec::new();
+        for mint in mints {
+            let reconstructed = self.balance_at(&owner, &mint, BalancePoint::Slot(slot)).await?;
+            let live = live.get(&mint).copied().unwrap_or_default();
+            if reconstructed != live {
+                counter!(BALANCE_VALIDATION_MISMATCHES_TOTAL, 1);
+                warn!(
+                    "Reconstructed balance of {} in {} at slot {} is {}, chain has {}",
+                    owner, mint, slot, reconstructed, live
+                );
+                mismatches.push(BalanceMismatch {
+                    owner: owner.clone(),
+                    mint,
+                    slot,
+                    reconstructed,
+                    live,
+                });
+            }
+        }
+        Ok(mismatches)
+    }
+}
+
+/// Mint and amount of an SPL Token or Token-2022 account
+pub(crate) fn token_account_balance(data: &[u8]) -> Option<(Pubkey, u64)> {
+    let account = StateWithExtensions::<spl_token_2022::state::Account>::unpack(data).ok()?;
+    Some((account.base.mint, account.base.amount))
+}
+
+#[cfg(test)]
+pub(crate) fn encode_token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    use solana_sdk::program_pack::Pack;
+    let account = spl_token_2022::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: spl_token_2022::state::AccountState::Initialized,
+        ..Default::default()
+    };
+    let mut data = vec![0; spl_token_2022::state::Account::LEN];
+    spl_token_2022::state::Account::pack(account, &mut data).unwrap();
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::client::MockTokenAccountClientTrait;
+    use chrono::TimeZone;
+    use mockall::predicate::eq;
+    use solana_client::rpc_response::{Response, RpcResponseContext};
+    use solana_sdk::account::Account;
+
+    const OWNER: &str = "67vHA8qZGCJKw1UNGUJZME4MwEWDRGWzp7MGvsut43A8";
+    const MINT: &str = "9hGdMK6RVJEcUVVBzBeJFQsS2mv7WMQ2zFd2JmpwLPFE";
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn change(account: &str, slot: i64, delta: i128, post_balance: i128) -> BalanceChange {
+        BalanceChange {
+            signature: format!("sig{}", slot),
+            slot,
+            timestamp: Some(at(slot * 10)),
+            account: account.to_string(),
+            owner: Some(OWNER.to_string()),
+            mint: MINT.to_string(),
+            delta: RawAmount(delta),
+            post_balance: RawAmount(post_balance),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// An account closed at slot 3 and reopened at slot 4
+    fn reopened() -> Vec<BalanceChange> {
+        vec![
+            change("first", 1, 500, 500),
+            change("first", 3, -500, 0),
+            change("second", 4, 200, 200),
+            change("second", 6, 50, 250),
+        ]
+    }
+
+    /// Store answering with `history` and no snapshot
+    fn store(history: Vec<BalanceChange>) -> MockBalanceStore {
+        let mut store = MockBalanceStore::new();
+        store.expect_latest_snapshot().returning(|_, _, _| Ok(None));
+        store.expect_changes().returning(move |_, mint, after, until| {
+            let included = |change: &BalanceChange, point: BalancePoint| match point {
+                BalancePoint::Slot(slot) => change.slot <= slot as i64,
+                BalancePoint::Time(time) => change.timestamp.is_some_and(|timestamp| timestamp <= time),
+            };
+            Ok(history
+                .iter()
+                .filter(|change| change.mint == mint)
+                .filter(|change| included(change, until) && !after.is_some_and(|after| included(change, after)))
+                .cloned()
+                .collect())
+        });
+        store
+    }
+
+    #[tokio::test]
+    async fn test_balance_at_replays_from_snapshot() {
+        let history = BalanceHistory::new(Arc::new(store(reopened())));
+        assert_eq!(history.balance_at(OWNER, MINT, BalancePoint::Slot(2)).await.unwrap(), RawAmount(500));
+        assert_eq!(history.balance_at(OWNER, MINT, BalancePoint::Slot(3)).await.unwrap(), RawAmount(0));
+        assert_eq!(history.balance_at(OWNER, MINT, BalancePoint::Time(at(45))).await.unwrap(), RawAmount(200));
+        assert_eq!(history.balance_at(OWNER, MINT, BalancePoint::Slot(10)).await.unwrap(), RawAmount(250));
+
+        let mut store = MockBalanceStore::new();
+        store
+            .expect_latest_snapshot()
+            .returning(|_, _, _| Ok(Some(BalanceSnapshot::new(OWNER.to_string(), MINT.to_string(), 4, at(40), RawAmount(200), "rpc"))));
+        store
+            .expect_changes()
+            .with(eq(OWNER), eq(MINT), eq(Some(BalancePoint::Slot(4))), eq(BalancePoint::Slot(10)))
+            .returning(|_, _, _, _| Ok(vec![change("second", 6, 50, 250)]));
+        store
+            .expect_save_snapshots()
+            .withf(|snapshots| snapshots[0].slot == 6 && snapshots[0].balance == RawAmount(250) && snapshots[0].source == "replay")
+            .times(1)
+            .returning(|_| Ok(()));
+        let history = BalanceHistory::new(Arc::new(store)).with_snapshot_every(1);
+        assert_eq!(history.balance_at(OWNER, MINT, BalancePoint::Slot(10)).await.unwrap(), RawAmount(250));
+    }
+
+    #[tokio::test]
+    async fn test_balance_series() {
+        let history = BalanceHistory::new(Arc::new(store(reopened())));
+        let series = history
+            .balance_series(OWNER, MINT, at(0)..=at(60), Duration::seconds(20))
+            .await
+            .unwrap();
+        assert_eq!(series, vec![(at(0), RawAmount(0)), (at(20), RawAmount(500)), (at(40), RawAmount(200)), (at(60), RawAmount(250))]);
+
+        assert!(history.balance_series(OWNER, MINT, at(0)..=at(60), Duration::zero()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_reports_mismatches() {
+        let owner: Pubkey = OWNER.parse().unwrap();
+        let mint: Pubkey = MINT.parse().unwrap();
+        let other = Pubkey::new_unique();
+        let mut client = MockTokenAccountClientTrait::new();
+        client.expect_get_token_accounts_by_owner().returning(move |owner, program_id| {
+            let accounts = if *program_id == TOKEN_PROGRAM_ID {
+                vec![
+                    (Pubkey::new_unique(), token_account(&mint, owner, 250)),
+                    (Pubkey::new_unique(), token_account(&other, owner, 7)),
+                ]
+            } else {
+                vec![]
+            };
+            Ok(Response {
+                context: RpcResponseContext { slot: 10, api_version: None },
+                value: accounts,
+            })
+        });
+
+        let mut store = store(reopened());
+        store.expect_owner_mints().returning(|_| Ok(vec![MINT.to_string(), NATIVE_MINT.to_string()]));
+        let history = BalanceHistory::new(Arc::new(store));
+        let mismatches = history.validate(&client, &owner).await.unwrap();
+        // The reconstructed mint matches; the untracked one has no history
+        assert_eq!(
+            mismatches,
+            vec![BalanceMismatch {
+                owner: OWNER.to_string(),
+                mint: other.to_string(),
+                slot: 10,
+                reconstructed: RawAmount::ZERO,
+                live: RawAmount(7),
+            }]
+        );
+    }
+
+    fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+        Account {
+            lamports: 2_039_280,
+            data: encode_token_account(mint, owner, amount),
+            owner: TOKEN_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+}
@@ -0,0 +1,16 @@
+//! Balance tracking
+//!
+//! [`balance_changes`] turns the pre/post lamport and token balances of a
+//! transaction's status meta into per-account, per-mint delta rows, checking
+//! that the deltas add up: lamports only disappear as the fee, and token
+//! supplies only change through mints and burns. A [`BalanceHistory`] answers
+//! point-in-time and series queries from stored snapshots plus those deltas,
+//! and a [`WalletSnapshotter`] takes those snapshots from live token accounts.
+
+pub mod history;
+pub mod snapshot;
+pub mod tracker;
+
+pub use history::{BalanceHistory, BalanceMismatch, BalancePoint, BalanceStore};
+pub use snapshot::{SnapshotReport, SnapshotStore, WalletSnapshotter};
+pub use tracker::{balance_changes, BalanceChanges, BalanceViolation, NATIVE_MINT};
@@ -0,0 +1,301 @@
+//! Wallet snapshots from live token accounts and SOL balances
+
+use crate::balances::history::token_account_balance;
+use crate::balances::NATIVE_MINT;
+use crate::core::error::{Error, Result};
+use crate::db::Database;
+use crate::models::{BalanceSnapshot, RawAmount, TokenAccount};
+use crate::parsers::token::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::rpc::client::TokenAccountClientTrait;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Storage of token accounts and the snapshots taken from them
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait SnapshotStore: Send + Sync {
+    /// Token accounts of `owner` not marked closed
+    async fn open_token_accounts(&self, owner: &str) -> Result<Vec<TokenAccount>>;
+
+    async fn save_token_accounts(&self, accounts: &[TokenAccount]) -> Result<()>;
+
+    async fn close_token_accounts(&self, pubkeys: &[String], closed_at: DateTime<Utc>) -> Result<()>;
+
+    async fn save_snapshots(&self, snapshots: &[BalanceSnapshot]) -> Result<()>;
+}
+
+#[async_trait]
+impl SnapshotStore for Database {
+    async fn open_token_accounts(&self, owner: &str) -> Result<Vec<TokenAccount>> {
+        self.get_open_token_accounts(owner)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to load token accounts: {}", e)))
+    }
+
+    async fn save_token_accounts(&self, accounts: &[TokenAccount]) -> Result<()> {
+        self.upsert_token_accounts(accounts)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to persist token accounts: {}", e)))
+    }
+
+    async fn close_token_accounts(&self, pubkeys: &[String], closed_at: DateTime<Utc>) -> Result<()> {
+        Database::close_token_accounts(self, pubkeys, closed_at)
+            .await
+            .map(|_| ())
+            .map_err(|e| Error::pipeline(format!("Failed to close token accounts: {}", e)))
+    }
+
+    async fn save_snapshots(&self, snapshots: &[BalanceSnapshot]) -> Result<()> {
+        self.insert_balance_snapshots(snapshots)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to persist balance snapshots: {}", e)))
+    }
+}
+
+/// Outcome of [`WalletSnapshotter::snapshot`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotReport {
+    /// Wallets snapshotted
+    pub wallets: usize,
+    /// Open token accounts found
+    pub accounts: usize,
+    /// Accounts stored as open that no longer exist
+    pub closed: usize,
+    /// Wallets that could not be snapshotted, with the error
+    pub failed: Vec<(String, String)>,
+}
+
+/// Reads the token accounts of both token programs and the SOL balance of
+/// wallets, refreshes `token_accounts` and writes a [`BalanceSnapshot`] per
+/// wallet and mint for [`BalanceHistory`](crate::balances::BalanceHistory).
+///
+/// Stored accounts missing from the chain are marked closed, and mints the
+/// wallet no longer holds get a zero snapshot. Snapshots carry the slot of
+/// the response they were read from, and the time they were taken.
+pub struct WalletSnapshotter {
+    client: Arc<dyn TokenAccountClientTrait>,
+    store: Arc<dyn SnapshotStore>,
+}
+
+impl WalletSnapshotter {
+    pub fn new(client: Arc<dyn TokenAccountClientTrait>, store: Arc<dyn SnapshotStore>) -> Self {
+        Self { client, store }
+    }
+
+    /// Snapshot every wallet of `wallets`. A wallet that fails is logged and
+    /// reported without stopping the others.
+    pub async fn snapshot(&self, wallets: &[Pubkey]) -> SnapshotReport {
+        let mut report = SnapshotReport::default();
+        for wallet in wallets {
+            match self.snapshot_wallet(wallet).await {
+                Ok((accounts, closed)) => {
+                    report.wallets += 1;
+                    report.accounts += accounts;
+                    report.closed += closed;
+                }
+                Err(e) => {
+                    warn!("Failed to snapshot wallet {}: {}", wallet, e);
+                    report.failed.push((wallet.to_string(), e.to_string()));
+                }
+            }
+        }
+        report
+    }
+
+    /// Snapshot `wallet`, returning the number of open and newly closed
+    /// token accounts
+    async fn snapshot_wallet(&self, wallet: &Pubkey) -> Result<(usize, usize)> {
+        let owner = wallet.to_string();
+        let now = Utc::now();
+        let native = self
+            .client
+            .get_balance(wallet)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to fetch balance: {}", e)))?;
+        let mut snapshots = vec![BalanceSnapshot::new(
+            owner.clone(),
+            NATIVE_MINT.to_string(),
+            native.context.slot as i64,
+            now,
+            RawAmount::from(native.value),
+            "rpc",
+        )];
+
+        let mut accounts = Vec::new();
+        // Balance and slot per mint
+        let mut mints: BTreeMap<String, (RawAmount, u64)> = BTreeMap::new();
+        for program_id in [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID] {
+            let response = self
+                .client
+                .get_token_accounts_by_owner(wallet, &program_id)
+                .await
+                .map_err(|e| Error::pipeline(format!("Failed to fetch token accounts: {}", e)))?;
+            let slot = response.context.slot;
+            for (pubkey, account) in response.value {
+                let (mint, amount) = token_account_balance(&account.data)
+                    .ok_or_else(|| Error::validation(format!("Invalid token account {}", pubkey)))?;
+                let entry = mints.entry(mint.to_string()).or_insert((RawAmount::ZERO, slot));
+                entry.0 += RawAmount::from(amount);
+                let mut record = TokenAccount::new(pubkey.to_string(), mint.to_string(), owner.clone(), amount, now);
+                record.program_id = Some(program_id.to_string());
+                record.slot = Some(slot as i64);
+                accounts.push(record);
+            }
+        }
+
+        let live: HashSet<&str> = accounts.iter().map(|account| account.pubkey.as_str()).collect();
+        let closed: Vec<TokenAccount> = self
+            .store
+            .open_token_accounts(&owner)
+            .await?
+            .into_iter()
+            .filter(|account| !live.contains(account.pubkey.as_str()))
+            .collect();
+        for account in &closed {
+            if !mints.contains_key(&account.mint) {
+                let slot = account.slot.unwrap_or_default().max(native.context.slot as i64);
+                snapshots.push(BalanceSnapshot::new(owner.clone(), account.mint.clone(), slot, now, RawAmount::ZERO, "rpc"));
+            }
+        }
+        snapshots.extend(mints.into_iter().map(|(mint, (balance, slot))| {
+            BalanceSnapshot::new(owner.clone(), mint, slot as i64, now, balance, "rpc")
+        }));
+
+        if !accounts.is_empty() {
+            self.store.save_token_accounts(&accounts).await?;
+        }
+        if !closed.is_empty() {
+            let pubkeys: Vec<String> = closed.iter().map(|account| account.pubkey.clone()).collect();
+            self.store.close_token_accounts(&pubkeys, now).await?;
+        }
+        self.store.save_snapshots(&snapshots).await?;
+        Ok((accounts.len(), closed.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::balances::history::encode_token_account;
+    use crate::rpc::client::MockTokenAccountClientTrait;
+    use crate::rpc::error::RpcError;
+    use solana_client::rpc_response::{Response, RpcResponseContext};
+    use solana_sdk::account::Account;
+
+    fn response<T>(slot: u64, value: T) -> Response<T> {
+        Response {
+            context: RpcResponseContext { slot, api_version: None },
+            value,
+        }
+    }
+
+    fn token_account(program_id: Pubkey, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+        Account {
+            lamports: 2_039_280,
+            data: encode_token_account(mint, owner, amount),
+            owner: program_id,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_upserts_accounts_and_marks_closed() {
+        let wallet = Pubkey::new_unique();
+        let usdc = Pubkey::new_unique();
+        let pyusd = Pubkey::new_unique();
+        let (first, second, token_2022) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let gone = Pubkey::new_unique().to_string();
+        let dropped_mint = Pubkey::new_unique().to_string();
+
+        let mut client = MockTokenAccountClientTrait::new();
+        client.expect_get_balance().returning(|_| Ok(response(100, 5_000_000)));
+        client.expect_get_token_accounts_by_owner().returning(move |owner, program_id| {
+            Ok(if *program_id == TOKEN_PROGRAM_ID {
+                response(
+                    101,
+                    vec![
+                        (first, token_account(*program_id, &usdc, owner, 40)),
+                        (second, token_account(*program_id, &usdc, owner, 2)),
+                    ],
+                )
+            } else {
+                response(102, vec![(token_2022, token_account(*program_id, &pyusd, owner, 9))])
+            })
+        });
+
+        let mut store = MockSnapshotStore::new();
+        let stored = [first.to_string(), gone.clone()];
+        let (dropped, owner) = (dropped_mint.clone(), wallet.to_string());
+        store.expect_open_token_accounts().times(1).returning(move |_| {
+            Ok(stored
+                .iter()
+                .map(|pubkey| TokenAccount::new(pubkey.clone(), dropped.clone(), owner.clone(), 1, Utc::now()))
+                .collect())
+        });
+        store
+            .expect_save_token_accounts()
+            .withf(move |accounts| {
+                accounts.len() == 3
+                    && accounts[2].program_id == Some(TOKEN_2022_PROGRAM_ID.to_string())
+                    && accounts[2].slot == Some(102)
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let closed = gone.clone();
+        store
+            .expect_close_token_accounts()
+            .withf(move |pubkeys, _| pubkeys == [closed.clone()])
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let (usdc_mint, pyusd_mint) = (usdc.to_string(), pyusd.to_string());
+        store
+            .expect_save_snapshots()
+            .withf(move |snapshots| {
+                let balances: Vec<_> = snapshots
+                    .iter()
+                    .map(|snapshot| (snapshot.mint.clone(), snapshot.slot, snapshot.balance.0))
+                    .collect();
+                let mut expected = vec![
+                    (NATIVE_MINT.to_string(), 100, 5_000_000),
+                    (dropped_mint.clone(), 100, 0),
+                    (usdc_mint.clone(), 101, 42),
+                    (pyusd_mint.clone(), 102, 9),
+                ];
+                expected[2..].sort();
+                balances == expected
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let snapshotter = WalletSnapshotter::new(Arc::new(client), Arc::new(store));
+        let report = snapshotter.snapshot(&[wallet]).await;
+        assert_eq!(
+            report,
+            SnapshotReport {
+                wallets: 1,
+                accounts: 3,
+                closed: 1,
+                failed: vec![],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_wallet_is_reported() {
+        let mut client = MockTokenAccountClientTrait::new();
+        client
+            .expect_get_balance()
+            .returning(|_| Err(RpcError::Internal("unavailable".to_string())));
+        let snapshotter = WalletSnapshotter::new(Arc::new(client), Arc::new(MockSnapshotStore::new()));
+        let wallet = Pubkey::new_unique();
+        let report = snapshotter.snapshot(&[wallet]).await;
+        assert_eq!(report.wallets, 0);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, wallet.to_string());
+    }
+}
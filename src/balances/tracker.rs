@@ -0,0 +1,272 @@
+//! Per-account balance deltas from transaction status metas
+
+use crate::models::{BalanceChange, RawAmount};
+use crate::parsers::{ParsedTransaction, ProtocolEvent, TokenBalance, TokenEvent};
+use chrono::{TimeZone, Utc};
+use std::collections::BTreeMap;
+
+/// Mint recorded for native SOL balances, keeping lamports apart from wrapped
+/// SOL token accounts
+pub const NATIVE_MINT: &str = "native";
+
+/// Token-2022 transfer fee instructions crediting withheld fees to an account
+const WITHDRAW_WITHHELD_FROM_MINT: u8 = 2;
+const WITHDRAW_WITHHELD_FROM_ACCOUNTS: u8 = 3;
+
+/// A transaction whose balance changes do not add up
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum BalanceViolation {
+    /// Lamport changes do not sum to the fee debit, the only lamports a
+    /// transaction may create or destroy
+    #[error("Lamport changes sum to {actual}, expected {expected}")]
+    Lamports { expected: i128, actual: i128 },
+    /// Token changes of `mint` do not sum to the amount minted minus the
+    /// amount burned by the transaction's token instructions, adjusted for
+    /// Token-2022 transfer fees moved in and out of withheld amounts
+    #[error("Changes of mint {mint} sum to {actual}, expected {expected}")]
+    TokenSupply { mint: String, expected: i128, actual: i128 },
+    /// The status meta does not list a balance per account
+    #[error("{accounts} accounts with {pre} pre and {post} post balances")]
+    MissingBalances { accounts: usize, pre: usize, post: usize },
+}
+
+impl BalanceViolation {
+    /// Short name used as metric label
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BalanceViolation::Lamports { .. } => "lamports",
+            BalanceViolation::TokenSupply { .. } => "token_supply",
+            BalanceViolation::MissingBalances { .. } => "missing_balances",
+        }
+    }
+}
+
+/// Pre and post token balance of one account
+type PrePost<'a> = (Option<&'a TokenBalance>, Option<&'a TokenBalance>);
+
+/// Balance changes of one transaction and the invariants they break
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BalanceChanges {
+    pub changes: Vec<BalanceChange>,
+    pub violations: Vec<BalanceViolation>,
+}
+
+/// Derive one row per account and mint whose balance `transaction` changed,
+/// from the pre/post lamport and token balances of its status meta.
+///
+/// A token account missing from the pre balances was created by the
+/// transaction and one missing from the post balances was closed, so their
+/// balances count as zero on that side. Mint and burn amounts are taken from
+/// [`TokenEvent`]s, so the supply check needs a `TokenParser` registered.
+pub fn balance_changes(transaction: &ParsedTransaction) -> BalanceChanges {
+    let context = &transaction.context;
+    let timestamp = context.block_time.and_then(|t| Utc.timestamp_opt(t, 0).single());
+    let mut result = BalanceChanges::default();
+    let mut push = |account: String, owner: Option<String>, mint: &str, pre: u64, post: u64| {
+        let delta = RawAmount::change(pre, post);
+        if delta.is_zero() {
+            return;
+        }
+        result.changes.push(BalanceChange {
+            signature: context.signature.clone(),
+            slot: context.slot as i64,
+            timestamp,
+            account,
+            owner,
+            mint: mint.to_string(),
+            delta,
+            post_balance: RawAmount::from(post),
+            created_at: Utc::now(),
+        });
+    };
+
+    // Native SOL, owned by the account itself
+    let accounts = context.account_keys.len();
+    let (pre, post) = (&context.pre_balances, &context.post_balances);
+    let mut lamports = 0;
+    for (key, (&pre, &post)) in context.account_keys.iter().zip(pre.iter().zip(post)) {
+        lamports += post as i128 - pre as i128;
+        push(key.to_string(), Some(key.to_string()), NATIVE_MINT, pre, post);
+    }
+
+    // Token accounts, keyed by account index and mint
+    let mut tokens: BTreeMap<(usize, &str), PrePost> = BTreeMap::new();
+    for balance in &context.pre_token_balances {
+        tokens.entry((balance.account_index, &balance.mint)).or_default().0 = Some(balance);
+    }
+    for balance in &context.post_token_balances {
+        tokens.entry((balance.account_index, &balance.mint)).or_default().1 = Some(balance);
+    }
+    let mut supply: BTreeMap<&str, i128> = BTreeMap::new();
+    for ((index, mint), (pre, post)) in tokens {
+        let Some(account) = context.account_keys.get(index) else {
+            continue;
+        };
+        let (pre_amount, post_amount) = (pre.map_or(0, |b| b.amount), post.map_or(0, |b| b.amount));
+        *supply.entry(mint).or_default() += post_amount as i128 - pre_amount as i128;
+        let owner = post.or(pre).and_then(|balance| balance.owner.clone());
+        push(account.to_string(), owner, mint, pre_amount, post_amount);
+    }
+
+    if pre.len() != accounts || post.len() != accounts {
+        result.violations.push(BalanceViolation::MissingBalances {
+            accounts,
+            pre: pre.len(),
+            post: post.len(),
+        });
+    } else if lamports != -(context.fee as i128) {
+        result.violations.push(BalanceViolation::Lamports {
+            expected: -(context.fee as i128),
+            actual: lamports,
+        });
+    }
+
+    let mut expected: BTreeMap<&str, i128> = BTreeMap::new();
+    if context.success {
+        for parsed in &transaction.events {
+            match &parsed.event {
+                ProtocolEvent::Token(TokenEvent::MintTo { mint, amount, .. }) => {
+                    *expected.entry(mint).or_default() += *amount as i128
+                }
+                ProtocolEvent::Token(TokenEvent::Burn { mint, amount, .. }) => {
+                    *expected.entry(mint).or_default() -= *amount as i128
+                }
+                // The fee is withheld in the destination account, outside its balance
+                ProtocolEvent::Token(TokenEvent::Transfer { mint: Some(mint), fee: Some(fee), .. }) => {
+                    *expected.entry(mint).or_default() -= *fee as i128
+                }
+                // Withdrawn fees credit the destination without a debit
+                ProtocolEvent::Token(TokenEvent::Extension { extension, instruction: Some(instruction), accounts })
+                    if extension == "transfer_fee"
+                        && matches!(*instruction, WITHDRAW_WITHHELD_FROM_MINT | WITHDRAW_WITHHELD_FROM_ACCOUNTS) =>
+                {
+                    if let [mint, destination, ..] = accounts.as_slice() {
+                        let withdrawn: i128 = result
+                            .changes
+                            .iter()
+                            .filter(|change| change.account == *destination && change.mint == *mint)
+                            .map(|change| change.delta.0)
+                            .sum();
+                        *expected.entry(mint).or_default() += withdrawn;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    for (mint, actual) in supply {
+        let expected = expected.get(mint).copied().unwrap_or(0);
+        if actual != expected {
+            result.violations.push(BalanceViolation::TokenSupply {
+                mint: mint.to_string(),
+                expected,
+                actual,
+            });
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::{fixture, parse_encoded, parse_fixture};
+    use crate::parsers::{ParserRegistry, TokenParser};
+    use std::sync::Arc;
+
+    fn token_registry() -> ParserRegistry {
+        let mut registry = ParserRegistry::new();
+        registry.register(Arc::new(TokenParser::new()));
+        registry
+    }
+
+    #[test]
+    fn test_native_and_token_changes() {
+        let sol = balance_changes(&parse_fixture("system_transfers", &ParserRegistry::new()));
+        assert_eq!(sol.violations, vec![]);
+        let payer = &sol.changes[0];
+        assert_eq!(payer.account, "AWxggjuZRmWULwxwPeM6ZZxRtdDdekVq22mFRx2QbW7U");
+        assert_eq!(payer.owner.as_deref(), Some(payer.account.as_str()));
+        assert_eq!(payer.mint, NATIVE_MINT);
+        assert_eq!((payer.delta, payer.post_balance), (RawAmount(-1_002_049_280), RawAmount(8_997_950_720)));
+        assert_eq!(payer.pre_balance(), RawAmount(10_000_000_000));
+        assert_eq!(sol.changes.len(), 5);
+
+        let mut untimed = parse_fixture("system_transfers", &ParserRegistry::new());
+        untimed.context.block_time = None;
+        assert!(balance_changes(&untimed).changes.iter().all(|change| change.timestamp.is_none()));
+
+        let tokens = balance_changes(&parse_fixture("spl_token_transfer", &token_registry()));
+        assert_eq!(tokens.violations, vec![]);
+        // The fee payer's lamports, then the token accounts
+        assert_eq!((tokens.changes[0].mint.as_str(), tokens.changes[0].delta), (NATIVE_MINT, RawAmount(-5000)));
+        let deltas: Vec<_> = tokens
+            .changes
+            .iter()
+            .skip(1)
+            .map(|change| (change.account.as_str(), change.owner.as_deref(), change.delta.0, change.post_balance.0))
+            .collect();
+        assert_eq!(
+            deltas,
+            vec![
+                (
+                    "4sAFqwXMzZFwSDjjHceT9txELSCNEbVd7iw7G6yBJs4s",
+                    Some("67vHA8qZGCJKw1UNGUJZME4MwEWDRGWzp7MGvsut43A8"),
+                    -1_250_000,
+                    3_750_000
+                ),
+                (
+                    "5tC4Qjk6b9xuP7a9B9FFs3m5Rto4siXsfqGt95zFJhf7",
+                    Some("7tark5iZaRrMfGKtKy1aqpGuRgoxbE6ec7Z5Qa4Jc5xr"),
+                    1_250_000,
+                    1_250_000
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transfer_fee_is_withheld() {
+        // 10 tokens leave the source, 9.99 reach the destination
+        let parsed = balance_changes(&parse_fixture("token_2022_transfer_fee", &token_registry()));
+        assert_eq!(parsed.violations, vec![]);
+        let deltas: Vec<_> = parsed.changes.iter().skip(1).map(|change| change.delta.0).collect();
+        assert_eq!(deltas, vec![-10_000_000, 9_990_000]);
+
+        let unparsed = balance_changes(&parse_fixture("token_2022_transfer_fee", &ParserRegistry::new()));
+        assert_eq!(
+            unparsed.violations,
+            vec![BalanceViolation::TokenSupply {
+                mint: "68gSUsLAHa2byJLwzNjtPUMgkKvLDViWaMkBZ5kx2rZ3".to_string(),
+                expected: 0,
+                actual: -10_000,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_flags_changes_that_do_not_add_up() {
+        let parsed = balance_changes(&parse_fixture("spl_token_mint_burn", &token_registry()));
+        assert_eq!(parsed.violations, vec![]);
+        assert_eq!(parsed.changes.len(), 2);
+
+        // Without the token parser the minted amount is unaccounted for
+        let unparsed = balance_changes(&parse_fixture("spl_token_mint_burn", &ParserRegistry::new()));
+        assert_eq!(
+            unparsed.violations[0],
+            BalanceViolation::TokenSupply {
+                mint: "B7ScJ3BvgitUPFxK8TiauFbSh6mMh4iKQKGgTMWdPg4T".to_string(),
+                expected: 0,
+                actual: 999_600_000,
+            }
+        );
+        assert_eq!(unparsed.violations[0].kind(), "token_supply");
+
+        // Post balances leaving out the fee debit
+        let mut corrupted = fixture("spl_token_mint_burn");
+        corrupted.transaction.meta.as_mut().unwrap().post_balances[0] += 5000;
+        let corrupted = balance_changes(&parse_encoded(&corrupted, &token_registry()));
+        assert_eq!(corrupted.violations, vec![BalanceViolation::Lamports { expected: -5000, actual: 0 }]);
+        assert_eq!(corrupted.violations[0].kind(), "lamports");
+    }
+}
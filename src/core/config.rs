@@ -59,7 +59,7 @@ pub struct Config {
     pub max_concurrent_requests: usize,
     
     /// Retry configuration
-    #[validate(nested)]
+    #[validate]
     pub retry_config: RetryConfig,
 
     /// Connection pool size
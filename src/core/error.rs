@@ -5,9 +5,9 @@ use std::time::Duration;
 /// Custom error type for the Solana RPC client
 #[derive(Error, Debug)]
 pub enum Error {
-    /// RPC request failed
+    /// RPC request failed, boxed to keep the error small
     #[error("RPC error: {0}")]
-    Rpc(#[from] ClientError),
+    Rpc(Box<ClientError>),
 
     /// Configuration error
     #[error("Configuration error: {0}")]
@@ -40,6 +40,10 @@ pub enum Error {
     /// Serialization error
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+
+    /// Indexing pipeline error
+    #[error("Pipeline error: {0}")]
+    Pipeline(String),
 }
 
 impl Error {
@@ -73,6 +77,11 @@ impl Error {
         Error::InvalidUrl(url.into())
     }
 
+    /// Create a new pipeline error
+    pub fn pipeline(msg: impl Into<String>) -> Self {
+        Error::Pipeline(msg.into())
+    }
+
     /// Check if the error is retryable
     pub fn is_retryable(&self) -> bool {
         matches!(self,
@@ -83,6 +92,12 @@ impl Error {
     }
 }
 
+impl From<ClientError> for Error {
+    fn from(error: ClientError) -> Self {
+        Error::Rpc(Box::new(error))
+    }
+}
+
 /// Custom result type for the Solana RPC client
 pub type Result<T> = std::result::Result<T, Error>;
 
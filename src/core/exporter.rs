@@ -0,0 +1,191 @@
+//! Prometheus exporter for the metrics registered in [`crate::core::metrics`]
+//!
+//! Enabled with the `prometheus` feature. [`install`] sets the global
+//! `metrics` recorder and serves the scrape endpoint on
+//! [`PrometheusConfig::listen_addr`] (scrape `/metrics`).
+
+use crate::core::error::{Error, Result};
+use crate::core::metrics::{describe_metrics_with, MetricKind, REGISTRY};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusRecorder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+
+/// Default histogram buckets in seconds, suited to RPC and query latencies
+pub const DEFAULT_BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0,
+];
+
+/// Configuration for the Prometheus exporter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrometheusConfig {
+    /// Address the scrape endpoint listens on
+    pub listen_addr: SocketAddr,
+    /// Histogram buckets applied to every histogram without an override
+    pub buckets: Vec<f64>,
+    /// Per-metric histogram buckets keyed by full metric name
+    pub bucket_overrides: HashMap<String, Vec<f64>>,
+    /// Cluster label attached to every metric (e.g. "mainnet-beta")
+    pub cluster: Option<String>,
+    /// Instance label attached to every metric
+    pub instance: Option<String>,
+    /// Additional labels attached to every metric
+    pub global_labels: HashMap<String, String>,
+}
+
+impl Default for PrometheusConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: SocketAddr::from(([0, 0, 0, 0], 9090)),
+            buckets: DEFAULT_BUCKETS.to_vec(),
+            bucket_overrides: HashMap::new(),
+            cluster: None,
+            instance: None,
+            global_labels: HashMap::new(),
+        }
+    }
+}
+
+impl PrometheusConfig {
+    /// Create a new exporter configuration listening on `listen_addr`
+    pub fn new(listen_addr: SocketAddr) -> Self {
+        Self {
+            listen_addr,
+            ..Default::default()
+        }
+    }
+
+    /// Set the cluster label
+    pub fn with_cluster(mut self, cluster: impl Into<String>) -> Self {
+        self.cluster = Some(cluster.into());
+        self
+    }
+
+    /// Set the instance label
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    fn builder(&self) -> Result<PrometheusBuilder> {
+        let mut builder = PrometheusBuilder::new()
+            .with_http_listener(self.listen_addr)
+            .set_buckets(&self.buckets)
+            .map_err(|e| Error::config(format!("Invalid histogram buckets: {}", e)))?;
+
+        for (name, buckets) in &self.bucket_overrides {
+            let known = REGISTRY
+                .iter()
+                .any(|d| d.name == name && d.kind == MetricKind::Histogram);
+            if !known {
+                return Err(Error::config(format!("Bucket override for unknown histogram: {}", name)));
+            }
+            builder = builder
+                .set_buckets_for_metric(Matcher::Full(name.clone()), buckets)
+                .map_err(|e| Error::config(format!("Invalid buckets for {}: {}", name, e)))?;
+        }
+
+        if let Some(cluster) = &self.cluster {
+            builder = builder.add_global_label("cluster", cluster);
+        }
+        if let Some(instance) = &self.instance {
+            builder = builder.add_global_label("instance", instance);
+        }
+        for (key, value) in &self.global_labels {
+            builder = builder.add_global_label(key, value);
+        }
+
+        Ok(builder)
+    }
+}
+
+/// Build the recorder and scrape endpoint without installing them globally.
+///
+/// The returned future serves the scrape endpoint and must be spawned on a
+/// Tokio runtime. The recorder already carries descriptions for every
+/// registered metric.
+pub fn build(config: &PrometheusConfig) -> Result<(PrometheusRecorder, impl Future<Output = ()> + Send)> {
+    let (recorder, exporter) = config
+        .builder()?
+        .build()
+        .map_err(|e| Error::config(format!("Failed to build Prometheus exporter: {}", e)))?;
+    describe_metrics_with(&recorder);
+
+    let exporter = async move {
+        if let Err(e) = exporter.await {
+            tracing::error!("Prometheus exporter stopped: {}", e);
+        }
+    };
+    Ok((recorder, exporter))
+}
+
+/// Install the Prometheus recorder globally and start serving the scrape endpoint.
+///
+/// Must be called from within a Tokio runtime. Fails if a recorder is already installed.
+pub async fn install(config: &PrometheusConfig) -> Result<()> {
+    let (recorder, exporter) = build(config)?;
+    metrics::set_boxed_recorder(Box::new(recorder))
+        .map_err(|e| Error::config(format!("Failed to install metrics recorder: {}", e)))?;
+    tokio::spawn(exporter);
+    tracing::info!("Serving Prometheus metrics on http://{}/metrics", config.listen_addr);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::metrics::RPC_REQUEST_DURATION_SECONDS;
+    use metrics::{Key, Label, Recorder};
+
+    fn free_addr() -> SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    #[test]
+    fn test_unknown_bucket_override_rejected() {
+        let mut config = PrometheusConfig::new(free_addr());
+        config.bucket_overrides.insert("not_a_metric".to_string(), vec![1.0]);
+        assert!(build(&config).is_err());
+    }
+
+    #[test]
+    fn test_empty_buckets_rejected() {
+        let mut config = PrometheusConfig::new(free_addr());
+        config.buckets.clear();
+        assert!(build(&config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scrape_endpoint() {
+        let addr = free_addr();
+        let mut config = PrometheusConfig::new(addr)
+            .with_cluster("devnet")
+            .with_instance("indexer-0");
+        config
+            .bucket_overrides
+            .insert(RPC_REQUEST_DURATION_SECONDS.to_string(), vec![0.1, 1.0]);
+
+        let (recorder, exporter) = build(&config).unwrap();
+        tokio::spawn(exporter);
+
+        let key = Key::from_parts(
+            RPC_REQUEST_DURATION_SECONDS,
+            vec![Label::new("method", "getBlock")],
+        );
+        recorder.register_histogram(&key).record(0.05);
+
+        let body = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("# HELP rpc_request_duration_seconds Latency of a single RPC attempt"));
+        assert!(body.contains("rpc_request_duration_seconds_bucket"));
+        assert!(body.contains("le=\"0.1\""));
+        assert!(body.contains("cluster=\"devnet\""));
+        assert!(body.contains("instance=\"indexer-0\""));
+    }
+}
@@ -1,10 +1,16 @@
-//! Health monitoring for RPC endpoints
+//! Health monitoring for RPC endpoints and composite system health
 
+use async_trait::async_trait;
+use serde_json::json;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use url::Url;
 use metrics::{counter, histogram};
+use crate::core::error::Result;
+use crate::core::metrics::{RPC_ERROR_TOTAL, RPC_RESPONSE_TIME_SECONDS, RPC_SUCCESS_TOTAL};
+use crate::core::system::SystemSampler;
+use crate::core::traits::{Cache, ComponentHealth, HealthCheck, HealthComponent, HealthDetails, HealthStatus};
 
 #[derive(Debug, Clone)]
 pub struct EndpointHealth {
@@ -37,8 +43,8 @@ impl EndpointHealth {
         self.is_healthy = true;
 
         // Record metrics
-        counter!("rpc_success_total", 1, "endpoint" => self.url.to_string());
-        histogram!("rpc_response_time_seconds", response_time.as_secs_f64(), "endpoint" => self.url.to_string());
+        counter!(RPC_SUCCESS_TOTAL, 1, "endpoint" => self.url.to_string());
+        histogram!(RPC_RESPONSE_TIME_SECONDS, response_time.as_secs_f64(), "endpoint" => self.url.to_string());
     }
 
     pub fn record_error(&mut self, error: String) {
@@ -47,7 +53,7 @@ impl EndpointHealth {
         self.is_healthy = false;
 
         // Record metrics
-        counter!("rpc_error_total", 1, "endpoint" => self.url.to_string());
+        counter!(RPC_ERROR_TOTAL, 1, "endpoint" => self.url.to_string());
     }
 
     pub fn health_score(&self) -> f64 {
@@ -58,7 +64,7 @@ impl EndpointHealth {
 
         let success_rate = self.success_count as f64 / total as f64;
         let avg_response_time = self.response_time.as_millis() as f64;
-        
+
         // Add time-based degradation
         let time_factor = if let Some(last_success) = self.last_success {
             let time_since_last_success = last_success.elapsed();
@@ -70,7 +76,7 @@ impl EndpointHealth {
         } else {
             0.5
         };
-        
+
         // Score based on success rate, response time, and time factor
         success_rate * (1000.0 / (avg_response_time + 1.0)) * time_factor
     }
@@ -124,4 +130,267 @@ impl HealthMonitor {
     pub async fn get_health_stats(&self) -> Vec<EndpointHealth> {
         self.endpoints.read().await.clone()
     }
-} 
\ No newline at end of file
+}
+
+/// Default time allowed for a single component probe
+pub const DEFAULT_COMPONENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+struct RegisteredComponent {
+    component: Arc<dyn HealthComponent>,
+    critical: bool,
+}
+
+/// Aggregates [`ComponentHealth`] from every registered component into
+/// [`HealthDetails`], together with host metrics from procfs.
+///
+/// An unhealthy critical component makes the whole system unhealthy; an
+/// unhealthy optional component, or any degraded component, makes it degraded.
+#[derive(Debug)]
+pub struct HealthRegistry {
+    components: RwLock<Vec<RegisteredComponent>>,
+    sampler: SystemSampler,
+    timeout: Duration,
+}
+
+impl Default for HealthRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HealthRegistry {
+    /// Create an empty registry sampling host metrics from `/proc`
+    pub fn new() -> Self {
+        Self::with_sampler(SystemSampler::default())
+    }
+
+    /// Create an empty registry using `sampler` for host metrics
+    pub fn with_sampler(sampler: SystemSampler) -> Self {
+        Self {
+            components: RwLock::new(Vec::new()),
+            sampler,
+            timeout: DEFAULT_COMPONENT_TIMEOUT,
+        }
+    }
+
+    /// Set the time allowed for each component probe
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Register a component whose failure makes the system unhealthy
+    pub async fn register(&self, component: Arc<dyn HealthComponent>) {
+        self.components.write().await.push(RegisteredComponent { component, critical: true });
+    }
+
+    /// Register a component whose failure only degrades the system
+    pub async fn register_optional(&self, component: Arc<dyn HealthComponent>) {
+        self.components.write().await.push(RegisteredComponent { component, critical: false });
+    }
+
+    /// Probe every registered component concurrently
+    pub async fn components(&self) -> Vec<ComponentHealth> {
+        self.probe().await.into_iter().map(|(health, _)| health).collect()
+    }
+
+    async fn probe(&self) -> Vec<(ComponentHealth, bool)> {
+        let components = self.components.read().await;
+        let probes = components.iter().map(|registered| async move {
+            let component = &registered.component;
+            let health = match tokio::time::timeout(self.timeout, component.component_health()).await {
+                Ok(health) => health,
+                Err(_) => ComponentHealth {
+                    name: component.name().to_string(),
+                    status: HealthStatus::Unhealthy(Some(format!(
+                        "health check timed out after {:?}",
+                        self.timeout
+                    ))),
+                    metrics: None,
+                },
+            };
+            (health, registered.critical)
+        });
+        futures::future::join_all(probes).await
+    }
+
+    /// Combine component results into an overall status
+    fn aggregate(results: &[(ComponentHealth, bool)]) -> HealthStatus {
+        let mut unhealthy = Vec::new();
+        let mut degraded = Vec::new();
+        for (health, critical) in results {
+            match (&health.status, critical) {
+                (HealthStatus::Healthy, _) => {}
+                (HealthStatus::Unhealthy(_), true) => unhealthy.push(health.name.as_str()),
+                _ => degraded.push(health.name.as_str()),
+            }
+        }
+
+        if !unhealthy.is_empty() {
+            HealthStatus::Unhealthy(Some(format!("unhealthy: {}", unhealthy.join(", "))))
+        } else if !degraded.is_empty() {
+            HealthStatus::Degraded(Some(format!("degraded: {}", degraded.join(", "))))
+        } else {
+            HealthStatus::Healthy
+        }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for HealthRegistry {
+    async fn check_health(&self) -> Result<HealthStatus> {
+        Ok(Self::aggregate(&self.probe().await))
+    }
+
+    async fn get_health_details(&self) -> Result<HealthDetails> {
+        let results = self.probe().await;
+        Ok(HealthDetails {
+            status: Self::aggregate(&results),
+            components: results.into_iter().map(|(health, _)| health).collect(),
+            metrics: self.sampler.sample(),
+        })
+    }
+}
+
+/// Probes a [`Cache`] with a write, read and remove of a sentinel key
+#[derive(Debug)]
+pub struct CacheProbe<C> {
+    name: String,
+    cache: Arc<C>,
+}
+
+impl<C> CacheProbe<C> {
+    /// Create a probe reporting as `name`
+    pub fn new(name: impl Into<String>, cache: Arc<C>) -> Self {
+        Self { name: name.into(), cache }
+    }
+}
+
+#[async_trait]
+impl<C: Cache<String, String>> HealthComponent for CacheProbe<C> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn component_health(&self) -> ComponentHealth {
+        let key = format!("__health_probe_{}", self.name);
+        let start = Instant::now();
+        let result = async {
+            self.cache.set(key.clone(), "ok".to_string(), Some(Duration::from_secs(60))).await?;
+            let value = self.cache.get(&key).await?;
+            self.cache.remove(&key).await?;
+            Ok::<_, crate::core::error::Error>(value)
+        }
+        .await;
+
+        let status = match result {
+            Ok(Some(value)) if value == "ok" => HealthStatus::Healthy,
+            Ok(_) => HealthStatus::Degraded(Some("probe value was not read back".to_string())),
+            Err(e) => HealthStatus::Unhealthy(Some(e.to_string())),
+        };
+        ComponentHealth {
+            name: self.name.clone(),
+            status,
+            metrics: Some(json!({ "latency_ms": start.elapsed().as_secs_f64() * 1000.0 })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Fixed(&'static str, HealthStatus);
+
+    #[async_trait]
+    impl HealthComponent for Fixed {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn component_health(&self) -> ComponentHealth {
+            ComponentHealth { name: self.0.to_string(), status: self.1.clone(), metrics: None }
+        }
+    }
+
+    #[derive(Debug)]
+    struct Hanging;
+
+    #[async_trait]
+    impl HealthComponent for Hanging {
+        fn name(&self) -> &str {
+            "hanging"
+        }
+
+        async fn component_health(&self) -> ComponentHealth {
+            futures::future::pending().await
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct MapCache(std::sync::Mutex<std::collections::HashMap<String, String>>);
+
+    #[async_trait]
+    impl Cache<String, String> for MapCache {
+        async fn get(&self, key: &String) -> Result<Option<String>> {
+            Ok(self.0.lock().unwrap().get(key).cloned())
+        }
+        async fn set(&self, key: String, value: String, _ttl: Option<Duration>) -> Result<()> {
+            self.0.lock().unwrap().insert(key, value);
+            Ok(())
+        }
+        async fn remove(&self, key: &String) -> Result<()> {
+            self.0.lock().unwrap().remove(key);
+            Ok(())
+        }
+        async fn clear(&self) -> Result<()> {
+            self.0.lock().unwrap().clear();
+            Ok(())
+        }
+    }
+
+    fn registry() -> HealthRegistry {
+        HealthRegistry::with_sampler(SystemSampler::new("/nonexistent"))
+    }
+
+    #[tokio::test]
+    async fn test_registry_aggregation() {
+        let registry = registry();
+        assert_eq!(registry.check_health().await.unwrap(), HealthStatus::Healthy);
+
+        registry.register(Arc::new(Fixed("rpc", HealthStatus::Healthy))).await;
+        registry
+            .register_optional(Arc::new(Fixed("cache", HealthStatus::Unhealthy(None))))
+            .await;
+        assert_eq!(
+            registry.check_health().await.unwrap(),
+            HealthStatus::Degraded(Some("degraded: cache".to_string()))
+        );
+
+        registry
+            .register(Arc::new(Fixed("postgres", HealthStatus::Unhealthy(None))))
+            .await;
+        let details = registry.get_health_details().await.unwrap();
+        assert_eq!(details.status, HealthStatus::Unhealthy(Some("unhealthy: postgres".to_string())));
+        let names: Vec<_> = details.components.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["rpc", "cache", "postgres"]);
+    }
+
+    #[tokio::test]
+    async fn test_registry_times_out_components() {
+        let registry = registry().with_timeout(Duration::from_millis(10));
+        registry.register(Arc::new(Hanging)).await;
+        let components = registry.components().await;
+        assert!(!components[0].status.is_available());
+    }
+
+    #[tokio::test]
+    async fn test_cache_probe() {
+        let probe = CacheProbe::new("cache", Arc::new(MapCache::default()));
+        let health = probe.component_health().await;
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert!(health.metrics.unwrap().get("latency_ms").is_some());
+    }
+}
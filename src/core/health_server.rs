@@ -0,0 +1,198 @@
+//! Embedded HTTP server for orchestrator liveness and readiness probes
+//!
+//! Enabled with the `health-server` feature. Serves:
+//!
+//! - `GET /livez`: always `200 OK` while the process is serving requests
+//! - `GET /readyz`: `200 OK` when the [`HealthCheck`] reports `Healthy` or
+//!   `Degraded`, `503 Service Unavailable` otherwise
+//! - `GET /health`: the JSON-serialized [`HealthDetails`](crate::core::traits::HealthDetails),
+//!   with the same status code as `/readyz`
+//!
+//! Readiness is driven entirely by the [`HealthCheck`] passed in, normally a
+//! [`HealthRegistry`](crate::core::health::HealthRegistry) with the RPC client,
+//! the database pool and a [`MigrationCheck`](crate::db::MigrationCheck) registered.
+
+use crate::core::error::{Error, Result};
+use crate::core::traits::HealthCheck;
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Configuration for the health server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthServerConfig {
+    /// Address the probe endpoints listen on
+    pub listen_addr: SocketAddr,
+}
+
+impl Default for HealthServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: SocketAddr::from(([0, 0, 0, 0], 8080)),
+        }
+    }
+}
+
+impl HealthServerConfig {
+    /// Create a new configuration listening on `listen_addr`
+    pub fn new(listen_addr: SocketAddr) -> Self {
+        Self { listen_addr }
+    }
+}
+
+/// Bind the health server without starting it.
+///
+/// Returns the bound address (useful with port 0) and a future serving
+/// requests, which must be spawned on a Tokio runtime.
+pub fn bind(
+    config: &HealthServerConfig,
+    check: Arc<dyn HealthCheck>,
+) -> Result<(SocketAddr, impl Future<Output = ()> + Send)> {
+    let make_svc = make_service_fn(move |_| {
+        let check = check.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let check = check.clone();
+                async move { Ok::<_, Infallible>(handle(check.as_ref(), req).await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&config.listen_addr)
+        .map_err(|e| Error::config(format!("Failed to bind health server on {}: {}", config.listen_addr, e)))?
+        .serve(make_svc);
+    let addr = server.local_addr();
+
+    let server = async move {
+        if let Err(e) = server.await {
+            tracing::error!("Health server stopped: {}", e);
+        }
+    };
+    Ok((addr, server))
+}
+
+/// Bind the health server and serve it in the background.
+///
+/// Must be called from within a Tokio runtime.
+pub fn spawn(config: &HealthServerConfig, check: Arc<dyn HealthCheck>) -> Result<SocketAddr> {
+    let (addr, server) = bind(config, check)?;
+    tokio::spawn(server);
+    tracing::info!("Serving health probes on http://{}", addr);
+    Ok(addr)
+}
+
+async fn handle(check: &dyn HealthCheck, req: Request<Body>) -> Response<Body> {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return text(StatusCode::METHOD_NOT_ALLOWED, "method not allowed\n".to_string());
+    }
+
+    match req.uri().path() {
+        "/livez" => text(StatusCode::OK, "ok\n".to_string()),
+        "/readyz" => match check.check_health().await {
+            Ok(status) if status.is_available() => text(StatusCode::OK, "ready\n".to_string()),
+            Ok(status) => text(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("not ready: {}\n", status.reason().unwrap_or("unhealthy")),
+            ),
+            Err(e) => text(StatusCode::SERVICE_UNAVAILABLE, format!("not ready: {}\n", e)),
+        },
+        "/health" => match check.get_health_details().await {
+            Ok(details) => {
+                let status = if details.status.is_available() {
+                    StatusCode::OK
+                } else {
+                    StatusCode::SERVICE_UNAVAILABLE
+                };
+                match serde_json::to_vec(&details) {
+                    Ok(body) => Response::builder()
+                        .status(status)
+                        .header(CONTENT_TYPE, "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                    Err(e) => text(StatusCode::INTERNAL_SERVER_ERROR, format!("{}\n", e)),
+                }
+            }
+            Err(e) => text(StatusCode::SERVICE_UNAVAILABLE, format!("{}\n", e)),
+        },
+        _ => text(StatusCode::NOT_FOUND, "not found\n".to_string()),
+    }
+}
+
+fn text(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::health::HealthRegistry;
+    use crate::core::system::SystemSampler;
+    use crate::core::traits::{ComponentHealth, HealthComponent, HealthDetails, HealthStatus};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    #[derive(Debug)]
+    struct Switch(&'static str, Mutex<HealthStatus>);
+
+    #[async_trait]
+    impl HealthComponent for Switch {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn component_health(&self) -> ComponentHealth {
+            ComponentHealth {
+                name: self.0.to_string(),
+                status: self.1.lock().unwrap().clone(),
+                metrics: None,
+            }
+        }
+    }
+
+    async fn start() -> (String, Arc<Switch>, Arc<Switch>) {
+        let registry = HealthRegistry::with_sampler(SystemSampler::new("/nonexistent"));
+        let rpc = Arc::new(Switch("rpc", Mutex::new(HealthStatus::Healthy)));
+        let migrations = Arc::new(Switch("migrations", Mutex::new(HealthStatus::Unhealthy(None))));
+        registry.register(rpc.clone()).await;
+        registry.register(migrations.clone()).await;
+
+        let config = HealthServerConfig::new(SocketAddr::from(([127, 0, 0, 1], 0)));
+        let addr = spawn(&config, Arc::new(registry)).unwrap();
+        (format!("http://{}", addr), rpc, migrations)
+    }
+
+    #[tokio::test]
+    async fn test_probe_endpoints() {
+        let (base, rpc, migrations) = start().await;
+
+        let livez = reqwest::get(format!("{}/livez", base)).await.unwrap();
+        assert_eq!(livez.status(), 200);
+
+        let readyz = reqwest::get(format!("{}/readyz", base)).await.unwrap();
+        assert_eq!(readyz.status(), 503);
+        assert!(readyz.text().await.unwrap().contains("migrations"));
+
+        *migrations.1.lock().unwrap() = HealthStatus::Healthy;
+        *rpc.1.lock().unwrap() = HealthStatus::Degraded(Some("1 of 2 endpoints failing".to_string()));
+        let readyz = reqwest::get(format!("{}/readyz", base)).await.unwrap();
+        assert_eq!(readyz.status(), 200);
+
+        let health = reqwest::get(format!("{}/health", base)).await.unwrap();
+        assert_eq!(health.status(), 200);
+        let details: HealthDetails = health.json().await.unwrap();
+        assert_eq!(details.status, HealthStatus::Degraded(Some("degraded: rpc".to_string())));
+        assert_eq!(details.components.len(), 2);
+
+        let missing = reqwest::get(format!("{}/metrics", base)).await.unwrap();
+        assert_eq!(missing.status(), 404);
+    }
+}
@@ -1,35 +1,868 @@
+//! Fetch → parse → persist pipeline
+//!
+//! [`CoreIndexer`] drives every configured [`IndexSource`] (address backfills,
+//! block ranges, live feeds) through a [`ParserRegistry`] into an
+//! [`IndexSink`], optionally completing parsed events with [`Enricher`]s on
+//! the way. Each stage runs as its own task and stages are connected by
+//! bounded channels, so a slow sink applies backpressure all the way back to
+//! the RPC fetchers.
+//!
+//! A source's checkpoint only advances once a batch has been persisted. On
+//! shutdown the sources stop fetching, batches already in flight are parsed
+//! and persisted, and their checkpoints committed before
+//! [`CoreIndexer::run`] returns.
+
 use crate::core::config::Config;
+use crate::core::error::{Error, Result};
+use crate::balances::balance_changes;
+use crate::core::metrics::{
+    BALANCE_INVARIANT_VIOLATIONS_TOTAL, INDEXER_BATCHES_TOTAL, INDEXER_ENRICH_ERRORS_TOTAL, INDEXER_ITEMS_TOTAL, INDEXER_PARSE_ERRORS_TOTAL, INDEXER_QUEUE_DEPTH,
+    INDEXER_SOURCE_ERRORS_TOTAL, INDEXER_STAGE_DURATION_SECONDS,
+};
+use crate::db::Database;
+use crate::fetcher::{
+    BlockFetcher, FetchTransactions, FetchedBlock, FetchedTransaction, MemoryCheckpointStore, SignatureCheckpointStore,
+    TransactionFetcher,
+};
+use crate::models::{Transaction, UsdValuation};
+use crate::parsers::{
+    decode_transaction_with, governance_records, swap_interactions, LookupTableCache, ParsedTransaction, ParserRegistry,
+    ProtocolParser,
+};
+use crate::rpc::client::{BlockClientTrait, RpcClientTrait};
 use async_trait::async_trait;
-use tracing::info;
+use metrics::{counter, gauge, histogram};
+use solana_transaction_status::EncodedTransactionWithStatusMeta;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+use tracing::{error, info, warn};
+
+/// A unit of work flowing through the pipeline
+#[derive(Debug)]
+pub enum IndexItem {
+    /// A transaction record without status meta, persisted without parsing
+    Transaction(Transaction),
+    /// A transaction fetched by signature with its status meta
+    Fetched(Box<FetchedTransaction>),
+    /// A block with all of its transactions
+    Block(FetchedBlock),
+}
+
+impl IndexItem {
+    /// Transaction records to persist for this item
+    pub fn transactions(&self) -> Vec<Transaction> {
+        match self {
+            IndexItem::Transaction(tx) => vec![tx.clone()],
+            IndexItem::Fetched(fetched) => {
+                Transaction::from_block_transaction(fetched.slot, fetched.block_time, &fetched.transaction)
+                    .into_iter()
+                    .collect()
+            }
+            IndexItem::Block(fetched) => fetched
+                .block
+                .transactions
+                .iter()
+                .filter_map(|tx| Transaction::from_block_transaction(fetched.slot, fetched.block.block_time, tx))
+                .collect(),
+        }
+    }
+
+    /// Decode the item and run it through `registry`.
+    ///
+    /// Transaction records without status meta are persisted without parsing.
+    /// v0 transactions whose status meta lacks their loaded addresses are
+    /// resolved through `lookup_tables`, and skipped without one.
+    pub async fn parse(
+        &self,
+        registry: &ParserRegistry,
+        lookup_tables: Option<&LookupTableCache>,
+    ) -> Vec<ParsedTransaction> {
+        match self {
+            IndexItem::Transaction(_) => Vec::new(),
+            IndexItem::Fetched(fetched) => {
+                parse_transaction(fetched.slot, fetched.block_time, &fetched.transaction, registry, lookup_tables)
+                    .await
+                    .into_iter()
+                    .collect()
+            }
+            IndexItem::Block(fetched) => {
+                let mut parsed = Vec::with_capacity(fetched.block.transactions.len());
+                for tx in &fetched.block.transactions {
+                    parsed.extend(parse_transaction(fetched.slot, fetched.block.block_time, tx, registry, lookup_tables).await);
+                }
+                parsed
+            }
+        }
+    }
+}
+
+/// Decode `tx`, fetched at `slot`, and run it through `registry`
+async fn parse_transaction(
+    slot: u64,
+    block_time: Option<i64>,
+    tx: &EncodedTransactionWithStatusMeta,
+    registry: &ParserRegistry,
+    lookup_tables: Option<&LookupTableCache>,
+) -> Option<ParsedTransaction> {
+    let loaded = match lookup_tables {
+        Some(tables) => match tables.resolve(tx).await {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                counter!(INDEXER_PARSE_ERRORS_TOTAL, 1);
+                tracing::debug!("Failed to resolve lookup tables in slot {}: {}", slot, e);
+                return None;
+            }
+        },
+        None => None,
+    };
+    match decode_transaction_with(slot, block_time, tx, loaded.as_ref()) {
+        Ok(decoded) => Some(registry.parse_transaction(&decoded)),
+        Err(e) => {
+            counter!(INDEXER_PARSE_ERRORS_TOTAL, 1);
+            tracing::debug!("Failed to decode a transaction in slot {}: {}", slot, e);
+            None
+        }
+    }
+}
+
+/// Position a source resumes from after a restart
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Checkpoint {
+    /// Last slot fully processed
+    Slot(u64),
+    /// Last signature fully processed
+    Signature(String),
+}
+
+/// A batch produced by an [`IndexSource`]
+#[derive(Debug, Default)]
+pub struct SourceBatch {
+    pub items: Vec<IndexItem>,
+    /// Checkpoint to commit once every item of this batch has been persisted
+    pub checkpoint: Option<Checkpoint>,
+}
+
+/// A producer of items for the pipeline
+#[async_trait]
+pub trait IndexSource: Send {
+    /// Name used in logs and metrics
+    fn name(&self) -> &str;
+
+    /// Fetch the next batch, or `None` once the source is exhausted.
+    ///
+    /// May wait for new data (e.g. at the chain tip). The pending call is
+    /// dropped on shutdown, so it must not commit anything itself.
+    async fn next_batch(&mut self) -> Result<Option<SourceBatch>>;
+
+    /// Durably record that everything up to `checkpoint` has been persisted
+    async fn commit(&mut self, checkpoint: Checkpoint) -> Result<()>;
+}
+
+/// A batch after the parse stage
+#[derive(Debug, Default)]
+pub struct ParsedBatch {
+    pub items: Vec<IndexItem>,
+    /// Parser output for the transactions of `items`
+    pub transactions: Vec<ParsedTransaction>,
+    /// USD values of the amounts moved by `transactions`, filled in by a
+    /// [`UsdValuer`](crate::prices::UsdValuer)
+    pub valuations: Vec<UsdValuation>,
+}
+
+/// Completes parsed events with data the transactions do not carry, such as
+/// account state fetched over RPC.
+///
+/// Enrichers run in order after parsing. A failing enricher is logged and
+/// skipped, so the batch is persisted with whatever it did fill in.
+#[async_trait]
+pub trait Enricher: Send + Sync {
+    /// Name recorded on logs and metrics
+    fn name(&self) -> &str;
+
+    async fn enrich(&self, batch: &mut ParsedBatch) -> Result<()>;
+}
+
+/// Destination of parsed batches
+#[async_trait]
+pub trait IndexSink: Send + Sync {
+    async fn persist(&self, batch: &ParsedBatch) -> Result<()>;
+}
+
+#[async_trait]
+impl IndexSink for Database {
+    async fn persist(&self, batch: &ParsedBatch) -> Result<()> {
+        let transactions: Vec<Transaction> = batch.items.iter().flat_map(IndexItem::transactions).collect();
+        self.insert_transactions(&transactions)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to persist transactions: {}", e)))?;
+
+        let interactions: Vec<_> = batch.transactions.iter().flat_map(swap_interactions).collect();
+        if !interactions.is_empty() {
+            self.insert_protocol_interactions(&interactions)
+                .await
+                .map_err(|e| Error::pipeline(format!("Failed to persist protocol interactions: {}", e)))?;
+        }
+
+        let (votes, events): (Vec<_>, Vec<_>) = batch.transactions.iter().map(governance_records).unzip();
+        let (votes, events): (Vec<_>, Vec<_>) = (votes.concat(), events.concat());
+        if !events.is_empty() {
+            self.insert_governance_records(&votes, &events)
+                .await
+                .map_err(|e| Error::pipeline(format!("Failed to persist governance records: {}", e)))?;
+        }
+
+        let mut changes = Vec::new();
+        for transaction in &batch.transactions {
+            let balances = balance_changes(transaction);
+            for violation in &balances.violations {
+                counter!(BALANCE_INVARIANT_VIOLATIONS_TOTAL, 1, "kind" => violation.kind());
+                warn!("Balance changes of {} do not add up: {}", transaction.context.signature, violation);
+            }
+            changes.extend(balances.changes);
+        }
+        if !changes.is_empty() {
+            self.insert_balance_changes(&changes)
+                .await
+                .map_err(|e| Error::pipeline(format!("Failed to persist balance changes: {}", e)))?;
+        }
+
+        if !batch.valuations.is_empty() {
+            self.insert_usd_valuations(&batch.valuations)
+                .await
+                .map_err(|e| Error::pipeline(format!("Failed to persist USD valuations: {}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: BlockClientTrait + 'static> IndexSource for BlockFetcher<C> {
+    fn name(&self) -> &str {
+        "blocks"
+    }
+
+    async fn next_batch(&mut self) -> Result<Option<SourceBatch>> {
+        if self.is_done() {
+            return Ok(None);
+        }
+        let start = self.next_slot();
+        let blocks = BlockFetcher::next_batch(self)
+            .await
+            .map_err(|e| Error::pipeline(e.to_string()))?;
+        if self.next_slot() == start {
+            // Caught up with the tip
+            tokio::time::sleep(self.config().poll_interval).await;
+            return Ok(Some(SourceBatch::default()));
+        }
+        Ok(Some(SourceBatch {
+            items: blocks.into_iter().map(IndexItem::Block).collect(),
+            checkpoint: Some(Checkpoint::Slot(self.next_slot() - 1)),
+        }))
+    }
+
+    async fn commit(&mut self, checkpoint: Checkpoint) -> Result<()> {
+        match checkpoint {
+            Checkpoint::Slot(slot) => self.commit_slot(slot).await.map_err(|e| Error::pipeline(e.to_string())),
+            other => Err(Error::pipeline(format!("Unexpected block checkpoint {:?}", other))),
+        }
+    }
+}
+
+/// Backfills the transaction history of one address, newest first
+pub struct AddressSource<C: RpcClientTrait> {
+    name: String,
+    fetcher: TransactionFetcher<C>,
+    store: Box<dyn SignatureCheckpointStore>,
+}
+
+impl<C: RpcClientTrait> AddressSource<C> {
+    /// Wrap `fetcher` with an in-memory checkpoint
+    pub fn new(fetcher: TransactionFetcher<C>) -> Self {
+        Self {
+            name: format!("address:{}", fetcher.address),
+            fetcher,
+            store: Box::new(MemoryCheckpointStore::default()),
+        }
+    }
+
+    /// Use `store` for checkpoints, resuming before the signature it holds
+    pub async fn with_checkpoint_store(mut self, store: impl SignatureCheckpointStore + 'static) -> Result<Self> {
+        if let Some(signature) = store.load_signature().await.map_err(|e| Error::pipeline(e.to_string()))? {
+            info!("Resuming {} before signature {}", self.name, signature);
+            self.fetcher.set_checkpoint(Some(signature));
+        }
+        self.store = Box::new(store);
+        Ok(self)
+    }
+}
+
+#[async_trait]
+impl<C: RpcClientTrait> IndexSource for AddressSource<C> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn next_batch(&mut self) -> Result<Option<SourceBatch>> {
+        let before = self.fetcher.get_checkpoint();
+        let transactions = self
+            .fetcher
+            .fetch_next_encoded_batch()
+            .await
+            .map_err(|e| Error::pipeline(e.to_string()))?;
+        let checkpoint = self.fetcher.get_checkpoint();
+        if transactions.is_empty() && checkpoint == before {
+            return Ok(None);
+        }
+        Ok(Some(SourceBatch {
+            items: transactions.into_iter().map(|tx| IndexItem::Fetched(Box::new(tx))).collect(),
+            checkpoint: checkpoint.map(Checkpoint::Signature),
+        }))
+    }
+
+    async fn commit(&mut self, checkpoint: Checkpoint) -> Result<()> {
+        match checkpoint {
+            Checkpoint::Signature(signature) => self
+                .store
+                .save_signature(&signature)
+                .await
+                .map_err(|e| Error::pipeline(e.to_string())),
+            other => Err(Error::pipeline(format!("Unexpected address checkpoint {:?}", other))),
+        }
+    }
+}
+
+/// Feeds items pushed by a live subscription (e.g. a websocket
+/// `blockSubscribe` task) into the pipeline.
+///
+/// Live feeds have no checkpoint; the source ends when every sender is dropped.
+pub struct ChannelSource {
+    name: String,
+    receiver: mpsc::Receiver<IndexItem>,
+    max_batch: usize,
+}
+
+impl ChannelSource {
+    /// Create a source buffering up to `capacity` items, returning the sender
+    /// the subscription pushes into
+    pub fn new(name: impl Into<String>, capacity: usize) -> (Self, mpsc::Sender<IndexItem>) {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        let source = Self {
+            name: name.into(),
+            receiver,
+            max_batch: capacity.max(1),
+        };
+        (source, sender)
+    }
+}
 
-/// Trait for protocol-specific parsers
 #[async_trait]
-pub trait ProtocolParser: Send + Sync {
-    async fn parse(&self, data: &[u8]) -> anyhow::Result<()>;
+impl IndexSource for ChannelSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn next_batch(&mut self) -> Result<Option<SourceBatch>> {
+        let Some(first) = self.receiver.recv().await else {
+            return Ok(None);
+        };
+        let mut items = vec![first];
+        while items.len() < self.max_batch {
+            match self.receiver.try_recv() {
+                Ok(item) => items.push(item),
+                Err(_) => break,
+            }
+        }
+        Ok(Some(SourceBatch { items, checkpoint: None }))
+    }
+
+    async fn commit(&mut self, _checkpoint: Checkpoint) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Tuning for the stages of a [`CoreIndexer`]
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    /// Batches buffered between consecutive stages
+    pub channel_capacity: usize,
+    /// Delay before retrying a source or the sink after a failure
+    pub retry_delay: Duration,
+    /// Attempts to persist a batch before the pipeline stops
+    pub persist_attempts: u32,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            channel_capacity: 16,
+            retry_delay: Duration::from_secs(1),
+            persist_attempts: 3,
+        }
+    }
+}
+
+/// A batch in flight, tagged with the source it came from
+struct Envelope {
+    source: usize,
+    batch: ParsedBatch,
+    checkpoint: Option<Checkpoint>,
 }
 
 /// Core indexer struct
 pub struct CoreIndexer {
     pub config: Config,
+    pub pipeline: PipelineConfig,
+    sources: Vec<Box<dyn IndexSource>>,
+    registry: ParserRegistry,
+    lookup_tables: Option<Arc<LookupTableCache>>,
+    enrichers: Vec<Arc<dyn Enricher>>,
+    sink: Option<Arc<dyn IndexSink>>,
 }
 
 impl CoreIndexer {
     pub fn new(config: Config) -> Self {
         info!("Initializing CoreIndexer");
-        Self { config }
+        Self {
+            config,
+            pipeline: PipelineConfig::default(),
+            sources: Vec::new(),
+            registry: ParserRegistry::new(),
+            lookup_tables: None,
+            enrichers: Vec::new(),
+            sink: None,
+        }
     }
 
-    // Placeholder for starting the indexer
-    pub async fn run(&self) -> anyhow::Result<()> {
-        info!("Running CoreIndexer");
-        Ok(())
+    /// Set the stage tuning
+    pub fn with_pipeline_config(mut self, pipeline: PipelineConfig) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    /// Add a source of items
+    pub fn with_source(mut self, source: impl IndexSource + 'static) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    /// Register a parser for the programs it lists
+    pub fn with_parser(mut self, parser: Arc<dyn ProtocolParser>) -> Self {
+        self.registry.register(parser);
+        self
     }
+
+    /// Replace the parser registry
+    pub fn with_registry(mut self, registry: ParserRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Resolve lookup tables of v0 transactions whose status meta does not
+    /// list their loaded addresses
+    pub fn with_lookup_tables(mut self, lookup_tables: Arc<LookupTableCache>) -> Self {
+        self.lookup_tables = Some(lookup_tables);
+        self
+    }
+
+    /// Add an enricher, run after the ones already added
+    pub fn with_enricher(mut self, enricher: Arc<dyn Enricher>) -> Self {
+        self.enrichers.push(enricher);
+        self
+    }
+
+    /// Set where parsed batches are persisted
+    pub fn with_sink(mut self, sink: Arc<dyn IndexSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Run the pipeline until every source is exhausted or SIGINT/SIGTERM is received
+    pub async fn run(self) -> Result<()> {
+        self.run_until(shutdown_signal()).await
+    }
+
+    /// Run the pipeline until every source is exhausted or `shutdown` completes.
+    ///
+    /// Batches already handed to the pipeline when `shutdown` completes are
+    /// still persisted and their checkpoints committed.
+    pub async fn run_until(self, shutdown: impl Future<Output = ()> + Send) -> Result<()> {
+        if self.sources.is_empty() {
+            info!("CoreIndexer has no sources configured");
+            return Ok(());
+        }
+        let sink = self
+            .sink
+            .ok_or_else(|| Error::config("CoreIndexer has sources but no sink"))?;
+        let capacity = self.pipeline.channel_capacity.max(1);
+        let (stop, _) = watch::channel(false);
+        let stop = Arc::new(stop);
+        let (parse_tx, parse_rx) = mpsc::channel(capacity);
+        let (persist_tx, persist_rx) = mpsc::channel(capacity);
+
+        info!("Running CoreIndexer with {} sources", self.sources.len());
+        let mut acks = Vec::with_capacity(self.sources.len());
+        let mut source_tasks = Vec::with_capacity(self.sources.len());
+        for (id, source) in self.sources.into_iter().enumerate() {
+            let (ack_tx, ack_rx) = mpsc::unbounded_channel();
+            acks.push(ack_tx);
+            source_tasks.push(tokio::spawn(run_source(
+                id,
+                source,
+                parse_tx.clone(),
+                ack_rx,
+                stop.clone(),
+                self.pipeline.retry_delay,
+            )));
+        }
+        drop(parse_tx);
+        let parse_task = tokio::spawn(run_parse_stage(
+            parse_rx,
+            persist_tx,
+            Arc::new(self.registry),
+            self.lookup_tables,
+            self.enrichers,
+        ));
+        let persist_task = tokio::spawn(run_persist_stage(persist_rx, sink, acks, self.pipeline.clone(), stop.clone()));
+
+        let sources = futures::future::join_all(source_tasks);
+        tokio::pin!(sources, shutdown);
+        let source_results = tokio::select! {
+            results = &mut sources => results,
+            _ = &mut shutdown => {
+                info!("Shutdown requested, flushing in-flight batches");
+                stop.send_replace(true);
+                sources.await
+            }
+        };
+
+        let mut result = Ok(());
+        for outcome in source_results {
+            match outcome {
+                Ok(Err(e)) if result.is_ok() => result = Err(e),
+                Err(e) if result.is_ok() => result = Err(Error::pipeline(e.to_string())),
+                _ => {}
+            }
+        }
+        if let Err(e) = parse_task.await {
+            return Err(Error::pipeline(e.to_string()));
+        }
+        let persisted = match persist_task.await {
+            Ok(persisted) => persisted,
+            Err(e) => Err(Error::pipeline(e.to_string())),
+        };
+        info!("CoreIndexer stopped");
+        persisted.and(result)
+    }
+}
+
+/// Resolve when the process receives SIGINT or, on Unix, SIGTERM
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => info!("Received SIGINT"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
+
+async fn run_source(
+    id: usize,
+    mut source: Box<dyn IndexSource>,
+    parse_tx: mpsc::Sender<Envelope>,
+    mut acks: mpsc::UnboundedReceiver<Checkpoint>,
+    stop: Arc<watch::Sender<bool>>,
+    retry_delay: Duration,
+) -> Result<()> {
+    let name = source.name().to_string();
+    let mut stopped = stop.subscribe();
+    // Batches with a checkpoint handed downstream and not yet persisted
+    let mut pending = 0usize;
+
+    while !*stopped.borrow() {
+        while let Ok(checkpoint) = acks.try_recv() {
+            pending -= 1;
+            commit(&mut source, checkpoint, &stop).await?;
+        }
+
+        let start = Instant::now();
+        let batch = tokio::select! {
+            _ = stopped.changed() => break,
+            batch = source.next_batch() => batch,
+        };
+        match batch {
+            Ok(None) => {
+                info!("Source {} is exhausted", name);
+                break;
+            }
+            Ok(Some(batch)) if batch.items.is_empty() && batch.checkpoint.is_none() => {}
+            Ok(Some(batch)) => {
+                histogram!(INDEXER_STAGE_DURATION_SECONDS, start.elapsed().as_secs_f64(), "stage" => "fetch");
+                counter!(INDEXER_BATCHES_TOTAL, 1, "stage" => "fetch");
+                counter!(INDEXER_ITEMS_TOTAL, batch.items.len() as u64, "stage" => "fetch");
+                if batch.checkpoint.is_some() {
+                    pending += 1;
+                }
+                let envelope = Envelope {
+                    source: id,
+                    batch: ParsedBatch {
+                        items: batch.items,
+                        transactions: Vec::new(),
+                        valuations: Vec::new(),
+                    },
+                    checkpoint: batch.checkpoint,
+                };
+                if parse_tx.send(envelope).await.is_err() {
+                    break;
+                }
+                gauge!(INDEXER_QUEUE_DEPTH, (parse_tx.max_capacity() - parse_tx.capacity()) as f64, "stage" => "parse");
+            }
+            Err(e) => {
+                counter!(INDEXER_SOURCE_ERRORS_TOTAL, 1, "source" => name.clone());
+                warn!("Source {} failed, retrying: {}", name, e);
+                tokio::select! {
+                    _ = stopped.changed() => break,
+                    _ = tokio::time::sleep(retry_delay) => {}
+                }
+            }
+        }
+    }
+
+    // Wait for the batches already handed downstream; the channel closes early
+    // if the sink gave up
+    drop(parse_tx);
+    while pending > 0 {
+        match acks.recv().await {
+            Some(checkpoint) => {
+                pending -= 1;
+                commit(&mut source, checkpoint, &stop).await?;
+            }
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+async fn commit(source: &mut Box<dyn IndexSource>, checkpoint: Checkpoint, stop: &watch::Sender<bool>) -> Result<()> {
+    if let Err(e) = source.commit(checkpoint).await {
+        error!("Failed to commit checkpoint for {}: {}", source.name(), e);
+        stop.send_replace(true);
+        return Err(e);
+    }
+    Ok(())
+}
+
+async fn run_parse_stage(
+    mut parse_rx: mpsc::Receiver<Envelope>,
+    persist_tx: mpsc::Sender<Envelope>,
+    registry: Arc<ParserRegistry>,
+    lookup_tables: Option<Arc<LookupTableCache>>,
+    enrichers: Vec<Arc<dyn Enricher>>,
+) {
+    while let Some(mut envelope) = parse_rx.recv().await {
+        let start = Instant::now();
+        let mut transactions = Vec::new();
+        for item in &envelope.batch.items {
+            transactions.extend(item.parse(&registry, lookup_tables.as_deref()).await);
+        }
+        envelope.batch.transactions = transactions;
+        histogram!(INDEXER_STAGE_DURATION_SECONDS, start.elapsed().as_secs_f64(), "stage" => "parse");
+        counter!(INDEXER_BATCHES_TOTAL, 1, "stage" => "parse");
+        counter!(INDEXER_ITEMS_TOTAL, envelope.batch.items.len() as u64, "stage" => "parse");
+
+        if !enrichers.is_empty() && !envelope.batch.transactions.is_empty() {
+            let start = Instant::now();
+            for enricher in &enrichers {
+                if let Err(e) = enricher.enrich(&mut envelope.batch).await {
+                    counter!(INDEXER_ENRICH_ERRORS_TOTAL, 1, "enricher" => enricher.name().to_string());
+                    warn!("Enricher {} failed, persisting batch without it: {}", enricher.name(), e);
+                }
+            }
+            histogram!(INDEXER_STAGE_DURATION_SECONDS, start.elapsed().as_secs_f64(), "stage" => "enrich");
+        }
+        if persist_tx.send(envelope).await.is_err() {
+            break;
+        }
+        gauge!(INDEXER_QUEUE_DEPTH, (persist_tx.max_capacity() - persist_tx.capacity()) as f64, "stage" => "persist");
+    }
+}
+
+async fn run_persist_stage(
+    mut persist_rx: mpsc::Receiver<Envelope>,
+    sink: Arc<dyn IndexSink>,
+    acks: Vec<mpsc::UnboundedSender<Checkpoint>>,
+    config: PipelineConfig,
+    stop: Arc<watch::Sender<bool>>,
+) -> Result<()> {
+    while let Some(envelope) = persist_rx.recv().await {
+        let start = Instant::now();
+        let mut attempt = 1;
+        while !envelope.batch.items.is_empty() {
+            match sink.persist(&envelope.batch).await {
+                Ok(()) => break,
+                Err(e) if attempt < config.persist_attempts => {
+                    warn!("Persisting batch failed (attempt {}), retrying: {}", attempt, e);
+                    attempt += 1;
+                    tokio::time::sleep(config.retry_delay).await;
+                }
+                Err(e) => {
+                    error!("Persisting batch failed after {} attempts, stopping: {}", attempt, e);
+                    stop.send_replace(true);
+                    return Err(e);
+                }
+            }
+        }
+        histogram!(INDEXER_STAGE_DURATION_SECONDS, start.elapsed().as_secs_f64(), "stage" => "persist");
+        counter!(INDEXER_BATCHES_TOTAL, 1, "stage" => "persist");
+        counter!(INDEXER_ITEMS_TOTAL, envelope.batch.items.len() as u64, "stage" => "persist");
+        if let Some(checkpoint) = envelope.checkpoint {
+            // The source may already have stopped after a failed commit
+            let _ = acks[envelope.source].send(checkpoint);
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::core::config::Config;
+    use crate::fetcher::{BlockFetcherConfig, FileCheckpointStore, SlotCheckpointStore};
+    use crate::parsers::{DecodedInstruction, ParseResult, ProtocolEvent, TransactionContext};
+    use crate::rpc::client::{MockBlockClientTrait, MockRpcClientTrait};
+    use serde_json::json;
+    use solana_sdk::pubkey::Pubkey;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemorySink {
+        signatures: Mutex<Vec<String>>,
+        events: Mutex<usize>,
+        delay: Duration,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl IndexSink for MemorySink {
+        async fn persist(&self, batch: &ParsedBatch) -> Result<()> {
+            tokio::time::sleep(self.delay).await;
+            if self.fail {
+                return Err(Error::pipeline("database unavailable"));
+            }
+            let mut signatures = self.signatures.lock().unwrap();
+            signatures.extend(batch.items.iter().flat_map(IndexItem::transactions).map(|tx| tx.signature));
+            *self.events.lock().unwrap() += batch.transactions.iter().map(|tx| tx.events.len()).sum::<usize>();
+            Ok(())
+        }
+    }
+
+    /// Emits one event per system program instruction
+    struct SystemCounter;
+
+    impl ProtocolParser for SystemCounter {
+        fn name(&self) -> &str {
+            "system-counter"
+        }
+
+        fn program_ids(&self) -> Vec<Pubkey> {
+            vec![solana_sdk::system_program::id()]
+        }
+
+        fn parse(&self, _instruction: &DecodedInstruction, context: &TransactionContext) -> ParseResult<Vec<ProtocolEvent>> {
+            Ok(vec![ProtocolEvent::Custom {
+                kind: "system".to_string(),
+                data: json!(context.slot),
+            }])
+        }
+    }
+
+    /// Produces one single-transaction batch per call, forever
+    struct CountingSource {
+        produced: Arc<Mutex<u64>>,
+        committed: Arc<Mutex<Vec<Checkpoint>>>,
+    }
+
+    #[async_trait]
+    impl IndexSource for CountingSource {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn next_batch(&mut self) -> Result<Option<SourceBatch>> {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            let mut produced = self.produced.lock().unwrap();
+            let slot = *produced;
+            *produced += 1;
+            let tx = Transaction {
+                signature: format!("sig{}", slot),
+                instructions_json: "[]".to_string(),
+                ..Default::default()
+            };
+            Ok(Some(SourceBatch {
+                items: vec![IndexItem::Transaction(tx)],
+                checkpoint: Some(Checkpoint::Slot(slot)),
+            }))
+        }
+
+        async fn commit(&mut self, checkpoint: Checkpoint) -> Result<()> {
+            self.committed.lock().unwrap().push(checkpoint);
+            Ok(())
+        }
+    }
+
+    /// A transaction invoking the system program once
+    fn system_transaction(signature: &str) -> serde_json::Value {
+        json!({
+            "transaction": {
+                "signatures": [signature],
+                "message": {
+                    "header": {
+                        "numRequiredSignatures": 1,
+                        "numReadonlySignedAccounts": 0,
+                        "numReadonlyUnsignedAccounts": 1,
+                    },
+                    "accountKeys": ["11111111111111111111111111111112", "11111111111111111111111111111111"],
+                    "recentBlockhash": "11111111111111111111111111111111",
+                    "instructions": [{ "programIdIndex": 1, "accounts": [0], "data": "" }],
+                },
+            },
+            "meta": null,
+        })
+    }
+
+    fn block_with_transaction(slot: u64) -> solana_transaction_status::EncodedConfirmedBlock {
+        serde_json::from_value(json!({
+            "previousBlockhash": "11111111111111111111111111111111",
+            "blockhash": "11111111111111111111111111111111",
+            "parentSlot": slot.saturating_sub(1),
+            "transactions": [system_transaction(&format!("sig{}", slot))],
+            "rewards": [],
+            "blockTime": 1_700_000_000,
+            "blockHeight": slot,
+        }))
+        .unwrap()
+    }
 
     #[tokio::test]
     async fn test_core_indexer_instantiation() {
@@ -37,4 +870,184 @@ mod tests {
         let indexer = CoreIndexer::new(config);
         assert!(indexer.run().await.is_ok());
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_sources_require_sink() {
+        let (source, _sender) = ChannelSource::new("live", 4);
+        let indexer = CoreIndexer::new(Config::default()).with_source(source);
+        assert!(matches!(indexer.run().await, Err(Error::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn test_block_range_pipeline_resumes_from_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.checkpoint");
+
+        let run = |start: u64, end: u64| {
+            let path = path.clone();
+            async move {
+                let mut mock = MockBlockClientTrait::new();
+                mock.expect_get_blocks()
+                    .returning(|start, end| Ok((start..=end.unwrap()).collect()));
+                mock.expect_get_block().returning(|slot| Ok(block_with_transaction(slot)));
+                let config = BlockFetcherConfig {
+                    slots_per_batch: 2,
+                    ..BlockFetcherConfig::range(start, end)
+                };
+                let fetcher = BlockFetcher::new(mock, config)
+                    .with_checkpoint_store(FileCheckpointStore::new(path))
+                    .await
+                    .unwrap();
+
+                let sink = Arc::new(MemorySink::default());
+                CoreIndexer::new(Config::default())
+                    .with_source(fetcher)
+                    .with_parser(Arc::new(SystemCounter))
+                    .with_sink(sink.clone())
+                    .run_until(std::future::pending())
+                    .await
+                    .unwrap();
+                let events = *sink.events.lock().unwrap();
+                let persisted = sink.signatures.lock().unwrap().clone();
+                (events, persisted)
+            }
+        };
+
+        let (events, persisted) = run(10, 14).await;
+        assert_eq!(events, 5);
+        assert_eq!(persisted, vec!["sig10", "sig11", "sig12", "sig13", "sig14"]);
+        assert_eq!(SlotCheckpointStore::load(&FileCheckpointStore::new(&path)).await.unwrap(), Some(14));
+
+        // A second run over a wider range only indexes the new slots
+        let (_, persisted) = run(10, 16).await;
+        assert_eq!(persisted, vec!["sig15", "sig16"]);
+    }
+
+    #[tokio::test]
+    async fn test_address_source_parses_fetched_transactions() {
+        let mut mock = MockRpcClientTrait::new();
+        mock.expect_get_signatures_for_address()
+            .withf(|_, before, _| before.is_none())
+            .returning(|_, _, _| Ok(vec!["sigA".to_string(), "sigB".to_string()]));
+        mock.expect_get_signatures_for_address()
+            .returning(|_, _, _| Ok(Vec::new()));
+        mock.expect_get_encoded_transaction().times(2).returning(|signature| {
+            let mut confirmed = system_transaction(signature);
+            confirmed["slot"] = json!(42);
+            confirmed["blockTime"] = json!(1_700_000_000);
+            confirmed["meta"] = json!({
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5000,
+                "preBalances": [1_000_000, 1],
+                "postBalances": [995_000, 1],
+                "innerInstructions": [],
+                "logMessages": [],
+                "preTokenBalances": [],
+                "postTokenBalances": [],
+                "rewards": [],
+            });
+            Ok(serde_json::from_value(confirmed).unwrap())
+        });
+        mock.expect_get_transaction().never();
+        let fetcher = TransactionFetcher {
+            rpc_client: mock,
+            address: Pubkey::new_unique(),
+            batch_size: 2,
+            checkpoint: None,
+            health: None,
+        };
+
+        let sink = Arc::new(MemorySink::default());
+        CoreIndexer::new(Config::default())
+            .with_source(AddressSource::new(fetcher))
+            .with_parser(Arc::new(SystemCounter))
+            .with_sink(sink.clone())
+            .run_until(std::future::pending())
+            .await
+            .unwrap();
+        assert_eq!(*sink.signatures.lock().unwrap(), vec!["sigA", "sigB"]);
+        assert_eq!(*sink.events.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_flushes_in_flight_batches() {
+        let produced = Arc::new(Mutex::new(0));
+        let committed = Arc::new(Mutex::new(Vec::new()));
+        let source = CountingSource {
+            produced: produced.clone(),
+            committed: committed.clone(),
+        };
+        let sink = Arc::new(MemorySink {
+            delay: Duration::from_millis(5),
+            ..Default::default()
+        });
+        let config = PipelineConfig {
+            channel_capacity: 2,
+            ..Default::default()
+        };
+
+        CoreIndexer::new(Config::default())
+            .with_pipeline_config(config)
+            .with_source(source)
+            .with_sink(sink.clone())
+            .run_until(tokio::time::sleep(Duration::from_millis(50)))
+            .await
+            .unwrap();
+
+        // Every batch handed to the pipeline was persisted and committed in order
+        let produced = *produced.lock().unwrap();
+        let persisted = sink.signatures.lock().unwrap().len() as u64;
+        let committed = committed.lock().unwrap().clone();
+        assert!(persisted > 0);
+        assert_eq!(persisted, produced);
+        assert_eq!(committed, (0..produced).map(Checkpoint::Slot).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_persist_failure_stops_without_committing() {
+        let committed = Arc::new(Mutex::new(Vec::new()));
+        let source = CountingSource {
+            produced: Arc::new(Mutex::new(0)),
+            committed: committed.clone(),
+        };
+        let sink = Arc::new(MemorySink {
+            fail: true,
+            ..Default::default()
+        });
+        let config = PipelineConfig {
+            retry_delay: Duration::from_millis(1),
+            persist_attempts: 2,
+            ..Default::default()
+        };
+
+        let result = CoreIndexer::new(Config::default())
+            .with_pipeline_config(config)
+            .with_source(source)
+            .with_sink(sink)
+            .run_until(std::future::pending())
+            .await;
+        assert!(matches!(result, Err(Error::Pipeline(_))));
+        assert!(committed.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_channel_source_batches_until_closed() {
+        let (mut source, sender) = ChannelSource::new("live", 2);
+        for slot in 0..3 {
+            let tx = Transaction {
+                signature: format!("sig{}", slot),
+                ..Default::default()
+            };
+            if slot == 2 {
+                let batch = source.next_batch().await.unwrap().unwrap();
+                assert_eq!(batch.items.len(), 2);
+                assert!(batch.checkpoint.is_none());
+            }
+            sender.send(IndexItem::Transaction(tx)).await.unwrap();
+        }
+        drop(sender);
+        assert_eq!(source.next_batch().await.unwrap().unwrap().items.len(), 1);
+        assert!(source.next_batch().await.unwrap().is_none());
+    }
+}
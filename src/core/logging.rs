@@ -7,6 +7,11 @@ use tracing_subscriber::{
 };
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 
+#[cfg(feature = "otel")]
+use crate::core::otel::{OtelConfig, OtelGuard};
+#[cfg(feature = "otel")]
+use std::sync::Mutex;
+
 /// Configuration for logging
 #[derive(Debug, Clone)]
 pub struct LogConfig {
@@ -15,12 +20,19 @@ pub struct LogConfig {
     
     /// Whether to use JSON format
     pub json: bool,
+
+    /// Whether to write to stdout
+    pub console: bool,
     
     /// Log file path
     pub file_path: Option<PathBuf>,
     
     /// Log file rotation
     pub rotation: Rotation,
+
+    /// OpenTelemetry trace export
+    #[cfg(feature = "otel")]
+    pub otel: Option<OtelConfig>,
 }
 
 impl Default for LogConfig {
@@ -28,14 +40,19 @@ impl Default for LogConfig {
         Self {
             level: Level::INFO,
             json: false,
+            console: true,
             file_path: None,
             rotation: Rotation::DAILY,
+            #[cfg(feature = "otel")]
+            otel: None,
         }
     }
 }
 
 impl LogConfig {
-    /// Create a new logging configuration
+    /// Create a new logging configuration.
+    ///
+    /// Logs go to stdout unless a file path is given.
     pub fn new(
         level: Level,
         json: bool,
@@ -45,71 +62,107 @@ impl LogConfig {
         Self {
             level,
             json,
+            console: file_path.is_none(),
             file_path,
             rotation,
+            #[cfg(feature = "otel")]
+            otel: None,
         }
     }
 }
 
 static INIT: Once = Once::new();
 
-/// Initialize logging with the given configuration
+#[cfg(feature = "otel")]
+static OTEL_GUARD: Mutex<Option<OtelGuard>> = Mutex::new(None);
+
+/// Initialize logging with the given configuration.
+///
+/// Console (plain or JSON), file and OpenTelemetry outputs can be combined.
+/// With the `otel` feature and [`LogConfig::otel`] set, this must be called
+/// from within a Tokio runtime; call [`shutdown`] before exit to flush spans.
+/// If the exporter cannot be built, the other outputs are still installed and
+/// the error is logged as a warning through them.
 pub fn init(config: LogConfig) {
     INIT.call_once(|| {
         let env_filter = EnvFilter::try_from_default_env()
             .unwrap_or_else(|_| EnvFilter::new(format!("{}", config.level)));
 
-        if let Some(file_path) = config.file_path {
+        let console_layer = (config.console && !config.json).then(|| {
+            tracing_subscriber::fmt::layer()
+                .with_timer(UtcTime::rfc_3339())
+                .with_span_events(FmtSpan::CLOSE)
+                .with_target(true)
+                .with_thread_ids(true)
+                .with_file(true)
+                .with_line_number(true)
+        });
+
+        let json_layer = (config.console && config.json).then(|| {
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_timer(UtcTime::rfc_3339())
+                .with_span_events(FmtSpan::CLOSE)
+                .with_target(true)
+                .with_thread_ids(true)
+                .with_file(true)
+                .with_line_number(true)
+        });
+
+        let file_layer = config.file_path.as_ref().map(|file_path| {
             let file_appender = RollingFileAppender::new(
-                config.rotation,
+                config.rotation.clone(),
                 file_path.parent().unwrap_or(&PathBuf::from(".")),
                 file_path.file_name().unwrap().to_str().unwrap(),
             );
 
-            let file_layer = tracing_subscriber::fmt::layer()
+            tracing_subscriber::fmt::layer()
                 .with_writer(file_appender)
                 .with_timer(UtcTime::rfc_3339())
                 .with_span_events(FmtSpan::CLOSE)
                 .with_target(true)
                 .with_thread_ids(true)
                 .with_file(true)
-                .with_line_number(true);
-
-            tracing_subscriber::registry()
-                .with(env_filter)
-                .with(file_layer)
-                .init();
-        } else if config.json {
-            tracing_subscriber::registry()
-                .with(env_filter)
-                .with(
-                    tracing_subscriber::fmt::layer()
-                        .json()
-                        .with_timer(UtcTime::rfc_3339())
-                        .with_span_events(FmtSpan::CLOSE)
-                        .with_target(true)
-                        .with_thread_ids(true)
-                        .with_file(true)
-                        .with_line_number(true),
-                )
-                .init();
-        } else {
-            tracing_subscriber::registry()
-                .with(env_filter)
-                .with(
-                    tracing_subscriber::fmt::layer()
-                        .with_timer(UtcTime::rfc_3339())
-                        .with_span_events(FmtSpan::CLOSE)
-                        .with_target(true)
-                        .with_thread_ids(true)
-                        .with_file(true)
-                        .with_line_number(true),
-                )
-                .init();
+                .with_line_number(true)
+        });
+
+        let registry = tracing_subscriber::registry()
+            .with(env_filter)
+            .with(console_layer)
+            .with(json_layer)
+            .with(file_layer);
+
+        #[cfg(feature = "otel")]
+        {
+            let (otel_layer, otel_error) = match config.otel.as_ref().map(crate::core::otel::layer) {
+                Some(Ok((layer, guard))) => {
+                    *OTEL_GUARD.lock().unwrap() = Some(guard);
+                    (Some(layer), None)
+                }
+                Some(Err(e)) => (None, Some(e)),
+                None => (None, None),
+            };
+            registry.with(otel_layer).init();
+
+            // Reported once the subscriber is installed, so it reaches the configured outputs
+            if let Some(e) = otel_error {
+                tracing::warn!("OpenTelemetry export disabled: {}", e);
+            }
         }
+
+        #[cfg(not(feature = "otel"))]
+        registry.init();
     });
 }
 
+/// Flush any buffered spans and stop trace export
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    if let Some(guard) = OTEL_GUARD.lock().unwrap().take() {
+        guard.shutdown();
+    }
+}
+
 /// Initialize logging with default level (INFO)
 pub fn init_default_logging() {
     init(LogConfig::default());
@@ -117,51 +170,58 @@ pub fn init_default_logging() {
 
 /// Initialize logging with debug level
 pub fn init_debug_logging() {
-    let mut config = LogConfig::default();
-    config.level = Level::DEBUG;
-    init(config);
+    init(LogConfig {
+        level: Level::DEBUG,
+        ..Default::default()
+    });
 }
 
 /// Initialize logging with trace level
 pub fn init_trace_logging() {
-    let mut config = LogConfig::default();
-    config.level = Level::TRACE;
-    init(config);
+    init(LogConfig {
+        level: Level::TRACE,
+        ..Default::default()
+    });
 }
 
 /// Initialize logging with error level
 pub fn init_error_logging() {
-    let mut config = LogConfig::default();
-    config.level = Level::ERROR;
-    init(config);
+    init(LogConfig {
+        level: Level::ERROR,
+        ..Default::default()
+    });
 }
 
 /// Initialize logging with JSON output
 pub fn init_json_logging() {
-    let mut config = LogConfig::default();
-    config.json = true;
-    init(config);
+    init(LogConfig {
+        json: true,
+        ..Default::default()
+    });
 }
 
 /// Initialize logging with file output
 pub fn init_file_logging(file_path: PathBuf) {
-    let mut config = LogConfig::default();
-    config.file_path = Some(file_path);
-    init(config);
+    init(LogConfig {
+        console: false,
+        file_path: Some(file_path),
+        ..Default::default()
+    });
 }
 
 /// Initialize logging with rotation
 pub fn init_rotating_logging(file_path: PathBuf, rotation: Rotation) {
-    let mut config = LogConfig::default();
-    config.file_path = Some(file_path);
-    config.rotation = rotation;
-    init(config);
+    init(LogConfig {
+        console: false,
+        file_path: Some(file_path),
+        rotation,
+        ..Default::default()
+    });
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
     use tempfile::tempdir;
     use tracing::{debug, error, info, trace, warn};
 
@@ -170,6 +230,7 @@ mod tests {
         let config = LogConfig::default();
         assert_eq!(config.level, Level::INFO);
         assert!(!config.json);
+        assert!(config.console);
         assert!(config.file_path.is_none());
         assert_eq!(config.rotation, Rotation::DAILY);
     }
@@ -186,6 +247,7 @@ mod tests {
         assert_eq!(config.level, level);
         assert_eq!(config.json, json);
         assert_eq!(config.file_path, file_path);
+        assert!(!config.console);
         assert_eq!(config.rotation, rotation_clone);
     }
 
@@ -0,0 +1,398 @@
+//! Registry of every metric emitted by the crate
+//!
+//! Metric names are defined once here and referenced from the rpc, db and
+//! fetcher modules. [`describe_metrics`] registers their help text and units
+//! with whichever `metrics` recorder is installed (see the `prometheus` feature).
+
+use metrics::{KeyName, Recorder, Unit};
+
+/// Successful requests recorded by `core::health::EndpointHealth`
+pub const RPC_SUCCESS_TOTAL: &str = "rpc_success_total";
+/// Failed requests recorded by `core::health::EndpointHealth`
+pub const RPC_ERROR_TOTAL: &str = "rpc_error_total";
+/// Last response time recorded by `core::health::EndpointHealth`
+pub const RPC_RESPONSE_TIME_SECONDS: &str = "rpc_response_time_seconds";
+/// RPC attempts, successful or not
+pub const RPC_REQUESTS_TOTAL: &str = "rpc_requests_total";
+/// Failed RPC attempts by error class
+pub const RPC_ERRORS_TOTAL: &str = "rpc_errors_total";
+/// Latency of a single RPC attempt
+pub const RPC_REQUEST_DURATION_SECONDS: &str = "rpc_request_duration_seconds";
+/// JSON-RPC request body bytes sent over HTTP
+pub const RPC_BYTES_SENT_TOTAL: &str = "rpc_bytes_sent_total";
+/// JSON-RPC response body bytes received over HTTP
+pub const RPC_BYTES_RECEIVED_TOTAL: &str = "rpc_bytes_received_total";
+/// Requests per second over the sliding window
+pub const RPC_CURRENT_RPS: &str = "rpc_current_rps";
+/// Time spent waiting for a pooled database connection
+pub const DB_POOL_ACQUIRE_SECONDS: &str = "db_pool_acquire_seconds";
+/// Failures to acquire a pooled database connection
+pub const DB_POOL_ERRORS_TOTAL: &str = "db_pool_errors_total";
+/// Schema migrations applied by this process
+pub const DB_MIGRATIONS_APPLIED_TOTAL: &str = "db_migrations_applied_total";
+/// Transactions fetched by the address fetcher
+pub const FETCHER_TRANSACTIONS_TOTAL: &str = "fetcher_transactions_total";
+/// Transactions the address fetcher failed to retrieve
+pub const FETCHER_TRANSACTION_ERRORS_TOTAL: &str = "fetcher_transaction_errors_total";
+/// Signature pages requested by the address fetcher
+pub const FETCHER_BATCHES_TOTAL: &str = "fetcher_batches_total";
+/// Blocks emitted by the block fetcher
+pub const FETCHER_BLOCKS_TOTAL: &str = "fetcher_blocks_total";
+/// Slots the block fetcher found skipped
+pub const FETCHER_SKIPPED_SLOTS_TOTAL: &str = "fetcher_skipped_slots_total";
+/// Block fetcher batches that failed and will be retried
+pub const FETCHER_BLOCK_ERRORS_TOTAL: &str = "fetcher_block_errors_total";
+/// Last slot checkpointed by the block fetcher
+pub const FETCHER_CHECKPOINT_SLOT: &str = "fetcher_checkpoint_slot";
+/// Batches completed by each indexer pipeline stage
+pub const INDEXER_BATCHES_TOTAL: &str = "indexer_batches_total";
+/// Items completed by each indexer pipeline stage
+pub const INDEXER_ITEMS_TOTAL: &str = "indexer_items_total";
+/// Time each indexer pipeline stage spends on one batch
+pub const INDEXER_STAGE_DURATION_SECONDS: &str = "indexer_stage_duration_seconds";
+/// Batches waiting in the channel in front of each indexer stage
+pub const INDEXER_QUEUE_DEPTH: &str = "indexer_queue_depth";
+/// Indexer source batches that failed and will be retried
+pub const INDEXER_SOURCE_ERRORS_TOTAL: &str = "indexer_source_errors_total";
+/// Transactions the indexer failed to decode for the parsers
+pub const INDEXER_PARSE_ERRORS_TOTAL: &str = "indexer_parse_errors_total";
+/// Batches each indexer enricher failed to complete
+pub const INDEXER_ENRICH_ERRORS_TOTAL: &str = "indexer_enrich_errors_total";
+/// Events produced by each protocol parser
+pub const PARSER_EVENTS_TOTAL: &str = "parser_events_total";
+/// Instructions each protocol parser failed to parse
+pub const PARSER_ERRORS_TOTAL: &str = "parser_errors_total";
+/// Instructions of programs without a registered parser
+pub const PARSER_UNKNOWN_INSTRUCTIONS_TOTAL: &str = "parser_unknown_instructions_total";
+/// Transactions whose balance changes break an invariant
+pub const BALANCE_INVARIANT_VIOLATIONS_TOTAL: &str = "balance_invariant_violations_total";
+/// Reconstructed balances that differ from the live chain state
+pub const BALANCE_VALIDATION_MISMATCHES_TOTAL: &str = "balance_validation_mismatches_total";
+/// Price requests each price provider failed
+pub const PRICE_PROVIDER_ERRORS_TOTAL: &str = "price_provider_errors_total";
+/// Quotes each price provider returned that were rejected as outliers
+pub const PRICE_OUTLIERS_TOTAL: &str = "price_outliers_total";
+/// Oracle prices rejected as stale or low-confidence
+pub const ORACLE_REJECTED_PRICES_TOTAL: &str = "oracle_rejected_prices_total";
+/// Amounts left without a USD value for lack of a stored price
+pub const USD_VALUATIONS_UNPRICED_TOTAL: &str = "usd_valuations_unpriced_total";
+
+/// Kind of a registered metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+/// Description of a registered metric
+#[derive(Debug, Clone, Copy)]
+pub struct MetricDescriptor {
+    /// Metric name
+    pub name: &'static str,
+    /// Metric kind
+    pub kind: MetricKind,
+    /// Unit of the recorded values
+    pub unit: Unit,
+    /// Help text exported alongside the metric
+    pub help: &'static str,
+    /// Label keys attached to the metric
+    pub labels: &'static [&'static str],
+}
+
+/// Every metric emitted by the crate
+pub const REGISTRY: &[MetricDescriptor] = &[
+    MetricDescriptor {
+        name: RPC_SUCCESS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Successful requests per endpoint",
+        labels: &["endpoint"],
+    },
+    MetricDescriptor {
+        name: RPC_ERROR_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Failed requests per endpoint",
+        labels: &["endpoint"],
+    },
+    MetricDescriptor {
+        name: RPC_RESPONSE_TIME_SECONDS,
+        kind: MetricKind::Histogram,
+        unit: Unit::Seconds,
+        help: "Response time of successful requests per endpoint",
+        labels: &["endpoint"],
+    },
+    MetricDescriptor {
+        name: RPC_REQUESTS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "RPC attempts per endpoint and method",
+        labels: &["endpoint", "method"],
+    },
+    MetricDescriptor {
+        name: RPC_ERRORS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Failed RPC attempts per endpoint, method and error class",
+        labels: &["endpoint", "method", "class"],
+    },
+    MetricDescriptor {
+        name: RPC_REQUEST_DURATION_SECONDS,
+        kind: MetricKind::Histogram,
+        unit: Unit::Seconds,
+        help: "Latency of a single RPC attempt",
+        labels: &["endpoint", "method"],
+    },
+    MetricDescriptor {
+        name: RPC_BYTES_SENT_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Bytes,
+        help: "JSON-RPC request body bytes sent over HTTP",
+        labels: &["endpoint", "method"],
+    },
+    MetricDescriptor {
+        name: RPC_BYTES_RECEIVED_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Bytes,
+        help: "JSON-RPC response body bytes received over HTTP",
+        labels: &["endpoint", "method"],
+    },
+    MetricDescriptor {
+        name: RPC_CURRENT_RPS,
+        kind: MetricKind::Gauge,
+        unit: Unit::CountPerSecond,
+        help: "Requests per second over the sliding window",
+        labels: &["endpoint"],
+    },
+    MetricDescriptor {
+        name: DB_POOL_ACQUIRE_SECONDS,
+        kind: MetricKind::Histogram,
+        unit: Unit::Seconds,
+        help: "Time spent waiting for a pooled database connection",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: DB_POOL_ERRORS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Failures to acquire a pooled database connection",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: DB_MIGRATIONS_APPLIED_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Schema migrations applied by this process",
+        labels: &["migration"],
+    },
+    MetricDescriptor {
+        name: FETCHER_TRANSACTIONS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Transactions fetched by the address fetcher",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: FETCHER_TRANSACTION_ERRORS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Transactions the address fetcher failed to retrieve",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: FETCHER_BATCHES_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Signature pages requested by the address fetcher",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: FETCHER_BLOCKS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Blocks emitted by the block fetcher",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: FETCHER_SKIPPED_SLOTS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Slots the block fetcher found skipped",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: FETCHER_BLOCK_ERRORS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Block fetcher batches that failed and will be retried",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: FETCHER_CHECKPOINT_SLOT,
+        kind: MetricKind::Gauge,
+        unit: Unit::Count,
+        help: "Last slot checkpointed by the block fetcher",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: INDEXER_BATCHES_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Batches completed by each indexer pipeline stage",
+        labels: &["stage"],
+    },
+    MetricDescriptor {
+        name: INDEXER_ITEMS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Items completed by each indexer pipeline stage",
+        labels: &["stage"],
+    },
+    MetricDescriptor {
+        name: INDEXER_STAGE_DURATION_SECONDS,
+        kind: MetricKind::Histogram,
+        unit: Unit::Seconds,
+        help: "Time each indexer pipeline stage spends on one batch",
+        labels: &["stage"],
+    },
+    MetricDescriptor {
+        name: INDEXER_QUEUE_DEPTH,
+        kind: MetricKind::Gauge,
+        unit: Unit::Count,
+        help: "Batches waiting in the channel in front of each indexer stage",
+        labels: &["stage"],
+    },
+    MetricDescriptor {
+        name: INDEXER_SOURCE_ERRORS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Indexer source batches that failed and will be retried",
+        labels: &["source"],
+    },
+    MetricDescriptor {
+        name: INDEXER_PARSE_ERRORS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Transactions the indexer failed to decode for the parsers",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: INDEXER_ENRICH_ERRORS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Batches each indexer enricher failed to complete",
+        labels: &["enricher"],
+    },
+    MetricDescriptor {
+        name: PARSER_EVENTS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Events produced by each protocol parser",
+        labels: &["parser"],
+    },
+    MetricDescriptor {
+        name: PARSER_ERRORS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Instructions each protocol parser failed to parse",
+        labels: &["parser"],
+    },
+    MetricDescriptor {
+        name: PARSER_UNKNOWN_INSTRUCTIONS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Instructions of programs without a registered parser",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: BALANCE_INVARIANT_VIOLATIONS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Balance invariant violations per kind",
+        labels: &["kind"],
+    },
+    MetricDescriptor {
+        name: BALANCE_VALIDATION_MISMATCHES_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Reconstructed balances differing from getTokenAccountsByOwner",
+        labels: &[],
+    },
+    MetricDescriptor {
+        name: PRICE_PROVIDER_ERRORS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Price requests each price provider failed",
+        labels: &["provider"],
+    },
+    MetricDescriptor {
+        name: PRICE_OUTLIERS_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Quotes of each price provider rejected as outliers",
+        labels: &["provider"],
+    },
+    MetricDescriptor {
+        name: ORACLE_REJECTED_PRICES_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Oracle prices rejected per oracle and reason",
+        labels: &["oracle", "reason"],
+    },
+    MetricDescriptor {
+        name: USD_VALUATIONS_UNPRICED_TOTAL,
+        kind: MetricKind::Counter,
+        unit: Unit::Count,
+        help: "Amounts without a USD price per kind",
+        labels: &["kind"],
+    },
+];
+
+/// Look up a registered metric by name
+pub fn descriptor(name: &str) -> Option<&'static MetricDescriptor> {
+    REGISTRY.iter().find(|d| d.name == name)
+}
+
+/// Register help text and units for every metric with the installed recorder
+pub fn describe_metrics() {
+    describe_metrics_with(metrics::recorder());
+}
+
+/// Register help text and units for every metric with `recorder`
+pub fn describe_metrics_with(recorder: &dyn Recorder) {
+    for d in REGISTRY {
+        let key = KeyName::from_const_str(d.name);
+        match d.kind {
+            MetricKind::Counter => recorder.describe_counter(key, Some(d.unit), d.help.into()),
+            MetricKind::Gauge => recorder.describe_gauge(key, Some(d.unit), d.help.into()),
+            MetricKind::Histogram => recorder.describe_histogram(key, Some(d.unit), d.help.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_registry_names_are_unique() {
+        let names: HashSet<_> = REGISTRY.iter().map(|d| d.name).collect();
+        assert_eq!(names.len(), REGISTRY.len());
+    }
+
+    #[test]
+    fn test_descriptor_lookup() {
+        let d = descriptor(RPC_ERRORS_TOTAL).unwrap();
+        assert_eq!(d.kind, MetricKind::Counter);
+        assert!(d.labels.contains(&"class"));
+        assert!(descriptor("not_a_metric").is_none());
+    }
+
+    #[test]
+    fn test_describe_without_recorder() {
+        // Describing with no recorder installed is a no-op
+        describe_metrics();
+    }
+}
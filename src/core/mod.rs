@@ -1,7 +1,16 @@
 pub mod config;
 pub mod error;
 pub mod health;
+#[cfg(feature = "health-server")]
+pub mod health_server;
+pub mod indexer;
 pub mod logging;
+pub mod metrics;
+pub mod system;
+#[cfg(feature = "prometheus")]
+pub mod exporter;
+#[cfg(feature = "otel")]
+pub mod otel;
 pub mod test_utils;
 pub mod traits;
 pub mod utils;
@@ -11,5 +20,5 @@ pub use error::*;
 pub use health::*;
 pub use logging::*;
 pub use test_utils::*;
-pub use traits::{Client, HealthStatus, HealthDetails, ClientMetrics, SystemMetrics, HealthCheck, Repository, Cache, ComponentHealth};
+pub use traits::{Client, HealthStatus, HealthDetails, ClientMetrics, MethodMetrics, SystemMetrics, HealthCheck, HealthComponent, Repository, Cache, ComponentHealth};
 pub use utils::*; 
\ No newline at end of file
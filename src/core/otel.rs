@@ -0,0 +1,211 @@
+//! OpenTelemetry trace export for `tracing` spans
+//!
+//! Enabled with the `otel` feature. Spans are exported over OTLP/HTTP
+//! (protobuf) to [`OtelConfig::endpoint`], which is typically a local
+//! OpenTelemetry collector listening on port 4318.
+
+use crate::core::error::{Error, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::runtime;
+use opentelemetry_sdk::trace::{self as sdktrace, Sampler, Tracer, TracerProvider};
+use opentelemetry_sdk::Resource;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Configuration for OpenTelemetry trace export
+#[derive(Debug, Clone)]
+pub struct OtelConfig {
+    /// Base URL of the OTLP/HTTP collector; `/v1/traces` is appended
+    pub endpoint: String,
+    /// Value of the `service.name` resource attribute
+    pub service_name: String,
+    /// Additional resource attributes (e.g. `deployment.environment`)
+    pub resource_attributes: HashMap<String, String>,
+    /// Fraction of root traces to sample, between 0.0 and 1.0
+    pub sample_ratio: f64,
+    /// Timeout for a single export request
+    pub export_timeout: Duration,
+    /// Extra headers sent with each export request
+    pub headers: HashMap<String, String>,
+}
+
+impl Default for OtelConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://localhost:4318".to_string(),
+            service_name: env!("CARGO_PKG_NAME").to_string(),
+            resource_attributes: HashMap::new(),
+            sample_ratio: 1.0,
+            export_timeout: Duration::from_secs(10),
+            headers: HashMap::new(),
+        }
+    }
+}
+
+impl OtelConfig {
+    /// Create a new configuration exporting to `endpoint`
+    pub fn new(endpoint: impl Into<String>, service_name: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            service_name: service_name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a resource attribute
+    pub fn with_resource_attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.resource_attributes.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the root trace sampling ratio
+    pub fn with_sample_ratio(mut self, ratio: f64) -> Self {
+        self.sample_ratio = ratio;
+        self
+    }
+
+    fn resource(&self) -> Resource {
+        let mut attributes = vec![
+            KeyValue::new("service.name", self.service_name.clone()),
+            KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+        ];
+        attributes.extend(
+            self.resource_attributes
+                .iter()
+                .map(|(k, v)| KeyValue::new(k.clone(), v.clone())),
+        );
+        Resource::new(attributes)
+    }
+
+    fn sampler(&self) -> Sampler {
+        let root = if self.sample_ratio >= 1.0 {
+            Sampler::AlwaysOn
+        } else if self.sample_ratio <= 0.0 {
+            Sampler::AlwaysOff
+        } else {
+            Sampler::TraceIdRatioBased(self.sample_ratio)
+        };
+        Sampler::ParentBased(Box::new(root))
+    }
+}
+
+/// Owns the tracer provider; flushes and shuts down the exporter on drop
+#[derive(Debug)]
+pub struct OtelGuard {
+    provider: Option<TracerProvider>,
+}
+
+impl OtelGuard {
+    /// Export all finished spans that are still buffered
+    pub fn flush(&self) {
+        if let Some(provider) = &self.provider {
+            for result in provider.force_flush() {
+                if let Err(e) = result {
+                    tracing::warn!("Failed to flush spans: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Flush buffered spans and stop the exporter
+    pub fn shutdown(mut self) {
+        self.flush();
+        self.provider.take();
+    }
+}
+
+impl Drop for OtelGuard {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Build a tracer exporting over OTLP/HTTP, together with its guard.
+///
+/// Must be called from within a Tokio runtime; spans are exported in
+/// batches on that runtime.
+pub fn tracer(config: &OtelConfig) -> Result<(Tracer, OtelGuard)> {
+    let exporter = opentelemetry_otlp::new_exporter()
+        .http()
+        .with_endpoint(config.endpoint.clone())
+        .with_timeout(config.export_timeout)
+        .with_headers(config.headers.clone())
+        .build_span_exporter()
+        .map_err(|e| Error::config(format!("Failed to build OTLP exporter: {}", e)))?;
+
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_config(
+            sdktrace::config()
+                .with_sampler(config.sampler())
+                .with_resource(config.resource()),
+        )
+        .build();
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+
+    Ok((tracer, OtelGuard { provider: Some(provider) }))
+}
+
+/// Build a `tracing` layer exporting spans over OTLP/HTTP
+pub fn layer<S>(config: &OtelConfig) -> Result<(OpenTelemetryLayer<S, Tracer>, OtelGuard)>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let (tracer, guard) = tracer(config)?;
+    Ok((tracing_opentelemetry::layer().with_tracer(tracer), guard))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_sampler_selection() {
+        let config = OtelConfig::default();
+        assert!(matches!(config.sampler(), Sampler::ParentBased(_)));
+        assert_eq!(config.with_sample_ratio(0.25).sample_ratio, 0.25);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_export_to_collector_stub() {
+        let collector = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/traces"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&collector)
+            .await;
+
+        let config = OtelConfig::new(collector.uri(), "indexer-test")
+            .with_resource_attribute("deployment.environment", "test");
+        let (layer, guard) = layer(&config).unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "rpc.attempt",
+                rpc.method = "getBlock",
+                rpc.endpoint = "http://localhost:8899",
+                slot = 42u64,
+            );
+            let _entered = span.enter();
+        });
+        tokio::task::spawn_blocking(move || guard.shutdown()).await.unwrap();
+
+        let requests = collector.received_requests().await.unwrap();
+        assert!(!requests.is_empty(), "collector received no export requests");
+        let body = &requests[0].body;
+        let contains = |needle: &[u8]| body.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"rpc.attempt"));
+        assert!(contains(b"getBlock"));
+        assert!(contains(b"indexer-test"));
+        assert!(contains(b"deployment.environment"));
+    }
+}
@@ -0,0 +1,273 @@
+//! Host metrics read from procfs
+//!
+//! [`SystemSampler`] fills [`SystemMetrics`] from `/proc/stat`, `/proc/meminfo`,
+//! `/proc/diskstats` and `/proc/net/dev`, and disk usage from `statvfs` of the
+//! data directory. On hosts without procfs every value read from it is
+//! reported as zero.
+
+use crate::core::traits::SystemMetrics;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Bytes per sector as reported by `/proc/diskstats`, independent of the device
+const SECTOR_SIZE: u64 = 512;
+
+/// Aggregate CPU time counters from the `cpu` line of `/proc/stat`, in jiffies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CpuTimes {
+    /// Time spent idle or waiting for I/O
+    pub idle: u64,
+    /// Total time across all states
+    pub total: u64,
+}
+
+impl CpuTimes {
+    /// Busy percentage between `previous` and `self`
+    pub fn usage_since(&self, previous: &CpuTimes) -> f64 {
+        let total = self.total.saturating_sub(previous.total);
+        if total == 0 {
+            return 0.0;
+        }
+        let idle = self.idle.saturating_sub(previous.idle);
+        (total - idle.min(total)) as f64 / total as f64 * 100.0
+    }
+}
+
+/// Parse the aggregate `cpu` line of `/proc/stat`
+pub fn parse_cpu_times(stat: &str) -> Option<CpuTimes> {
+    let line = stat.lines().find(|l| l.starts_with("cpu "))?;
+    let values: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .map(|v| v.parse().ok())
+        .collect::<Option<_>>()?;
+    if values.len() < 4 {
+        return None;
+    }
+    // user nice system idle iowait irq softirq steal; guest time is already counted in user
+    let total = values.iter().take(8).sum();
+    let idle = values[3] + values.get(4).copied().unwrap_or(0);
+    Some(CpuTimes { idle, total })
+}
+
+/// Parse used memory in bytes (`MemTotal - MemAvailable`) from `/proc/meminfo`
+pub fn parse_memory_used(meminfo: &str) -> Option<u64> {
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find(|l| l.starts_with(name))
+            .and_then(|l| l.split_whitespace().nth(1))
+            .and_then(|v| v.parse::<u64>().ok())
+    };
+    let total = field("MemTotal:")?;
+    let available = field("MemAvailable:")?;
+    Some(total.saturating_sub(available) * 1024)
+}
+
+/// Parse bytes read and written by whole block devices from `/proc/diskstats`.
+///
+/// Partitions, loop, RAM and device-mapper devices are skipped so that I/O is
+/// not counted twice.
+pub fn parse_disk_bytes(diskstats: &str) -> u64 {
+    let mut devices: Vec<&str> = Vec::new();
+    let mut sectors = 0u64;
+    for line in diskstats.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 {
+            continue;
+        }
+        let name = fields[2];
+        let virtual_device = ["loop", "ram", "zram", "dm-", "md"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix));
+        let partition = devices.iter().any(|device| name.starts_with(device));
+        if virtual_device || partition {
+            continue;
+        }
+        devices.push(name);
+        let read = fields[5].parse::<u64>().unwrap_or(0);
+        let written = fields[9].parse::<u64>().unwrap_or(0);
+        sectors += read + written;
+    }
+    sectors * SECTOR_SIZE
+}
+
+/// Parse bytes received and sent on non-loopback interfaces from `/proc/net/dev`
+pub fn parse_network_bytes(net_dev: &str) -> u64 {
+    net_dev
+        .lines()
+        .skip(2)
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(iface, _)| iface.trim() != "lo")
+        .map(|(_, counters)| {
+            let fields: Vec<u64> = counters
+                .split_whitespace()
+                .filter_map(|v| v.parse().ok())
+                .collect();
+            fields.first().copied().unwrap_or(0) + fields.get(8).copied().unwrap_or(0)
+        })
+        .sum()
+}
+
+/// Used bytes of the filesystem holding `path`, counting blocks reserved for
+/// the superuser as used like `df` does
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // Block counts and sizes are narrower on some platforms
+pub fn disk_used(path: &Path) -> Option<u64> {
+    match nix::sys::statvfs::statvfs(path) {
+        Ok(stats) => {
+            let used = (stats.blocks() as u64).saturating_sub(stats.blocks_free() as u64);
+            Some(used * stats.fragment_size() as u64)
+        }
+        Err(e) => {
+            tracing::debug!("Failed to stat filesystem of {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn disk_used(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Samples [`SystemMetrics`] from procfs.
+///
+/// CPU usage is computed between consecutive calls to [`SystemSampler::sample`];
+/// the first call reports the average since boot.
+#[derive(Debug)]
+pub struct SystemSampler {
+    proc_root: PathBuf,
+    data_dir: PathBuf,
+    previous_cpu: Mutex<Option<CpuTimes>>,
+}
+
+impl Default for SystemSampler {
+    fn default() -> Self {
+        Self::new("/proc")
+    }
+}
+
+impl SystemSampler {
+    /// Create a sampler reading from `proc_root` (normally `/proc`), with
+    /// disk usage reported for the root filesystem
+    pub fn new(proc_root: impl Into<PathBuf>) -> Self {
+        Self {
+            proc_root: proc_root.into(),
+            data_dir: PathBuf::from("/"),
+            previous_cpu: Mutex::new(None),
+        }
+    }
+
+    /// Report disk usage for the filesystem holding `data_dir`
+    pub fn with_data_dir(mut self, data_dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = data_dir.into();
+        self
+    }
+
+    /// Take a sample of the host metrics
+    pub fn sample(&self) -> SystemMetrics {
+        let cpu_usage = self
+            .read("stat")
+            .as_deref()
+            .and_then(parse_cpu_times)
+            .map(|current| {
+                let mut previous = self.previous_cpu.lock().unwrap();
+                let usage = current.usage_since(&previous.unwrap_or_default());
+                *previous = Some(current);
+                usage
+            })
+            .unwrap_or(0.0);
+
+        SystemMetrics {
+            cpu_usage,
+            memory_usage: self.read("meminfo").as_deref().and_then(parse_memory_used).unwrap_or(0),
+            disk_usage: disk_used(&self.data_dir).unwrap_or(0),
+            disk_io: self.read("diskstats").as_deref().map(parse_disk_bytes).unwrap_or(0),
+            network_usage: self.read("net/dev").as_deref().map(parse_network_bytes).unwrap_or(0),
+        }
+    }
+
+    fn read(&self, file: &str) -> Option<String> {
+        let path: &Path = &self.proc_root.join(file);
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Some(contents),
+            Err(e) => {
+                tracing::debug!("Failed to read {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STAT: &str = "cpu  100 0 50 800 50 0 0 0 0 0\ncpu0 100 0 50 800 50 0 0 0 0 0\n";
+    const MEMINFO: &str = "MemTotal:       16000 kB\nMemFree:         2000 kB\nMemAvailable:    6000 kB\n";
+    const DISKSTATS: &str = "\
+   7       0 loop0 10 0 100 0 0 0 0 0 0 0 0
+   8       0 sda 100 0 1000 0 50 0 2000 0 0 0 0
+   8       1 sda1 90 0 900 0 40 0 1800 0 0 0 0
+ 259       0 nvme0n1 10 0 10 0 10 0 20 0 0 0 0
+ 259       1 nvme0n1p1 10 0 10 0 10 0 20 0 0 0 0
+ 253       0 dm-0 10 0 500 0 10 0 500 0 0 0 0
+";
+    const NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:    5000      10    0    0    0     0          0         0     5000      10    0    0    0     0       0          0
+  eth0:    1000      10    0    0    0     0          0         0      300       3    0    0    0     0       0          0
+";
+
+    #[test]
+    fn test_parse_cpu_times() {
+        let times = parse_cpu_times(STAT).unwrap();
+        assert_eq!(times, CpuTimes { idle: 850, total: 1000 });
+        assert_eq!(times.usage_since(&CpuTimes::default()), 15.0);
+
+        let later = CpuTimes { idle: 900, total: 1100 };
+        assert_eq!(later.usage_since(&times), 50.0);
+        assert_eq!(times.usage_since(&times), 0.0);
+        assert!(parse_cpu_times("intr 1 2 3").is_none());
+    }
+
+    #[test]
+    fn test_parse_proc_files() {
+        assert_eq!(parse_memory_used(MEMINFO), Some(10000 * 1024));
+        assert_eq!(parse_memory_used("MemTotal: 1 kB\n"), None);
+        assert_eq!(parse_disk_bytes(DISKSTATS), (1000 + 2000 + 10 + 20) * SECTOR_SIZE);
+        assert_eq!(parse_network_bytes(NET_DEV), 1300);
+    }
+
+    #[test]
+    fn test_sampler_reads_proc_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("net")).unwrap();
+        std::fs::write(dir.path().join("stat"), STAT).unwrap();
+        std::fs::write(dir.path().join("meminfo"), MEMINFO).unwrap();
+        std::fs::write(dir.path().join("diskstats"), DISKSTATS).unwrap();
+        std::fs::write(dir.path().join("net/dev"), NET_DEV).unwrap();
+
+        let sampler = SystemSampler::new(dir.path()).with_data_dir(dir.path());
+        let metrics = sampler.sample();
+        assert_eq!(metrics.cpu_usage, 15.0);
+        assert_eq!(metrics.memory_usage, 10000 * 1024);
+        assert_eq!(metrics.disk_usage, disk_used(dir.path()).unwrap());
+        assert!(metrics.disk_usage > 0);
+        assert_eq!(metrics.disk_io, (1000 + 2000 + 10 + 20) * SECTOR_SIZE);
+        assert_eq!(metrics.network_usage, 1300);
+
+        std::fs::write(dir.path().join("stat"), "cpu  150 0 50 850 50 0 0 0 0 0\n").unwrap();
+        assert_eq!(sampler.sample().cpu_usage, 50.0);
+    }
+
+    #[test]
+    fn test_sampler_without_procfs() {
+        let metrics = SystemSampler::new("/nonexistent").with_data_dir("/nonexistent").sample();
+        assert_eq!(metrics.cpu_usage, 0.0);
+        assert_eq!(metrics.memory_usage, 0);
+        assert_eq!(metrics.disk_usage, 0);
+    }
+}
@@ -50,6 +50,12 @@ pub fn test_client_metrics() -> ClientMetrics {
         avg_response_time_ms: 50.0,
         current_rps: 10.0,
         bytes_transferred: 1000,
+        bytes_sent: 200,
+        bytes_received: 800,
+        p50_response_time_ms: 45.0,
+        p95_response_time_ms: 90.0,
+        p99_response_time_ms: 120.0,
+        ..Default::default()
     }
 }
 
@@ -72,6 +78,7 @@ pub fn test_system_metrics() -> SystemMetrics {
         cpu_usage: 0.0,
         memory_usage: 0,
         disk_usage: 0,
+        disk_io: 0,
         network_usage: 0,
     }
 }
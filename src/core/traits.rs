@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use crate::core::error::Result;
 use std::time::Duration;
@@ -71,6 +72,16 @@ pub trait HealthCheck: Send + Sync + Debug {
     async fn get_health_details(&self) -> Result<HealthDetails>;
 }
 
+/// A single component contributing to system health
+#[async_trait]
+pub trait HealthComponent: Send + Sync + Debug {
+    /// Component name reported in [`ComponentHealth::name`]
+    fn name(&self) -> &str;
+
+    /// Probe the component and report its health
+    async fn component_health(&self) -> ComponentHealth;
+}
+
 /// Cache trait for caching operations
 #[async_trait]
 pub trait Cache<K: Send + Sync + Debug, V: Send + Sync + Debug>: Send + Sync + Debug {
@@ -88,7 +99,7 @@ pub trait Cache<K: Send + Sync + Debug, V: Send + Sync + Debug>: Send + Sync + D
 }
 
 /// Client metrics for monitoring
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ClientMetrics {
     /// Number of successful requests
     pub successful_requests: u64,
@@ -100,17 +111,77 @@ pub struct ClientMetrics {
     pub current_rps: f64,
     /// Total bytes transferred
     pub bytes_transferred: u64,
+    /// Bytes sent in request payloads
+    pub bytes_sent: u64,
+    /// Bytes received in response payloads
+    pub bytes_received: u64,
+    /// Median response time in milliseconds
+    pub p50_response_time_ms: f64,
+    /// 95th percentile response time in milliseconds
+    pub p95_response_time_ms: f64,
+    /// 99th percentile response time in milliseconds
+    pub p99_response_time_ms: f64,
+    /// Failed requests keyed by error class
+    pub errors_by_class: HashMap<String, u64>,
+    /// Per-method breakdown keyed by RPC method name
+    pub methods: HashMap<String, MethodMetrics>,
+}
+
+/// Per-method metrics for monitoring
+#[derive(Debug, Clone, Default)]
+pub struct MethodMetrics {
+    /// Number of successful requests
+    pub successful_requests: u64,
+    /// Number of failed requests
+    pub failed_requests: u64,
+    /// Average response time in milliseconds
+    pub avg_response_time_ms: f64,
+    /// Median response time in milliseconds
+    pub p50_response_time_ms: f64,
+    /// 95th percentile response time in milliseconds
+    pub p95_response_time_ms: f64,
+    /// 99th percentile response time in milliseconds
+    pub p99_response_time_ms: f64,
+    /// Bytes sent in request payloads
+    pub bytes_sent: u64,
+    /// Bytes received in response payloads
+    pub bytes_received: u64,
+    /// Failed requests keyed by error class
+    pub errors_by_class: HashMap<String, u64>,
 }
 
 /// Health status for monitoring
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", content = "reason", rename_all = "lowercase")]
 pub enum HealthStatus {
     Healthy,
+    /// Serving, but with reduced capacity or a failing non-critical component
+    Degraded(Option<String>),
     Unhealthy(Option<String>),
 }
 
+impl HealthStatus {
+    /// Whether the status is `Healthy`
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, HealthStatus::Healthy)
+    }
+
+    /// Whether the service can still serve requests (`Healthy` or `Degraded`)
+    pub fn is_available(&self) -> bool {
+        !matches!(self, HealthStatus::Unhealthy(_))
+    }
+
+    /// Reason attached to a degraded or unhealthy status
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            HealthStatus::Healthy => None,
+            HealthStatus::Degraded(reason) | HealthStatus::Unhealthy(reason) => reason.as_deref(),
+        }
+    }
+}
+
 /// Detailed health information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthDetails {
     /// Overall health status
     pub status: HealthStatus,
@@ -121,7 +192,7 @@ pub struct HealthDetails {
 }
 
 /// Component-specific health information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentHealth {
     /// Component name
     pub name: String,
@@ -132,15 +203,18 @@ pub struct ComponentHealth {
 }
 
 /// System metrics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SystemMetrics {
     /// CPU usage percentage
     pub cpu_usage: f64,
     /// Memory usage in bytes
     pub memory_usage: u64,
-    /// Disk usage in bytes
+    /// Disk usage in bytes (used space of the filesystem holding the data directory)
     pub disk_usage: u64,
-    /// Network usage in bytes
+    /// Disk I/O in bytes (bytes read and written by block devices since boot)
+    #[serde(default)]
+    pub disk_io: u64,
+    /// Network usage in bytes (bytes received and sent on non-loopback interfaces since boot)
     pub network_usage: u64,
 }
 
@@ -226,6 +300,28 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_health_status_predicates() {
+        let degraded = HealthStatus::Degraded(Some("slow".to_string()));
+        assert!(HealthStatus::Healthy.is_healthy());
+        assert!(degraded.is_available());
+        assert!(!degraded.is_healthy());
+        assert_eq!(degraded.reason(), Some("slow"));
+        assert!(!HealthStatus::Unhealthy(None).is_available());
+    }
+
+    #[test]
+    fn test_health_status_serialization() {
+        let degraded = HealthStatus::Degraded(Some("slow".to_string()));
+        let json = serde_json::to_value(&degraded).unwrap();
+        assert_eq!(json, serde_json::json!({ "state": "degraded", "reason": "slow" }));
+        assert_eq!(serde_json::from_value::<HealthStatus>(json).unwrap(), degraded);
+        assert_eq!(
+            serde_json::to_value(HealthStatus::Healthy).unwrap(),
+            serde_json::json!({ "state": "healthy" })
+        );
+    }
+
     #[tokio::test]
     async fn test_cache() {
         let mut mock = MockTestCache::new();
@@ -1,5 +1,6 @@
 //! Utility functions and types for the Solana analytics system
 
+use crate::models::Lamports;
 use chrono::{DateTime, Duration, Utc};
 use std::str::FromStr;
 
@@ -18,9 +19,9 @@ pub fn get_last_n_days(n: i64) -> (DateTime<Utc>, DateTime<Utc>) {
     (start, end)
 }
 
-/// Formats a lamports amount as SOL
-pub fn format_sol_amount(lamports: i64) -> String {
-    format!("{:.9} SOL", lamports as f64 / 1e9)
+/// Formats a lamports amount as SOL, with all nine decimals
+pub fn format_sol_amount(lamports: Lamports) -> String {
+    lamports.to_string()
 }
 
 #[cfg(feature = "phase1")]
@@ -74,7 +75,9 @@ mod tests {
 
     #[test]
     fn test_format_sol_amount() {
-        assert_eq!(format_sol_amount(1_000_000_000), "1.000000000 SOL");
-        assert_eq!(format_sol_amount(1_500_000_000), "1.500000000 SOL");
+        assert_eq!(format_sol_amount(Lamports(1_000_000_000)), "1.000000000 SOL");
+        assert_eq!(format_sol_amount(Lamports(1_500_000_000)), "1.500000000 SOL");
+        // Beyond 2^53 lamports, where an f64 would round
+        assert_eq!(format_sol_amount(Lamports(9_007_199_254_740_993)), "9007199.254740993 SOL");
     }
 } 
\ No newline at end of file
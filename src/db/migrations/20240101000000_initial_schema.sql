@@ -17,7 +17,7 @@ CREATE TABLE token_accounts (
     pubkey VARCHAR(44) PRIMARY KEY,
     mint VARCHAR(44) NOT NULL,
     owner VARCHAR(44) NOT NULL,
-    amount BIGINT NOT NULL,
+    amount NUMERIC(20, 0) NOT NULL,
     updated_at TIMESTAMPTZ NOT NULL,
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
@@ -26,7 +26,7 @@ CREATE TABLE token_accounts (
 CREATE TABLE price_history (
     id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
     token_mint VARCHAR(44) NOT NULL,
-    price_usd DECIMAL(20, 8) NOT NULL,
+    price_usd NUMERIC NOT NULL,
     timestamp TIMESTAMPTZ NOT NULL,
     source VARCHAR(50) NOT NULL,
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
@@ -38,7 +38,8 @@ CREATE TABLE protocol_interactions (
     wallet VARCHAR(44) NOT NULL,
     protocol VARCHAR(100) NOT NULL,
     interaction_type VARCHAR(50) NOT NULL,
-    amount DECIMAL(20, 8) NOT NULL,
+    amount NUMERIC NOT NULL,
+    decimals SMALLINT NOT NULL,
     timestamp TIMESTAMPTZ NOT NULL,
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
@@ -1,5 +1,8 @@
 use deadpool_postgres::{Pool};
-use crate::db::DatabaseError;
+use metrics::counter;
+use crate::core::metrics::DB_MIGRATIONS_APPLIED_TOTAL;
+use crate::db::{query_span, DatabaseError};
+use tracing::Instrument;
 
 pub trait Migration {
     fn name(&self) -> &str;
@@ -16,6 +19,23 @@ pub fn get_migrations() -> Vec<Box<dyn Migration>> {
         Box::new(PriceHistoryMigration),
         Box::new(ProtocolInteractionsMigration),
         Box::new(GovernanceVotesMigration),
+        Box::new(ProtocolInteractionSourceMigration),
+        Box::new(GovernanceVoteDetailsMigration),
+        Box::new(GovernanceProposalEventsMigration),
+        Box::new(TokensMigration),
+        Box::new(BalanceChangesMigration),
+        Box::new(BalanceSnapshotsMigration),
+        Box::new(TokenAccountStateMigration),
+        Box::new(SwapMintIndexesMigration),
+        Box::new(PriceHistoryProvenanceMigration),
+        Box::new(OracleUpdatesMigration),
+        Box::new(PriceHistoryVolumeMigration),
+        Box::new(UsdValuationsMigration),
+        Box::new(NumericAmountsMigration),
+        Box::new(PriceHistoryUniqueKeyMigration),
+        Box::new(NumericRawAmountsMigration),
+        Box::new(ProtocolInteractionDecimalsMigration),
+        Box::new(OptionalBlockTimeMigration),
     ]
 }
 
@@ -147,6 +167,110 @@ impl Migration for GovernanceVotesMigration {
     }
 }
 
+/// Links protocol interactions to the transaction event they were parsed
+/// from, so re-persisting a batch does not duplicate rows
+pub struct ProtocolInteractionSourceMigration;
+
+impl Migration for ProtocolInteractionSourceMigration {
+    fn name(&self) -> &str {
+        "add_protocol_interaction_source"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        ALTER TABLE protocol_interactions
+            ADD COLUMN IF NOT EXISTS signature VARCHAR(88),
+            ADD COLUMN IF NOT EXISTS event_index INTEGER,
+            ADD COLUMN IF NOT EXISTS details JSONB,
+            ADD CONSTRAINT protocol_interactions_event_key UNIQUE (signature, event_index)
+        "#
+    }
+}
+
+/// Records the realm, vote record, weight and choices of parsed votes
+pub struct GovernanceVoteDetailsMigration;
+
+impl Migration for GovernanceVoteDetailsMigration {
+    fn name(&self) -> &str {
+        "add_governance_vote_details"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        ALTER TABLE governance_votes
+            ALTER COLUMN proposal_id TYPE VARCHAR(44),
+            ALTER COLUMN dao_name TYPE VARCHAR(100),
+            ADD COLUMN IF NOT EXISTS signature VARCHAR(88),
+            ADD COLUMN IF NOT EXISTS event_index INTEGER,
+            ADD COLUMN IF NOT EXISTS realm VARCHAR(44),
+            ADD COLUMN IF NOT EXISTS vote_record VARCHAR(44),
+            ADD COLUMN IF NOT EXISTS voter_weight BIGINT,
+            ADD COLUMN IF NOT EXISTS choices JSONB,
+            ADD COLUMN IF NOT EXISTS relinquished BOOLEAN NOT NULL DEFAULT FALSE,
+            ADD CONSTRAINT governance_votes_event_key UNIQUE (signature, event_index)
+        "#
+    }
+}
+
+pub struct GovernanceProposalEventsMigration;
+
+impl Migration for GovernanceProposalEventsMigration {
+    fn name(&self) -> &str {
+        "create_governance_proposal_events_table"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        CREATE TABLE IF NOT EXISTS governance_proposal_events (
+            id UUID PRIMARY KEY,
+            signature VARCHAR(88) NOT NULL,
+            event_index INTEGER NOT NULL,
+            realm VARCHAR(44),
+            dao_name VARCHAR(100),
+            proposal VARCHAR(44) NOT NULL,
+            event_type VARCHAR(32) NOT NULL,
+            actor VARCHAR(44),
+            details JSONB,
+            timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+            created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (signature, event_index)
+        )
+        "#
+    }
+
+    fn cleanup(&self) -> &str {
+        "DROP TABLE IF EXISTS governance_proposal_events CASCADE"
+    }
+}
+
+pub struct TokensMigration;
+
+impl Migration for TokensMigration {
+    fn name(&self) -> &str {
+        "create_tokens_table"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        CREATE TABLE IF NOT EXISTS tokens (
+            mint VARCHAR(44) PRIMARY KEY,
+            decimals SMALLINT NOT NULL,
+            name VARCHAR(64),
+            symbol VARCHAR(32),
+            uri TEXT,
+            logo_uri TEXT,
+            source VARCHAR(16) NOT NULL,
+            updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+            created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+        )
+        "#
+    }
+
+    fn cleanup(&self) -> &str {
+        "DROP TABLE IF EXISTS tokens CASCADE"
+    }
+}
+
 pub async fn run_migrations(pool: &Pool) -> Result<(), DatabaseError> {
     let mut client = pool.get().await.map_err(DatabaseError::ConnectionError)?;
     
@@ -158,11 +282,12 @@ pub async fn run_migrations(pool: &Pool) -> Result<(), DatabaseError> {
             applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
         )",
         &[],
-    ).await.map_err(DatabaseError::QueryError)?;
+    ).instrument(query_span("CREATE", "migrations")).await.map_err(DatabaseError::QueryError)?;
 
     // Get list of applied migrations
     let applied = client
         .query("SELECT name FROM migrations ORDER BY id", &[])
+        .instrument(query_span("SELECT", "migrations"))
         .await
         .map_err(DatabaseError::QueryError)?
         .iter()
@@ -174,105 +299,526 @@ pub async fn run_migrations(pool: &Pool) -> Result<(), DatabaseError> {
         let name = migration.name();
         
         if !applied.contains(&name.to_string()) {
-            // Start transaction
-            let transaction = client.transaction().await.map_err(DatabaseError::QueryError)?;
-
-            // Clean up existing tables if they exist
-            if !migration.cleanup().is_empty() {
-                transaction.execute(migration.cleanup(), &[])
-                    .await
-                    .map_err(DatabaseError::QueryError)?;
-            }
-
-            // Execute migration
-            transaction.execute(migration.sql(), &[])
-                .await
-                .map_err(|e| DatabaseError::MigrationError(format!("Failed to run migration {}: {}", name, e)))?;
-
-            // Create indexes
-            match name {
-                "create_transactions_table" => {
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_transactions_slot ON transactions(slot)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                    
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_transactions_block_time ON transactions(block_time)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                },
-                "create_token_accounts_table" => {
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_token_accounts_mint ON token_accounts(mint)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                    
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_token_accounts_owner ON token_accounts(owner)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                },
-                "create_price_history_table" => {
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_price_history_token_mint ON price_history(token_mint)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                    
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_price_history_timestamp ON price_history(timestamp)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                },
-                "create_protocol_interactions_table" => {
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_protocol_interactions_wallet ON protocol_interactions(wallet)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                    
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_protocol_interactions_protocol ON protocol_interactions(protocol)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                    
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_protocol_interactions_timestamp ON protocol_interactions(timestamp)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                },
-                "create_governance_votes_table" => {
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_governance_votes_voter ON governance_votes(voter)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                    
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_governance_votes_proposal_id ON governance_votes(proposal_id)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                    
-                    transaction.execute(
-                        "CREATE INDEX IF NOT EXISTS idx_governance_votes_timestamp ON governance_votes(timestamp)",
-                        &[],
-                    ).await.map_err(DatabaseError::QueryError)?;
-                },
-                _ => {}
-            }
-
-            // Record migration
-            transaction.execute(
-                "INSERT INTO migrations (name) VALUES ($1)",
-                &[&name],
-            ).await.map_err(DatabaseError::QueryError)?;
-
-            // Commit transaction
-            transaction.commit().await.map_err(DatabaseError::QueryError)?;
+            apply_migration(&mut client, migration.as_ref())
+                .instrument(tracing::info_span!("db.migration", db.system = "postgresql", migration = name))
+                .await?;
+            counter!(DB_MIGRATIONS_APPLIED_TOTAL, 1, "migration" => name.to_string());
         }
     }
 
     Ok(())
 }
 
+pub struct BalanceChangesMigration;
+
+impl Migration for BalanceChangesMigration {
+    fn name(&self) -> &str {
+        "create_balance_changes_table"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        CREATE TABLE IF NOT EXISTS balance_changes (
+            signature VARCHAR(88) NOT NULL,
+            slot BIGINT NOT NULL,
+            timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+            account VARCHAR(44) NOT NULL,
+            owner VARCHAR(44),
+            mint VARCHAR(44) NOT NULL,
+            delta BIGINT NOT NULL,
+            post_balance BIGINT NOT NULL,
+            created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (signature, account, mint)
+        )
+        "#
+    }
+
+    fn cleanup(&self) -> &str {
+        "DROP TABLE IF EXISTS balance_changes CASCADE"
+    }
+}
+
+pub struct BalanceSnapshotsMigration;
+
+impl Migration for BalanceSnapshotsMigration {
+    fn name(&self) -> &str {
+        "create_balance_snapshots_table"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        CREATE TABLE IF NOT EXISTS balance_snapshots (
+            owner VARCHAR(44) NOT NULL,
+            mint VARCHAR(44) NOT NULL,
+            slot BIGINT NOT NULL,
+            timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+            balance BIGINT NOT NULL,
+            source VARCHAR(16) NOT NULL,
+            created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (owner, mint, slot)
+        )
+        "#
+    }
+
+    fn cleanup(&self) -> &str {
+        "DROP TABLE IF EXISTS balance_snapshots CASCADE"
+    }
+}
+
+/// Records the program and last observed slot of token accounts, and when
+/// they were found closed
+pub struct TokenAccountStateMigration;
+
+impl Migration for TokenAccountStateMigration {
+    fn name(&self) -> &str {
+        "add_token_account_state"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        ALTER TABLE token_accounts
+            ADD COLUMN IF NOT EXISTS program_id VARCHAR(44),
+            ADD COLUMN IF NOT EXISTS slot BIGINT,
+            ADD COLUMN IF NOT EXISTS closed_at TIMESTAMP WITH TIME ZONE
+        "#
+    }
+}
+
+pub struct SwapMintIndexesMigration;
+
+impl Migration for SwapMintIndexesMigration {
+    fn name(&self) -> &str {
+        "add_swap_mint_indexes"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        CREATE INDEX IF NOT EXISTS idx_protocol_interactions_swap_input_mint
+            ON protocol_interactions((details->>'input_mint'), timestamp)
+            WHERE interaction_type = 'swap'
+        "#
+    }
+}
+
+pub struct PriceHistoryProvenanceMigration;
+
+impl Migration for PriceHistoryProvenanceMigration {
+    fn name(&self) -> &str {
+        "add_price_history_provenance"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        ALTER TABLE price_history
+            ADD COLUMN IF NOT EXISTS feed VARCHAR(44),
+            ADD COLUMN IF NOT EXISTS slot BIGINT,
+            ADD COLUMN IF NOT EXISTS confidence DOUBLE PRECISION
+        "#
+    }
+}
+
+pub struct OracleUpdatesMigration;
+
+impl Migration for OracleUpdatesMigration {
+    fn name(&self) -> &str {
+        "create_oracle_updates_table"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        CREATE TABLE IF NOT EXISTS oracle_updates (
+            account VARCHAR(44) NOT NULL,
+            oracle VARCHAR(20) NOT NULL,
+            slot BIGINT NOT NULL,
+            publish_time TIMESTAMP WITH TIME ZONE NOT NULL,
+            data BYTEA NOT NULL,
+            created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (account, slot)
+        )
+        "#
+    }
+
+    fn cleanup(&self) -> &str {
+        "DROP TABLE IF EXISTS oracle_updates CASCADE"
+    }
+}
+
+pub struct PriceHistoryVolumeMigration;
+
+impl Migration for PriceHistoryVolumeMigration {
+    fn name(&self) -> &str {
+        "add_price_history_volume"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        ALTER TABLE price_history
+            ADD COLUMN IF NOT EXISTS volume_usd DOUBLE PRECISION
+        "#
+    }
+}
+
+pub struct UsdValuationsMigration;
+
+impl Migration for UsdValuationsMigration {
+    fn name(&self) -> &str {
+        "create_usd_valuations_table"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        CREATE TABLE IF NOT EXISTS usd_valuations (
+            signature VARCHAR(88) NOT NULL,
+            kind VARCHAR(20) NOT NULL,
+            item VARCHAR(44) NOT NULL,
+            mint VARCHAR(44) NOT NULL,
+            amount BIGINT NOT NULL,
+            decimals SMALLINT NOT NULL,
+            timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+            price_usd DOUBLE PRECISION,
+            value_usd DOUBLE PRECISION,
+            price_source VARCHAR(50),
+            price_timestamp TIMESTAMP WITH TIME ZONE,
+            staleness_seconds BIGINT,
+            valued_at TIMESTAMP WITH TIME ZONE NOT NULL,
+            PRIMARY KEY (signature, kind, item, mint)
+        )
+        "#
+    }
+
+    fn cleanup(&self) -> &str {
+        "DROP TABLE IF EXISTS usd_valuations CASCADE"
+    }
+}
+
+pub struct NumericAmountsMigration;
+
+impl Migration for NumericAmountsMigration {
+    fn name(&self) -> &str {
+        "use_numeric_amounts"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        ALTER TABLE price_history
+            ALTER COLUMN price_usd TYPE NUMERIC USING price_usd::NUMERIC,
+            ALTER COLUMN confidence TYPE NUMERIC USING confidence::NUMERIC,
+            ALTER COLUMN volume_usd TYPE NUMERIC USING volume_usd::NUMERIC
+        "#
+    }
+}
+
+pub struct PriceHistoryUniqueKeyMigration;
+
+impl Migration for PriceHistoryUniqueKeyMigration {
+    fn name(&self) -> &str {
+        "add_price_history_unique_key"
+    }
+
+    fn sql(&self) -> &str {
+        // Keep the latest of the duplicates stored before the key existed
+        r#"
+        DELETE FROM price_history
+        WHERE id IN (
+            SELECT id FROM (
+                SELECT id, ROW_NUMBER() OVER (
+                    PARTITION BY token_mint, source, COALESCE(feed, ''), timestamp
+                    ORDER BY created_at DESC, id
+                ) AS duplicate
+                FROM price_history
+            ) ranked
+            WHERE duplicate > 1
+        )
+        "#
+    }
+}
+
+pub struct NumericRawAmountsMigration;
+
+impl Migration for NumericRawAmountsMigration {
+    fn name(&self) -> &str {
+        "use_numeric_raw_amounts"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        ALTER TABLE balance_changes
+            ALTER COLUMN delta TYPE NUMERIC(20, 0),
+            ALTER COLUMN post_balance TYPE NUMERIC(20, 0)
+        "#
+    }
+}
+
+pub struct ProtocolInteractionDecimalsMigration;
+
+impl Migration for ProtocolInteractionDecimalsMigration {
+    fn name(&self) -> &str {
+        "add_protocol_interaction_decimals"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        ALTER TABLE protocol_interactions
+            ADD COLUMN IF NOT EXISTS decimals SMALLINT
+        "#
+    }
+}
+
+pub struct OptionalBlockTimeMigration;
+
+impl Migration for OptionalBlockTimeMigration {
+    fn name(&self) -> &str {
+        "allow_missing_block_time"
+    }
+
+    fn sql(&self) -> &str {
+        r#"
+        ALTER TABLE transactions
+            ALTER COLUMN block_time DROP NOT NULL
+        "#
+    }
+}
+
+/// Apply a single migration and record it, inside one transaction
+async fn apply_migration(client: &mut deadpool_postgres::Client, migration: &dyn Migration) -> Result<(), DatabaseError> {
+    let name = migration.name();
+
+    // Start transaction
+    let transaction = client.transaction().await.map_err(DatabaseError::QueryError)?;
+
+    // Clean up existing tables if they exist
+    if !migration.cleanup().is_empty() {
+        transaction.execute(migration.cleanup(), &[])
+            .await
+            .map_err(DatabaseError::QueryError)?;
+    }
+
+    // Execute migration
+    transaction.execute(migration.sql(), &[])
+        .await
+        .map_err(|e| DatabaseError::MigrationError(format!("Failed to run migration {}: {}", name, e)))?;
+
+    // Create indexes
+    match name {
+        "create_transactions_table" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_transactions_slot ON transactions(slot)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+            
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_transactions_block_time ON transactions(block_time)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "create_token_accounts_table" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_token_accounts_mint ON token_accounts(mint)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+            
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_token_accounts_owner ON token_accounts(owner)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "create_price_history_table" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_price_history_token_mint ON price_history(token_mint)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+            
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_price_history_timestamp ON price_history(timestamp)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "create_protocol_interactions_table" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_protocol_interactions_wallet ON protocol_interactions(wallet)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+            
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_protocol_interactions_protocol ON protocol_interactions(protocol)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+            
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_protocol_interactions_timestamp ON protocol_interactions(timestamp)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "create_governance_votes_table" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_governance_votes_voter ON governance_votes(voter)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+            
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_governance_votes_proposal_id ON governance_votes(proposal_id)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+            
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_governance_votes_timestamp ON governance_votes(timestamp)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "create_tokens_table" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_tokens_symbol ON tokens(symbol)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "create_governance_proposal_events_table" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_governance_proposal_events_proposal ON governance_proposal_events(proposal)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "create_balance_changes_table" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_balance_changes_owner_mint_slot ON balance_changes(owner, mint, slot)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_balance_changes_account_slot ON balance_changes(account, slot)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_balance_changes_owner_mint_timestamp ON balance_changes(owner, mint, timestamp)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "create_balance_snapshots_table" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_balance_snapshots_owner_mint_timestamp ON balance_snapshots(owner, mint, timestamp)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "create_oracle_updates_table" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_oracle_updates_account_publish_time ON oracle_updates(account, publish_time)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "add_price_history_volume" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_price_history_token_mint_timestamp ON price_history(token_mint, timestamp)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "create_usd_valuations_table" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_usd_valuations_mint_timestamp ON usd_valuations(mint, timestamp)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "use_numeric_amounts" => {
+            // Token amounts keep the scale they are written with, the mint decimals
+            transaction.execute(
+                "ALTER TABLE protocol_interactions ALTER COLUMN amount TYPE NUMERIC USING amount::NUMERIC",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+
+            transaction.execute(
+                "ALTER TABLE token_accounts ALTER COLUMN amount TYPE NUMERIC(20, 0)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+
+            transaction.execute(
+                "ALTER TABLE usd_valuations
+                     ALTER COLUMN price_usd TYPE NUMERIC USING price_usd::NUMERIC,
+                     ALTER COLUMN value_usd TYPE NUMERIC USING value_usd::NUMERIC",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "add_price_history_unique_key" => {
+            // Prices without a feed share the empty feed
+            transaction.execute(
+                "CREATE UNIQUE INDEX IF NOT EXISTS idx_price_history_unique_key
+                     ON price_history(token_mint, source, (COALESCE(feed, '')), timestamp)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "use_numeric_raw_amounts" => {
+            // Raw amounts span the whole u64 range, and their changes its negation
+            transaction.execute(
+                "ALTER TABLE balance_snapshots ALTER COLUMN balance TYPE NUMERIC(20, 0)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+
+            transaction.execute(
+                "ALTER TABLE usd_valuations ALTER COLUMN amount TYPE NUMERIC(20, 0)",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "add_protocol_interaction_decimals" => {
+            // Amounts converted from DOUBLE PRECISION carry float noise in
+            // their scale. Swaps record their input decimals; other rows get
+            // the fewest decimals holding their amount rounded to 9 places.
+            // Amounts are then rounded to their decimals, so they read back
+            // exactly
+            transaction.execute(
+                "UPDATE protocol_interactions
+                 SET decimals = COALESCE((details->>'input_decimals')::SMALLINT, min_scale(round(amount, 9))::SMALLINT)
+                 WHERE decimals IS NULL",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+
+            transaction.execute(
+                "UPDATE protocol_interactions SET amount = round(amount, decimals) WHERE scale(amount) > decimals",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+
+            transaction.execute(
+                "ALTER TABLE protocol_interactions ALTER COLUMN decimals SET NOT NULL",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "allow_missing_block_time" => {
+            // Missing block times used to be stored as the Unix epoch
+            transaction.execute(
+                "UPDATE transactions SET block_time = NULL WHERE block_time = 'epoch'",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+
+            transaction.execute(
+                "ALTER TABLE balance_changes ALTER COLUMN timestamp DROP NOT NULL",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+
+            transaction.execute(
+                "UPDATE balance_changes SET timestamp = NULL WHERE timestamp = 'epoch'",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        "add_swap_mint_indexes" => {
+            transaction.execute(
+                "CREATE INDEX IF NOT EXISTS idx_protocol_interactions_swap_output_mint
+                     ON protocol_interactions((details->>'output_mint'), timestamp)
+                     WHERE interaction_type = 'swap'",
+                &[],
+            ).await.map_err(DatabaseError::QueryError)?;
+        },
+        _ => {}
+    }
+
+    // Record migration
+    transaction.execute(
+        "INSERT INTO migrations (name) VALUES ($1)",
+        &[&name],
+    ).await.map_err(DatabaseError::QueryError)?;
+
+    // Commit transaction
+    transaction.commit().await.map_err(DatabaseError::QueryError)?;
+
+    Ok(())
+}
+
 pub async fn create_database_if_not_exists(
     host: &str,
     port: u16,
@@ -91,6 +91,12 @@ async fn test_migrations() {
         "price_history",
         "protocol_interactions",
         "governance_votes",
+        "governance_proposal_events",
+        "tokens",
+        "balance_changes",
+        "balance_snapshots",
+        "oracle_updates",
+        "usd_valuations",
     ];
 
     for table in tables {
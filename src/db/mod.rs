@@ -1,7 +1,13 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use deadpool_postgres::{Pool, Manager};
+use metrics::{counter, histogram};
+use crate::core::metrics::{DB_MIGRATIONS_APPLIED_TOTAL, DB_POOL_ACQUIRE_SECONDS, DB_POOL_ERRORS_TOTAL};
 use tokio_postgres::Error as PostgresError;
 use thiserror::Error;
+use tracing::Instrument;
+use async_trait::async_trait;
+use serde_json::json;
+use crate::core::traits::{ComponentHealth, HealthComponent, HealthStatus};
 
 pub mod migrations;
 pub mod models;
@@ -16,6 +22,8 @@ pub enum DatabaseError {
     QueryError(#[from] PostgresError),
     #[error("Migration error: {0}")]
     MigrationError(String),
+    #[error("Invalid stored amount: {0}")]
+    InvalidAmount(#[from] crate::models::AmountError),
 }
 
 pub type Result<T> = std::result::Result<T, DatabaseError>;
@@ -45,6 +53,17 @@ impl Default for DatabaseConfig {
     }
 }
 
+/// Span for a single database statement, following the OpenTelemetry
+/// database semantic conventions
+pub(crate) fn query_span(operation: &str, table: &str) -> tracing::Span {
+    tracing::info_span!(
+        "db.query",
+        db.system = "postgresql",
+        db.operation = operation,
+        db.sql.table = table,
+    )
+}
+
 #[derive(Debug, Clone)]
 pub struct Database {
     pool: Pool,
@@ -70,7 +89,13 @@ impl Database {
     }
 
     pub async fn get_client(&self) -> Result<deadpool_postgres::Client> {
-        self.pool.get().await.map_err(DatabaseError::ConnectionError)
+        let start = Instant::now();
+        let client = self.pool.get().await;
+        histogram!(DB_POOL_ACQUIRE_SECONDS, start.elapsed().as_secs_f64());
+        client.map_err(|e| {
+            counter!(DB_POOL_ERRORS_TOTAL, 1);
+            DatabaseError::ConnectionError(e)
+        })
     }
 
     pub async fn run_migrations(&self) -> Result<()> {
@@ -84,7 +109,7 @@ impl Database {
                 applied_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
             )",
             &[],
-        ).await.map_err(DatabaseError::QueryError)?;
+        ).instrument(query_span("CREATE", "migrations")).await.map_err(DatabaseError::QueryError)?;
 
         // Run migrations
         for migration in crate::db::migrations::get_migrations() {
@@ -94,11 +119,12 @@ impl Database {
             let row = client.query_opt(
                 "SELECT id FROM migrations WHERE name = $1",
                 &[&name],
-            ).await.map_err(DatabaseError::QueryError)?;
+            ).instrument(query_span("SELECT", "migrations")).await.map_err(DatabaseError::QueryError)?;
 
             if row.is_none() {
                 // Run migration
                 client.execute(migration.sql(), &[])
+                    .instrument(tracing::info_span!("db.migration", db.system = "postgresql", migration = name))
                     .await
                     .map_err(|e| DatabaseError::MigrationError(format!("Failed to run migration {}: {}", name, e)))?;
 
@@ -106,12 +132,800 @@ impl Database {
                 client.execute(
                     "INSERT INTO migrations (name) VALUES ($1)",
                     &[&name],
-                ).await.map_err(DatabaseError::QueryError)?;
+                ).instrument(query_span("INSERT", "migrations")).await.map_err(DatabaseError::QueryError)?;
+                counter!(DB_MIGRATIONS_APPLIED_TOTAL, 1, "migration" => name.to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Insert `transactions` in a single database transaction, skipping
+    /// signatures that are already stored. Returns the number of new rows.
+    pub async fn insert_transactions(&self, transactions: &[crate::models::Transaction]) -> Result<u64> {
+        let mut client = self.get_client().await?;
+        let db_tx = client.transaction().await.map_err(DatabaseError::QueryError)?;
+        let statement = db_tx
+            .prepare(
+                "INSERT INTO transactions (signature, slot, block_time, fee, status, instructions_json)
+                 VALUES ($1, $2, $3, $4, $5, $6::text::jsonb)
+                 ON CONFLICT (signature) DO NOTHING",
+            )
+            .await
+            .map_err(DatabaseError::QueryError)?;
+
+        let mut inserted = 0;
+        for tx in transactions {
+            inserted += db_tx
+                .execute(
+                    &statement,
+                    &[&tx.signature, &tx.slot, &tx.block_time, &tx.fee, &tx.status, &tx.instructions_json],
+                )
+                .instrument(query_span("INSERT", "transactions"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+        }
+        db_tx.commit().await.map_err(DatabaseError::QueryError)?;
+        Ok(inserted)
+    }
+
+    /// Insert `interactions` in a single database transaction, skipping rows
+    /// whose source event is already stored. Returns the number of new rows.
+    pub async fn insert_protocol_interactions(
+        &self,
+        interactions: &[crate::models::ProtocolInteraction],
+    ) -> Result<u64> {
+        let mut client = self.get_client().await?;
+        let db_tx = client.transaction().await.map_err(DatabaseError::QueryError)?;
+        let statement = db_tx
+            .prepare(
+                "INSERT INTO protocol_interactions
+                     (id, wallet, protocol, interaction_type, amount, decimals, timestamp, signature, event_index,
+                      details)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                 ON CONFLICT (signature, event_index) DO NOTHING",
+            )
+            .await
+            .map_err(DatabaseError::QueryError)?;
+
+        let mut inserted = 0;
+        for interaction in interactions {
+            inserted += db_tx
+                .execute(
+                    &statement,
+                    &[
+                        &interaction.id,
+                        &interaction.wallet,
+                        &interaction.protocol,
+                        &interaction.interaction_type,
+                        &interaction.amount,
+                        &(interaction.amount.decimals as i16),
+                        &interaction.timestamp,
+                        &interaction.signature,
+                        &interaction.event_index,
+                        &interaction.details,
+                    ],
+                )
+                .instrument(query_span("INSERT", "protocol_interactions"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+        }
+        db_tx.commit().await.map_err(DatabaseError::QueryError)?;
+        Ok(inserted)
+    }
+
+    /// Insert governance `votes` and proposal `events` in a single database
+    /// transaction, skipping rows whose source event is already stored, and
+    /// flag the votes behind relinquish events as relinquished
+    pub async fn insert_governance_records(
+        &self,
+        votes: &[crate::models::GovernanceVote],
+        events: &[crate::models::GovernanceProposalEvent],
+    ) -> Result<()> {
+        let mut client = self.get_client().await?;
+        let db_tx = client.transaction().await.map_err(DatabaseError::QueryError)?;
+        let insert_vote = db_tx
+            .prepare(
+                "INSERT INTO governance_votes
+                     (id, voter, proposal_id, vote, timestamp, dao_name, signature, event_index,
+                      realm, vote_record, voter_weight, choices)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                 ON CONFLICT (signature, event_index) DO NOTHING",
+            )
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        for vote in votes {
+            db_tx
+                .execute(
+                    &insert_vote,
+                    &[
+                        &vote.id,
+                        &vote.voter,
+                        &vote.proposal_id,
+                        &vote.vote,
+                        &vote.timestamp,
+                        &vote.dao_name,
+                        &vote.signature,
+                        &vote.event_index,
+                        &vote.realm,
+                        &vote.vote_record,
+                        &vote.voter_weight,
+                        &vote.choices,
+                    ],
+                )
+                .instrument(query_span("INSERT", "governance_votes"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+        }
+
+        let insert_event = db_tx
+            .prepare(
+                "INSERT INTO governance_proposal_events
+                     (id, signature, event_index, realm, dao_name, proposal, event_type, actor, details, timestamp)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                 ON CONFLICT (signature, event_index) DO NOTHING",
+            )
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        for event in events {
+            db_tx
+                .execute(
+                    &insert_event,
+                    &[
+                        &event.id,
+                        &event.signature,
+                        &event.event_index,
+                        &event.realm,
+                        &event.dao_name,
+                        &event.proposal,
+                        &event.event_type,
+                        &event.actor,
+                        &event.details,
+                        &event.timestamp,
+                    ],
+                )
+                .instrument(query_span("INSERT", "governance_proposal_events"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+
+            let vote_record = event
+                .details
+                .as_ref()
+                .and_then(|details| details.get("vote_record"))
+                .and_then(|record| record.as_str());
+            if let ("vote_relinquished", Some(vote_record)) = (event.event_type.as_str(), vote_record) {
+                db_tx
+                    .execute(
+                        "UPDATE governance_votes SET relinquished = TRUE
+                         WHERE vote_record = $1 AND timestamp <= $2",
+                        &[&vote_record, &event.timestamp],
+                    )
+                    .instrument(query_span("UPDATE", "governance_votes"))
+                    .await
+                    .map_err(DatabaseError::QueryError)?;
             }
         }
+        db_tx.commit().await.map_err(DatabaseError::QueryError)?;
+        Ok(())
+    }
+
+    /// Insert balance `changes` in a single database transaction, skipping
+    /// rows already stored. Returns the number of new rows.
+    pub async fn insert_balance_changes(&self, changes: &[crate::models::BalanceChange]) -> Result<u64> {
+        let mut client = self.get_client().await?;
+        let db_tx = client.transaction().await.map_err(DatabaseError::QueryError)?;
+        let statement = db_tx
+            .prepare(
+                "INSERT INTO balance_changes
+                     (signature, slot, timestamp, account, owner, mint, delta, post_balance)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                 ON CONFLICT (signature, account, mint) DO NOTHING",
+            )
+            .await
+            .map_err(DatabaseError::QueryError)?;
+
+        let mut inserted = 0;
+        for change in changes {
+            inserted += db_tx
+                .execute(
+                    &statement,
+                    &[
+                        &change.signature,
+                        &change.slot,
+                        &change.timestamp,
+                        &change.account,
+                        &change.owner,
+                        &change.mint,
+                        &change.delta,
+                        &change.post_balance,
+                    ],
+                )
+                .instrument(query_span("INSERT", "balance_changes"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+        }
+        db_tx.commit().await.map_err(DatabaseError::QueryError)?;
+        Ok(inserted)
+    }
+
+    /// Insert balance `snapshots` in a single database transaction, replacing
+    /// snapshots of the same owner, mint and slot
+    pub async fn insert_balance_snapshots(&self, snapshots: &[crate::models::BalanceSnapshot]) -> Result<()> {
+        let mut client = self.get_client().await?;
+        let db_tx = client.transaction().await.map_err(DatabaseError::QueryError)?;
+        let statement = db_tx
+            .prepare(
+                "INSERT INTO balance_snapshots (owner, mint, slot, timestamp, balance, source)
+                 VALUES ($1, $2, $3, $4, $5, $6)
+                 ON CONFLICT (owner, mint, slot) DO UPDATE SET
+                     timestamp = EXCLUDED.timestamp,
+                     balance = EXCLUDED.balance,
+                     source = EXCLUDED.source",
+            )
+            .await
+            .map_err(DatabaseError::QueryError)?;
+
+        for snapshot in snapshots {
+            db_tx
+                .execute(
+                    &statement,
+                    &[
+                        &snapshot.owner,
+                        &snapshot.mint,
+                        &snapshot.slot,
+                        &snapshot.timestamp,
+                        &snapshot.balance,
+                        &snapshot.source,
+                    ],
+                )
+                .instrument(query_span("INSERT", "balance_snapshots"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+        }
+        db_tx.commit().await.map_err(DatabaseError::QueryError)?;
+        Ok(())
+    }
+
+    /// Latest snapshot of `owner`'s balance in `mint` at or before `at`
+    pub async fn latest_balance_snapshot(
+        &self,
+        owner: &str,
+        mint: &str,
+        at: crate::balances::BalancePoint,
+    ) -> Result<Option<crate::models::BalanceSnapshot>> {
+        let client = self.get_client().await?;
+        let (column, value) = at.sql();
+        let query = format!(
+            "SELECT * FROM balance_snapshots WHERE owner = $1 AND mint = $2 AND {} <= $3
+             ORDER BY slot DESC LIMIT 1",
+            column
+        );
+        let row = client
+            .query_opt(&query, &[&owner, &mint, &*value])
+            .instrument(query_span("SELECT", "balance_snapshots"))
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        Ok(row.as_ref().map(crate::models::BalanceSnapshot::from_row))
+    }
+
+    /// Changes of `owner`'s balance in `mint` after `after`, if set, up to and
+    /// including `until`, oldest first
+    pub async fn get_balance_changes(
+        &self,
+        owner: &str,
+        mint: &str,
+        after: Option<crate::balances::BalancePoint>,
+        until: crate::balances::BalancePoint,
+    ) -> Result<Vec<crate::models::BalanceChange>> {
+        let client = self.get_client().await?;
+        let (until_column, until_value) = until.sql();
+        let after = after.map(|after| after.sql());
+        let mut query = format!(
+            "SELECT * FROM balance_changes WHERE owner = $1 AND mint = $2 AND {} <= $3",
+            until_column
+        );
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = vec![&owner, &mint, &*until_value];
+        if let Some((after_column, after_value)) = &after {
+            query.push_str(&format!(" AND {} > $4", after_column));
+            params.push(&**after_value);
+        }
+        query.push_str(" ORDER BY slot, signature");
+        let rows = client
+            .query(&query, &params)
+            .instrument(query_span("SELECT", "balance_changes"))
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        Ok(rows.iter().map(crate::models::BalanceChange::from_row).collect())
+    }
+
+    /// Mints `owner` has stored balance changes or snapshots of
+    pub async fn get_owner_mints(&self, owner: &str) -> Result<Vec<String>> {
+        let client = self.get_client().await?;
+        let rows = client
+            .query(
+                "SELECT mint FROM balance_changes WHERE owner = $1
+                 UNION SELECT mint FROM balance_snapshots WHERE owner = $1",
+                &[&owner],
+            )
+            .instrument(query_span("SELECT", "balance_changes"))
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        Ok(rows.iter().map(|row| row.get("mint")).collect())
+    }
+
+    /// Insert or refresh token `accounts` in a single database transaction,
+    /// reopening accounts previously marked closed
+    pub async fn upsert_token_accounts(&self, accounts: &[crate::models::TokenAccount]) -> Result<()> {
+        let mut client = self.get_client().await?;
+        let db_tx = client.transaction().await.map_err(DatabaseError::QueryError)?;
+        let statement = db_tx
+            .prepare(
+                "INSERT INTO token_accounts (pubkey, mint, owner, amount, updated_at, program_id, slot, closed_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                 ON CONFLICT (pubkey) DO UPDATE SET
+                     mint = EXCLUDED.mint,
+                     owner = EXCLUDED.owner,
+                     amount = EXCLUDED.amount,
+                     updated_at = EXCLUDED.updated_at,
+                     program_id = EXCLUDED.program_id,
+                     slot = EXCLUDED.slot,
+                     closed_at = EXCLUDED.closed_at",
+            )
+            .await
+            .map_err(DatabaseError::QueryError)?;
+
+        for account in accounts {
+            // Raw units, stored as a whole NUMERIC
+            let amount = crate::models::TokenAmount::new(account.amount, 0);
+            db_tx
+                .execute(
+                    &statement,
+                    &[
+                        &account.pubkey,
+                        &account.mint,
+                        &account.owner,
+                        &amount,
+                        &account.updated_at,
+                        &account.program_id,
+                        &account.slot,
+                        &account.closed_at,
+                    ],
+                )
+                .instrument(query_span("INSERT", "token_accounts"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+        }
+        db_tx.commit().await.map_err(DatabaseError::QueryError)?;
+        Ok(())
+    }
+
+    /// Token accounts of `owner` not marked closed
+    pub async fn get_open_token_accounts(&self, owner: &str) -> Result<Vec<crate::models::TokenAccount>> {
+        let client = self.get_client().await?;
+        let rows = client
+            .query(
+                "SELECT * FROM token_accounts WHERE owner = $1 AND closed_at IS NULL",
+                &[&owner],
+            )
+            .instrument(query_span("SELECT", "token_accounts"))
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        Ok(rows.iter().map(crate::models::TokenAccount::from_row).collect::<std::result::Result<_, _>>()?)
+    }
 
+    /// Mark the token accounts `pubkeys` closed at `closed_at`, with a zero
+    /// balance. Returns the number of accounts updated.
+    pub async fn close_token_accounts(&self, pubkeys: &[String], closed_at: chrono::DateTime<chrono::Utc>) -> Result<u64> {
+        let client = self.get_client().await?;
+        client
+            .execute(
+                "UPDATE token_accounts SET amount = 0, closed_at = $2, updated_at = $2
+                 WHERE pubkey = ANY($1) AND closed_at IS NULL",
+                &[&pubkeys, &closed_at],
+            )
+            .instrument(query_span("UPDATE", "token_accounts"))
+            .await
+            .map_err(DatabaseError::QueryError)
+    }
+
+    /// Stored swaps selling or buying any of `mints` between `from` and `to`,
+    /// oldest first
+    pub async fn get_swaps(
+        &self,
+        mints: &[String],
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<crate::models::ProtocolInteraction>> {
+        let client = self.get_client().await?;
+        let rows = client
+            .query(
+                "SELECT * FROM protocol_interactions
+                 WHERE interaction_type = 'swap' AND timestamp >= $2 AND timestamp <= $3
+                   AND (details->>'input_mint' = ANY($1) OR details->>'output_mint' = ANY($1))
+                 ORDER BY timestamp, signature, event_index",
+                &[&mints, &from, &to],
+            )
+            .instrument(query_span("SELECT", "protocol_interactions"))
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        Ok(rows.iter().map(crate::models::ProtocolInteraction::from_row).collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Insert `prices` into `price_history` in a single database transaction,
+    /// replacing stored prices of the same mint, source, feed and timestamp
+    pub async fn insert_price_history(&self, prices: &[crate::models::PriceHistory]) -> Result<()> {
+        let mut client = self.get_client().await?;
+        let db_tx = client.transaction().await.map_err(DatabaseError::QueryError)?;
+        let statement = db_tx
+            .prepare(
+                "INSERT INTO price_history
+                     (id, token_mint, price_usd, timestamp, source, feed, slot, confidence, volume_usd, created_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                 ON CONFLICT (token_mint, source, (COALESCE(feed, '')), timestamp) DO UPDATE SET
+                     price_usd = EXCLUDED.price_usd,
+                     slot = EXCLUDED.slot,
+                     confidence = EXCLUDED.confidence,
+                     volume_usd = EXCLUDED.volume_usd,
+                     created_at = EXCLUDED.created_at",
+            )
+            .await
+            .map_err(DatabaseError::QueryError)?;
+
+        for price in prices {
+            db_tx
+                .execute(
+                    &statement,
+                    &[
+                        &price.id,
+                        &price.token_mint,
+                        &price.price_usd,
+                        &price.timestamp,
+                        &price.source,
+                        &price.feed,
+                        &price.slot,
+                        &price.confidence,
+                        &price.volume_usd,
+                        &price.created_at,
+                    ],
+                )
+                .instrument(query_span("INSERT", "price_history"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+        }
+        db_tx.commit().await.map_err(DatabaseError::QueryError)?;
         Ok(())
     }
+
+    /// Stored prices of each `(mint, from, to)` range in one query, ordered
+    /// by mint and time. Only prices of `sources` are returned, unless it is
+    /// empty.
+    pub async fn get_price_history(
+        &self,
+        ranges: &[(String, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)],
+        sources: &[String],
+    ) -> Result<Vec<crate::models::PriceHistory>> {
+        let client = self.get_client().await?;
+        let mints: Vec<&str> = ranges.iter().map(|(mint, _, _)| mint.as_str()).collect();
+        let starts: Vec<_> = ranges.iter().map(|(_, from, _)| *from).collect();
+        let ends: Vec<_> = ranges.iter().map(|(_, _, to)| *to).collect();
+        let rows = client
+            .query(
+                "SELECT DISTINCT p.* FROM price_history p
+                 JOIN unnest($1::varchar[], $2::timestamptz[], $3::timestamptz[]) AS r(mint, start_at, end_at)
+                   ON p.token_mint = r.mint AND p.timestamp BETWEEN r.start_at AND r.end_at
+                 WHERE cardinality($4::varchar[]) = 0 OR p.source = ANY($4)
+                 ORDER BY p.token_mint, p.timestamp, p.id",
+                &[&mints, &starts, &ends, &sources],
+            )
+            .instrument(query_span("SELECT", "price_history"))
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        Ok(rows.iter().map(crate::models::PriceHistory::from_row).collect())
+    }
+
+    /// Insert oracle account `updates` in a single database transaction,
+    /// skipping updates of an account already stored at the same slot
+    pub async fn insert_oracle_updates(&self, updates: &[crate::models::OracleUpdate]) -> Result<u64> {
+        let mut client = self.get_client().await?;
+        let db_tx = client.transaction().await.map_err(DatabaseError::QueryError)?;
+        let statement = db_tx
+            .prepare(
+                "INSERT INTO oracle_updates (account, oracle, slot, publish_time, data)
+                 VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (account, slot) DO NOTHING",
+            )
+            .await
+            .map_err(DatabaseError::QueryError)?;
+
+        let mut inserted = 0;
+        for update in updates {
+            inserted += db_tx
+                .execute(
+                    &statement,
+                    &[&update.account, &update.oracle, &update.slot, &update.publish_time, &update.data],
+                )
+                .instrument(query_span("INSERT", "oracle_updates"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+        }
+        db_tx.commit().await.map_err(DatabaseError::QueryError)?;
+        Ok(inserted)
+    }
+
+    /// Latest stored update of oracle `account` published at or before `at`
+    pub async fn latest_oracle_update(
+        &self,
+        account: &str,
+        at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<crate::models::OracleUpdate>> {
+        let client = self.get_client().await?;
+        let row = client
+            .query_opt(
+                "SELECT * FROM oracle_updates WHERE account = $1 AND publish_time <= $2
+                 ORDER BY publish_time DESC, slot DESC LIMIT 1",
+                &[&account, &at],
+            )
+            .instrument(query_span("SELECT", "oracle_updates"))
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        Ok(row.as_ref().map(crate::models::OracleUpdate::from_row))
+    }
+
+    /// Stored updates of oracle `account` published between `from` and `to`,
+    /// oldest first
+    pub async fn get_oracle_updates(
+        &self,
+        account: &str,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<crate::models::OracleUpdate>> {
+        let client = self.get_client().await?;
+        let rows = client
+            .query(
+                "SELECT * FROM oracle_updates WHERE account = $1 AND publish_time >= $2 AND publish_time <= $3
+                 ORDER BY publish_time, slot",
+                &[&account, &from, &to],
+            )
+            .instrument(query_span("SELECT", "oracle_updates"))
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        Ok(rows.iter().map(crate::models::OracleUpdate::from_row).collect())
+    }
+
+    /// Insert USD `valuations` in a single database transaction. A stored
+    /// valuation is only replaced by a priced one that is fresher, so values
+    /// never regress to staler prices.
+    pub async fn insert_usd_valuations(&self, valuations: &[crate::models::UsdValuation]) -> Result<u64> {
+        let mut client = self.get_client().await?;
+        let db_tx = client.transaction().await.map_err(DatabaseError::QueryError)?;
+        let statement = db_tx
+            .prepare(
+                "INSERT INTO usd_valuations
+                     (signature, kind, item, mint, amount, decimals, timestamp, price_usd, value_usd,
+                      price_source, price_timestamp, staleness_seconds, valued_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                 ON CONFLICT (signature, kind, item, mint) DO UPDATE SET
+                     price_usd = EXCLUDED.price_usd,
+                     value_usd = EXCLUDED.value_usd,
+                     price_source = EXCLUDED.price_source,
+                     price_timestamp = EXCLUDED.price_timestamp,
+                     staleness_seconds = EXCLUDED.staleness_seconds,
+                     valued_at = EXCLUDED.valued_at
+                 WHERE EXCLUDED.staleness_seconds IS NOT NULL
+                   AND (usd_valuations.staleness_seconds IS NULL
+                        OR EXCLUDED.staleness_seconds < usd_valuations.staleness_seconds)",
+            )
+            .await
+            .map_err(DatabaseError::QueryError)?;
+
+        let mut written = 0;
+        for valuation in valuations {
+            written += db_tx
+                .execute(
+                    &statement,
+                    &[
+                        &valuation.signature,
+                        &valuation.kind,
+                        &valuation.item,
+                        &valuation.mint,
+                        &valuation.amount,
+                        &valuation.decimals,
+                        &valuation.timestamp,
+                        &valuation.price_usd,
+                        &valuation.value_usd,
+                        &valuation.price_source,
+                        &valuation.price_timestamp,
+                        &valuation.staleness_seconds,
+                        &valuation.valued_at,
+                    ],
+                )
+                .instrument(query_span("INSERT", "usd_valuations"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+        }
+        db_tx.commit().await.map_err(DatabaseError::QueryError)?;
+        Ok(written)
+    }
+
+    /// USD valuations of every `(mint, from, to)` range, ordered by mint and
+    /// time
+    pub async fn get_usd_valuations(
+        &self,
+        ranges: &[(String, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)],
+    ) -> Result<Vec<crate::models::UsdValuation>> {
+        let client = self.get_client().await?;
+        let mints: Vec<&str> = ranges.iter().map(|(mint, _, _)| mint.as_str()).collect();
+        let starts: Vec<_> = ranges.iter().map(|(_, from, _)| *from).collect();
+        let ends: Vec<_> = ranges.iter().map(|(_, _, to)| *to).collect();
+        let rows = client
+            .query(
+                "SELECT DISTINCT v.* FROM usd_valuations v
+                 JOIN unnest($1::varchar[], $2::timestamptz[], $3::timestamptz[]) AS r(mint, start_at, end_at)
+                   ON v.mint = r.mint AND v.timestamp BETWEEN r.start_at AND r.end_at
+                 ORDER BY v.mint, v.timestamp, v.signature, v.kind, v.item",
+                &[&mints, &starts, &ends],
+            )
+            .instrument(query_span("SELECT", "usd_valuations"))
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        Ok(rows.iter().map(crate::models::UsdValuation::from_row).collect())
+    }
+
+    /// Insert or refresh `tokens` in a single database transaction
+    pub async fn upsert_tokens(&self, tokens: &[crate::models::TokenInfo]) -> Result<()> {
+        let mut client = self.get_client().await?;
+        let db_tx = client.transaction().await.map_err(DatabaseError::QueryError)?;
+        let statement = db_tx
+            .prepare(
+                "INSERT INTO tokens (mint, decimals, name, symbol, uri, logo_uri, source, updated_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                 ON CONFLICT (mint) DO UPDATE SET
+                     decimals = EXCLUDED.decimals,
+                     name = EXCLUDED.name,
+                     symbol = EXCLUDED.symbol,
+                     uri = EXCLUDED.uri,
+                     logo_uri = EXCLUDED.logo_uri,
+                     source = EXCLUDED.source,
+                     updated_at = EXCLUDED.updated_at",
+            )
+            .await
+            .map_err(DatabaseError::QueryError)?;
+
+        for token in tokens {
+            db_tx
+                .execute(
+                    &statement,
+                    &[
+                        &token.mint,
+                        &(token.decimals as i16),
+                        &token.name,
+                        &token.symbol,
+                        &token.uri,
+                        &token.logo_uri,
+                        &token.source,
+                        &token.updated_at,
+                    ],
+                )
+                .instrument(query_span("INSERT", "tokens"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+        }
+        db_tx.commit().await.map_err(DatabaseError::QueryError)?;
+        Ok(())
+    }
+
+    /// Stored tokens among `mints`
+    pub async fn get_tokens(&self, mints: &[String]) -> Result<Vec<crate::models::TokenInfo>> {
+        let client = self.get_client().await?;
+        let rows = client
+            .query("SELECT * FROM tokens WHERE mint = ANY($1)", &[&mints])
+            .instrument(query_span("SELECT", "tokens"))
+            .await
+            .map_err(DatabaseError::QueryError)?;
+        Ok(rows.iter().map(crate::models::TokenInfo::from_row).collect())
+    }
+
+    /// Names of migrations from `migrations::get_migrations` not yet recorded as applied
+    pub async fn pending_migrations(&self) -> Result<Vec<String>> {
+        let client = self.get_client().await?;
+        let applied: Vec<String> = match client
+            .query("SELECT name FROM migrations", &[])
+            .instrument(query_span("SELECT", "migrations"))
+            .await
+        {
+            Ok(rows) => rows.iter().map(|row| row.get("name")).collect(),
+            Err(e) if e.code() == Some(&tokio_postgres::error::SqlState::UNDEFINED_TABLE) => Vec::new(),
+            Err(e) => return Err(DatabaseError::QueryError(e)),
+        };
+
+        Ok(crate::db::migrations::get_migrations()
+            .iter()
+            .map(|migration| migration.name().to_string())
+            .filter(|name| !applied.contains(name))
+            .collect())
+    }
+}
+
+/// Reports unhealthy until every migration has been applied
+#[derive(Debug, Clone)]
+pub struct MigrationCheck {
+    database: Database,
+}
+
+impl MigrationCheck {
+    /// Create a check against `database`
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl HealthComponent for MigrationCheck {
+    fn name(&self) -> &str {
+        "migrations"
+    }
+
+    async fn component_health(&self) -> ComponentHealth {
+        let (status, pending) = match self.database.pending_migrations().await {
+            Ok(pending) if pending.is_empty() => (HealthStatus::Healthy, pending),
+            Ok(pending) => (
+                HealthStatus::Unhealthy(Some(format!("pending migrations: {}", pending.join(", ")))),
+                pending,
+            ),
+            Err(e) => (HealthStatus::Unhealthy(Some(e.to_string())), Vec::new()),
+        };
+        ComponentHealth {
+            name: self.name().to_string(),
+            status,
+            metrics: Some(json!({ "pending": pending })),
+        }
+    }
+}
+
+#[async_trait]
+impl HealthComponent for Database {
+    fn name(&self) -> &str {
+        "postgres"
+    }
+
+    /// Runs `SELECT 1` on a pooled connection. The pool is degraded when no
+    /// connections are idle and callers are queued for one.
+    async fn component_health(&self) -> ComponentHealth {
+        let start = Instant::now();
+        let probe = async {
+            let client = self.get_client().await?;
+            client
+                .query_one("SELECT 1", &[])
+                .instrument(tracing::info_span!("db.query", db.system = "postgresql", db.operation = "SELECT"))
+                .await
+                .map_err(DatabaseError::QueryError)?;
+            Ok::<_, DatabaseError>(())
+        }
+        .await;
+        let latency = start.elapsed();
+
+        let pool = self.pool.status();
+        let status = match probe {
+            Err(e) => HealthStatus::Unhealthy(Some(e.to_string())),
+            Ok(()) if pool.available == 0 && pool.waiting > 0 => HealthStatus::Degraded(Some(format!(
+                "pool exhausted: {} callers waiting for {} connections",
+                pool.waiting, pool.max_size
+            ))),
+            Ok(()) => HealthStatus::Healthy,
+        };
+
+        ComponentHealth {
+            name: self.name().to_string(),
+            status,
+            metrics: Some(json!({
+                "latency_ms": latency.as_secs_f64() * 1000.0,
+                "max_size": pool.max_size,
+                "size": pool.size,
+                "available": pool.available,
+                "waiting": pool.waiting,
+            })),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -124,4 +938,25 @@ mod tests {
         let db = Database::new(config).await;
         assert!(db.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_component_health_reports_pool() {
+        let config = DatabaseConfig::default();
+        let max_connections = config.max_connections;
+        let db = Database::new(config).await.unwrap();
+
+        let health = db.component_health().await;
+        assert_eq!(health.name, "postgres");
+        assert_eq!(health.metrics.unwrap()["max_size"], max_connections as u64);
+    }
+
+    #[tokio::test]
+    async fn test_migration_check_unhealthy_without_database() {
+        let config = DatabaseConfig { port: 1, ..Default::default() };
+        let db = Database::new(config).await.unwrap();
+
+        let health = MigrationCheck::new(db).component_health().await;
+        assert_eq!(health.name, "migrations");
+        assert!(!health.status.is_available());
+    }
 } 
\ No newline at end of file
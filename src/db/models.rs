@@ -1,3 +1,5 @@
+use crate::db::DatabaseError;
+use crate::models::{TokenAmount, UsdValue};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tokio_postgres::Row;
@@ -7,7 +9,7 @@ use uuid::Uuid;
 pub struct Transaction {
     pub id: Uuid,
     pub signature: String,
-    pub block_time: DateTime<Utc>,
+    pub block_time: Option<DateTime<Utc>>,
     pub slot: i64,
     pub status: String,
     pub created_at: DateTime<Utc>,
@@ -31,21 +33,23 @@ pub struct TokenAccount {
     pub pubkey: String,
     pub mint: String,
     pub owner: String,
-    pub amount: i64,
+    pub amount: u64,
     pub updated_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
 }
 
-impl From<Row> for TokenAccount {
-    fn from(row: Row) -> Self {
-        Self {
+impl TryFrom<Row> for TokenAccount {
+    type Error = DatabaseError;
+
+    fn try_from(row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
             pubkey: row.get("pubkey"),
             mint: row.get("mint"),
             owner: row.get("owner"),
-            amount: row.get("amount"),
+            amount: TokenAmount::from_decimal(row.get("amount"), 0)?.raw,
             updated_at: row.get("updated_at"),
             created_at: row.get("created_at"),
-        }
+        })
     }
 }
 
@@ -53,7 +57,7 @@ impl From<Row> for TokenAccount {
 pub struct PriceHistory {
     pub id: Uuid,
     pub token_mint: String,
-    pub price_usd: f64,
+    pub price_usd: UsdValue,
     pub timestamp: DateTime<Utc>,
     pub source: String,
     pub created_at: DateTime<Utc>,
@@ -78,22 +82,24 @@ pub struct ProtocolInteraction {
     pub wallet: String,
     pub protocol: String,
     pub interaction_type: String,
-    pub amount: f64,
+    pub amount: TokenAmount,
     pub timestamp: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
 }
 
-impl From<Row> for ProtocolInteraction {
-    fn from(row: Row) -> Self {
-        Self {
+impl TryFrom<Row> for ProtocolInteraction {
+    type Error = DatabaseError;
+
+    fn try_from(row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
             id: row.get("id"),
             wallet: row.get("wallet"),
             protocol: row.get("protocol"),
             interaction_type: row.get("interaction_type"),
-            amount: row.get("amount"),
+            amount: TokenAmount::from_decimal(row.get("amount"), row.get::<_, i16>("decimals") as u8)?,
             timestamp: row.get("timestamp"),
             created_at: row.get("created_at"),
-        }
+        })
     }
 }
 
@@ -133,7 +139,7 @@ mod tests {
             id: Uuid::new_v4(),
             signature: "test_sig".to_string(),
             slot: 123,
-            block_time: Utc::now(),
+            block_time: Some(Utc::now()),
             status: "success".to_string(),
             created_at: Utc::now(),
         };
@@ -171,7 +177,7 @@ mod tests {
         let price_history = PriceHistory {
             id: Uuid::new_v4(),
             token_mint: "test_mint".to_string(),
-            price_usd: 1.23,
+            price_usd: "1.23".parse().unwrap(),
             timestamp: Utc::now(),
             source: "test_source".to_string(),
             created_at: Utc::now(),
@@ -192,7 +198,7 @@ mod tests {
             wallet: "test_wallet".to_string(),
             protocol: "test_protocol".to_string(),
             interaction_type: "test_type".to_string(),
-            amount: 100.0,
+            amount: TokenAmount::new(100_000_000, 6),
             timestamp: Utc::now(),
             created_at: Utc::now(),
         };
@@ -0,0 +1,612 @@
+use crate::core::metrics::{
+    FETCHER_BLOCKS_TOTAL, FETCHER_BLOCK_ERRORS_TOTAL, FETCHER_CHECKPOINT_SLOT, FETCHER_SKIPPED_SLOTS_TOTAL,
+};
+use crate::fetcher::health::PipelineHealth;
+use crate::fetcher::transaction_fetcher::FetchError;
+use crate::rpc::client::BlockClientTrait;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use metrics::{counter, gauge};
+use solana_transaction_status::{
+    EncodedConfirmedBlock, EncodedTransaction, EncodedTransactionWithStatusMeta, UiInstruction, UiMessage,
+    UiParsedInstruction,
+};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::Instrument;
+
+/// Configuration for a [`BlockFetcher`]
+#[derive(Debug, Clone)]
+pub struct BlockFetcherConfig {
+    /// First slot to fetch
+    pub start_slot: u64,
+    /// Last slot to fetch (inclusive); `None` follows the chain tip
+    pub end_slot: Option<u64>,
+    /// Maximum number of `getBlock` requests in flight
+    pub concurrency: usize,
+    /// Slots covered by one `getBlocks` call (block count for `getBlocksWithLimit`)
+    pub slots_per_batch: u64,
+    /// Drop vote transactions from fetched blocks
+    pub exclude_votes: bool,
+    /// Delay before polling again once caught up with the tip
+    pub poll_interval: Duration,
+}
+
+impl Default for BlockFetcherConfig {
+    fn default() -> Self {
+        Self {
+            start_slot: 0,
+            end_slot: None,
+            concurrency: 8,
+            slots_per_batch: 100,
+            exclude_votes: false,
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl BlockFetcherConfig {
+    /// Create a configuration for the inclusive range `[start_slot, end_slot]`
+    pub fn range(start_slot: u64, end_slot: u64) -> Self {
+        Self {
+            start_slot,
+            end_slot: Some(end_slot),
+            ..Default::default()
+        }
+    }
+}
+
+/// A block emitted by the [`BlockFetcher`]
+#[derive(Debug)]
+pub struct FetchedBlock {
+    pub slot: u64,
+    pub block: EncodedConfirmedBlock,
+}
+
+/// Durable storage for the last slot a [`BlockFetcher`] has fully processed
+#[async_trait]
+pub trait SlotCheckpointStore: Send + Sync {
+    async fn load(&self) -> Result<Option<u64>, FetchError>;
+    async fn save(&self, slot: u64) -> Result<(), FetchError>;
+}
+
+/// Durable storage for the last signature an address backfill has fully processed
+#[async_trait]
+pub trait SignatureCheckpointStore: Send + Sync {
+    async fn load_signature(&self) -> Result<Option<String>, FetchError>;
+    async fn save_signature(&self, signature: &str) -> Result<(), FetchError>;
+}
+
+/// Stores the checkpoint as text in a file, replaced atomically on save
+#[derive(Debug, Clone)]
+pub struct FileCheckpointStore {
+    path: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    async fn read(&self) -> Result<Option<String>, FetchError> {
+        match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => Ok(Some(contents.trim().to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(FetchError::Checkpoint(format!("{}: {}", self.path.display(), e))),
+        }
+    }
+
+    async fn write(&self, contents: &str) -> Result<(), FetchError> {
+        let tmp = self.path.with_extension("tmp");
+        let write = async {
+            let mut file = tokio::fs::File::create(&tmp).await?;
+            tokio::io::AsyncWriteExt::write_all(&mut file, contents.as_bytes()).await?;
+            file.sync_all().await?;
+            tokio::fs::rename(&tmp, &self.path).await
+        };
+        write
+            .await
+            .map_err(|e| FetchError::Checkpoint(format!("{}: {}", self.path.display(), e)))
+    }
+}
+
+#[async_trait]
+impl SlotCheckpointStore for FileCheckpointStore {
+    async fn load(&self) -> Result<Option<u64>, FetchError> {
+        self.read()
+            .await?
+            .map(|contents| {
+                contents
+                    .parse()
+                    .map_err(|e| FetchError::Checkpoint(format!("{}: {}", self.path.display(), e)))
+            })
+            .transpose()
+    }
+
+    async fn save(&self, slot: u64) -> Result<(), FetchError> {
+        self.write(&slot.to_string()).await
+    }
+}
+
+#[async_trait]
+impl SignatureCheckpointStore for FileCheckpointStore {
+    async fn load_signature(&self) -> Result<Option<String>, FetchError> {
+        Ok(self.read().await?.filter(|signature| !signature.is_empty()))
+    }
+
+    async fn save_signature(&self, signature: &str) -> Result<(), FetchError> {
+        self.write(signature).await
+    }
+}
+
+/// Keeps the checkpoint in memory; useful for tests and one-off backfills
+#[derive(Debug, Default)]
+pub struct MemoryCheckpointStore {
+    slot: Mutex<Option<u64>>,
+    signature: Mutex<Option<String>>,
+}
+
+#[async_trait]
+impl SlotCheckpointStore for MemoryCheckpointStore {
+    async fn load(&self) -> Result<Option<u64>, FetchError> {
+        Ok(*self.slot.lock().unwrap())
+    }
+
+    async fn save(&self, slot: u64) -> Result<(), FetchError> {
+        *self.slot.lock().unwrap() = Some(slot);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SignatureCheckpointStore for MemoryCheckpointStore {
+    async fn load_signature(&self) -> Result<Option<String>, FetchError> {
+        Ok(self.signature.lock().unwrap().clone())
+    }
+
+    async fn save_signature(&self, signature: &str) -> Result<(), FetchError> {
+        *self.signature.lock().unwrap() = Some(signature.to_string());
+        Ok(())
+    }
+}
+
+/// Whether any instruction of `tx` invokes the vote program
+pub fn is_vote_transaction(tx: &EncodedTransactionWithStatusMeta) -> bool {
+    let vote_program = solana_sdk::vote::program::id();
+    let vote_program_str = vote_program.to_string();
+    match &tx.transaction {
+        EncodedTransaction::Json(ui) => match &ui.message {
+            UiMessage::Raw(message) => message.instructions.iter().any(|ix| {
+                message.account_keys.get(ix.program_id_index as usize) == Some(&vote_program_str)
+            }),
+            UiMessage::Parsed(message) => message.instructions.iter().any(|ix| match ix {
+                UiInstruction::Compiled(ix) => message
+                    .account_keys
+                    .get(ix.program_id_index as usize)
+                    .is_some_and(|key| key.pubkey == vote_program_str),
+                UiInstruction::Parsed(UiParsedInstruction::Parsed(ix)) => ix.program_id == vote_program_str,
+                UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(ix)) => {
+                    ix.program_id == vote_program_str
+                }
+            }),
+        },
+        encoded => encoded.decode().is_some_and(|tx| {
+            let keys = tx.message.static_account_keys();
+            tx.message
+                .instructions()
+                .iter()
+                .any(|ix| keys.get(ix.program_id_index as usize) == Some(&vote_program))
+        }),
+    }
+}
+
+/// Walks a slot range with `getBlocks`/`getBlocksWithLimit` and fetches each
+/// produced block with bounded concurrency.
+///
+/// Blocks are emitted in slot order. Slots reported as skipped by the node
+/// (-32007/-32009) are omitted. The checkpoint only advances when
+/// [`BlockFetcher::commit`] is called, so a batch that was not fully processed
+/// is fetched again after a restart.
+pub struct BlockFetcher<C: BlockClientTrait> {
+    client: C,
+    config: BlockFetcherConfig,
+    checkpoint: Box<dyn SlotCheckpointStore>,
+    /// First slot not covered by a returned batch
+    next_slot: u64,
+    /// Last slot covered by a returned batch, not yet committed
+    uncommitted: Option<u64>,
+    /// Last slot persisted to the checkpoint store
+    committed: Option<u64>,
+    health: Option<PipelineHealth>,
+}
+
+impl<C: BlockClientTrait> BlockFetcher<C> {
+    /// Create a fetcher starting at `config.start_slot` with an in-memory checkpoint
+    pub fn new(client: C, config: BlockFetcherConfig) -> Self {
+        Self {
+            client,
+            next_slot: config.start_slot,
+            config,
+            checkpoint: Box::new(MemoryCheckpointStore::default()),
+            uncommitted: None,
+            committed: None,
+            health: None,
+        }
+    }
+
+    /// Use `store` for checkpoints, resuming after the slot it holds
+    pub async fn with_checkpoint_store(
+        mut self,
+        store: impl SlotCheckpointStore + 'static,
+    ) -> Result<Self, FetchError> {
+        if let Some(slot) = store.load().await? {
+            self.next_slot = self.next_slot.max(slot + 1);
+            self.committed = Some(slot);
+            tracing::info!("Resuming block fetch at slot {}", self.next_slot);
+        }
+        self.checkpoint = Box::new(store);
+        Ok(self)
+    }
+
+    /// Report batch outcomes to `health`
+    pub fn with_health(mut self, health: PipelineHealth) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Configuration this fetcher was created with
+    pub fn config(&self) -> &BlockFetcherConfig {
+        &self.config
+    }
+
+    /// First slot the next batch will cover
+    pub fn next_slot(&self) -> u64 {
+        self.next_slot
+    }
+
+    /// Whether the whole configured range has been fetched
+    pub fn is_done(&self) -> bool {
+        self.config.end_slot.is_some_and(|end| self.next_slot > end)
+    }
+
+    /// Fetch the next batch of blocks in slot order.
+    ///
+    /// Returns an empty batch when the range is exhausted or, without an end
+    /// slot, when caught up with the tip. On error the cursor is unchanged and
+    /// the same slots are fetched again on the next call.
+    pub async fn next_batch(&mut self) -> Result<Vec<FetchedBlock>, FetchError> {
+        if self.is_done() {
+            return Ok(vec![]);
+        }
+        let span = tracing::info_span!(
+            "fetch.blocks",
+            start_slot = self.next_slot,
+            end_slot = self.config.end_slot,
+        );
+        let result = self.fetch_batch().instrument(span).await;
+        match &result {
+            Ok(blocks) => {
+                counter!(FETCHER_BLOCKS_TOTAL, blocks.len() as u64);
+                if let Some(health) = &self.health {
+                    health.record_batch(blocks.len());
+                }
+            }
+            Err(e) => {
+                counter!(FETCHER_BLOCK_ERRORS_TOTAL, 1);
+                if let Some(health) = &self.health {
+                    health.record_failure(e);
+                }
+            }
+        }
+        result
+    }
+
+    async fn fetch_batch(&mut self) -> Result<Vec<FetchedBlock>, FetchError> {
+        let start = self.next_slot;
+        let (slots, covered_to) = match self.config.end_slot {
+            Some(end) => {
+                let page_end = end.min(start.saturating_add(self.config.slots_per_batch.max(1) - 1));
+                let slots = self
+                    .client
+                    .get_blocks(start, Some(page_end))
+                    .await
+                    .map_err(|e| FetchError::Rpc(e.to_string()))?;
+                (slots, Some(page_end))
+            }
+            None => {
+                let slots = self
+                    .client
+                    .get_blocks_with_limit(start, self.config.slots_per_batch.max(1) as usize)
+                    .await
+                    .map_err(|e| FetchError::Rpc(e.to_string()))?;
+                // Past the last produced block the tip may still advance, so only
+                // the slots actually returned are covered
+                let covered_to = slots.last().copied();
+                (slots, covered_to)
+            }
+        };
+
+        let client = &self.client;
+        let exclude_votes = self.config.exclude_votes;
+        let results: Vec<Result<Option<FetchedBlock>, FetchError>> = stream::iter(
+            slots.into_iter().filter(|slot| covered_to.is_some_and(|end| (start..=end).contains(slot))),
+        )
+        .map(|slot| async move {
+            match client.get_block(slot).await {
+                Ok(mut block) => {
+                    if exclude_votes {
+                        block.transactions.retain(|tx| !is_vote_transaction(tx));
+                    }
+                    Ok(Some(FetchedBlock { slot, block }))
+                }
+                Err(e) if e.is_skipped_slot() => {
+                    tracing::debug!("Slot {} was skipped", slot);
+                    counter!(FETCHER_SKIPPED_SLOTS_TOTAL, 1);
+                    Ok(None)
+                }
+                Err(e) => Err(FetchError::Rpc(format!("slot {}: {}", slot, e))),
+            }
+        })
+        .buffered(self.config.concurrency.max(1))
+        .collect()
+        .await;
+
+        let blocks = results
+            .into_iter()
+            .filter_map(Result::transpose)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(covered_to) = covered_to {
+            self.next_slot = covered_to + 1;
+            self.uncommitted = Some(covered_to);
+        }
+        Ok(blocks)
+    }
+
+    /// Persist the checkpoint through the last slot of the batches returned so far
+    pub async fn commit(&mut self) -> Result<(), FetchError> {
+        if let Some(slot) = self.uncommitted.take() {
+            self.save_checkpoint(slot).await?;
+        }
+        Ok(())
+    }
+
+    /// Persist the checkpoint through `slot`, the last slot of a batch already
+    /// returned by [`BlockFetcher::next_batch`].
+    ///
+    /// Used when batches are processed asynchronously, so that batches fetched
+    /// after `slot` are not committed before they have been handled. A slot at
+    /// or before the current checkpoint, e.g. from a batch acknowledged out of
+    /// order, leaves the checkpoint unchanged.
+    pub async fn commit_slot(&mut self, slot: u64) -> Result<(), FetchError> {
+        if slot >= self.next_slot {
+            return Err(FetchError::Checkpoint(format!(
+                "slot {} has not been fetched yet (next slot {})",
+                slot, self.next_slot
+            )));
+        }
+        self.save_checkpoint(slot).await?;
+        if self.uncommitted.is_some_and(|uncommitted| uncommitted <= slot) {
+            self.uncommitted = None;
+        }
+        Ok(())
+    }
+
+    async fn save_checkpoint(&mut self, slot: u64) -> Result<(), FetchError> {
+        if self.committed.is_some_and(|committed| committed >= slot) {
+            return Ok(());
+        }
+        self.checkpoint.save(slot).await?;
+        self.committed = Some(slot);
+        gauge!(FETCHER_CHECKPOINT_SLOT, slot as f64);
+        Ok(())
+    }
+
+    /// Fetch until the range is exhausted, handing each non-empty batch to
+    /// `handle` and committing after it succeeds.
+    ///
+    /// Without an end slot this follows the tip indefinitely, sleeping
+    /// `poll_interval` whenever caught up.
+    pub async fn run<F, Fut>(&mut self, mut handle: F) -> Result<(), FetchError>
+    where
+        F: FnMut(Vec<FetchedBlock>) -> Fut + Send,
+        Fut: Future<Output = Result<(), FetchError>> + Send,
+    {
+        while !self.is_done() {
+            let next_slot = self.next_slot;
+            let blocks = self.next_batch().await?;
+            if !blocks.is_empty() {
+                handle(blocks).await?;
+            }
+            self.commit().await?;
+            if self.next_slot == next_slot {
+                tokio::time::sleep(self.config.poll_interval).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::client::MockBlockClientTrait;
+    use crate::rpc::error::RpcError;
+    use serde_json::json;
+
+    fn block(slot: u64, transactions: Vec<serde_json::Value>) -> EncodedConfirmedBlock {
+        serde_json::from_value(json!({
+            "previousBlockhash": "11111111111111111111111111111111",
+            "blockhash": "11111111111111111111111111111111",
+            "parentSlot": slot.saturating_sub(1),
+            "transactions": transactions,
+            "rewards": [],
+            "blockTime": null,
+            "blockHeight": slot,
+        }))
+        .unwrap()
+    }
+
+    fn raw_transaction(program_id: &str) -> serde_json::Value {
+        json!({
+            "transaction": {
+                "signatures": ["1111111111111111111111111111111111111111111111111111111111111111"],
+                "message": {
+                    "header": {
+                        "numRequiredSignatures": 1,
+                        "numReadonlySignedAccounts": 0,
+                        "numReadonlyUnsignedAccounts": 1,
+                    },
+                    "accountKeys": ["11111111111111111111111111111112", program_id],
+                    "recentBlockhash": "11111111111111111111111111111111",
+                    "instructions": [{ "programIdIndex": 1, "accounts": [0], "data": "" }],
+                },
+            },
+            "meta": null,
+        })
+    }
+
+    #[test]
+    fn test_is_vote_transaction() {
+        let vote = solana_sdk::vote::program::id().to_string();
+        let b = block(1, vec![raw_transaction(&vote), raw_transaction("11111111111111111111111111111111")]);
+        assert!(is_vote_transaction(&b.transactions[0]));
+        assert!(!is_vote_transaction(&b.transactions[1]));
+    }
+
+    #[tokio::test]
+    async fn test_range_in_slot_order_with_skipped_slots() {
+        let mut mock = MockBlockClientTrait::new();
+        mock.expect_get_blocks()
+            .withf(|start, end| *start == 10 && *end == Some(13))
+            .returning(|_, _| Ok(vec![10, 11, 12, 13]));
+        mock.expect_get_blocks()
+            .withf(|start, end| *start == 14 && *end == Some(15))
+            .returning(|_, _| Ok(vec![15]));
+        mock.expect_get_block().returning(|slot| {
+            if slot == 12 {
+                Err(RpcError::SlotSkipped(slot))
+            } else {
+                Ok(block(slot, vec![]))
+            }
+        });
+
+        let config = BlockFetcherConfig { slots_per_batch: 4, concurrency: 3, ..BlockFetcherConfig::range(10, 15) };
+        let mut fetcher = BlockFetcher::new(mock, config);
+
+        let mut slots = Vec::new();
+        fetcher
+            .run(|blocks| {
+                slots.extend(blocks.iter().map(|b| b.slot));
+                async { Ok(()) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(slots, vec![10, 11, 13, 15]);
+        assert!(fetcher.is_done());
+    }
+
+    #[tokio::test]
+    async fn test_exclude_votes() {
+        let vote = solana_sdk::vote::program::id().to_string();
+        let mut mock = MockBlockClientTrait::new();
+        mock.expect_get_blocks().returning(|_, _| Ok(vec![1]));
+        mock.expect_get_block().returning(move |slot| {
+            Ok(block(slot, vec![raw_transaction(&vote), raw_transaction("11111111111111111111111111111111")]))
+        });
+
+        let config = BlockFetcherConfig { exclude_votes: true, ..BlockFetcherConfig::range(1, 1) };
+        let blocks = BlockFetcher::new(mock, config).next_batch().await.unwrap();
+        assert_eq!(blocks[0].block.transactions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_is_retried_and_not_committed() {
+        let mut mock = MockBlockClientTrait::new();
+        mock.expect_get_blocks().returning(|_, _| Ok(vec![5, 6]));
+        mock.expect_get_block()
+            .withf(|slot| *slot == 6)
+            .times(1)
+            .returning(|_| Err(RpcError::Timeout));
+        mock.expect_get_block().returning(|slot| Ok(block(slot, vec![])));
+
+        let health = PipelineHealth::new("blocks");
+        let mut fetcher = BlockFetcher::new(mock, BlockFetcherConfig::range(5, 6)).with_health(health.clone());
+        assert!(fetcher.next_batch().await.is_err());
+        assert_eq!(fetcher.next_slot(), 5);
+        assert!(!health.status().is_healthy());
+
+        let blocks = fetcher.next_batch().await.unwrap();
+        assert_eq!(blocks.iter().map(|b| b.slot).collect::<Vec<_>>(), vec![5, 6]);
+    }
+
+    #[tokio::test]
+    async fn test_resume_from_file_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.checkpoint");
+
+        let mut mock = MockBlockClientTrait::new();
+        mock.expect_get_blocks().returning(|start, _| Ok(vec![start]));
+        mock.expect_get_block().returning(|slot| Ok(block(slot, vec![])));
+        let config = BlockFetcherConfig { slots_per_batch: 2, ..BlockFetcherConfig::range(100, 110) };
+        let mut fetcher = BlockFetcher::new(mock, config.clone())
+            .with_checkpoint_store(FileCheckpointStore::new(&path))
+            .await
+            .unwrap();
+        fetcher.next_batch().await.unwrap();
+        fetcher.commit().await.unwrap();
+        // An uncommitted batch does not move the checkpoint
+        fetcher.next_batch().await.unwrap();
+        assert_eq!(FileCheckpointStore::new(&path).load().await.unwrap(), Some(101));
+
+        let resumed = BlockFetcher::new(MockBlockClientTrait::new(), config)
+            .with_checkpoint_store(FileCheckpointStore::new(&path))
+            .await
+            .unwrap();
+        assert_eq!(resumed.next_slot(), 102);
+    }
+
+    #[tokio::test]
+    async fn test_commit_slot_out_of_order_keeps_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.checkpoint");
+
+        let mut mock = MockBlockClientTrait::new();
+        mock.expect_get_blocks().returning(|start, _| Ok(vec![start]));
+        mock.expect_get_block().returning(|slot| Ok(block(slot, vec![])));
+        let config = BlockFetcherConfig { slots_per_batch: 2, ..BlockFetcherConfig::range(100, 110) };
+        let mut fetcher = BlockFetcher::new(mock, config)
+            .with_checkpoint_store(FileCheckpointStore::new(&path))
+            .await
+            .unwrap();
+        fetcher.next_batch().await.unwrap();
+        fetcher.next_batch().await.unwrap();
+
+        // The second batch is acknowledged before the first
+        fetcher.commit_slot(103).await.unwrap();
+        fetcher.commit_slot(101).await.unwrap();
+        assert_eq!(FileCheckpointStore::new(&path).load().await.unwrap(), Some(103));
+        assert!(fetcher.commit_slot(104).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_follow_tip_with_limit() {
+        let mut mock = MockBlockClientTrait::new();
+        mock.expect_get_blocks_with_limit()
+            .withf(|start, limit| *start == 50 && *limit == 10)
+            .returning(|_, _| Ok(vec![50, 52]));
+        mock.expect_get_blocks_with_limit()
+            .withf(|start, _| *start == 53)
+            .returning(|_, _| Ok(vec![]));
+        mock.expect_get_block().returning(|slot| Ok(block(slot, vec![])));
+
+        let config = BlockFetcherConfig { start_slot: 50, slots_per_batch: 10, ..Default::default() };
+        let mut fetcher = BlockFetcher::new(mock, config);
+        assert_eq!(fetcher.next_batch().await.unwrap().len(), 2);
+        assert!(fetcher.next_batch().await.unwrap().is_empty());
+        assert_eq!(fetcher.next_slot(), 53);
+        assert!(!fetcher.is_done());
+    }
+}
@@ -0,0 +1,158 @@
+//! Liveness tracking for fetcher pipelines
+
+use crate::core::traits::{ComponentHealth, HealthComponent, HealthStatus};
+use async_trait::async_trait;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default time without progress before a pipeline is reported as degraded
+pub const DEFAULT_STALL_AFTER: Duration = Duration::from_secs(120);
+/// Default consecutive failed batches before a pipeline is reported as unhealthy
+pub const DEFAULT_MAX_CONSECUTIVE_FAILURES: u32 = 5;
+
+#[derive(Debug)]
+struct PipelineState {
+    started: Instant,
+    last_progress: Option<Instant>,
+    last_error: Option<String>,
+    consecutive_failures: u32,
+    items: u64,
+    batches: u64,
+}
+
+/// Shared health handle updated by a fetcher pipeline.
+///
+/// Clones share state, so one clone can be handed to the pipeline and another
+/// registered with a [`crate::core::health::HealthRegistry`].
+#[derive(Debug, Clone)]
+pub struct PipelineHealth {
+    name: String,
+    stall_after: Duration,
+    max_consecutive_failures: u32,
+    state: Arc<Mutex<PipelineState>>,
+}
+
+impl PipelineHealth {
+    /// Create a tracker for the pipeline `name`
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            stall_after: DEFAULT_STALL_AFTER,
+            max_consecutive_failures: DEFAULT_MAX_CONSECUTIVE_FAILURES,
+            state: Arc::new(Mutex::new(PipelineState {
+                started: Instant::now(),
+                last_progress: None,
+                last_error: None,
+                consecutive_failures: 0,
+                items: 0,
+                batches: 0,
+            })),
+        }
+    }
+
+    /// Set the time without progress after which the pipeline is degraded
+    pub fn with_stall_after(mut self, stall_after: Duration) -> Self {
+        self.stall_after = stall_after;
+        self
+    }
+
+    /// Set the consecutive failed batches after which the pipeline is unhealthy
+    pub fn with_max_consecutive_failures(mut self, max: u32) -> Self {
+        self.max_consecutive_failures = max;
+        self
+    }
+
+    /// Record a completed batch of `items`
+    pub fn record_batch(&self, items: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.last_progress = Some(Instant::now());
+        state.consecutive_failures = 0;
+        state.items += items as u64;
+        state.batches += 1;
+    }
+
+    /// Record a failed batch
+    pub fn record_failure(&self, error: impl ToString) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        state.last_error = Some(error.to_string());
+    }
+
+    /// Current status of the pipeline
+    pub fn status(&self) -> HealthStatus {
+        let state = self.state.lock().unwrap();
+        let idle_for = state.last_progress.unwrap_or(state.started).elapsed();
+        if state.consecutive_failures >= self.max_consecutive_failures {
+            HealthStatus::Unhealthy(Some(format!(
+                "{} consecutive failures, last: {}",
+                state.consecutive_failures,
+                state.last_error.as_deref().unwrap_or("unknown")
+            )))
+        } else if state.consecutive_failures > 0 {
+            HealthStatus::Degraded(state.last_error.clone())
+        } else if idle_for > self.stall_after {
+            HealthStatus::Degraded(Some(format!("no progress for {}s", idle_for.as_secs())))
+        } else {
+            HealthStatus::Healthy
+        }
+    }
+}
+
+#[async_trait]
+impl HealthComponent for PipelineHealth {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn component_health(&self) -> ComponentHealth {
+        let status = self.status();
+        let state = self.state.lock().unwrap();
+        ComponentHealth {
+            name: self.name.clone(),
+            status,
+            metrics: Some(json!({
+                "items": state.items,
+                "batches": state.batches,
+                "consecutive_failures": state.consecutive_failures,
+                "seconds_since_progress": state.last_progress.map(|t| t.elapsed().as_secs()),
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_status_transitions() {
+        let health = PipelineHealth::new("fetcher").with_max_consecutive_failures(2);
+        assert_eq!(health.status(), HealthStatus::Healthy);
+
+        health.record_failure("rpc timeout");
+        assert_eq!(health.status(), HealthStatus::Degraded(Some("rpc timeout".to_string())));
+
+        health.record_failure("rpc timeout");
+        assert!(!health.status().is_available());
+
+        health.record_batch(10);
+        assert_eq!(health.status(), HealthStatus::Healthy);
+    }
+
+    #[test]
+    fn test_pipeline_stall() {
+        let health = PipelineHealth::new("fetcher").with_stall_after(Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(matches!(health.status(), HealthStatus::Degraded(_)));
+    }
+
+    #[tokio::test]
+    async fn test_component_health_shares_state() {
+        let health = PipelineHealth::new("fetcher");
+        health.clone().record_batch(3);
+        let component = health.component_health().await;
+        assert_eq!(component.name, "fetcher");
+        assert_eq!(component.metrics.unwrap()["items"], 3);
+    }
+}
@@ -0,0 +1,12 @@
+//! Transaction and block fetching from Solana RPC endpoints
+
+pub mod block_fetcher;
+pub mod health;
+pub mod transaction_fetcher;
+
+pub use block_fetcher::{
+    BlockFetcher, BlockFetcherConfig, FetchedBlock, FileCheckpointStore, MemoryCheckpointStore, SignatureCheckpointStore,
+    SlotCheckpointStore,
+};
+pub use health::PipelineHealth;
+pub use transaction_fetcher::{FetchError, FetchProgress, FetchTransactions, FetchedTransaction, TransactionFetcher};
@@ -2,9 +2,11 @@ use solana_sdk::pubkey::Pubkey;
 use crate::models::transaction::Transaction;
 use async_trait::async_trait;
 use crate::rpc::client::RpcClientTrait;
-use mockall::predicate::*;
-use tracing;
-use chrono::Utc;
+use crate::fetcher::health::PipelineHealth;
+use crate::core::metrics::{FETCHER_BATCHES_TOTAL, FETCHER_TRANSACTIONS_TOTAL, FETCHER_TRANSACTION_ERRORS_TOTAL};
+use metrics::counter;
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, EncodedTransactionWithStatusMeta};
+use tracing::Instrument;
 
 /// Tracks progress of transaction fetching
 pub struct FetchProgress {
@@ -22,6 +24,8 @@ pub enum FetchError {
     RateLimited,
     #[error("Network error: {0}")]
     Network(String),
+    #[error("Checkpoint error: {0}")]
+    Checkpoint(String),
     #[error("Other: {0}")]
     Other(String),
 }
@@ -32,6 +36,7 @@ pub struct TransactionFetcher<C: RpcClientTrait> {
     pub address: Pubkey,
     pub batch_size: usize,
     pub checkpoint: Option<String>, // Last fetched signature
+    pub health: Option<PipelineHealth>, // Updated after every batch when set
 }
 
 #[async_trait]
@@ -43,31 +48,114 @@ pub trait FetchTransactions {
     fn progress(&self) -> FetchProgress;
 }
 
-#[async_trait]
-impl<C: RpcClientTrait> FetchTransactions for TransactionFetcher<C> {
-    async fn fetch_next_batch(&mut self) -> Result<Vec<Transaction>, FetchError> {
-        // Fetch signatures for the address, paginated by checkpoint
-        let sigs = self
+/// A transaction fetched by signature with its full status meta
+#[derive(Debug)]
+pub struct FetchedTransaction {
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub transaction: EncodedTransactionWithStatusMeta,
+}
+
+impl From<EncodedConfirmedTransactionWithStatusMeta> for FetchedTransaction {
+    fn from(confirmed: EncodedConfirmedTransactionWithStatusMeta) -> Self {
+        Self {
+            slot: confirmed.slot,
+            block_time: confirmed.block_time,
+            transaction: confirmed.transaction,
+        }
+    }
+}
+
+impl<C: RpcClientTrait> TransactionFetcher<C> {
+    /// Fetch the next page of signatures and advance the checkpoint past it
+    async fn next_signatures(&mut self) -> Result<Vec<String>, FetchError> {
+        let sigs = match self
             .rpc_client
             .get_signatures_for_address(&self.address, self.checkpoint.clone(), self.batch_size)
             .await
-            .map_err(|e| FetchError::Rpc(e.to_string()))?;
-        if sigs.is_empty() {
-            return Ok(vec![]);
-        }
+        {
+            Ok(sigs) => sigs,
+            Err(e) => {
+                if let Some(health) = &self.health {
+                    health.record_failure(&e);
+                }
+                return Err(FetchError::Rpc(e.to_string()));
+            }
+        };
+        counter!(FETCHER_BATCHES_TOTAL, 1);
         // Update checkpoint to the last signature
-        self.checkpoint = sigs.last().cloned();
-        // Fetch full transactions for each signature
-        let mut txs = Vec::with_capacity(sigs.len());
-        for sig in sigs {
-            match self.rpc_client.get_transaction(&sig).await {
-                Ok(tx) => txs.push(tx),
-                Err(e) => {
-                    tracing::warn!("Failed to fetch transaction for {}: {}", sig, e);
+        if let Some(last) = sigs.last() {
+            self.checkpoint = Some(last.clone());
+        }
+        Ok(sigs)
+    }
+
+    fn record_fetched(&self, fetched: usize) {
+        counter!(FETCHER_TRANSACTIONS_TOTAL, fetched as u64);
+        if let Some(health) = &self.health {
+            health.record_batch(fetched);
+        }
+    }
+
+    /// Like [`FetchTransactions::fetch_next_batch`], returning each
+    /// transaction with its full status meta so that it can be parsed
+    pub async fn fetch_next_encoded_batch(&mut self) -> Result<Vec<FetchedTransaction>, FetchError> {
+        let span = tracing::info_span!(
+            "fetch.batch",
+            address = %self.address,
+            before = self.checkpoint.as_deref().unwrap_or(""),
+            batch_size = self.batch_size,
+        );
+        async {
+            let sigs = self.next_signatures().await?;
+            let mut txs = Vec::with_capacity(sigs.len());
+            for sig in sigs {
+                let tx_span = tracing::info_span!("fetch.transaction", signature = %sig);
+                match self.rpc_client.get_encoded_transaction(&sig).instrument(tx_span).await {
+                    Ok(tx) => txs.push(FetchedTransaction::from(tx)),
+                    Err(e) => {
+                        counter!(FETCHER_TRANSACTION_ERRORS_TOTAL, 1);
+                        tracing::warn!("Failed to fetch transaction for {}: {}", sig, e);
+                    }
                 }
             }
+            self.record_fetched(txs.len());
+            Ok(txs)
         }
-        Ok(txs)
+        .instrument(span)
+        .await
+    }
+}
+
+#[async_trait]
+impl<C: RpcClientTrait> FetchTransactions for TransactionFetcher<C> {
+    async fn fetch_next_batch(&mut self) -> Result<Vec<Transaction>, FetchError> {
+        let span = tracing::info_span!(
+            "fetch.batch",
+            address = %self.address,
+            before = self.checkpoint.as_deref().unwrap_or(""),
+            batch_size = self.batch_size,
+        );
+        async {
+            // Fetch signatures for the address, paginated by checkpoint
+            let sigs = self.next_signatures().await?;
+            // Fetch full transactions for each signature
+            let mut txs = Vec::with_capacity(sigs.len());
+            for sig in sigs {
+                let tx_span = tracing::info_span!("fetch.transaction", signature = %sig);
+                match self.rpc_client.get_transaction(&sig).instrument(tx_span).await {
+                    Ok(tx) => txs.push(tx),
+                    Err(e) => {
+                        counter!(FETCHER_TRANSACTION_ERRORS_TOTAL, 1);
+                        tracing::warn!("Failed to fetch transaction for {}: {}", sig, e);
+                    }
+                }
+            }
+            self.record_fetched(txs.len());
+            Ok(txs)
+        }
+        .instrument(span)
+        .await
     }
 
     async fn fetch_all(&mut self) -> Result<Vec<Transaction>, FetchError> {
@@ -99,6 +187,7 @@ mod tests {
     use mockall::predicate::*;
     use crate::rpc::client::MockRpcClientTrait;
     use crate::models::transaction::Transaction;
+    use chrono::Utc;
 
     #[tokio::test]
     async fn test_fetch_signatures_pagination() {
@@ -137,6 +226,7 @@ mod tests {
             address,
             batch_size: 2,
             checkpoint: None,
+            health: None,
         };
 
         // First batch
@@ -170,7 +260,7 @@ mod tests {
         let tx1 = Transaction {
             signature: "sig1".to_string(),
             slot: 1,
-            block_time: Utc::now(),
+            block_time: Some(Utc::now()),
             fee: 5000,
             status: "success".to_string(),
             instructions_json: "{}".to_string(),
@@ -179,7 +269,7 @@ mod tests {
         let tx2 = Transaction {
             signature: "sig2".to_string(),
             slot: 2,
-            block_time: Utc::now(),
+            block_time: Some(Utc::now()),
             fee: 6000,
             status: "success".to_string(),
             instructions_json: "{}".to_string(),
@@ -200,6 +290,7 @@ mod tests {
             address,
             batch_size: 2,
             checkpoint: None,
+            health: None,
         };
 
         let txs = fetcher.fetch_next_batch().await.unwrap();
@@ -207,4 +298,23 @@ mod tests {
         assert_eq!(txs[0].signature, "sig1");
         assert_eq!(txs[1].signature, "sig2");
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_fetch_updates_pipeline_health() {
+        let mut mock = MockRpcClientTrait::new();
+        mock.expect_get_signatures_for_address()
+            .returning(|_, _, _| Err(crate::rpc::error::RpcError::Timeout));
+
+        let health = PipelineHealth::new("address-fetcher").with_max_consecutive_failures(1);
+        let mut fetcher = TransactionFetcher {
+            rpc_client: mock,
+            address: Pubkey::new_unique(),
+            batch_size: 2,
+            checkpoint: None,
+            health: Some(health.clone()),
+        };
+
+        assert!(fetcher.fetch_next_batch().await.is_err());
+        assert!(!health.status().is_available());
+    }
+}
@@ -11,6 +11,11 @@ pub mod core;
 pub mod rpc;
 pub mod models;
 pub mod db;
+pub mod fetcher;
+pub mod parsers;
+pub mod tokens;
+pub mod balances;
+pub mod prices;
 
 // Re-export commonly used types
 pub use core::error::{Error, Result};
@@ -0,0 +1,659 @@
+//! Exact lamport, token and USD amounts
+//!
+//! Amounts never go through `f64`: [`Lamports`], [`TokenAmount`] and
+//! [`RawAmount`] keep the raw integer units of the chain and [`UsdValue`] is
+//! a decimal. All of them are stored as `NUMERIC`, so values round-trip
+//! through the database unchanged.
+
+use bytes::BytesMut;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::RoundingStrategy;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub};
+use std::str::FromStr;
+use tokio_postgres::types::{accepts, to_sql_checked, FromSql, IsNull, ToSql, Type};
+
+pub use rust_decimal::Decimal;
+
+type SqlError = Box<dyn std::error::Error + Sync + Send>;
+
+/// Largest scale a [`Decimal`] holds exactly
+const MAX_SCALE: u8 = 28;
+
+/// An amount that cannot be parsed or represented
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum AmountError {
+    #[error("Invalid amount {0:?}")]
+    Invalid(String),
+    #[error("Amount {amount:?} has more than {decimals} decimals")]
+    Precision { amount: String, decimals: u8 },
+    #[error("Amount {0} is out of range")]
+    OutOfRange(String),
+}
+
+/// `raw` base units of a mint with `decimals` decimals, as a decimal in UI
+/// units
+fn scaled(raw: i128, decimals: u8) -> Decimal {
+    if decimals <= MAX_SCALE {
+        if let Ok(value) = Decimal::try_from_i128_with_scale(raw, decimals as u32) {
+            return value;
+        }
+    }
+    // Beyond the exact range, round the surplus digits away
+    let mut value = Decimal::from_i128_with_scale(raw, MAX_SCALE as u32);
+    for _ in MAX_SCALE..decimals {
+        value /= Decimal::TEN;
+    }
+    value
+}
+
+/// Base units of the UI amount `text` with `decimals` decimals
+fn parse_units(text: &str, decimals: u8) -> Result<u64, AmountError> {
+    let invalid = || AmountError::Invalid(text.to_string());
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || !whole.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals as usize {
+        return Err(AmountError::Precision {
+            amount: text.to_string(),
+            decimals,
+        });
+    }
+    let digits = format!("{}{}{}", whole, fraction, "0".repeat(decimals as usize - fraction.len()));
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    digits.parse().map_err(|_| AmountError::OutOfRange(text.to_string()))
+}
+
+/// `raw` base units with `decimals` decimals in UI units, without trailing
+/// zeros
+fn format_units(raw: u64, decimals: u8) -> String {
+    let digits = format!("{:0>width$}", raw, width = decimals as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals as usize);
+    match fraction.trim_end_matches('0') {
+        "" => whole.to_string(),
+        fraction => format!("{}.{}", whole, fraction),
+    }
+}
+
+/// A non-negative whole [`Decimal`] as `u64`
+fn whole_u64(value: Decimal) -> Option<u64> {
+    let value = value.normalize();
+    (value.scale() == 0).then(|| value.to_u64()).flatten()
+}
+
+/// An amount of native SOL in lamports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Lamports(pub u64);
+
+impl Lamports {
+    pub const ZERO: Lamports = Lamports(0);
+    /// Decimals of SOL
+    pub const DECIMALS: u8 = 9;
+    pub const PER_SOL: u64 = 1_000_000_000;
+
+    pub fn checked_add(self, other: Lamports) -> Option<Lamports> {
+        self.0.checked_add(other.0).map(Lamports)
+    }
+
+    pub fn checked_sub(self, other: Lamports) -> Option<Lamports> {
+        self.0.checked_sub(other.0).map(Lamports)
+    }
+
+    pub fn saturating_sub(self, other: Lamports) -> Lamports {
+        Lamports(self.0.saturating_sub(other.0))
+    }
+
+    /// Amount in SOL
+    pub fn to_sol(self) -> Decimal {
+        scaled(self.0 as i128, Self::DECIMALS)
+    }
+
+    pub fn to_token_amount(self) -> TokenAmount {
+        TokenAmount::new(self.0, Self::DECIMALS)
+    }
+}
+
+impl From<u64> for Lamports {
+    fn from(lamports: u64) -> Self {
+        Lamports(lamports)
+    }
+}
+
+impl Add for Lamports {
+    type Output = Lamports;
+
+    fn add(self, other: Lamports) -> Lamports {
+        Lamports(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Lamports {
+    fn add_assign(&mut self, other: Lamports) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Lamports {
+    type Output = Lamports;
+
+    fn sub(self, other: Lamports) -> Lamports {
+        Lamports(self.0 - other.0)
+    }
+}
+
+impl Sum for Lamports {
+    fn sum<I: Iterator<Item = Lamports>>(iter: I) -> Lamports {
+        iter.fold(Lamports::ZERO, Add::add)
+    }
+}
+
+/// SOL with all nine decimals, e.g. `1.500000000 SOL`
+impl fmt::Display for Lamports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:09} SOL", self.0 / Self::PER_SOL, self.0 % Self::PER_SOL)
+    }
+}
+
+/// Parses an amount of SOL, with or without the `SOL` suffix
+impl FromStr for Lamports {
+    type Err = AmountError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let sol = text.strip_suffix("SOL").unwrap_or(text).trim_end();
+        parse_units(sol, Self::DECIMALS).map(Lamports)
+    }
+}
+
+impl ToSql for Lamports {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, SqlError> {
+        Decimal::from(self.0).to_sql(ty, out)
+    }
+
+    accepts!(NUMERIC);
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for Lamports {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, SqlError> {
+        let value = Decimal::from_sql(ty, raw)?;
+        whole_u64(value)
+            .map(Lamports)
+            .ok_or_else(|| AmountError::OutOfRange(value.to_string()).into())
+    }
+
+    accepts!(NUMERIC);
+}
+
+/// A signed amount in raw base units, lamports or token base units, of a
+/// mint whose decimals are kept alongside. Wide enough for any balance and
+/// for the change between two balances.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RawAmount(pub i128);
+
+impl RawAmount {
+    pub const ZERO: RawAmount = RawAmount(0);
+
+    /// Change from `pre` to `post`
+    pub fn change(pre: u64, post: u64) -> RawAmount {
+        RawAmount(post as i128 - pre as i128)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    /// Amount in UI units of a mint with `decimals` decimals
+    pub fn to_decimal(&self, decimals: u8) -> Decimal {
+        scaled(self.0, decimals)
+    }
+}
+
+impl From<u64> for RawAmount {
+    fn from(raw: u64) -> Self {
+        RawAmount(raw as i128)
+    }
+}
+
+impl From<Lamports> for RawAmount {
+    fn from(lamports: Lamports) -> Self {
+        RawAmount::from(lamports.0)
+    }
+}
+
+impl Add for RawAmount {
+    type Output = RawAmount;
+
+    fn add(self, other: RawAmount) -> RawAmount {
+        RawAmount(self.0 + other.0)
+    }
+}
+
+impl AddAssign for RawAmount {
+    fn add_assign(&mut self, other: RawAmount) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for RawAmount {
+    type Output = RawAmount;
+
+    fn sub(self, other: RawAmount) -> RawAmount {
+        RawAmount(self.0 - other.0)
+    }
+}
+
+impl Neg for RawAmount {
+    type Output = RawAmount;
+
+    fn neg(self) -> RawAmount {
+        RawAmount(-self.0)
+    }
+}
+
+impl Sum for RawAmount {
+    fn sum<I: Iterator<Item = RawAmount>>(iter: I) -> RawAmount {
+        iter.fold(RawAmount::ZERO, Add::add)
+    }
+}
+
+impl fmt::Display for RawAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl ToSql for RawAmount {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, SqlError> {
+        Decimal::try_from_i128_with_scale(self.0, 0)
+            .map_err(|_| AmountError::OutOfRange(self.to_string()))?
+            .to_sql(ty, out)
+    }
+
+    accepts!(NUMERIC);
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for RawAmount {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, SqlError> {
+        let value = Decimal::from_sql(ty, raw)?.normalize();
+        if value.scale() != 0 {
+            return Err(AmountError::Invalid(value.to_string()).into());
+        }
+        Ok(RawAmount(value.mantissa()))
+    }
+
+    accepts!(NUMERIC);
+}
+
+/// An amount of a token in raw base units, with the decimals of its mint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TokenAmount {
+    pub raw: u64,
+    pub decimals: u8,
+}
+
+impl TokenAmount {
+    pub const fn new(raw: u64, decimals: u8) -> Self {
+        Self { raw, decimals }
+    }
+
+    /// Parses an amount in UI units, e.g. `"12.5"` for 12 500 000 base units
+    /// of a 6-decimal mint. Fails on more fractional digits than `decimals`.
+    pub fn parse(text: &str, decimals: u8) -> Result<Self, AmountError> {
+        parse_units(text.trim(), decimals).map(|raw| Self::new(raw, decimals))
+    }
+
+    /// Amount of `value` UI units of a mint with `decimals` decimals. Fails
+    /// on more fractional digits than `decimals`.
+    pub fn from_decimal(value: Decimal, decimals: u8) -> Result<Self, AmountError> {
+        parse_units(&value.normalize().to_string(), decimals).map(|raw| Self::new(raw, decimals))
+    }
+
+    /// Amount in UI units
+    pub fn to_decimal(&self) -> Decimal {
+        scaled(self.raw as i128, self.decimals)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.raw == 0
+    }
+
+    /// Sum of two amounts of the same mint, `None` on overflow or when the
+    /// decimals differ
+    pub fn checked_add(self, other: TokenAmount) -> Option<TokenAmount> {
+        (self.decimals == other.decimals)
+            .then(|| self.raw.checked_add(other.raw))
+            .flatten()
+            .map(|raw| Self::new(raw, self.decimals))
+    }
+
+    /// Difference of two amounts of the same mint, `None` below zero or when
+    /// the decimals differ
+    pub fn checked_sub(self, other: TokenAmount) -> Option<TokenAmount> {
+        (self.decimals == other.decimals)
+            .then(|| self.raw.checked_sub(other.raw))
+            .flatten()
+            .map(|raw| Self::new(raw, self.decimals))
+    }
+}
+
+impl From<Lamports> for TokenAmount {
+    fn from(lamports: Lamports) -> Self {
+        lamports.to_token_amount()
+    }
+}
+
+/// UI units without trailing zeros, e.g. `12.5`
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_units(self.raw, self.decimals))
+    }
+}
+
+/// Stored in UI units, so the column must be an unconstrained `NUMERIC`.
+/// There is no `FromSql`: the scale of a `NUMERIC` does not survive column
+/// conversions and aggregates, so tables keep the decimals in a column of
+/// their own and rows are read back with [`TokenAmount::from_decimal`].
+impl ToSql for TokenAmount {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, SqlError> {
+        if self.decimals > MAX_SCALE {
+            return Err(AmountError::OutOfRange(self.to_string()).into());
+        }
+        self.to_decimal().to_sql(ty, out)
+    }
+
+    accepts!(NUMERIC);
+    to_sql_checked!();
+}
+
+/// An exact amount of US dollars
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UsdValue(Decimal);
+
+impl UsdValue {
+    pub const ZERO: UsdValue = UsdValue(Decimal::ZERO);
+    pub const ONE: UsdValue = UsdValue(Decimal::ONE);
+
+    pub const fn new(value: Decimal) -> Self {
+        Self(value)
+    }
+
+    /// Value of a float reported by an external source, keeping its shortest
+    /// decimal representation, `None` if it is not finite
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        Decimal::from_str(&value.to_string()).ok().map(Self)
+    }
+
+    pub fn as_decimal(&self) -> Decimal {
+        self.0
+    }
+
+    /// Nearest float, for statistics and display only
+    pub fn to_f64(&self) -> f64 {
+        self.0.to_f64().unwrap_or_default()
+    }
+
+    /// Value of `amount` at this price per whole token
+    pub fn value_of(&self, amount: TokenAmount) -> UsdValue {
+        Self(self.0 * amount.to_decimal())
+    }
+
+    /// Value of `raw` base units, negative for outflows, with `decimals`
+    /// decimals at this price per whole token
+    pub fn value_of_raw(&self, raw: RawAmount, decimals: u8) -> UsdValue {
+        Self(self.0 * raw.to_decimal(decimals))
+    }
+
+    /// Rounded to `dp` decimals, halves away from zero
+    pub fn round_dp(&self, dp: u32) -> UsdValue {
+        Self(self.0.round_dp_with_strategy(dp, RoundingStrategy::MidpointAwayFromZero))
+    }
+
+    pub fn abs(&self) -> UsdValue {
+        Self(self.0.abs())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// Whether the value is above zero
+    pub fn is_positive(&self) -> bool {
+        self.0 > Decimal::ZERO
+    }
+
+    pub fn checked_add(self, other: UsdValue) -> Option<UsdValue> {
+        self.0.checked_add(other.0).map(Self)
+    }
+
+    pub fn checked_sub(self, other: UsdValue) -> Option<UsdValue> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    pub fn checked_mul(self, factor: Decimal) -> Option<UsdValue> {
+        self.0.checked_mul(factor).map(Self)
+    }
+
+    /// Ratio of two values, `None` when `other` is zero
+    pub fn checked_ratio(self, other: UsdValue) -> Option<Decimal> {
+        self.0.checked_div(other.0)
+    }
+}
+
+impl From<Decimal> for UsdValue {
+    fn from(value: Decimal) -> Self {
+        Self(value)
+    }
+}
+
+impl From<i64> for UsdValue {
+    fn from(dollars: i64) -> Self {
+        Self(Decimal::from(dollars))
+    }
+}
+
+impl Add for UsdValue {
+    type Output = UsdValue;
+
+    fn add(self, other: UsdValue) -> UsdValue {
+        Self(self.0 + other.0)
+    }
+}
+
+impl AddAssign for UsdValue {
+    fn add_assign(&mut self, other: UsdValue) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for UsdValue {
+    type Output = UsdValue;
+
+    fn sub(self, other: UsdValue) -> UsdValue {
+        Self(self.0 - other.0)
+    }
+}
+
+impl Neg for UsdValue {
+    type Output = UsdValue;
+
+    fn neg(self) -> UsdValue {
+        Self(-self.0)
+    }
+}
+
+impl Mul<Decimal> for UsdValue {
+    type Output = UsdValue;
+
+    fn mul(self, factor: Decimal) -> UsdValue {
+        Self(self.0 * factor)
+    }
+}
+
+/// Panics when dividing by zero, like [`Decimal`]
+impl Div<Decimal> for UsdValue {
+    type Output = UsdValue;
+
+    fn div(self, divisor: Decimal) -> UsdValue {
+        Self(self.0 / divisor)
+    }
+}
+
+impl Sum for UsdValue {
+    fn sum<I: Iterator<Item = UsdValue>>(iter: I) -> UsdValue {
+        iter.fold(UsdValue::ZERO, Add::add)
+    }
+}
+
+/// Plain decimal, rounded to the precision if one is given, e.g. `{:.2}`
+/// for cents
+impl fmt::Display for UsdValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.precision() {
+            Some(precision) => fmt::Display::fmt(&self.round_dp(precision as u32).0, f),
+            None => fmt::Display::fmt(&self.0, f),
+        }
+    }
+}
+
+/// Parses a decimal, with or without a leading `$`
+impl FromStr for UsdValue {
+    type Err = AmountError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let digits = text.strip_prefix('$').unwrap_or(text);
+        Decimal::from_str_exact(digits)
+            .map(Self)
+            .map_err(|_| AmountError::Invalid(text.to_string()))
+    }
+}
+
+impl ToSql for UsdValue {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, SqlError> {
+        self.0.to_sql(ty, out)
+    }
+
+    accepts!(NUMERIC);
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for UsdValue {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, SqlError> {
+        Decimal::from_sql(ty, raw).map(Self)
+    }
+
+    accepts!(NUMERIC);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usd(text: &str) -> UsdValue {
+        text.parse().unwrap()
+    }
+
+    /// `value` written as NUMERIC and read back
+    fn round_trip<T: ToSql + for<'a> FromSql<'a>>(value: &T) -> T {
+        let mut buffer = BytesMut::new();
+        value.to_sql(&Type::NUMERIC, &mut buffer).unwrap();
+        T::from_sql(&Type::NUMERIC, &buffer).unwrap()
+    }
+
+    #[test]
+    fn test_lamports_parse_format_and_arithmetic() {
+        let lamports: Lamports = "1.5 SOL".parse().unwrap();
+        assert_eq!(lamports, Lamports(1_500_000_000));
+        assert_eq!("0.000000001".parse::<Lamports>().unwrap(), Lamports(1));
+        assert_eq!(lamports.to_string(), "1.500000000 SOL");
+        assert_eq!(lamports.to_sol().to_string(), "1.500000000");
+        assert!(matches!("0.0000000001".parse::<Lamports>(), Err(AmountError::Precision { .. })));
+        assert!(matches!("-1".parse::<Lamports>(), Err(AmountError::Invalid(_))));
+        assert!(matches!("18446744073.709551616".parse::<Lamports>(), Err(AmountError::OutOfRange(_))));
+
+        assert_eq!(Lamports(u64::MAX).checked_add(Lamports(1)), None);
+        assert_eq!(Lamports(5).checked_sub(Lamports(6)), None);
+        assert_eq!([Lamports(1), Lamports(2)].into_iter().sum::<Lamports>(), Lamports(3));
+        assert_eq!(round_trip(&Lamports(u64::MAX)), Lamports(u64::MAX));
+    }
+
+    #[test]
+    fn test_raw_amount_spans_u64_changes() {
+        let drained = RawAmount::change(u64::MAX, 0);
+        assert_eq!(drained, -RawAmount::from(u64::MAX));
+        assert!(drained.is_negative());
+        assert_eq!(drained + RawAmount::from(u64::MAX), RawAmount::ZERO);
+        assert_eq!(drained.to_decimal(9).to_string(), "-18446744073.709551615");
+        assert_eq!(round_trip(&drained), drained);
+        assert_eq!(serde_json::to_string(&RawAmount(-5)).unwrap(), "-5");
+    }
+
+    #[test]
+    fn test_token_amount_is_exact() {
+        let amount = TokenAmount::parse("12.5", 6).unwrap();
+        assert_eq!(amount, TokenAmount::new(12_500_000, 6));
+        assert_eq!(amount.to_string(), "12.5");
+        assert_eq!(TokenAmount::new(1, 9).to_string(), "0.000000001");
+        assert_eq!(TokenAmount::new(42, 0).to_string(), "42");
+        assert_eq!(TokenAmount::parse(".25", 2).unwrap().raw, 25);
+        assert!(TokenAmount::parse("1.2345", 2).is_err());
+        assert!(TokenAmount::parse("1e5", 2).is_err());
+
+        // u64::MAX base units survive, which an f64 would round
+        let max = TokenAmount::new(u64::MAX, 9);
+        assert_eq!(max.to_string(), "18446744073.709551615");
+        assert_eq!(TokenAmount::parse(&max.to_string(), 9).unwrap(), max);
+
+        // Read back with the stored decimals, whatever the scale
+        assert_eq!(TokenAmount::from_decimal(round_trip(&max.to_decimal()), 9).unwrap(), max);
+        assert_eq!(TokenAmount::from_decimal("12.50000000".parse().unwrap(), 6).unwrap(), amount);
+        assert!(TokenAmount::from_decimal("0.0000001".parse().unwrap(), 6).is_err());
+        assert!(TokenAmount::from_decimal("-1".parse().unwrap(), 6).is_err());
+
+        assert_eq!(amount.checked_add(TokenAmount::new(1, 6)).unwrap().raw, 12_500_001);
+        assert_eq!(amount.checked_add(TokenAmount::new(1, 9)), None);
+        assert_eq!(Lamports(2).to_token_amount().checked_sub(TokenAmount::new(3, 9)), None);
+    }
+
+    #[test]
+    fn test_usd_value_arithmetic() {
+        // 0.1 + 0.2 is 0.3, unlike in f64
+        assert_eq!(usd("0.1") + usd("0.2"), usd("0.3"));
+        assert_eq!(usd("$19.99").to_string(), "19.99");
+        assert_eq!(format!("{:.2}", usd("2.005")), "2.01");
+        assert!("abc".parse::<UsdValue>().is_err());
+        assert_eq!(UsdValue::from_f64(0.00002), Some(usd("0.00002")));
+        assert_eq!(UsdValue::from_f64(f64::NAN), None);
+
+        let price = usd("150.25");
+        assert_eq!(price.value_of(TokenAmount::new(2_500_000_000, 9)), usd("375.625"));
+        assert_eq!(price.value_of_raw(RawAmount(-1_000_000_000), 9), usd("-150.25"));
+        assert_eq!(usd("10").checked_ratio(UsdValue::ZERO), None);
+        assert_eq!([usd("1.5"), usd("2")].into_iter().sum::<UsdValue>(), usd("3.5"));
+        assert_eq!(round_trip(&price), price);
+
+        let json = serde_json::to_string(&price).unwrap();
+        assert_eq!(serde_json::from_str::<UsdValue>(&json).unwrap(), price);
+    }
+}
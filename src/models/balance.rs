@@ -0,0 +1,94 @@
+use crate::models::RawAmount;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Net change of one account's balance of one mint within a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceChange {
+    /// Signature of the transaction that changed the balance
+    pub signature: String,
+    pub slot: i64,
+    /// Block time of the transaction, when the node reports one
+    pub timestamp: Option<DateTime<Utc>>,
+    /// The token account, or the wallet itself for native SOL (base58 encoded)
+    pub account: String,
+    /// The owner's public key (base58 encoded), when the status meta lists it
+    pub owner: Option<String>,
+    /// The token mint (base58 encoded), or `native` for SOL
+    pub mint: String,
+    /// Change in raw units: lamports or token base units
+    pub delta: RawAmount,
+    /// Balance after the transaction in raw units
+    pub post_balance: RawAmount,
+    /// When this record was created
+    pub created_at: DateTime<Utc>,
+}
+
+impl BalanceChange {
+    /// Balance before the transaction in raw units
+    pub fn pre_balance(&self) -> RawAmount {
+        self.post_balance - self.delta
+    }
+
+    /// Converts a database row into a BalanceChange
+    pub fn from_row(row: &tokio_postgres::Row) -> Self {
+        Self {
+            signature: row.get("signature"),
+            slot: row.get("slot"),
+            timestamp: row.get("timestamp"),
+            account: row.get("account"),
+            owner: row.get("owner"),
+            mint: row.get("mint"),
+            delta: row.get("delta"),
+            post_balance: row.get("post_balance"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+/// Balance of one owner in one mint at a slot, summed over their accounts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceSnapshot {
+    /// The owner's public key (base58 encoded)
+    pub owner: String,
+    /// The token mint (base58 encoded), or `native` for SOL
+    pub mint: String,
+    /// Slot the balance was observed at, including the changes of that slot
+    pub slot: i64,
+    pub timestamp: DateTime<Utc>,
+    /// Balance in raw units
+    pub balance: RawAmount,
+    /// Where the balance came from: `rpc` when read from the chain, `replay`
+    /// when reconstructed from stored changes
+    pub source: String,
+    /// When this record was created
+    pub created_at: DateTime<Utc>,
+}
+
+impl BalanceSnapshot {
+    /// Creates a new balance snapshot record
+    pub fn new(owner: String, mint: String, slot: i64, timestamp: DateTime<Utc>, balance: RawAmount, source: &str) -> Self {
+        Self {
+            owner,
+            mint,
+            slot,
+            timestamp,
+            balance,
+            source: source.to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Converts a database row into a BalanceSnapshot
+    pub fn from_row(row: &tokio_postgres::Row) -> Self {
+        Self {
+            owner: row.get("owner"),
+            mint: row.get("mint"),
+            slot: row.get("slot"),
+            timestamp: row.get("timestamp"),
+            balance: row.get("balance"),
+            source: row.get("source"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
@@ -19,6 +19,27 @@ pub struct GovernanceVote {
     pub dao_name: String,
     /// When this record was created
     pub created_at: DateTime<Utc>,
+    /// Signature of the transaction the vote was cast in
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// Position of the vote among the transaction's parsed events
+    #[serde(default)]
+    pub event_index: Option<i32>,
+    /// Realm address of the DAO
+    #[serde(default)]
+    pub realm: Option<String>,
+    /// Vote record account, shared by later relinquishes of the vote
+    #[serde(default)]
+    pub vote_record: Option<String>,
+    /// Weight of the vote in governing token base units
+    #[serde(default)]
+    pub voter_weight: Option<i64>,
+    /// Approved options with their weight percentages
+    #[serde(default)]
+    pub choices: Option<serde_json::Value>,
+    /// Whether the vote was later relinquished
+    #[serde(default)]
+    pub relinquished: bool,
 }
 
 impl GovernanceVote {
@@ -38,9 +59,23 @@ impl GovernanceVote {
             timestamp,
             dao_name,
             created_at: Utc::now(),
+            signature: None,
+            event_index: None,
+            realm: None,
+            vote_record: None,
+            voter_weight: None,
+            choices: None,
+            relinquished: false,
         }
     }
 
+    /// Sets the transaction event the vote was parsed from
+    pub fn with_source(mut self, signature: String, event_index: i32) -> Self {
+        self.signature = Some(signature);
+        self.event_index = Some(event_index);
+        self
+    }
+
     /// Updates the vote
     pub fn update_vote(&mut self, new_vote: String) {
         self.vote = new_vote;
@@ -57,6 +92,59 @@ impl GovernanceVote {
             timestamp: row.get("timestamp"),
             dao_name: row.get("dao_name"),
             created_at: row.get("created_at"),
+            signature: row.get("signature"),
+            event_index: row.get("event_index"),
+            realm: row.get("realm"),
+            vote_record: row.get("vote_record"),
+            voter_weight: row.get("voter_weight"),
+            choices: row.get("choices"),
+            relinquished: row.get("relinquished"),
+        }
+    }
+}
+
+/// A step in a proposal's lifecycle (creation, sign-off, votes, finalization,
+/// execution)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GovernanceProposalEvent {
+    pub id: Uuid,
+    /// Signature of the transaction the event was parsed from
+    pub signature: String,
+    /// Position of the event among the transaction's parsed events
+    pub event_index: i32,
+    /// Realm address, unknown for executions
+    pub realm: Option<String>,
+    /// Realm name, or its address when the name is unknown
+    pub dao_name: Option<String>,
+    /// Proposal account address
+    pub proposal: String,
+    /// Lifecycle step (e.g., "created", "vote_cast", "executed")
+    pub event_type: String,
+    /// Wallet that performed the step, when known
+    pub actor: Option<String>,
+    /// The full parsed event
+    pub details: Option<serde_json::Value>,
+    /// When the step happened
+    pub timestamp: DateTime<Utc>,
+    /// When this record was created
+    pub created_at: DateTime<Utc>,
+}
+
+impl GovernanceProposalEvent {
+    /// Converts a database row into a GovernanceProposalEvent
+    pub fn from_row(row: &tokio_postgres::Row) -> Self {
+        Self {
+            id: row.get("id"),
+            signature: row.get("signature"),
+            event_index: row.get("event_index"),
+            realm: row.get("realm"),
+            dao_name: row.get("dao_name"),
+            proposal: row.get("proposal"),
+            event_type: row.get("event_type"),
+            actor: row.get("actor"),
+            details: row.get("details"),
+            timestamp: row.get("timestamp"),
+            created_at: row.get("created_at"),
         }
     }
 }
@@ -110,6 +198,13 @@ mod tests {
             timestamp: now,
             dao_name: "test_dao".to_string(),
             created_at: now,
+            signature: Some("sig".to_string()),
+            event_index: Some(1),
+            realm: Some("realm".to_string()),
+            vote_record: Some("record".to_string()),
+            voter_weight: Some(1_000),
+            choices: Some(serde_json::json!([{ "rank": 0, "weight_percentage": 100 }])),
+            relinquished: false,
         };
 
         let serialized = serde_json::to_string(&vote).unwrap();
@@ -118,5 +213,7 @@ mod tests {
         assert_eq!(vote.proposal_id, deserialized.proposal_id);
         assert_eq!(vote.vote, deserialized.vote);
         assert_eq!(vote.dao_name, deserialized.dao_name);
+        assert_eq!(vote.voter_weight, deserialized.voter_weight);
+        assert_eq!(vote.choices, deserialized.choices);
     }
 } 
\ No newline at end of file
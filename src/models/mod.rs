@@ -1,14 +1,20 @@
 //! Data models for the Solana analytics system
 
+pub mod amount;
 pub mod transaction;
 pub mod token;
 pub mod price;
 pub mod protocol;
 pub mod governance;
+pub mod balance;
+pub mod valuation;
 
 // Re-export commonly used types
+pub use amount::{AmountError, Lamports, RawAmount, TokenAmount, UsdValue};
 pub use transaction::Transaction;
-pub use token::TokenAccount;
-pub use price::PriceHistory;
+pub use token::{TokenAccount, TokenInfo};
+pub use price::{OracleUpdate, PriceHistory};
 pub use protocol::ProtocolInteraction;
-pub use governance::GovernanceVote; 
\ No newline at end of file
+pub use governance::{GovernanceProposalEvent, GovernanceVote};
+pub use balance::{BalanceChange, BalanceSnapshot};
+pub use valuation::UsdValuation;
\ No newline at end of file
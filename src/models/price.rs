@@ -1,3 +1,4 @@
+use crate::models::UsdValue;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -10,11 +11,23 @@ pub struct PriceHistory {
     /// The token mint public key (base58 encoded)
     pub token_mint: String,
     /// The price in USD
-    pub price_usd: f64,
+    pub price_usd: UsdValue,
     /// When this price was recorded
     pub timestamp: DateTime<Utc>,
     /// The source of the price data (e.g., "coingecko", "binance")
     pub source: String,
+    /// Account the price was read from, for on-chain sources such as oracles
+    #[serde(default)]
+    pub feed: Option<String>,
+    /// Slot the price was published at, for on-chain sources
+    #[serde(default)]
+    pub slot: Option<i64>,
+    /// Confidence interval in USD, if the source reports one
+    #[serde(default)]
+    pub confidence: Option<UsdValue>,
+    /// Traded volume in USD behind the price, if the source reports one
+    #[serde(default)]
+    pub volume_usd: Option<UsdValue>,
     /// When this record was created
     pub created_at: DateTime<Utc>,
 }
@@ -23,7 +36,7 @@ impl PriceHistory {
     /// Creates a new price history record
     pub fn new(
         token_mint: String,
-        price_usd: f64,
+        price_usd: UsdValue,
         timestamp: DateTime<Utc>,
         source: String,
     ) -> Self {
@@ -33,12 +46,16 @@ impl PriceHistory {
             price_usd,
             timestamp,
             source,
+            feed: None,
+            slot: None,
+            confidence: None,
+            volume_usd: None,
             created_at: Utc::now(),
         }
     }
 
     /// Updates the price
-    pub fn update_price(&mut self, new_price: f64) {
+    pub fn update_price(&mut self, new_price: UsdValue) {
         self.price_usd = new_price;
         self.timestamp = Utc::now();
     }
@@ -51,6 +68,42 @@ impl PriceHistory {
             price_usd: row.get("price_usd"),
             timestamp: row.get("timestamp"),
             source: row.get("source"),
+            feed: row.get("feed"),
+            slot: row.get("slot"),
+            confidence: row.get("confidence"),
+            volume_usd: row.get("volume_usd"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+/// Raw state of an oracle price account at a slot, kept so historical oracle
+/// prices can be decoded again later
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleUpdate {
+    /// The oracle account public key (base58 encoded)
+    pub account: String,
+    /// Oracle program, "pyth" or "switchboard"
+    pub oracle: String,
+    /// Slot the price was published at
+    pub slot: i64,
+    /// Publish time of the price
+    pub publish_time: DateTime<Utc>,
+    /// Account data
+    pub data: Vec<u8>,
+    /// When this record was created
+    pub created_at: DateTime<Utc>,
+}
+
+impl OracleUpdate {
+    /// Converts a database row into an OracleUpdate
+    pub fn from_row(row: &tokio_postgres::Row) -> Self {
+        Self {
+            account: row.get("account"),
+            oracle: row.get("oracle"),
+            slot: row.get("slot"),
+            publish_time: row.get("publish_time"),
+            data: row.get("data"),
             created_at: row.get("created_at"),
         }
     }
@@ -60,18 +113,22 @@ impl PriceHistory {
 mod tests {
     use super::*;
 
+    fn usd(text: &str) -> UsdValue {
+        text.parse().unwrap()
+    }
+
     #[test]
     fn test_price_history_creation() {
         let now = Utc::now();
         let price = PriceHistory::new(
             "test_mint".to_string(),
-            1.23,
+            usd("1.23"),
             now,
             "coingecko".to_string(),
         );
 
         assert_eq!(price.token_mint, "test_mint");
-        assert_eq!(price.price_usd, 1.23);
+        assert_eq!(price.price_usd, usd("1.23"));
         assert_eq!(price.timestamp, now);
         assert_eq!(price.source, "coingecko");
     }
@@ -81,13 +138,13 @@ mod tests {
         let now = Utc::now();
         let mut price = PriceHistory::new(
             "test_mint".to_string(),
-            1.23,
+            usd("1.23"),
             now,
             "coingecko".to_string(),
         );
 
-        price.update_price(2.34);
-        assert_eq!(price.price_usd, 2.34);
+        price.update_price(usd("2.34"));
+        assert_eq!(price.price_usd, usd("2.34"));
         assert!(price.timestamp > now);
     }
 
@@ -97,9 +154,13 @@ mod tests {
         let price = PriceHistory {
             id: Uuid::new_v4(),
             token_mint: "test_mint".to_string(),
-            price_usd: 1.23,
+            price_usd: usd("1.23"),
             timestamp: now,
             source: "coingecko".to_string(),
+            feed: Some("feed".to_string()),
+            slot: Some(42),
+            confidence: Some(usd("0.01")),
+            volume_usd: Some(usd("1000")),
             created_at: now,
         };
 
@@ -108,5 +169,9 @@ mod tests {
         assert_eq!(price.token_mint, deserialized.token_mint);
         assert_eq!(price.price_usd, deserialized.price_usd);
         assert_eq!(price.source, deserialized.source);
+        assert_eq!(price.feed, deserialized.feed);
+        assert_eq!(price.slot, deserialized.slot);
+        assert_eq!(price.confidence, deserialized.confidence);
+        assert_eq!(price.volume_usd, deserialized.volume_usd);
     }
 } 
\ No newline at end of file
@@ -1,3 +1,4 @@
+use crate::models::{AmountError, TokenAmount};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -13,12 +14,22 @@ pub struct ProtocolInteraction {
     pub protocol: String,
     /// The type of interaction (e.g., "swap", "stake", "borrow")
     pub interaction_type: String,
-    /// The amount involved in the interaction
-    pub amount: f64,
+    /// The amount involved in the interaction, with the decimals of its
+    /// mint when known
+    pub amount: TokenAmount,
     /// When this interaction occurred
     pub timestamp: DateTime<Utc>,
     /// When this record was created
     pub created_at: DateTime<Utc>,
+    /// Signature of the transaction the interaction was parsed from
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// Position of the source event among the transaction's parsed events
+    #[serde(default)]
+    pub event_index: Option<i32>,
+    /// Protocol-specific details, such as the mints and amounts of a swap
+    #[serde(default)]
+    pub details: Option<serde_json::Value>,
 }
 
 impl ProtocolInteraction {
@@ -27,7 +38,7 @@ impl ProtocolInteraction {
         wallet: String,
         protocol: String,
         interaction_type: String,
-        amount: f64,
+        amount: TokenAmount,
         timestamp: DateTime<Utc>,
     ) -> Self {
         Self {
@@ -38,26 +49,47 @@ impl ProtocolInteraction {
             amount,
             timestamp,
             created_at: Utc::now(),
+            signature: None,
+            event_index: None,
+            details: None,
         }
     }
 
+    /// Sets the transaction event this interaction was parsed from
+    pub fn with_source(mut self, signature: String, event_index: i32) -> Self {
+        self.signature = Some(signature);
+        self.event_index = Some(event_index);
+        self
+    }
+
+    /// Sets the protocol-specific details
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
     /// Updates the interaction amount
-    pub fn update_amount(&mut self, new_amount: f64) {
+    pub fn update_amount(&mut self, new_amount: TokenAmount) {
         self.amount = new_amount;
         self.timestamp = Utc::now();
     }
 
-    /// Converts a database row into a ProtocolInteraction
-    pub fn from_row(row: &tokio_postgres::Row) -> Self {
-        Self {
+    /// Converts a database row into a ProtocolInteraction. Fails on an
+    /// amount that is negative or has more fractional digits than its
+    /// decimals.
+    pub fn from_row(row: &tokio_postgres::Row) -> Result<Self, AmountError> {
+        Ok(Self {
             id: row.get("id"),
             wallet: row.get("wallet"),
             protocol: row.get("protocol"),
             interaction_type: row.get("interaction_type"),
-            amount: row.get("amount"),
+            amount: TokenAmount::from_decimal(row.get("amount"), row.get::<_, i16>("decimals") as u8)?,
             timestamp: row.get("timestamp"),
             created_at: row.get("created_at"),
-        }
+            signature: row.get("signature"),
+            event_index: row.get("event_index"),
+            details: row.get("details"),
+        })
     }
 }
 
@@ -72,14 +104,14 @@ mod tests {
             "test_wallet".to_string(),
             "test_protocol".to_string(),
             "swap".to_string(),
-            100.0,
+            TokenAmount::new(100_000_000, 6),
             now,
         );
 
         assert_eq!(interaction.wallet, "test_wallet");
         assert_eq!(interaction.protocol, "test_protocol");
         assert_eq!(interaction.interaction_type, "swap");
-        assert_eq!(interaction.amount, 100.0);
+        assert_eq!(interaction.amount.to_string(), "100");
         assert_eq!(interaction.timestamp, now);
     }
 
@@ -90,12 +122,12 @@ mod tests {
             "test_wallet".to_string(),
             "test_protocol".to_string(),
             "swap".to_string(),
-            100.0,
+            TokenAmount::new(100_000_000, 6),
             now,
         );
 
-        interaction.update_amount(200.0);
-        assert_eq!(interaction.amount, 200.0);
+        interaction.update_amount(TokenAmount::new(200_000_000, 6));
+        assert_eq!(interaction.amount.raw, 200_000_000);
         assert!(interaction.timestamp > now);
     }
 
@@ -107,9 +139,12 @@ mod tests {
             wallet: "test_wallet".to_string(),
             protocol: "test_protocol".to_string(),
             interaction_type: "swap".to_string(),
-            amount: 100.0,
+            amount: TokenAmount::new(100_000_000, 6),
             timestamp: now,
             created_at: now,
+            signature: Some("sig".to_string()),
+            event_index: Some(2),
+            details: Some(serde_json::json!({ "input_mint": "mint" })),
         };
 
         let serialized = serde_json::to_string(&interaction).unwrap();
@@ -118,5 +153,8 @@ mod tests {
         assert_eq!(interaction.protocol, deserialized.protocol);
         assert_eq!(interaction.interaction_type, deserialized.interaction_type);
         assert_eq!(interaction.amount, deserialized.amount);
+        assert_eq!(interaction.signature, deserialized.signature);
+        assert_eq!(interaction.event_index, deserialized.event_index);
+        assert_eq!(interaction.details, deserialized.details);
     }
 } 
\ No newline at end of file
@@ -1,3 +1,4 @@
+use crate::models::{AmountError, TokenAmount};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -11,11 +12,20 @@ pub struct TokenAccount {
     /// The owner's public key (base58 encoded)
     pub owner: String,
     /// The token balance in raw units
-    pub amount: i64,
+    pub amount: u64,
     /// When this account was last updated
     pub updated_at: DateTime<Utc>,
     /// When this record was created
     pub created_at: DateTime<Utc>,
+    /// Token program owning the account (base58 encoded)
+    #[serde(default)]
+    pub program_id: Option<String>,
+    /// Slot the balance was last observed at
+    #[serde(default)]
+    pub slot: Option<i64>,
+    /// When the account was found closed, `None` while it is open
+    #[serde(default)]
+    pub closed_at: Option<DateTime<Utc>>,
 }
 
 impl TokenAccount {
@@ -24,7 +34,7 @@ impl TokenAccount {
         pubkey: String,
         mint: String,
         owner: String,
-        amount: i64,
+        amount: u64,
         updated_at: DateTime<Utc>,
     ) -> Self {
         Self {
@@ -34,24 +44,81 @@ impl TokenAccount {
             amount,
             updated_at,
             created_at: Utc::now(),
+            program_id: None,
+            slot: None,
+            closed_at: None,
         }
     }
 
     /// Updates the token account balance
-    pub fn update_balance(&mut self, new_amount: i64) {
+    pub fn update_balance(&mut self, new_amount: u64) {
         self.amount = new_amount;
         self.updated_at = Utc::now();
     }
 
-    /// Converts a database row into a TokenAccount
-    pub fn from_row(row: &tokio_postgres::Row) -> Self {
-        Self {
+    /// Converts a database row into a TokenAccount. Fails on an amount that
+    /// is not a whole u64.
+    pub fn from_row(row: &tokio_postgres::Row) -> Result<Self, AmountError> {
+        Ok(Self {
             pubkey: row.get("pubkey"),
             mint: row.get("mint"),
             owner: row.get("owner"),
-            amount: row.get("amount"),
+            // Raw units stored as a whole NUMERIC
+            amount: TokenAmount::from_decimal(row.get("amount"), 0)?.raw,
             updated_at: row.get("updated_at"),
             created_at: row.get("created_at"),
+            program_id: row.get("program_id"),
+            slot: row.get("slot"),
+            closed_at: row.get("closed_at"),
+        })
+    }
+}
+
+/// Decimals and display metadata of a token mint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenInfo {
+    /// The token mint public key (base58 encoded)
+    pub mint: String,
+    /// Number of decimals of the mint's base unit
+    pub decimals: u8,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    /// URI of the off-chain metadata JSON
+    pub uri: Option<String>,
+    pub logo_uri: Option<String>,
+    /// Where name and symbol came from: `metaplex`, `token_list` or `mint`
+    /// when only the mint account was found
+    pub source: String,
+    /// When this token was last resolved
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TokenInfo {
+    /// Creates a token record with no display metadata
+    pub fn new(mint: String, decimals: u8) -> Self {
+        Self {
+            mint,
+            decimals,
+            name: None,
+            symbol: None,
+            uri: None,
+            logo_uri: None,
+            source: "mint".to_string(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Converts a database row into a TokenInfo
+    pub fn from_row(row: &tokio_postgres::Row) -> Self {
+        Self {
+            mint: row.get("mint"),
+            decimals: row.get::<_, i16>("decimals") as u8,
+            name: row.get("name"),
+            symbol: row.get("symbol"),
+            uri: row.get("uri"),
+            logo_uri: row.get("logo_uri"),
+            source: row.get("source"),
+            updated_at: row.get("updated_at"),
         }
     }
 }
@@ -104,6 +171,9 @@ mod tests {
             amount: 1000,
             updated_at: now,
             created_at: now,
+            program_id: None,
+            slot: None,
+            closed_at: None,
         };
 
         let serialized = serde_json::to_string(&account).unwrap();
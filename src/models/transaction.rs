@@ -1,15 +1,16 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
+use solana_transaction_status::{EncodedTransaction, EncodedTransactionWithStatusMeta, UiMessage};
 
 /// Represents a Solana transaction with its metadata and instructions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Transaction {
     /// The transaction signature (base58 encoded)
     pub signature: String,
     /// The slot number when the transaction was processed
     pub slot: i64,
-    /// The block time when the transaction was processed
-    pub block_time: DateTime<Utc>,
+    /// The block time when the transaction was processed, when the node reports one
+    pub block_time: Option<DateTime<Utc>>,
     /// The transaction fee in lamports
     pub fee: i64,
     /// The transaction status (e.g., "success", "failed")
@@ -25,7 +26,7 @@ impl Transaction {
     pub fn new(
         signature: String,
         slot: i64,
-        block_time: DateTime<Utc>,
+        block_time: Option<DateTime<Utc>>,
         fee: i64,
         status: String,
         instructions_json: String,
@@ -41,6 +42,48 @@ impl Transaction {
         }
     }
 
+    /// Builds a record for a transaction taken from a block at `slot`.
+    ///
+    /// `instructions_json` holds the message instructions for JSON-encoded
+    /// transactions and the whole encoded transaction otherwise. Returns `None`
+    /// when the transaction carries no signature.
+    pub fn from_block_transaction(
+        slot: u64,
+        block_time: Option<i64>,
+        tx: &EncodedTransactionWithStatusMeta,
+    ) -> Option<Self> {
+        let (signature, instructions_json) = match &tx.transaction {
+            EncodedTransaction::Json(ui) => {
+                let instructions = match &ui.message {
+                    UiMessage::Raw(message) => serde_json::to_string(&message.instructions),
+                    UiMessage::Parsed(message) => serde_json::to_string(&message.instructions),
+                };
+                (ui.signatures.first().cloned()?, instructions.ok()?)
+            }
+            EncodedTransaction::Accounts(list) => {
+                (list.signatures.first().cloned()?, serde_json::to_string(&tx.transaction).ok()?)
+            }
+            encoded => {
+                let decoded = encoded.decode()?;
+                (decoded.signatures.first()?.to_string(), serde_json::to_string(encoded).ok()?)
+            }
+        };
+        let meta = tx.meta.as_ref();
+        let status = match meta.and_then(|meta| meta.err.as_ref()) {
+            Some(_) => "failed",
+            None => "success",
+        };
+
+        Some(Self::new(
+            signature,
+            slot as i64,
+            block_time.and_then(|t| Utc.timestamp_opt(t, 0).single()),
+            meta.map(|meta| meta.fee as i64).unwrap_or(0),
+            status.to_string(),
+            instructions_json,
+        ))
+    }
+
     /// Helper to get instructions_json as serde_json::Value
     pub fn instructions_json_value(&self) -> serde_json::Result<serde_json::Value> {
         serde_json::from_str(&self.instructions_json)
@@ -70,7 +113,7 @@ mod tests {
         let transaction = Transaction::new(
             "test_sig".to_string(),
             123,
-            Utc::now(),
+            Some(Utc::now()),
             1000,
             "success".to_string(),
             json!({}).to_string(),
@@ -87,7 +130,7 @@ mod tests {
         let transaction = Transaction {
             signature: "test_sig".to_string(),
             slot: 123,
-            block_time: Utc::now(),
+            block_time: Some(Utc::now()),
             fee: 1000,
             status: "success".to_string(),
             instructions_json: json!({}).to_string(),
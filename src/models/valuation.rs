@@ -0,0 +1,92 @@
+use crate::models::{RawAmount, UsdValue};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// USD value at block time of a token amount moved by a transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsdValuation {
+    /// Signature of the transaction that moved the amount
+    pub signature: String,
+    /// What was valued: `transfer`, `swap_input`, `swap_output` or
+    /// `balance_change`
+    pub kind: String,
+    /// Position of the source event among the transaction's parsed events, or
+    /// the account (base58 encoded) for balance changes
+    pub item: String,
+    /// The token mint (base58 encoded), or `native` for SOL
+    pub mint: String,
+    /// Amount in raw units, negative for balance decreases
+    pub amount: RawAmount,
+    pub decimals: i16,
+    /// Block time of the transaction
+    pub timestamp: DateTime<Utc>,
+    /// Price the amount was valued at, `None` while no price is known
+    pub price_usd: Option<UsdValue>,
+    pub value_usd: Option<UsdValue>,
+    /// Source of the price used
+    pub price_source: Option<String>,
+    /// Time of the price used
+    pub price_timestamp: Option<DateTime<Utc>>,
+    /// Distance in seconds between the block time and the price used
+    pub staleness_seconds: Option<i64>,
+    /// When the value was last computed
+    pub valued_at: DateTime<Utc>,
+}
+
+impl UsdValuation {
+    /// Creates an unpriced valuation record
+    pub fn new(
+        signature: String,
+        kind: &str,
+        item: String,
+        mint: String,
+        amount: RawAmount,
+        decimals: u8,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            signature,
+            kind: kind.to_string(),
+            item,
+            mint,
+            amount,
+            decimals: decimals as i16,
+            timestamp,
+            price_usd: None,
+            value_usd: None,
+            price_source: None,
+            price_timestamp: None,
+            staleness_seconds: None,
+            valued_at: Utc::now(),
+        }
+    }
+
+    /// Value the amount at `price_usd`, observed by `source` at `observed_at`
+    pub fn set_price(&mut self, price_usd: UsdValue, source: String, observed_at: DateTime<Utc>) {
+        self.price_usd = Some(price_usd);
+        self.value_usd = Some(price_usd.value_of_raw(self.amount, self.decimals as u8));
+        self.price_source = Some(source);
+        self.price_timestamp = Some(observed_at);
+        self.staleness_seconds = Some((self.timestamp - observed_at).num_seconds().abs());
+        self.valued_at = Utc::now();
+    }
+
+    /// Converts a database row into a UsdValuation
+    pub fn from_row(row: &tokio_postgres::Row) -> Self {
+        Self {
+            signature: row.get("signature"),
+            kind: row.get("kind"),
+            item: row.get("item"),
+            mint: row.get("mint"),
+            amount: row.get("amount"),
+            decimals: row.get("decimals"),
+            timestamp: row.get("timestamp"),
+            price_usd: row.get("price_usd"),
+            value_usd: row.get("value_usd"),
+            price_source: row.get("price_source"),
+            price_timestamp: row.get("price_timestamp"),
+            staleness_seconds: row.get("staleness_seconds"),
+            valued_at: row.get("valued_at"),
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Anchor IDL model and Borsh decoding driven by it
+//!
+//! Both the legacy IDL format (Anchor before 0.30, camelCase names,
+//! `publicKey`, `isMut`, discriminators derived from names) and the current
+//! one (explicit discriminators, `pubkey`, `writable`) are accepted.
+
+use crate::parsers::{ParseError, ParseResult};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use solana_sdk::hash::hashv;
+use solana_sdk::pubkey::Pubkey;
+
+/// First 8 bytes of `sha256("<namespace>:<name>")`, as Anchor prefixes
+/// instruction data (`global`), events (`event`) and accounts (`account`)
+pub fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let hash = hashv(&[namespace.as_bytes(), b":", name.as_bytes()]);
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&hash.as_ref()[..8]);
+    discriminator
+}
+
+/// An Anchor IDL
+#[derive(Debug, Clone, Deserialize)]
+pub struct Idl {
+    /// Program id, top-level since Anchor 0.30
+    #[serde(default)]
+    pub address: Option<String>,
+    /// Program name of legacy IDLs
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    metadata: Option<IdlMetadata>,
+    #[serde(default)]
+    pub instructions: Vec<IdlInstruction>,
+    #[serde(default)]
+    pub events: Vec<IdlEvent>,
+    #[serde(default)]
+    pub errors: Vec<IdlError>,
+    #[serde(default)]
+    pub types: Vec<IdlTypeDef>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct IdlMetadata {
+    #[serde(default)]
+    name: Option<String>,
+    /// Program id of legacy IDLs
+    #[serde(default)]
+    address: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    #[serde(default)]
+    pub discriminator: Vec<u8>,
+    #[serde(default)]
+    pub accounts: Vec<IdlAccountItem>,
+    #[serde(default)]
+    pub args: Vec<IdlField>,
+}
+
+/// An instruction account, or a group of them from a nested `Accounts` struct
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlAccountItem {
+    Group { name: String, accounts: Vec<IdlAccountItem> },
+    Single { name: String },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlEvent {
+    pub name: String,
+    #[serde(default)]
+    pub discriminator: Vec<u8>,
+    /// Inline fields of legacy IDLs; current IDLs define them in `types`
+    #[serde(default)]
+    pub fields: Option<Vec<IdlField>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlError {
+    pub code: u32,
+    pub name: String,
+    #[serde(default)]
+    pub msg: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlTypeDef {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlTypeDefTy,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlTypeDefTy {
+    Struct {
+        #[serde(default)]
+        fields: IdlFields,
+    },
+    Enum {
+        variants: Vec<IdlVariant>,
+    },
+    Type {
+        alias: IdlType,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlFields {
+    Named(Vec<IdlField>),
+    Tuple(Vec<IdlType>),
+}
+
+impl Default for IdlFields {
+    fn default() -> Self {
+        IdlFields::Named(Vec::new())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlVariant {
+    pub name: String,
+    #[serde(default)]
+    pub fields: Option<IdlFields>,
+}
+
+/// A field type. Primitives are plain strings, compound types objects
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlType {
+    Primitive(String),
+    Vec { vec: Box<IdlType> },
+    Option { option: Box<IdlType> },
+    COption { coption: Box<IdlType> },
+    Array { array: (Box<IdlType>, Value) },
+    Defined { defined: IdlDefined },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlDefined {
+    Name(String),
+    Named { name: String },
+}
+
+impl IdlDefined {
+    fn name(&self) -> &str {
+        match self {
+            IdlDefined::Name(name) | IdlDefined::Named { name } => name,
+        }
+    }
+}
+
+/// An instruction account with the name the IDL gives it
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, Deserialize)]
+pub struct NamedAccount {
+    /// Account name, prefixed with its group for nested `Accounts` structs
+    /// (`group.account`), or `remaining` past the accounts the IDL lists
+    pub name: String,
+    pub pubkey: String,
+}
+
+impl Idl {
+    /// Parse an IDL and derive the discriminators legacy IDLs leave out
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let mut idl: Idl = serde_json::from_str(json)?;
+        for instruction in idl.instructions.iter_mut().filter(|ix| ix.discriminator.is_empty()) {
+            instruction.discriminator = discriminator("global", &snake_case(&instruction.name)).to_vec();
+        }
+        for event in idl.events.iter_mut().filter(|event| event.discriminator.is_empty()) {
+            event.discriminator = discriminator("event", &event.name).to_vec();
+        }
+        Ok(idl)
+    }
+
+    /// Program name
+    pub fn name(&self) -> &str {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.name.as_deref())
+            .or(self.name.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Program id, from `address` or, for legacy IDLs, `metadata.address`
+    pub fn program_id(&self) -> Option<Pubkey> {
+        self.address
+            .as_deref()
+            .or_else(|| self.metadata.as_ref().and_then(|metadata| metadata.address.as_deref()))
+            .and_then(|address| address.parse().ok())
+    }
+
+    /// Instruction whose discriminator prefixes `data`
+    pub fn instruction(&self, data: &[u8]) -> Option<&IdlInstruction> {
+        self.instructions.iter().find(|ix| data.starts_with(&ix.discriminator))
+    }
+
+    /// Event whose discriminator prefixes `data`
+    pub fn event(&self, data: &[u8]) -> Option<&IdlEvent> {
+        self.events.iter().find(|event| data.starts_with(&event.discriminator))
+    }
+
+    pub fn error(&self, code: u32) -> Option<&IdlError> {
+        self.errors.iter().find(|error| error.code == code)
+    }
+
+    /// Decode the arguments of `instruction` following its discriminator
+    pub fn decode_args(&self, instruction: &IdlInstruction, data: &[u8]) -> ParseResult<Value> {
+        let mut input = &data[instruction.discriminator.len()..];
+        let args = self.decode_named(&instruction.args, &mut input)?;
+        Ok(Value::Object(args))
+    }
+
+    /// Decode the fields of `event` following its discriminator
+    pub fn decode_event(&self, event: &IdlEvent, data: &[u8]) -> ParseResult<Value> {
+        let mut input = &data[event.discriminator.len()..];
+        match &event.fields {
+            Some(fields) => Ok(Value::Object(self.decode_named(fields, &mut input)?)),
+            None => self.decode_defined(&event.name, &mut input),
+        }
+    }
+
+    /// Pair `accounts` with the names of `instruction`'s accounts
+    pub fn name_accounts(&self, instruction: &IdlInstruction, accounts: &[Pubkey]) -> Vec<NamedAccount> {
+        fn flatten(items: &[IdlAccountItem], prefix: &str, names: &mut Vec<String>) {
+            for item in items {
+                match item {
+                    IdlAccountItem::Single { name } => names.push(format!("{}{}", prefix, name)),
+                    IdlAccountItem::Group { name, accounts } => flatten(accounts, &format!("{}{}.", prefix, name), names),
+                }
+            }
+        }
+        let mut names = Vec::new();
+        flatten(&instruction.accounts, "", &mut names);
+        accounts
+            .iter()
+            .enumerate()
+            .map(|(i, pubkey)| NamedAccount {
+                name: names.get(i).cloned().unwrap_or_else(|| "remaining".to_string()),
+                pubkey: pubkey.to_string(),
+            })
+            .collect()
+    }
+
+    fn decode_named(&self, fields: &[IdlField], input: &mut &[u8]) -> ParseResult<Map<String, Value>> {
+        fields
+            .iter()
+            .map(|field| Ok((field.name.clone(), self.decode(&field.ty, input)?)))
+            .collect()
+    }
+
+    fn decode_fields(&self, fields: &IdlFields, input: &mut &[u8]) -> ParseResult<Value> {
+        match fields {
+            IdlFields::Named(fields) => Ok(Value::Object(self.decode_named(fields, input)?)),
+            IdlFields::Tuple(types) => Ok(Value::Array(
+                types.iter().map(|ty| self.decode(ty, input)).collect::<ParseResult<_>>()?,
+            )),
+        }
+    }
+
+    fn decode_defined(&self, name: &str, input: &mut &[u8]) -> ParseResult<Value> {
+        let def = self
+            .types
+            .iter()
+            .find(|def| def.name == name)
+            .ok_or_else(|| ParseError::InvalidData(format!("IDL type {} is not defined", name)))?;
+        match &def.ty {
+            IdlTypeDefTy::Struct { fields } => self.decode_fields(fields, input),
+            IdlTypeDefTy::Type { alias } => self.decode(alias, input),
+            IdlTypeDefTy::Enum { variants } => {
+                let tag = take(input, 1)?[0] as usize;
+                let variant = variants
+                    .get(tag)
+                    .ok_or_else(|| ParseError::InvalidData(format!("variant {} of {} is out of range", tag, name)))?;
+                match &variant.fields {
+                    None => Ok(Value::String(variant.name.clone())),
+                    Some(fields) => Ok(json!({ variant.name.clone(): self.decode_fields(fields, input)? })),
+                }
+            }
+        }
+    }
+
+    fn decode(&self, ty: &IdlType, input: &mut &[u8]) -> ParseResult<Value> {
+        macro_rules! int {
+            ($ty:ty) => {{
+                let bytes = take(input, std::mem::size_of::<$ty>())?;
+                <$ty>::from_le_bytes(bytes.try_into().unwrap())
+            }};
+        }
+        Ok(match ty {
+            IdlType::Primitive(name) => match name.as_str() {
+                "bool" => Value::Bool(int!(u8) != 0),
+                "u8" => json!(int!(u8)),
+                "i8" => json!(int!(i8)),
+                "u16" => json!(int!(u16)),
+                "i16" => json!(int!(i16)),
+                "u32" => json!(int!(u32)),
+                "i32" => json!(int!(i32)),
+                "u64" => json!(int!(u64)),
+                "i64" => json!(int!(i64)),
+                // Past the range JSON numbers hold exactly
+                "u128" => Value::String(int!(u128).to_string()),
+                "i128" => Value::String(int!(i128).to_string()),
+                "f32" => json!(f32::from_le_bytes(take(input, 4)?.try_into().unwrap())),
+                "f64" => json!(f64::from_le_bytes(take(input, 8)?.try_into().unwrap())),
+                "publicKey" | "pubkey" => {
+                    Value::String(Pubkey::new_from_array(take(input, 32)?.try_into().unwrap()).to_string())
+                }
+                "string" => {
+                    let len = int!(u32) as usize;
+                    let bytes = take(input, len)?;
+                    Value::String(
+                        String::from_utf8(bytes.to_vec())
+                            .map_err(|_| ParseError::InvalidData("IDL string is not UTF-8".to_string()))?,
+                    )
+                }
+                "bytes" => {
+                    let len = int!(u32) as usize;
+                    json!(take(input, len)?)
+                }
+                other => return Err(ParseError::InvalidData(format!("unsupported IDL type {}", other))),
+            },
+            IdlType::Vec { vec } => {
+                let len = int!(u32) as usize;
+                let mut items = Vec::with_capacity(len.min(input.len()));
+                for _ in 0..len {
+                    items.push(self.decode(vec, input)?);
+                }
+                Value::Array(items)
+            }
+            IdlType::Option { option } => match int!(u8) {
+                0 => Value::Null,
+                _ => self.decode(option, input)?,
+            },
+            IdlType::COption { coption } => match int!(u32) {
+                0 => Value::Null,
+                _ => self.decode(coption, input)?,
+            },
+            IdlType::Array { array: (item, len) } => {
+                let len = len
+                    .as_u64()
+                    .ok_or_else(|| ParseError::InvalidData(format!("unsupported IDL array length {}", len)))?;
+                Value::Array((0..len).map(|_| self.decode(item, input)).collect::<ParseResult<_>>()?)
+            }
+            IdlType::Defined { defined } => self.decode_defined(defined.name(), input)?,
+        })
+    }
+}
+
+/// Split `len` bytes off the front of `input`
+fn take<'a>(input: &mut &'a [u8], len: usize) -> ParseResult<&'a [u8]> {
+    if input.len() < len {
+        return Err(ParseError::InvalidData(format!(
+            "expected {} more bytes, {} left",
+            len,
+            input.len()
+        )));
+    }
+    let (head, rest) = input.split_at(len);
+    *input = rest;
+    Ok(head)
+}
+
+/// `camelCase` to `snake_case`, as Anchor derives legacy discriminators
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() {
+            if previous_lower {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+            previous_lower = false;
+        } else {
+            snake.push(c);
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        }
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY: &str = r#"{
+        "version": "0.1.0",
+        "name": "legacy_vault",
+        "instructions": [{
+            "name": "setLimits",
+            "accounts": [
+                {"name": "admin", "isMut": false, "isSigner": true},
+                {"name": "config", "accounts": [{"name": "state", "isMut": true, "isSigner": false}]}
+            ],
+            "args": [
+                {"name": "limits", "type": {"vec": {"defined": "Limit"}}},
+                {"name": "total", "type": "u128"},
+                {"name": "owner", "type": "publicKey"},
+                {"name": "seed", "type": {"array": ["u8", 2]}}
+            ]
+        }],
+        "types": [{"name": "Limit", "type": {"kind": "struct", "fields": ["u16", "bool"]}}],
+        "metadata": {"address": "11111111111111111111111111111111"}
+    }"#;
+
+    #[test]
+    fn test_legacy_idl_discriminators_and_types() {
+        let idl = Idl::from_json(LEGACY).unwrap();
+        assert_eq!(idl.name(), "legacy_vault");
+        assert_eq!(idl.program_id(), Some(Pubkey::default()));
+        assert_eq!(snake_case("setLimits"), "set_limits");
+
+        let owner = Pubkey::new_unique();
+        let mut data = discriminator("global", "set_limits").to_vec();
+        data.extend(2u32.to_le_bytes());
+        data.extend([10, 0, 1, 20, 0, 0]);
+        data.extend(u128::MAX.to_le_bytes());
+        data.extend(owner.to_bytes());
+        data.extend([7, 8]);
+
+        let ix = idl.instruction(&data).unwrap();
+        assert_eq!(
+            idl.decode_args(ix, &data).unwrap(),
+            json!({
+                "limits": [[10, true], [20, false]],
+                "total": u128::MAX.to_string(),
+                "owner": owner.to_string(),
+                "seed": [7, 8],
+            })
+        );
+        let names: Vec<_> = idl
+            .name_accounts(ix, &[Pubkey::default(), Pubkey::default()])
+            .into_iter()
+            .map(|account| account.name)
+            .collect();
+        assert_eq!(names, vec!["admin", "config.state"]);
+
+        // Truncated arguments are an error, not a partial decode
+        assert!(idl.decode_args(ix, &data[..data.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,404 @@
+//! Generic parser for Anchor programs driven by their IDL
+//!
+//! IDLs are loaded from JSON files or fetched from the IDL account Anchor
+//! programs publish on chain. Instructions are matched by their 8-byte
+//! discriminator and their Borsh arguments decoded into JSON, along with the
+//! events the program emits, through `Program data:` logs or `emit_cpi!`
+//! self-invocations, and its custom error codes.
+
+pub mod idl;
+
+pub use idl::{discriminator, Idl, NamedAccount};
+
+use crate::core::error::{Error, Result};
+use crate::parsers::{
+    DecodedInstruction, FrameStatus, ParseError, ParseResult, ProtocolEvent, ProtocolParser, TransactionContext,
+};
+use crate::rpc::client::AccountClientTrait;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Prefix of the self-invocations `emit_cpi!` uses to carry an event
+pub const EVENT_IX_TAG: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+
+/// Seed of the IDL account, derived from the program's signer address
+const IDL_SEED: &str = "anchor:idl";
+
+/// Discriminator, authority and data length preceding the compressed IDL
+const IDL_HEADER_LEN: usize = 8 + 32 + 4;
+
+/// Activity of an Anchor program decoded with its IDL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnchorEvent {
+    Instruction {
+        /// Program name from the IDL
+        program: String,
+        instruction: String,
+        accounts: Vec<NamedAccount>,
+        args: serde_json::Value,
+    },
+    Event {
+        program: String,
+        name: String,
+        data: serde_json::Value,
+    },
+    /// The instruction failed with a custom program error
+    Error {
+        program: String,
+        code: u32,
+        /// Error name and message, when the IDL lists the code
+        name: Option<String>,
+        message: Option<String>,
+    },
+}
+
+/// Address of the IDL account of `program_id`
+pub fn idl_address(program_id: &Pubkey) -> Pubkey {
+    let (base, _) = Pubkey::find_program_address(&[], program_id);
+    Pubkey::create_with_seed(&base, IDL_SEED, program_id).expect("IDL seed is within the seed length limit")
+}
+
+/// Decode the IDL stored in an IDL account: a zlib-compressed JSON document
+/// following the account header
+pub fn decode_idl_account(data: &[u8]) -> Result<Idl> {
+    let len = data
+        .get(IDL_HEADER_LEN - 4..IDL_HEADER_LEN)
+        .map(|len| u32::from_le_bytes(len.try_into().unwrap()) as usize)
+        .ok_or_else(|| Error::validation("IDL account is too short"))?;
+    let compressed = data
+        .get(IDL_HEADER_LEN..IDL_HEADER_LEN + len)
+        .ok_or_else(|| Error::validation("IDL account data is truncated"))?;
+    let mut json = String::new();
+    flate2::read::ZlibDecoder::new(compressed)
+        .read_to_string(&mut json)
+        .map_err(|e| Error::validation(format!("Invalid IDL account data: {}", e)))?;
+    Ok(Idl::from_json(&json)?)
+}
+
+/// Decodes instructions, events and errors of the Anchor programs it has an
+/// IDL for.
+///
+/// Meant to be registered with [`ParserRegistry::register_fallback`] so the
+/// dedicated parsers of programs keep precedence over it.
+///
+/// [`ParserRegistry::register_fallback`]: crate::parsers::ParserRegistry::register_fallback
+#[derive(Debug, Clone, Default)]
+pub struct AnchorParser {
+    idls: HashMap<Pubkey, Arc<Idl>>,
+}
+
+impl AnchorParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode `program_id` with `idl`, replacing any IDL already set for it
+    pub fn with_idl(mut self, program_id: Pubkey, idl: Idl) -> Self {
+        self.idls.insert(program_id, Arc::new(idl));
+        self
+    }
+
+    /// Add the IDL in the JSON file at `path`, for the program it names
+    pub fn with_idl_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let (program_id, idl) = load_idl_file(path.as_ref())?;
+        Ok(self.with_idl(program_id, idl))
+    }
+
+    /// Add every `*.json` IDL in `dir`. Files that are not IDLs naming their
+    /// program are skipped with a warning.
+    pub fn with_idl_dir(mut self, dir: impl AsRef<Path>) -> Result<Self> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            match load_idl_file(&path) {
+                Ok((program_id, idl)) => self = self.with_idl(program_id, idl),
+                Err(e) => warn!("Skipping IDL {}: {}", path.display(), e),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Fetch the on-chain IDLs of `program_ids` in one batch. Programs that
+    /// publish no IDL are skipped; IDLs already set are kept.
+    pub async fn with_onchain_idls(mut self, client: &dyn AccountClientTrait, program_ids: &[Pubkey]) -> Result<Self> {
+        let missing: Vec<Pubkey> = program_ids.iter().filter(|id| !self.idls.contains_key(id)).copied().collect();
+        if missing.is_empty() {
+            return Ok(self);
+        }
+        let addresses: Vec<Pubkey> = missing.iter().map(idl_address).collect();
+        let accounts = client
+            .get_multiple_accounts(&addresses)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to fetch IDL accounts: {}", e)))?;
+        for (program_id, account) in missing.into_iter().zip(accounts) {
+            let Some(account) = account else {
+                continue;
+            };
+            match decode_idl_account(&account.data) {
+                Ok(idl) => self = self.with_idl(program_id, idl),
+                Err(e) => warn!("Skipping on-chain IDL of {}: {}", program_id, e),
+            }
+        }
+        Ok(self)
+    }
+
+    /// IDL used for `program_id`
+    pub fn idl(&self, program_id: &Pubkey) -> Option<&Arc<Idl>> {
+        self.idls.get(program_id)
+    }
+
+    fn decode_event(&self, idl: &Idl, data: &[u8]) -> ParseResult<Option<AnchorEvent>> {
+        let Some(event) = idl.event(data) else {
+            return Ok(None);
+        };
+        Ok(Some(AnchorEvent::Event {
+            program: idl.name().to_string(),
+            name: event.name.clone(),
+            data: idl.decode_event(event, data)?,
+        }))
+    }
+}
+
+impl ProtocolParser for AnchorParser {
+    fn name(&self) -> &str {
+        "anchor"
+    }
+
+    fn program_ids(&self) -> Vec<Pubkey> {
+        self.idls.keys().copied().collect()
+    }
+
+    fn parse(&self, instruction: &DecodedInstruction, context: &TransactionContext) -> ParseResult<Vec<ProtocolEvent>> {
+        let idl = self
+            .idls
+            .get(&instruction.program_id)
+            .ok_or_else(|| ParseError::UnknownInstruction(format!("no IDL for {}", instruction.program_id)))?;
+
+        // emit_cpi! events are self-invocations carrying the event as data
+        if let Some(event) = instruction.data.strip_prefix(&EVENT_IX_TAG) {
+            return Ok(self.decode_event(idl, event)?.map(ProtocolEvent::Anchor).into_iter().collect());
+        }
+
+        let ix = idl
+            .instruction(&instruction.data)
+            .ok_or_else(|| ParseError::UnknownInstruction(format!("{} discriminator", idl.name())))?;
+        let mut events = vec![ProtocolEvent::Anchor(AnchorEvent::Instruction {
+            program: idl.name().to_string(),
+            instruction: ix.name.clone(),
+            accounts: idl.name_accounts(ix, &instruction.accounts),
+            args: idl.decode_args(ix, &instruction.data)?,
+        })];
+
+        let Some(frame) = instruction.frame(context) else {
+            return Ok(events);
+        };
+        for payload in &frame.data {
+            let Ok(data) = base64::engine::general_purpose::STANDARD.decode(payload) else {
+                continue;
+            };
+            // Logged data is not necessarily an IDL event
+            if let Ok(Some(event)) = self.decode_event(idl, &data) {
+                events.push(ProtocolEvent::Anchor(event));
+            }
+        }
+        if let FrameStatus::Failed { error } = &frame.status {
+            if let Some(code) = custom_error_code(error) {
+                let known = idl.error(code);
+                events.push(ProtocolEvent::Anchor(AnchorEvent::Error {
+                    program: idl.name().to_string(),
+                    code,
+                    name: known.map(|error| error.name.clone()),
+                    message: known.and_then(|error| error.msg.clone()),
+                }));
+            }
+        }
+        Ok(events)
+    }
+}
+
+fn load_idl_file(path: &Path) -> Result<(Pubkey, Idl)> {
+    let idl = Idl::from_json(&std::fs::read_to_string(path)?)?;
+    let program_id = idl
+        .program_id()
+        .ok_or_else(|| Error::config(format!("IDL {} does not name its program", path.display())))?;
+    Ok((program_id, idl))
+}
+
+/// Code of a `custom program error: 0x…` failure
+fn custom_error_code(error: &str) -> Option<u32> {
+    let hex = error.strip_prefix("custom program error: 0x")?;
+    u32::from_str_radix(hex, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::{CallTree, ParserRegistry};
+    use crate::rpc::client::MockAccountClientTrait;
+    use serde_json::json;
+    use solana_sdk::account::Account;
+    use std::io::Write;
+    use std::str::FromStr;
+
+    const PROGRAM: &str = "7VaBq8KWHH8vNAxhoS4vHYeU8rfi1uNUWdHfffpmpKEg";
+    const IDL_JSON: &str = include_str!("../../../tests/fixtures/idl/vault.json");
+
+    fn program() -> Pubkey {
+        Pubkey::from_str(PROGRAM).unwrap()
+    }
+
+    fn parser() -> AnchorParser {
+        let idl = Idl::from_json(IDL_JSON).unwrap();
+        AnchorParser::new().with_idl(idl.program_id().unwrap(), idl)
+    }
+
+    /// `deposit(amount: u64, memo: Option<String>)` data
+    fn deposit_data(amount: u64, memo: &str) -> Vec<u8> {
+        let mut data = discriminator("global", "deposit").to_vec();
+        data.extend(amount.to_le_bytes());
+        data.push(1);
+        data.extend((memo.len() as u32).to_le_bytes());
+        data.extend(memo.as_bytes());
+        data
+    }
+
+    /// `Deposited { owner, amount, kind: Kind::Locked { until } }` data
+    fn deposited_data(owner: &Pubkey, amount: u64, until: i64) -> Vec<u8> {
+        let mut data = discriminator("event", "Deposited").to_vec();
+        data.extend(owner.to_bytes());
+        data.extend(amount.to_le_bytes());
+        data.push(1);
+        data.extend(until.to_le_bytes());
+        data
+    }
+
+    fn deposit(accounts: Vec<Pubkey>, data: Vec<u8>) -> DecodedInstruction {
+        DecodedInstruction {
+            program_id: program(),
+            accounts,
+            data,
+            index: 0,
+            inner_index: None,
+            stack_height: 1,
+            inner_instructions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_decodes_instruction_and_logged_event() {
+        let owner = Pubkey::new_unique();
+        let vault = Pubkey::new_unique();
+        let extra = Pubkey::new_unique();
+        let ix = deposit(vec![owner, vault, extra], deposit_data(5_000, "rent"));
+        let logs = vec![
+            format!("Program {} invoke [1]", PROGRAM),
+            format!(
+                "Program data: {}",
+                base64::engine::general_purpose::STANDARD.encode(deposited_data(&owner, 5_000, 1_700_000_000))
+            ),
+            format!("Program {} consumed 4200 of 200000 compute units", PROGRAM),
+            format!("Program {} success", PROGRAM),
+        ];
+        let context = TransactionContext {
+            call_tree: CallTree::build(std::slice::from_ref(&ix), &logs, true),
+            ..Default::default()
+        };
+
+        let events = parser().parse(&ix, &context).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                ProtocolEvent::Anchor(AnchorEvent::Instruction {
+                    program: "vault".to_string(),
+                    instruction: "deposit".to_string(),
+                    accounts: vec![
+                        NamedAccount { name: "owner".to_string(), pubkey: owner.to_string() },
+                        NamedAccount { name: "vault".to_string(), pubkey: vault.to_string() },
+                        NamedAccount { name: "remaining".to_string(), pubkey: extra.to_string() },
+                    ],
+                    args: json!({"amount": 5_000, "memo": "rent"}),
+                }),
+                ProtocolEvent::Anchor(AnchorEvent::Event {
+                    program: "vault".to_string(),
+                    name: "Deposited".to_string(),
+                    data: json!({
+                        "owner": owner.to_string(),
+                        "amount": 5_000,
+                        "kind": {"Locked": {"until": 1_700_000_000}},
+                    }),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decodes_emit_cpi_event_and_custom_error() {
+        let owner = Pubkey::new_unique();
+        let mut data = EVENT_IX_TAG.to_vec();
+        data.extend(deposited_data(&owner, 1, 0));
+        let events = parser().parse(&deposit(vec![], data), &TransactionContext::default()).unwrap();
+        assert!(matches!(&events[..], [ProtocolEvent::Anchor(AnchorEvent::Event { name, .. })] if name == "Deposited"));
+
+        let ix = deposit(vec![owner], deposit_data(0, ""));
+        let logs = vec![
+            format!("Program {} invoke [1]", PROGRAM),
+            format!("Program {} failed: custom program error: 0x1770", PROGRAM),
+        ];
+        let context = TransactionContext {
+            call_tree: CallTree::build(std::slice::from_ref(&ix), &logs, false),
+            ..Default::default()
+        };
+        let events = parser().parse(&ix, &context).unwrap();
+        assert_eq!(
+            events.last(),
+            Some(&ProtocolEvent::Anchor(AnchorEvent::Error {
+                program: "vault".to_string(),
+                code: 6000,
+                name: Some("ZeroAmount".to_string()),
+                message: Some("Amount must be positive".to_string()),
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetches_onchain_idl() {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(IDL_JSON.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut data = vec![0; 40];
+        data.extend((compressed.len() as u32).to_le_bytes());
+        data.extend(compressed);
+        let account = Account {
+            lamports: 1,
+            data,
+            owner: program(),
+            executable: false,
+            rent_epoch: 0,
+        };
+
+        let mut client = MockAccountClientTrait::new();
+        client
+            .expect_get_multiple_accounts()
+            .withf(|keys| keys == [idl_address(&program())])
+            .times(1)
+            .returning(move |_| Ok(vec![Some(account.clone())]));
+        let parser = AnchorParser::new().with_onchain_idls(&client, &[program()]).await.unwrap();
+        assert_eq!(parser.idl(&program()).unwrap().name(), "vault");
+
+        // Programs without a dedicated parser fall back to the IDL
+        let mut registry = ParserRegistry::new();
+        registry.register_fallback(Arc::new(parser));
+        let events = registry.parse_instruction(&deposit(vec![], deposit_data(7, "")), &TransactionContext::default());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].parser, "anchor");
+    }
+}
@@ -0,0 +1,354 @@
+//! Reconstruction of the cross-program invocation tree of a transaction
+
+use crate::parsers::DecodedInstruction;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+/// Outcome of one program invocation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FrameStatus {
+    Success,
+    Failed { error: String },
+    /// No log covers the invocation: logs were truncated or missing, the
+    /// program does not log (precompiles), or the transaction failed before
+    /// reaching it
+    Unknown,
+}
+
+/// One program invocation: a top-level instruction or a CPI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallFrame {
+    pub program_id: Pubkey,
+    /// Index of the top-level instruction this invocation belongs to
+    pub instruction_index: usize,
+    /// Position within the top-level instruction's inner instructions, `None`
+    /// for the top-level instruction itself
+    pub inner_index: Option<usize>,
+    /// Invocation depth, 1 for top-level instructions
+    pub stack_height: u32,
+    /// Compute units consumed, including those of the invocations it made
+    pub compute_units: Option<u64>,
+    pub status: FrameStatus,
+    /// `Program log:` messages emitted by this invocation itself
+    pub logs: Vec<String>,
+    /// Base64 payloads of `Program data:` lines, e.g. Anchor events
+    pub data: Vec<String>,
+    /// Invocations made by this one, in execution order
+    pub children: Vec<CallFrame>,
+}
+
+impl CallFrame {
+    /// This frame followed by every frame below it, in execution order
+    pub fn iter(&self) -> impl Iterator<Item = &CallFrame> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let frame = stack.pop()?;
+            stack.extend(frame.children.iter().rev());
+            Some(frame)
+        })
+    }
+
+    /// Whether this frame or any frame below it invoked `program_id`
+    pub fn invokes(&self, program_id: &Pubkey) -> bool {
+        self.iter().any(|frame| &frame.program_id == program_id)
+    }
+}
+
+/// Invocation tree of a transaction, one root per top-level instruction.
+///
+/// Built from the instructions, whose inner instructions carry their stack
+/// height, and matched in execution order against the `invoke [n]`,
+/// `consumed`, `success` and `failed` lines of the log messages.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallTree {
+    pub roots: Vec<CallFrame>,
+}
+
+impl CallTree {
+    /// Build the tree of `instructions`. Frames with no matching log are
+    /// assumed successful in a successful transaction.
+    pub fn build(instructions: &[DecodedInstruction], log_messages: &[String], success: bool) -> Self {
+        let mut roots: Vec<CallFrame> = instructions
+            .iter()
+            .map(|ix| {
+                let inner: Vec<_> = ix.inner_instructions.iter().enumerate().collect();
+                let mut root = frame(ix, None);
+                root.children = nest(&inner, &mut 0, ix.stack_height);
+                root
+            })
+            .collect();
+
+        let mut invocations = parse_logs(log_messages).into_iter().peekable();
+        let fallback = if success { FrameStatus::Success } else { FrameStatus::Unknown };
+        let mut pending: Vec<&mut CallFrame> = roots.iter_mut().rev().collect();
+        while let Some(frame) = pending.pop() {
+            match invocations.peek() {
+                Some(logged) if logged.program_id == frame.program_id && logged.stack_height == frame.stack_height => {
+                    let logged = invocations.next().unwrap();
+                    frame.compute_units = logged.compute_units;
+                    frame.status = logged.status.unwrap_or_else(|| fallback.clone());
+                    frame.logs = logged.logs;
+                    frame.data = logged.data;
+                }
+                _ => frame.status = fallback.clone(),
+            }
+            pending.extend(frame.children.iter_mut().rev());
+        }
+        Self { roots }
+    }
+
+    /// Every frame, in execution order
+    pub fn iter(&self) -> impl Iterator<Item = &CallFrame> {
+        self.roots.iter().flat_map(CallFrame::iter)
+    }
+
+    /// Frame of the instruction at `instruction_index` / `inner_index`
+    pub fn frame(&self, instruction_index: usize, inner_index: Option<usize>) -> Option<&CallFrame> {
+        self.roots
+            .get(instruction_index)?
+            .iter()
+            .find(|frame| frame.inner_index == inner_index)
+    }
+
+    /// Frames from the top-level instruction down to the one at
+    /// `instruction_index` / `inner_index`, so the invoker of a CPI is the
+    /// second to last entry
+    pub fn call_stack(&self, instruction_index: usize, inner_index: Option<usize>) -> Vec<&CallFrame> {
+        fn walk<'a>(frame: &'a CallFrame, inner_index: Option<usize>, stack: &mut Vec<&'a CallFrame>) -> bool {
+            stack.push(frame);
+            if frame.inner_index == inner_index || frame.children.iter().any(|child| walk(child, inner_index, stack)) {
+                return true;
+            }
+            stack.pop();
+            false
+        }
+        let mut stack = Vec::new();
+        if let Some(root) = self.roots.get(instruction_index) {
+            walk(root, inner_index, &mut stack);
+        }
+        stack
+    }
+
+    /// Total compute units consumed by the top-level instructions
+    pub fn compute_units(&self) -> u64 {
+        self.roots.iter().filter_map(|root| root.compute_units).sum()
+    }
+}
+
+fn frame(ix: &DecodedInstruction, inner_index: Option<usize>) -> CallFrame {
+    CallFrame {
+        program_id: ix.program_id,
+        instruction_index: ix.index,
+        inner_index,
+        stack_height: ix.stack_height,
+        compute_units: None,
+        status: FrameStatus::Unknown,
+        logs: Vec::new(),
+        data: Vec::new(),
+        children: Vec::new(),
+    }
+}
+
+/// Frames of the instructions from `inner[*next]` on that sit deeper than
+/// `parent_height`, each holding the deeper instructions following it
+fn nest(inner: &[(usize, &DecodedInstruction)], next: &mut usize, parent_height: u32) -> Vec<CallFrame> {
+    let mut frames = Vec::new();
+    while let Some(&(inner_index, ix)) = inner.get(*next) {
+        if ix.stack_height <= parent_height {
+            break;
+        }
+        *next += 1;
+        let mut child = frame(ix, Some(inner_index));
+        child.children = nest(inner, next, ix.stack_height);
+        frames.push(child);
+    }
+    frames
+}
+
+/// An invocation as recorded in the log messages
+struct LoggedInvocation {
+    program_id: Pubkey,
+    stack_height: u32,
+    compute_units: Option<u64>,
+    status: Option<FrameStatus>,
+    logs: Vec<String>,
+    data: Vec<String>,
+}
+
+/// Invocations in the order they started. Parsing stops at a `Log truncated`
+/// line, leaving the invocations still open without a status.
+fn parse_logs(log_messages: &[String]) -> Vec<LoggedInvocation> {
+    let mut invocations: Vec<LoggedInvocation> = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+    for line in log_messages {
+        if line == "Log truncated" {
+            break;
+        }
+        if let Some(message) = line.strip_prefix("Program log: ") {
+            if let Some(&current) = open.last() {
+                invocations[current].logs.push(message.to_string());
+            }
+            continue;
+        }
+        if let Some(payload) = line.strip_prefix("Program data: ") {
+            if let Some(&current) = open.last() {
+                invocations[current].data.push(payload.to_string());
+            }
+            continue;
+        }
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        let Some((program, rest)) = rest.split_once(' ') else {
+            continue;
+        };
+        let Ok(program_id) = Pubkey::from_str(program) else {
+            continue;
+        };
+
+        if let Some(height) = rest.strip_prefix("invoke [").and_then(|rest| rest.strip_suffix(']')) {
+            open.push(invocations.len());
+            invocations.push(LoggedInvocation {
+                program_id,
+                stack_height: height.parse().unwrap_or(open.len() as u32),
+                compute_units: None,
+                status: None,
+                logs: Vec::new(),
+                data: Vec::new(),
+            });
+            continue;
+        }
+        let Some(&current) = open.last() else {
+            continue;
+        };
+        if invocations[current].program_id != program_id {
+            continue;
+        }
+        if let Some(consumed) = rest.strip_prefix("consumed ") {
+            invocations[current].compute_units = consumed.split(' ').next().and_then(|units| units.parse().ok());
+        } else if rest == "success" {
+            invocations[current].status = Some(FrameStatus::Success);
+            open.pop();
+        } else if let Some(error) = rest.strip_prefix("failed: ") {
+            invocations[current].status = Some(FrameStatus::Failed {
+                error: error.to_string(),
+            });
+            open.pop();
+        }
+    }
+    invocations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(program_id: Pubkey, stack_height: u32, inner: Vec<DecodedInstruction>) -> DecodedInstruction {
+        DecodedInstruction {
+            program_id,
+            accounts: vec![],
+            data: vec![],
+            index: 0,
+            inner_index: None,
+            stack_height,
+            inner_instructions: inner,
+        }
+    }
+
+    fn logs(lines: &[String]) -> Vec<String> {
+        lines.to_vec()
+    }
+
+    #[test]
+    fn test_nests_inner_instructions_and_matches_logs() {
+        let (router, amm, token, other) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let root = instruction(
+            router,
+            1,
+            vec![
+                instruction(amm, 2, vec![]),
+                instruction(token, 3, vec![]),
+                instruction(token, 3, vec![]),
+                instruction(token, 2, vec![]),
+            ],
+        );
+        let second = DecodedInstruction {
+            index: 1,
+            ..instruction(other, 1, vec![])
+        };
+        let messages = logs(&[
+            format!("Program {} invoke [1]", router),
+            "Program log: Instruction: Route".to_string(),
+            format!("Program {} invoke [2]", amm),
+            format!("Program {} invoke [3]", token),
+            format!("Program {} consumed 4645 of 180000 compute units", token),
+            format!("Program {} success", token),
+            format!("Program {} invoke [3]", token),
+            format!("Program {} success", token),
+            "Program data: ZXZlbnQ=".to_string(),
+            format!("Program {} consumed 30000 of 190000 compute units", amm),
+            format!("Program {} success", amm),
+            format!("Program {} invoke [2]", token),
+            format!("Program {} success", token),
+            format!("Program {} consumed 52000 of 200000 compute units", router),
+            format!("Program {} success", router),
+            format!("Program {} invoke [1]", other),
+            format!("Program {} consumed 150 of 148000 compute units", other),
+            format!("Program {} failed: custom program error: 0x1", other),
+        ]);
+        let tree = CallTree::build(&[root, second], &messages, false);
+
+        assert_eq!(tree.roots.len(), 2);
+        let route = &tree.roots[0];
+        assert_eq!(route.logs, ["Instruction: Route"]);
+        assert_eq!(route.compute_units, Some(52_000));
+        assert_eq!(route.children.len(), 2);
+        assert_eq!(route.children[0].program_id, amm);
+        assert_eq!(route.children[0].data, ["ZXZlbnQ="]);
+        assert_eq!(route.children[0].children.len(), 2);
+        assert_eq!(route.children[0].children[0].compute_units, Some(4645));
+        assert_eq!(route.children[1].inner_index, Some(3));
+        assert!(route.iter().all(|frame| frame.status == FrameStatus::Success));
+        assert!(route.invokes(&token));
+        assert_eq!(
+            tree.roots[1].status,
+            FrameStatus::Failed {
+                error: "custom program error: 0x1".to_string()
+            }
+        );
+        assert_eq!(tree.compute_units(), 52_150);
+        assert_eq!(tree.iter().count(), 6);
+
+        let stack: Vec<_> = tree.call_stack(0, Some(2)).iter().map(|frame| frame.program_id).collect();
+        assert_eq!(stack, [router, amm, token]);
+        assert_eq!(tree.frame(0, Some(1)).unwrap().stack_height, 3);
+        assert!(tree.frame(1, Some(0)).is_none());
+    }
+
+    #[test]
+    fn test_frames_without_logs() {
+        let program = Pubkey::new_unique();
+        let precompile = Pubkey::new_unique();
+        let instructions = [
+            instruction(precompile, 1, vec![]),
+            DecodedInstruction {
+                index: 1,
+                ..instruction(program, 1, vec![])
+            },
+        ];
+        let messages = logs(&[format!("Program {} invoke [1]", program), "Log truncated".to_string()]);
+
+        let succeeded = CallTree::build(&instructions, &messages, true);
+        assert!(succeeded.iter().all(|frame| frame.status == FrameStatus::Success));
+
+        let failed = CallTree::build(&instructions, &messages, false);
+        assert!(failed.iter().all(|frame| frame.status == FrameStatus::Unknown));
+    }
+}
@@ -0,0 +1,384 @@
+//! Decoding of fetched transactions into [`DecodedInstruction`]s
+
+use crate::parsers::{CallTree, DecodedInstruction, ParseError, ParseResult, TokenBalance, TransactionContext};
+use solana_sdk::bs58;
+use solana_sdk::message::v0::{LoadedAddresses, MessageAddressTableLookup};
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{
+    EncodedTransaction, EncodedTransactionWithStatusMeta, UiInstruction, UiMessage, UiTransactionTokenBalance,
+};
+use std::str::FromStr;
+
+/// A transaction resolved for the parsers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedTransaction {
+    pub context: TransactionContext,
+    /// Top-level instructions, each holding the instructions it invoked
+    pub instructions: Vec<DecodedInstruction>,
+}
+
+/// Decode a transaction fetched at `slot` with JSON (raw) or binary encoding.
+///
+/// Inner instructions, balances and logs are taken from the status meta when
+/// present, and so are the addresses a v0 transaction loads from lookup
+/// tables. `jsonParsed` transactions are rejected since their instruction data
+/// has already been interpreted by the node.
+pub fn decode_transaction(
+    slot: u64,
+    block_time: Option<i64>,
+    tx: &EncodedTransactionWithStatusMeta,
+) -> ParseResult<DecodedTransaction> {
+    decode_transaction_with(slot, block_time, tx, None)
+}
+
+/// Like [`decode_transaction`], using `loaded` for a v0 transaction whose
+/// status meta does not list the addresses loaded from its lookup tables, e.g.
+/// as resolved by a [`LookupTableCache`](crate::parsers::LookupTableCache).
+pub fn decode_transaction_with(
+    slot: u64,
+    block_time: Option<i64>,
+    tx: &EncodedTransactionWithStatusMeta,
+    loaded: Option<&LoadedAddresses>,
+) -> ParseResult<DecodedTransaction> {
+    let (signature, mut account_keys, num_required_signatures, outer) = match &tx.transaction {
+        EncodedTransaction::Json(ui) => match &ui.message {
+            UiMessage::Raw(message) => {
+                let keys = message
+                    .account_keys
+                    .iter()
+                    .map(|key| parse_pubkey(key))
+                    .collect::<ParseResult<Vec<_>>>()?;
+                let instructions = message
+                    .instructions
+                    .iter()
+                    .map(|ix| Ok((ix.program_id_index, ix.accounts.clone(), decode_data(&ix.data)?)))
+                    .collect::<ParseResult<Vec<_>>>()?;
+                (
+                    ui.signatures.first().cloned().unwrap_or_default(),
+                    keys,
+                    message.header.num_required_signatures as usize,
+                    instructions,
+                )
+            }
+            UiMessage::Parsed(_) => return Err(ParseError::UnsupportedEncoding("jsonParsed".to_string())),
+        },
+        EncodedTransaction::Accounts(_) => return Err(ParseError::UnsupportedEncoding("accounts".to_string())),
+        encoded => {
+            let decoded = encoded
+                .decode()
+                .ok_or_else(|| ParseError::UnsupportedEncoding("undecodable binary".to_string()))?;
+            let instructions = decoded
+                .message
+                .instructions()
+                .iter()
+                .map(|ix| (ix.program_id_index, ix.accounts.clone(), ix.data.clone()))
+                .collect();
+            (
+                decoded.signatures.first().map(|s| s.to_string()).unwrap_or_default(),
+                decoded.message.static_account_keys().to_vec(),
+                decoded.message.header().num_required_signatures as usize,
+                instructions,
+            )
+        }
+    };
+
+    let num_static_keys = account_keys.len();
+    let lookups = address_table_lookups(tx)?;
+    let expected_writable: usize = lookups.iter().map(|lookup| lookup.writable_indexes.len()).sum();
+    let expected_readonly: usize = lookups.iter().map(|lookup| lookup.readonly_indexes.len()).sum();
+    let loaded = match meta_loaded_addresses(tx)? {
+        Some(from_meta) => Some(from_meta),
+        None => loaded.cloned(),
+    };
+    let num_loaded_writable = match loaded {
+        Some(loaded) => {
+            if loaded.writable.len() != expected_writable || loaded.readonly.len() != expected_readonly {
+                return Err(ParseError::InvalidData(format!(
+                    "lookup tables load {} writable and {} readonly addresses, got {} and {}",
+                    expected_writable,
+                    expected_readonly,
+                    loaded.writable.len(),
+                    loaded.readonly.len()
+                )));
+            }
+            account_keys.extend(loaded.writable);
+            account_keys.extend(loaded.readonly);
+            expected_writable
+        }
+        None if lookups.is_empty() => 0,
+        None => return Err(ParseError::UnresolvedLookupTables),
+    };
+
+    let mut context = TransactionContext {
+        signature,
+        slot,
+        block_time,
+        success: true,
+        account_keys,
+        num_static_keys,
+        num_loaded_writable,
+        num_required_signatures,
+        ..Default::default()
+    };
+
+    let mut instructions = outer
+        .into_iter()
+        .enumerate()
+        .map(|(index, (program_id_index, accounts, data))| {
+            resolve(&context.account_keys, index, None, 1, program_id_index, &accounts, data)
+        })
+        .collect::<ParseResult<Vec<_>>>()?;
+
+    if let Some(meta) = &tx.meta {
+        context.success = meta.err.is_none();
+        context.fee = meta.fee;
+        context.pre_balances = meta.pre_balances.clone();
+        context.post_balances = meta.post_balances.clone();
+        context.pre_token_balances = token_balances(&meta.pre_token_balances);
+        context.post_token_balances = token_balances(&meta.post_token_balances);
+        if let OptionSerializer::Some(logs) = &meta.log_messages {
+            context.log_messages = logs.clone();
+        }
+        if let OptionSerializer::Some(inner) = &meta.inner_instructions {
+            for group in inner {
+                let index = group.index as usize;
+                let parent = instructions.get_mut(index).ok_or_else(|| {
+                    ParseError::InvalidData(format!("inner instructions for missing instruction {}", index))
+                })?;
+                for (inner_index, ix) in group.instructions.iter().enumerate() {
+                    let UiInstruction::Compiled(ix) = ix else {
+                        return Err(ParseError::UnsupportedEncoding("parsed inner instruction".to_string()));
+                    };
+                    parent.inner_instructions.push(resolve(
+                        &context.account_keys,
+                        index,
+                        Some(inner_index),
+                        ix.stack_height.unwrap_or(2),
+                        ix.program_id_index,
+                        &ix.accounts,
+                        decode_data(&ix.data)?,
+                    )?);
+                }
+            }
+        }
+    }
+
+    context.call_tree = CallTree::build(&instructions, &context.log_messages, context.success);
+    Ok(DecodedTransaction { context, instructions })
+}
+
+fn resolve(
+    keys: &[Pubkey],
+    index: usize,
+    inner_index: Option<usize>,
+    stack_height: u32,
+    program_id_index: u8,
+    accounts: &[u8],
+    data: Vec<u8>,
+) -> ParseResult<DecodedInstruction> {
+    let key = |i: u8| keys.get(i as usize).copied().ok_or(ParseError::AccountIndex(i as usize));
+    Ok(DecodedInstruction {
+        program_id: key(program_id_index)?,
+        accounts: accounts.iter().map(|&i| key(i)).collect::<ParseResult<_>>()?,
+        data,
+        index,
+        inner_index,
+        stack_height,
+        inner_instructions: Vec::new(),
+    })
+}
+
+/// Lookup tables a v0 transaction loads accounts from, empty for legacy
+/// transactions
+pub fn address_table_lookups(tx: &EncodedTransactionWithStatusMeta) -> ParseResult<Vec<MessageAddressTableLookup>> {
+    match &tx.transaction {
+        EncodedTransaction::Json(ui) => match &ui.message {
+            UiMessage::Raw(message) => message
+                .address_table_lookups
+                .iter()
+                .flatten()
+                .map(|lookup| {
+                    Ok(MessageAddressTableLookup {
+                        account_key: parse_pubkey(&lookup.account_key)?,
+                        writable_indexes: lookup.writable_indexes.clone(),
+                        readonly_indexes: lookup.readonly_indexes.clone(),
+                    })
+                })
+                .collect(),
+            UiMessage::Parsed(_) => Err(ParseError::UnsupportedEncoding("jsonParsed".to_string())),
+        },
+        EncodedTransaction::Accounts(_) => Err(ParseError::UnsupportedEncoding("accounts".to_string())),
+        encoded => {
+            let decoded = encoded
+                .decode()
+                .ok_or_else(|| ParseError::UnsupportedEncoding("undecodable binary".to_string()))?;
+            Ok(decoded.message.address_table_lookups().unwrap_or_default().to_vec())
+        }
+    }
+}
+
+/// Addresses loaded from lookup tables as listed in the status meta, `None`
+/// when the meta does not list them
+pub fn meta_loaded_addresses(tx: &EncodedTransactionWithStatusMeta) -> ParseResult<Option<LoadedAddresses>> {
+    let Some(OptionSerializer::Some(loaded)) = tx.meta.as_ref().map(|meta| &meta.loaded_addresses) else {
+        return Ok(None);
+    };
+    let parse = |keys: &[String]| keys.iter().map(|key| parse_pubkey(key)).collect::<ParseResult<Vec<_>>>();
+    Ok(Some(LoadedAddresses {
+        writable: parse(&loaded.writable)?,
+        readonly: parse(&loaded.readonly)?,
+    }))
+}
+
+fn parse_pubkey(key: &str) -> ParseResult<Pubkey> {
+    Pubkey::from_str(key).map_err(|_| ParseError::InvalidAccount(key.to_string()))
+}
+
+fn decode_data(data: &str) -> ParseResult<Vec<u8>> {
+    bs58::decode(data)
+        .into_vec()
+        .map_err(|e| ParseError::InvalidData(format!("instruction data is not base58: {}", e)))
+}
+
+fn token_balances(balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>) -> Vec<TokenBalance> {
+    let OptionSerializer::Some(balances) = balances else {
+        return Vec::new();
+    };
+    balances
+        .iter()
+        .map(|balance| TokenBalance {
+            account_index: balance.account_index as usize,
+            mint: balance.mint.clone(),
+            owner: Option::<String>::from(balance.owner.clone()),
+            program_id: Option::<String>::from(balance.program_id.clone()),
+            amount: balance.ui_token_amount.amount.parse().unwrap_or(0),
+            decimals: balance.ui_token_amount.decimals,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::fixture;
+    use serde_json::json;
+
+    const PAYER: &str = "7xLk17EQQ5KLDLDe44wCmupJKJjTGd8hs3eSVVhCx932";
+    const PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+    const SYSTEM: &str = "11111111111111111111111111111111";
+    const OWNER: &str = "67vHA8qZGCJKw1UNGUJZME4MwEWDRGWzp7MGvsut43A8";
+    const SOURCE: &str = "4sAFqwXMzZFwSDjjHceT9txELSCNEbVd7iw7G6yBJs4s";
+    const DESTINATION: &str = "5tC4Qjk6b9xuP7a9B9FFs3m5Rto4siXsfqGt95zFJhf7";
+
+    fn transaction() -> EncodedTransactionWithStatusMeta {
+        serde_json::from_value(json!({
+            "transaction": {
+                "signatures": ["5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW"],
+                "message": {
+                    "header": {
+                        "numRequiredSignatures": 1,
+                        "numReadonlySignedAccounts": 0,
+                        "numReadonlyUnsignedAccounts": 2,
+                    },
+                    "accountKeys": [PAYER, PROGRAM, SYSTEM],
+                    "recentBlockhash": "11111111111111111111111111111111",
+                    "instructions": [{ "programIdIndex": 1, "accounts": [0], "data": "3Bxs4h24hBtQy9rw" }],
+                },
+            },
+            "meta": {
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5000,
+                "preBalances": [10_000, 1, 1],
+                "postBalances": [5_000, 1, 1],
+                "innerInstructions": [{
+                    "index": 0,
+                    "instructions": [{ "programIdIndex": 2, "accounts": [0], "data": "", "stackHeight": 2 }],
+                }],
+                "logMessages": [format!("Program {} invoke [1]", PROGRAM)],
+                "preTokenBalances": [],
+                "postTokenBalances": [{
+                    "accountIndex": 0,
+                    "mint": SYSTEM,
+                    "uiTokenAmount": { "uiAmount": 1.5, "decimals": 6, "amount": "1500000", "uiAmountString": "1.5" },
+                    "owner": PAYER,
+                }],
+                "rewards": [],
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_decode_json_transaction() {
+        let decoded = decode_transaction(42, Some(1_700_000_000), &transaction()).unwrap();
+        let context = &decoded.context;
+        assert_eq!(context.slot, 42);
+        assert!(context.success);
+        assert_eq!(context.fee, 5000);
+        assert_eq!(context.fee_payer().unwrap().to_string(), PAYER);
+        assert!(context.is_signer(context.fee_payer().unwrap()));
+        assert!(!context.is_signer(&Pubkey::from_str(PROGRAM).unwrap()));
+        assert_eq!(context.log_messages.len(), 1);
+        assert_eq!(context.post_token_balances[0].amount, 1_500_000);
+        assert_eq!(context.post_token_balances[0].owner.as_deref(), Some(PAYER));
+
+        let ix = &decoded.instructions[0];
+        assert_eq!(ix.program_id.to_string(), PROGRAM);
+        assert_eq!(ix.accounts[0].to_string(), PAYER);
+        assert_eq!(ix.data, bs58::decode("3Bxs4h24hBtQy9rw").into_vec().unwrap());
+        assert_eq!(ix.stack_height, 1);
+        assert_eq!(ix.inner_instructions.len(), 1);
+        assert_eq!(ix.inner_instructions[0].program_id.to_string(), SYSTEM);
+        assert_eq!(ix.inner_instructions[0].stack_height, 2);
+        assert_eq!(ix.inner_instructions[0].index, 0);
+        assert_eq!(ix.inner_instructions[0].inner_index, Some(0));
+
+        let frame = ix.inner_instructions[0].frame(context).unwrap();
+        assert_eq!(frame.program_id.to_string(), SYSTEM);
+        assert_eq!(frame.stack_height, 2);
+        assert_eq!(context.call_tree.call_stack(0, Some(0)).len(), 2);
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_account_index() {
+        let mut value = serde_json::to_value(transaction()).unwrap();
+        value["transaction"]["message"]["instructions"][0]["programIdIndex"] = json!(9);
+        let tx: EncodedTransactionWithStatusMeta = serde_json::from_value(value).unwrap();
+        assert_eq!(decode_transaction(42, None, &tx), Err(ParseError::AccountIndex(9)));
+    }
+
+    #[test]
+    fn test_decode_v0_transaction_with_loaded_addresses() {
+        let fixture = fixture("v0_lookup_transfer");
+        let decoded = decode_transaction(fixture.slot, fixture.block_time, &fixture.transaction).unwrap();
+        let context = &decoded.context;
+        let keys: Vec<String> = context.account_keys.iter().map(|key| key.to_string()).collect();
+        assert_eq!(keys, [OWNER, SOURCE, DESTINATION, PROGRAM]);
+        assert_eq!(context.num_static_keys, 1);
+        assert_eq!(context.num_loaded_writable, 2);
+        assert_eq!(context.loaded_addresses().len(), 3);
+        assert_eq!(context.post_token_balance(&Pubkey::from_str(DESTINATION).unwrap()).unwrap().amount, 2_500_000);
+
+        let ix = &decoded.instructions[0];
+        assert_eq!(ix.program_id.to_string(), PROGRAM);
+        assert_eq!(ix.accounts.iter().map(|key| key.to_string()).collect::<Vec<_>>(), [SOURCE, DESTINATION, OWNER]);
+
+        // Without loaded addresses in the meta the caller has to supply them
+        let mut value = serde_json::to_value(&fixture.transaction).unwrap();
+        value["meta"].as_object_mut().unwrap().remove("loadedAddresses");
+        let tx: EncodedTransactionWithStatusMeta = serde_json::from_value(value).unwrap();
+        assert_eq!(decode_transaction(fixture.slot, None, &tx), Err(ParseError::UnresolvedLookupTables));
+        let loaded = LoadedAddresses {
+            writable: context.loaded_addresses()[..2].to_vec(),
+            readonly: context.loaded_addresses()[2..].to_vec(),
+        };
+        let resolved = decode_transaction_with(fixture.slot, fixture.block_time, &tx, Some(&loaded)).unwrap();
+        assert_eq!(resolved, decoded);
+        assert!(matches!(
+            decode_transaction_with(fixture.slot, None, &tx, Some(&LoadedAddresses::default())),
+            Err(ParseError::InvalidData(_))
+        ));
+    }
+}
@@ -0,0 +1,673 @@
+//! SPL Governance (Realms) parser and DAO resolution
+
+use crate::core::error::{Error, Result};
+use crate::core::indexer::{Enricher, ParsedBatch};
+use crate::models::governance::{GovernanceProposalEvent, GovernanceVote};
+use crate::parsers::{
+    DecodedInstruction, ParseError, ParseResult, ParsedTransaction, ProtocolEvent, ProtocolParser,
+    TransactionContext,
+};
+use crate::rpc::client::AccountClientTrait;
+use async_trait::async_trait;
+use borsh::BorshDeserialize;
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// Program id of the SPL Governance instance used by Realms
+pub const GOVERNANCE_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw");
+
+const CREATE_PROPOSAL: u8 = 6;
+const SIGN_OFF_PROPOSAL: u8 = 12;
+const CAST_VOTE: u8 = 13;
+const FINALIZE_VOTE: u8 = 14;
+const RELINQUISH_VOTE: u8 = 15;
+const EXECUTE_TRANSACTION: u8 = 16;
+
+/// Share of a voter's weight given to one proposal option
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshDeserialize)]
+pub struct VoteChoice {
+    pub rank: u8,
+    pub weight_percentage: u8,
+}
+
+#[derive(BorshDeserialize)]
+enum Vote {
+    Approve(Vec<VoteChoice>),
+    Deny,
+    Abstain,
+    Veto,
+}
+
+#[derive(BorshDeserialize)]
+enum VoteType {
+    SingleChoice,
+    MultiChoice {
+        _choice_type: u8,
+        _min_voter_options: u8,
+        _max_voter_options: u8,
+        _max_winning_options: u8,
+    },
+}
+
+#[derive(BorshDeserialize)]
+struct CreateProposalArgs {
+    name: String,
+    description_link: String,
+    vote_type: VoteType,
+    options: Vec<String>,
+    use_deny_option: bool,
+}
+
+/// Governance activity decoded from an SPL Governance instruction.
+///
+/// Proposals are identified by their account address and DAOs by their realm
+/// address. `dao_name` and `voter_weight` are not part of the instructions and
+/// are filled in by a [`GovernanceResolver`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GovernanceEvent {
+    ProposalCreated {
+        realm: String,
+        dao_name: Option<String>,
+        governance: String,
+        proposal: String,
+        /// Wallet that signed for the proposal owner
+        authority: String,
+        governing_token_mint: String,
+        name: String,
+        description_link: String,
+        options: Vec<String>,
+        multi_choice: bool,
+        use_deny_option: bool,
+    },
+    ProposalSignedOff {
+        realm: String,
+        dao_name: Option<String>,
+        governance: String,
+        proposal: String,
+        signatory: String,
+    },
+    VoteCast {
+        realm: String,
+        dao_name: Option<String>,
+        governance: String,
+        proposal: String,
+        /// Wallet that signed the vote, the token owner or its delegate
+        voter: String,
+        token_owner_record: String,
+        vote_record: String,
+        governing_token_mint: String,
+        /// `approve`, `deny`, `abstain` or `veto`
+        vote: String,
+        /// Options approved, with the share of weight given to each
+        choices: Vec<VoteChoice>,
+        /// Weight of the vote in governing token base units
+        voter_weight: Option<u64>,
+    },
+    VoteRelinquished {
+        realm: String,
+        dao_name: Option<String>,
+        governance: String,
+        proposal: String,
+        token_owner_record: String,
+        vote_record: String,
+        governing_token_mint: String,
+    },
+    VoteFinalized {
+        realm: String,
+        dao_name: Option<String>,
+        governance: String,
+        proposal: String,
+    },
+    TransactionExecuted {
+        governance: String,
+        proposal: String,
+        proposal_transaction: String,
+    },
+}
+
+impl GovernanceEvent {
+    pub fn realm(&self) -> Option<&str> {
+        match self {
+            GovernanceEvent::ProposalCreated { realm, .. }
+            | GovernanceEvent::ProposalSignedOff { realm, .. }
+            | GovernanceEvent::VoteCast { realm, .. }
+            | GovernanceEvent::VoteRelinquished { realm, .. }
+            | GovernanceEvent::VoteFinalized { realm, .. } => Some(realm),
+            GovernanceEvent::TransactionExecuted { .. } => None,
+        }
+    }
+
+    pub fn proposal(&self) -> &str {
+        match self {
+            GovernanceEvent::ProposalCreated { proposal, .. }
+            | GovernanceEvent::ProposalSignedOff { proposal, .. }
+            | GovernanceEvent::VoteCast { proposal, .. }
+            | GovernanceEvent::VoteRelinquished { proposal, .. }
+            | GovernanceEvent::VoteFinalized { proposal, .. }
+            | GovernanceEvent::TransactionExecuted { proposal, .. } => proposal,
+        }
+    }
+
+    /// Realm name when resolved, otherwise the realm address
+    pub fn dao(&self) -> Option<&str> {
+        match self {
+            GovernanceEvent::ProposalCreated { dao_name, .. }
+            | GovernanceEvent::ProposalSignedOff { dao_name, .. }
+            | GovernanceEvent::VoteCast { dao_name, .. }
+            | GovernanceEvent::VoteRelinquished { dao_name, .. }
+            | GovernanceEvent::VoteFinalized { dao_name, .. } => dao_name.as_deref().or(self.realm()),
+            GovernanceEvent::TransactionExecuted { .. } => None,
+        }
+    }
+
+    fn dao_name_mut(&mut self) -> Option<&mut Option<String>> {
+        match self {
+            GovernanceEvent::ProposalCreated { dao_name, .. }
+            | GovernanceEvent::ProposalSignedOff { dao_name, .. }
+            | GovernanceEvent::VoteCast { dao_name, .. }
+            | GovernanceEvent::VoteRelinquished { dao_name, .. }
+            | GovernanceEvent::VoteFinalized { dao_name, .. } => Some(dao_name),
+            GovernanceEvent::TransactionExecuted { .. } => None,
+        }
+    }
+
+    /// Lifecycle name stored with proposal events
+    fn kind(&self) -> &'static str {
+        match self {
+            GovernanceEvent::ProposalCreated { .. } => "created",
+            GovernanceEvent::ProposalSignedOff { .. } => "signed_off",
+            GovernanceEvent::VoteCast { .. } => "vote_cast",
+            GovernanceEvent::VoteRelinquished { .. } => "vote_relinquished",
+            GovernanceEvent::VoteFinalized { .. } => "finalized",
+            GovernanceEvent::TransactionExecuted { .. } => "executed",
+        }
+    }
+}
+
+/// Parser for SPL Governance instructions.
+///
+/// Realms DAOs may run their own deployment of the program; list them with
+/// [`GovernanceParser::with_program_ids`].
+#[derive(Debug, Clone)]
+pub struct GovernanceParser {
+    program_ids: Vec<Pubkey>,
+}
+
+impl Default for GovernanceParser {
+    fn default() -> Self {
+        Self {
+            program_ids: vec![GOVERNANCE_PROGRAM_ID],
+        }
+    }
+}
+
+impl GovernanceParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse the instructions of these governance deployments instead
+    pub fn with_program_ids(mut self, program_ids: Vec<Pubkey>) -> Self {
+        self.program_ids = program_ids;
+        self
+    }
+}
+
+impl ProtocolParser for GovernanceParser {
+    fn name(&self) -> &str {
+        "spl-governance"
+    }
+
+    fn program_ids(&self) -> Vec<Pubkey> {
+        self.program_ids.clone()
+    }
+
+    fn parse(&self, ix: &DecodedInstruction, _context: &TransactionContext) -> ParseResult<Vec<ProtocolEvent>> {
+        let Some((&tag, mut args)) = ix.data.split_first() else {
+            return Err(ParseError::InvalidData("empty governance instruction".to_string()));
+        };
+        let account = |name: &'static str, index: usize| ix.account(name, index).map(|key| key.to_string());
+        let invalid = |e: std::io::Error| ParseError::InvalidData(format!("governance instruction {}: {}", tag, e));
+
+        let event = match tag {
+            CREATE_PROPOSAL => {
+                let proposal = CreateProposalArgs::deserialize(&mut args).map_err(invalid)?;
+                GovernanceEvent::ProposalCreated {
+                    realm: account("CreateProposal", 0)?,
+                    dao_name: None,
+                    governance: account("CreateProposal", 2)?,
+                    proposal: account("CreateProposal", 1)?,
+                    authority: account("CreateProposal", 5)?,
+                    governing_token_mint: account("CreateProposal", 4)?,
+                    name: proposal.name,
+                    description_link: proposal.description_link,
+                    options: proposal.options,
+                    multi_choice: matches!(proposal.vote_type, VoteType::MultiChoice { .. }),
+                    use_deny_option: proposal.use_deny_option,
+                }
+            }
+            SIGN_OFF_PROPOSAL => GovernanceEvent::ProposalSignedOff {
+                realm: account("SignOffProposal", 0)?,
+                dao_name: None,
+                governance: account("SignOffProposal", 1)?,
+                proposal: account("SignOffProposal", 2)?,
+                signatory: account("SignOffProposal", 3)?,
+            },
+            CAST_VOTE => {
+                let (vote, choices) = match Vote::deserialize(&mut args).map_err(invalid)? {
+                    Vote::Approve(choices) => ("approve", choices),
+                    Vote::Deny => ("deny", Vec::new()),
+                    Vote::Abstain => ("abstain", Vec::new()),
+                    Vote::Veto => ("veto", Vec::new()),
+                };
+                GovernanceEvent::VoteCast {
+                    realm: account("CastVote", 0)?,
+                    dao_name: None,
+                    governance: account("CastVote", 1)?,
+                    proposal: account("CastVote", 2)?,
+                    voter: account("CastVote", 5)?,
+                    token_owner_record: account("CastVote", 4)?,
+                    vote_record: account("CastVote", 6)?,
+                    governing_token_mint: account("CastVote", 7)?,
+                    vote: vote.to_string(),
+                    choices,
+                    voter_weight: None,
+                }
+            }
+            FINALIZE_VOTE => GovernanceEvent::VoteFinalized {
+                realm: account("FinalizeVote", 0)?,
+                dao_name: None,
+                governance: account("FinalizeVote", 1)?,
+                proposal: account("FinalizeVote", 2)?,
+            },
+            RELINQUISH_VOTE => GovernanceEvent::VoteRelinquished {
+                realm: account("RelinquishVote", 0)?,
+                dao_name: None,
+                governance: account("RelinquishVote", 1)?,
+                proposal: account("RelinquishVote", 2)?,
+                token_owner_record: account("RelinquishVote", 3)?,
+                vote_record: account("RelinquishVote", 4)?,
+                governing_token_mint: account("RelinquishVote", 5)?,
+            },
+            EXECUTE_TRANSACTION => GovernanceEvent::TransactionExecuted {
+                governance: account("ExecuteTransaction", 0)?,
+                proposal: account("ExecuteTransaction", 1)?,
+                proposal_transaction: account("ExecuteTransaction", 2)?,
+            },
+            _ => return Ok(Vec::new()),
+        };
+        Ok(vec![ProtocolEvent::Governance(event)])
+    }
+}
+
+/// Leading fields shared by `RealmV1` and `RealmV2` accounts
+#[derive(BorshDeserialize)]
+struct RealmHeader {
+    _account_type: u8,
+    _community_mint: [u8; 32],
+    _config_legacy: [u8; 8],
+    _min_community_weight_to_create_governance: u64,
+    _community_mint_max_voter_weight_source: (u8, u64),
+    _council_mint: Option<[u8; 32]>,
+    _reserved: [u8; 6],
+    _legacy: u16,
+    _authority: Option<[u8; 32]>,
+    name: String,
+}
+
+/// `VoteRecordV1` stores the weight behind a yes/no tag, `VoteRecordV2`
+/// directly after `is_relinquished`
+const VOTE_RECORD_V1: u8 = 7;
+const VOTE_RECORD_V2: u8 = 12;
+const VOTE_RECORD_WEIGHT_OFFSET: usize = 1 + 32 + 32 + 1;
+
+fn realm_name(data: &[u8]) -> Option<String> {
+    RealmHeader::deserialize(&mut &data[..]).ok().map(|realm| realm.name)
+}
+
+fn voter_weight(data: &[u8]) -> Option<u64> {
+    let offset = match *data.first()? {
+        VOTE_RECORD_V1 => VOTE_RECORD_WEIGHT_OFFSET + 1,
+        VOTE_RECORD_V2 => VOTE_RECORD_WEIGHT_OFFSET,
+        _ => return None,
+    };
+    Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+/// Enricher filling in DAO names and vote weights from governance accounts.
+///
+/// Realm names are cached for the lifetime of the resolver. Vote weights are
+/// read from vote records, which are closed when a vote is relinquished, so
+/// backfilled votes may keep no weight.
+pub struct GovernanceResolver<C> {
+    client: Arc<C>,
+    realm_names: Mutex<HashMap<String, String>>,
+}
+
+impl<C: AccountClientTrait> GovernanceResolver<C> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            realm_names: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Seed the realm name cache, e.g. for realms whose account is unavailable
+    pub fn with_realm_name(self, realm: impl Into<String>, name: impl Into<String>) -> Self {
+        self.realm_names.lock().unwrap().insert(realm.into(), name.into());
+        self
+    }
+
+    /// Fill in the DAO names and vote weights of `transactions`
+    pub async fn resolve(&self, transactions: &mut [ParsedTransaction]) -> Result<()> {
+        let events = || {
+            transactions.iter().flat_map(|tx| &tx.events).filter_map(|parsed| match &parsed.event {
+                ProtocolEvent::Governance(event) => Some(event),
+                _ => None,
+            })
+        };
+        let mut wanted: Vec<String> = {
+            let cached = self.realm_names.lock().unwrap();
+            events()
+                .filter_map(GovernanceEvent::realm)
+                .filter(|realm| !cached.contains_key(*realm))
+                .map(str::to_string)
+                .collect()
+        };
+        wanted.extend(events().filter_map(|event| match event {
+            GovernanceEvent::VoteCast {
+                vote_record,
+                voter_weight: None,
+                ..
+            } => Some(vote_record.clone()),
+            _ => None,
+        }));
+        let mut seen = HashSet::new();
+        wanted.retain(|key| seen.insert(key.clone()));
+
+        let mut weights = HashMap::new();
+        if !wanted.is_empty() {
+            let mut keys = Vec::with_capacity(wanted.len());
+            for key in &wanted {
+                keys.push(Pubkey::from_str(key).map_err(|_| Error::validation(format!("Invalid account {}", key)))?);
+            }
+            let accounts = self
+                .client
+                .get_multiple_accounts(&keys)
+                .await
+                .map_err(|e| Error::pipeline(format!("Failed to fetch governance accounts: {}", e)))?;
+            let mut realm_names = self.realm_names.lock().unwrap();
+            for (key, account) in wanted.into_iter().zip(accounts) {
+                let Some(account) = account else { continue };
+                if let Some(weight) = voter_weight(&account.data) {
+                    weights.insert(key, weight);
+                } else if let Some(name) = realm_name(&account.data) {
+                    realm_names.insert(key, name);
+                }
+            }
+        }
+
+        let realm_names = self.realm_names.lock().unwrap();
+        for parsed in transactions.iter_mut().flat_map(|tx| tx.events.iter_mut()) {
+            let ProtocolEvent::Governance(event) = &mut parsed.event else {
+                continue;
+            };
+            if let Some(name) = event.realm().and_then(|realm| realm_names.get(realm)).cloned() {
+                if let Some(dao_name) = event.dao_name_mut() {
+                    *dao_name = Some(name);
+                }
+            }
+            if let GovernanceEvent::VoteCast {
+                vote_record,
+                voter_weight,
+                ..
+            } = event
+            {
+                if voter_weight.is_none() {
+                    *voter_weight = weights.get(vote_record.as_str()).copied();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: AccountClientTrait> Enricher for GovernanceResolver<C> {
+    fn name(&self) -> &str {
+        "governance"
+    }
+
+    async fn enrich(&self, batch: &mut ParsedBatch) -> Result<()> {
+        self.resolve(&mut batch.transactions).await
+    }
+}
+
+/// Vote rows and proposal lifecycle rows for the governance events of a
+/// successful transaction.
+///
+/// Every event, votes included, produces a lifecycle row; casts additionally
+/// produce a `governance_votes` row. Nothing is returned without a block
+/// time, which orders recasts of the same vote record.
+pub fn governance_records(transaction: &ParsedTransaction) -> (Vec<GovernanceVote>, Vec<GovernanceProposalEvent>) {
+    let context = &transaction.context;
+    let mut votes = Vec::new();
+    let mut lifecycle = Vec::new();
+    let Some(timestamp) = context.block_time.and_then(|t| Utc.timestamp_opt(t, 0).single()) else {
+        return (votes, lifecycle);
+    };
+    if !context.success {
+        return (votes, lifecycle);
+    }
+
+    for (index, parsed) in transaction.events.iter().enumerate() {
+        let ProtocolEvent::Governance(event) = &parsed.event else {
+            continue;
+        };
+        let actor = match event {
+            GovernanceEvent::ProposalCreated { authority, .. } => Some(authority.clone()),
+            GovernanceEvent::ProposalSignedOff { signatory, .. } => Some(signatory.clone()),
+            GovernanceEvent::VoteCast { voter, .. } => Some(voter.clone()),
+            _ => context.fee_payer().map(|payer| payer.to_string()),
+        };
+        if let GovernanceEvent::VoteCast {
+            voter,
+            vote,
+            vote_record,
+            choices,
+            voter_weight,
+            ..
+        } = event
+        {
+            let mut record = GovernanceVote::new(
+                voter.clone(),
+                event.proposal().to_string(),
+                vote.clone(),
+                timestamp,
+                event.dao().unwrap_or_default().to_string(),
+            )
+            .with_source(context.signature.clone(), index as i32);
+            record.realm = event.realm().map(str::to_string);
+            record.vote_record = Some(vote_record.clone());
+            record.voter_weight = voter_weight.map(|weight| weight.min(i64::MAX as u64) as i64);
+            record.choices = serde_json::to_value(choices).ok();
+            votes.push(record);
+        }
+        lifecycle.push(GovernanceProposalEvent {
+            id: uuid::Uuid::new_v4(),
+            signature: context.signature.clone(),
+            event_index: index as i32,
+            realm: event.realm().map(str::to_string),
+            dao_name: event.dao().map(str::to_string),
+            proposal: event.proposal().to_string(),
+            event_type: event.kind().to_string(),
+            actor,
+            details: serde_json::to_value(event).ok(),
+            timestamp,
+            created_at: Utc::now(),
+        });
+    }
+    (votes, lifecycle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::parse_fixture;
+    use crate::parsers::ParserRegistry;
+    use crate::rpc::client::MockAccountClientTrait;
+    use solana_sdk::account::Account;
+
+    const VOTER: &str = "4pc5Udk88yQL7QmfLmjnFhyuZto1ztps79KRuQLmnAqg";
+    const REALM: &str = "9Gmd8Dw9xef42QGnJRmUozpqAPgPK8H346Xn8oRy6Enx";
+    const PROPOSAL: &str = "583ESdaR689xUvXUZ5QW3Bvpk3gsX1D2qQdsHxe4sK4S";
+    const VOTE_RECORD: &str = "3ZGE7r5MNzQaR5TJH2SN5Uvtar8rRJ4Lg69cLayUU6Cu";
+    const OLD_VOTE_RECORD: &str = "36gDXrYHMGHtQ7q5aBsvm9vkQLTYo2kfuAawocEVzfiY";
+
+    fn parse(name: &str) -> ParsedTransaction {
+        let mut registry = ParserRegistry::new();
+        registry.register(Arc::new(GovernanceParser::new()));
+        parse_fixture(name, &registry)
+    }
+
+    fn governance_events(transaction: &ParsedTransaction) -> Vec<GovernanceEvent> {
+        transaction
+            .events
+            .iter()
+            .map(|parsed| match &parsed.event {
+                ProtocolEvent::Governance(event) => event.clone(),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect()
+    }
+
+    fn account(data: Vec<u8>) -> Account {
+        Account {
+            lamports: 1_000_000,
+            data,
+            owner: GOVERNANCE_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn realm_v2(name: &str) -> Vec<u8> {
+        let mut data = vec![16];
+        data.extend([7; 32]);
+        data.extend([0; 8]);
+        data.extend(1u64.to_le_bytes());
+        data.push(0);
+        data.extend(10_000_000_000u64.to_le_bytes());
+        data.push(0);
+        data.extend([0; 6]);
+        data.extend(0u16.to_le_bytes());
+        data.push(0);
+        data.extend((name.len() as u32).to_le_bytes());
+        data.extend(name.as_bytes());
+        data
+    }
+
+    fn vote_record_v2(weight: u64) -> Vec<u8> {
+        let mut data = vec![VOTE_RECORD_V2];
+        data.extend([1; 64]);
+        data.push(0);
+        data.extend(weight.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_proposal_created_and_signed_off() {
+        let parsed = parse("governance_proposal");
+        let events = governance_events(&parsed);
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            GovernanceEvent::ProposalCreated {
+                realm,
+                proposal,
+                authority,
+                name,
+                options,
+                multi_choice,
+                use_deny_option,
+                ..
+            } => {
+                assert_eq!(realm, REALM);
+                assert_eq!(proposal, PROPOSAL);
+                assert_eq!(authority, VOTER);
+                assert_eq!(name, "Fund grants");
+                assert_eq!(options, &vec!["Approve".to_string()]);
+                assert!(!multi_choice);
+                assert!(use_deny_option);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(events[1].kind(), "signed_off");
+        assert_eq!(events[1].proposal(), PROPOSAL);
+    }
+
+    #[tokio::test]
+    async fn test_votes_resolved_and_recorded() {
+        let mut transactions = vec![parse("governance_vote")];
+
+        let mut client = MockAccountClientTrait::new();
+        client
+            .expect_get_multiple_accounts()
+            .withf(|keys| {
+                keys.iter().map(|key| key.to_string()).collect::<Vec<_>>() == [REALM.to_string(), VOTE_RECORD.to_string()]
+            })
+            .times(1)
+            .returning(|_| Ok(vec![Some(account(realm_v2("Grants DAO"))), Some(account(vote_record_v2(42_000)))]));
+        let resolver = GovernanceResolver::new(Arc::new(client));
+        resolver.resolve(&mut transactions).await.unwrap();
+
+        let events = governance_events(&transactions[0]);
+        match &events[0] {
+            GovernanceEvent::VoteCast {
+                voter,
+                vote,
+                choices,
+                voter_weight,
+                dao_name,
+                ..
+            } => {
+                assert_eq!(voter, VOTER);
+                assert_eq!(vote, "approve");
+                assert_eq!(choices, &vec![VoteChoice { rank: 0, weight_percentage: 100 }]);
+                assert_eq!(*voter_weight, Some(42_000));
+                assert_eq!(dao_name.as_deref(), Some("Grants DAO"));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(events[1].dao(), Some("Grants DAO"));
+
+        let (votes, lifecycle) = governance_records(&transactions[0]);
+        assert_eq!(votes.len(), 1);
+        assert_eq!(votes[0].proposal_id, PROPOSAL);
+        assert_eq!(votes[0].dao_name, "Grants DAO");
+        assert_eq!(votes[0].vote_record.as_deref(), Some(VOTE_RECORD));
+        assert_eq!(votes[0].voter_weight, Some(42_000));
+        assert_eq!(votes[0].event_index, Some(0));
+        assert_eq!(
+            lifecycle.iter().map(|event| event.event_type.as_str()).collect::<Vec<_>>(),
+            ["vote_cast", "vote_relinquished"]
+        );
+        assert_eq!(lifecycle[1].actor.as_deref(), Some(VOTER));
+        assert_eq!(lifecycle[1].details.as_ref().unwrap()["vote_record"], OLD_VOTE_RECORD);
+
+        // Seeded realm names are not fetched again
+        let mut client = MockAccountClientTrait::new();
+        client
+            .expect_get_multiple_accounts()
+            .withf(|keys| keys.len() == 1 && keys[0].to_string() == VOTE_RECORD)
+            .returning(|_| Ok(vec![None]));
+        let resolver = GovernanceResolver::new(Arc::new(client)).with_realm_name(REALM, "Grants DAO");
+        let mut transactions = vec![parse("governance_vote")];
+        resolver.resolve(&mut transactions).await.unwrap();
+        assert_eq!(governance_events(&transactions[0])[0].dao(), Some("Grants DAO"));
+    }
+}
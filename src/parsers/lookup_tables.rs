@@ -0,0 +1,196 @@
+//! Address lookup table resolution for v0 transactions
+
+use crate::core::error::{Error, Result};
+use crate::parsers::decode::{address_table_lookups, meta_loaded_addresses};
+use crate::rpc::client::AccountClientTrait;
+use solana_sdk::address_lookup_table::state::AddressLookupTable;
+use solana_sdk::message::v0::{LoadedAddresses, MessageAddressTableLookup};
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::EncodedTransactionWithStatusMeta;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+/// Resolves the addresses v0 transactions load from lookup tables by fetching
+/// the tables, for transactions whose status meta does not list them.
+///
+/// Tables are cached by address. Lookup tables are append-only, so a cached
+/// table is only fetched again when a transaction references an index past
+/// its end.
+pub struct LookupTableCache {
+    client: Arc<dyn AccountClientTrait>,
+    tables: RwLock<HashMap<Pubkey, Arc<[Pubkey]>>>,
+}
+
+impl LookupTableCache {
+    pub fn new(client: Arc<dyn AccountClientTrait>) -> Self {
+        Self {
+            client,
+            tables: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Number of cached tables
+    pub fn len(&self) -> usize {
+        self.tables.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Addresses `tx` loads from lookup tables, or `None` when the decoder
+    /// needs no help: legacy transactions and transactions whose status meta
+    /// lists them.
+    pub async fn resolve(&self, tx: &EncodedTransactionWithStatusMeta) -> Result<Option<LoadedAddresses>> {
+        let lookups = address_table_lookups(tx).map_err(|e| Error::validation(e.to_string()))?;
+        let from_meta = meta_loaded_addresses(tx).map_err(|e| Error::validation(e.to_string()))?;
+        if lookups.is_empty() || from_meta.is_some() {
+            return Ok(None);
+        }
+        self.load(&lookups).await.map(Some)
+    }
+
+    /// Addresses selected by `lookups`: writable ones of every table first,
+    /// then readonly ones, as the runtime orders them
+    pub async fn load(&self, lookups: &[MessageAddressTableLookup]) -> Result<LoadedAddresses> {
+        let mut stale: Vec<Pubkey> = {
+            let tables = self.tables.read().unwrap();
+            lookups
+                .iter()
+                .filter(|lookup| {
+                    let needed = lookup.writable_indexes.iter().chain(&lookup.readonly_indexes).max();
+                    match (tables.get(&lookup.account_key), needed) {
+                        (Some(table), Some(&index)) => index as usize >= table.len(),
+                        (Some(_), None) => false,
+                        (None, _) => true,
+                    }
+                })
+                .map(|lookup| lookup.account_key)
+                .collect()
+        };
+        let mut seen = HashSet::new();
+        stale.retain(|key| seen.insert(*key));
+        if !stale.is_empty() {
+            self.fetch(&stale).await?;
+        }
+
+        let tables = self.tables.read().unwrap();
+        let mut loaded = LoadedAddresses::default();
+        for lookup in lookups {
+            loaded.writable.extend(select(&tables, lookup, &lookup.writable_indexes)?);
+        }
+        for lookup in lookups {
+            loaded.readonly.extend(select(&tables, lookup, &lookup.readonly_indexes)?);
+        }
+        Ok(loaded)
+    }
+
+    async fn fetch(&self, keys: &[Pubkey]) -> Result<()> {
+        let accounts = self
+            .client
+            .get_multiple_accounts(keys)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to fetch lookup tables: {}", e)))?;
+        let mut tables = self.tables.write().unwrap();
+        for (key, account) in keys.iter().zip(accounts) {
+            let account = account.ok_or_else(|| Error::validation(format!("Lookup table {} not found", key)))?;
+            let table = AddressLookupTable::deserialize(&account.data)
+                .map_err(|e| Error::validation(format!("Invalid lookup table {}: {}", key, e)))?;
+            tables.insert(*key, Arc::from(table.addresses.as_ref()));
+        }
+        Ok(())
+    }
+}
+
+fn select(
+    tables: &HashMap<Pubkey, Arc<[Pubkey]>>,
+    lookup: &MessageAddressTableLookup,
+    indexes: &[u8],
+) -> Result<Vec<Pubkey>> {
+    let table = &tables[&lookup.account_key];
+    let mut addresses = Vec::with_capacity(indexes.len());
+    for &index in indexes {
+        let address = table.get(index as usize).ok_or_else(|| {
+            Error::validation(format!("Index {} is past the end of lookup table {}", index, lookup.account_key))
+        })?;
+        addresses.push(*address);
+    }
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::fixture;
+    use crate::parsers::{decode_transaction, decode_transaction_with};
+    use crate::rpc::client::MockAccountClientTrait;
+    use solana_sdk::account::Account;
+    use solana_sdk::address_lookup_table::state::LookupTableMeta;
+    use std::borrow::Cow;
+    use std::str::FromStr;
+
+    const TABLE: &str = "CUDp5gcuAm64fAXUdWviywAdmrnTbrMbFPmP5u7tUL8q";
+    const SOURCE: &str = "4sAFqwXMzZFwSDjjHceT9txELSCNEbVd7iw7G6yBJs4s";
+    const DESTINATION: &str = "5tC4Qjk6b9xuP7a9B9FFs3m5Rto4siXsfqGt95zFJhf7";
+    const PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    fn table_account() -> Account {
+        let addresses: Vec<Pubkey> = [DESTINATION, PROGRAM, TABLE, SOURCE]
+            .iter()
+            .map(|key| Pubkey::from_str(key).unwrap())
+            .collect();
+        let table = AddressLookupTable {
+            meta: LookupTableMeta::default(),
+            addresses: Cow::Owned(addresses),
+        };
+        Account {
+            lamports: 1_000_000,
+            data: table.serialize_for_tests().unwrap(),
+            owner: solana_sdk::address_lookup_table::program::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolves_and_caches_lookup_tables() {
+        let fixture = fixture("v0_lookup_transfer");
+        let with_meta = decode_transaction(fixture.slot, fixture.block_time, &fixture.transaction).unwrap();
+        let mut value = serde_json::to_value(&fixture.transaction).unwrap();
+        value["meta"].as_object_mut().unwrap().remove("loadedAddresses");
+        let without_meta: EncodedTransactionWithStatusMeta = serde_json::from_value(value).unwrap();
+
+        let mut client = MockAccountClientTrait::new();
+        client
+            .expect_get_multiple_accounts()
+            .withf(|keys| keys.len() == 1 && keys[0].to_string() == TABLE)
+            .times(1)
+            .returning(|_| Ok(vec![Some(table_account())]));
+        let cache = LookupTableCache::new(Arc::new(client));
+
+        // Transactions listing their loaded addresses need no lookup
+        assert_eq!(cache.resolve(&fixture.transaction).await.unwrap(), None);
+        assert!(cache.is_empty());
+
+        let loaded = cache.resolve(&without_meta).await.unwrap().unwrap();
+        let decoded = decode_transaction_with(fixture.slot, fixture.block_time, &without_meta, Some(&loaded)).unwrap();
+        assert_eq!(decoded, with_meta);
+
+        // Served from the cache
+        assert_eq!(cache.resolve(&without_meta).await.unwrap(), Some(loaded));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_missing_table_is_an_error() {
+        let mut client = MockAccountClientTrait::new();
+        client.expect_get_multiple_accounts().returning(|_| Ok(vec![None]));
+        let cache = LookupTableCache::new(Arc::new(client));
+        let lookup = MessageAddressTableLookup {
+            account_key: Pubkey::from_str(TABLE).unwrap(),
+            writable_indexes: vec![0],
+            readonly_indexes: vec![],
+        };
+        assert!(cache.load(&[lookup]).await.is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Protocol parsers turning decoded instructions into typed events
+//!
+//! [`decode::decode_transaction`] resolves a fetched transaction into
+//! [`DecodedInstruction`]s and a shared [`TransactionContext`]. A
+//! [`ParserRegistry`] then hands every instruction, top-level or inner, to the
+//! [`ProtocolParser`] registered for its program id and collects the
+//! [`ProtocolEvent`]s it returns. The decoder also rebuilds each
+//! transaction's [`CallTree`] of cross-program invocations from its inner
+//! instructions and logs. Programs without a dedicated parser can fall back
+//! to an [`AnchorParser`] decoding them with their IDL.
+
+pub mod anchor;
+pub mod call_tree;
+pub mod decode;
+pub mod governance;
+pub mod lookup_tables;
+pub mod registry;
+pub mod swap;
+pub mod system;
+pub mod token;
+
+#[cfg(test)]
+pub(crate) mod test_utils;
+
+pub use anchor::{AnchorEvent, AnchorParser};
+pub use call_tree::{CallFrame, CallTree, FrameStatus};
+pub use decode::{decode_transaction, decode_transaction_with, DecodedTransaction};
+pub use governance::{governance_records, GovernanceEvent, GovernanceParser, GovernanceResolver};
+pub use lookup_tables::LookupTableCache;
+pub use registry::{ParsedEvent, ParsedTransaction, ParserRegistry};
+pub use swap::{swap_interactions, Dex, SwapEvent, SwapParser};
+pub use system::{fee_debit, SolLedger, SystemEvent, SystemParser};
+pub use token::{TokenEvent, TokenParser};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+/// Errors that can occur while decoding or parsing a transaction
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    #[error("Unsupported transaction encoding: {0}")]
+    UnsupportedEncoding(String),
+    #[error("Invalid account key: {0}")]
+    InvalidAccount(String),
+    #[error("Account index {0} is out of range")]
+    AccountIndex(usize),
+    #[error("Instruction {instruction} is missing account {index}")]
+    MissingAccount { instruction: &'static str, index: usize },
+    #[error("Unknown instruction: {0}")]
+    UnknownInstruction(String),
+    #[error("Invalid instruction data: {0}")]
+    InvalidData(String),
+    #[error("Transaction loads accounts from unresolved lookup tables")]
+    UnresolvedLookupTables,
+}
+
+pub type ParseResult<T> = std::result::Result<T, ParseError>;
+
+/// A token balance from the transaction status meta
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenBalance {
+    /// Index of the token account in [`TransactionContext::account_keys`]
+    pub account_index: usize,
+    pub mint: String,
+    pub owner: Option<String>,
+    /// Token program owning the account
+    pub program_id: Option<String>,
+    /// Raw amount in base units
+    pub amount: u64,
+    pub decimals: u8,
+}
+
+/// Transaction-level data shared by every instruction of a transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransactionContext {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    /// Whether the transaction executed successfully
+    pub success: bool,
+    /// Fee in lamports
+    pub fee: u64,
+    /// Account keys in message order, followed by the addresses loaded from
+    /// lookup tables: writable ones first, then readonly ones
+    pub account_keys: Vec<Pubkey>,
+    /// Number of leading `account_keys` listed in the message itself
+    pub num_static_keys: usize,
+    /// Number of writable addresses loaded from lookup tables
+    pub num_loaded_writable: usize,
+    /// Number of leading `account_keys` that signed the transaction
+    pub num_required_signatures: usize,
+    /// Lamport balances before execution, indexed like `account_keys`
+    pub pre_balances: Vec<u64>,
+    /// Lamport balances after execution, indexed like `account_keys`
+    pub post_balances: Vec<u64>,
+    pub pre_token_balances: Vec<TokenBalance>,
+    pub post_token_balances: Vec<TokenBalance>,
+    pub log_messages: Vec<String>,
+    /// Invocation tree rebuilt from the inner instructions and logs
+    pub call_tree: CallTree,
+}
+
+impl TransactionContext {
+    /// Account paying the transaction fee
+    pub fn fee_payer(&self) -> Option<&Pubkey> {
+        self.account_keys.first()
+    }
+
+    /// Whether `account` signed the transaction
+    pub fn is_signer(&self, account: &Pubkey) -> bool {
+        self.account_keys
+            .iter()
+            .take(self.num_required_signatures)
+            .any(|key| key == account)
+    }
+
+    /// Addresses loaded from lookup tables, writable ones first
+    pub fn loaded_addresses(&self) -> &[Pubkey] {
+        self.account_keys.get(self.num_static_keys..).unwrap_or_default()
+    }
+
+    /// Token balance of `account` before execution
+    pub fn pre_token_balance(&self, account: &Pubkey) -> Option<&TokenBalance> {
+        self.token_balance(&self.pre_token_balances, account)
+    }
+
+    /// Token balance of `account` after execution
+    pub fn post_token_balance(&self, account: &Pubkey) -> Option<&TokenBalance> {
+        self.token_balance(&self.post_token_balances, account)
+    }
+
+    fn token_balance<'a>(&self, balances: &'a [TokenBalance], account: &Pubkey) -> Option<&'a TokenBalance> {
+        let index = self.account_keys.iter().position(|key| key == account)?;
+        balances.iter().find(|balance| balance.account_index == index)
+    }
+}
+
+/// An instruction with its accounts resolved to public keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<Pubkey>,
+    pub data: Vec<u8>,
+    /// Index of the top-level instruction this instruction belongs to
+    pub index: usize,
+    /// Position within the top-level instruction's inner instructions, `None`
+    /// for top-level instructions
+    pub inner_index: Option<usize>,
+    /// Invocation depth, 1 for top-level instructions
+    pub stack_height: u32,
+    /// Instructions invoked by this one through CPI, in execution order
+    pub inner_instructions: Vec<DecodedInstruction>,
+}
+
+impl DecodedInstruction {
+    /// Frame of this instruction in the transaction's call tree, carrying its
+    /// compute units, status and logs
+    pub fn frame<'a>(&self, context: &'a TransactionContext) -> Option<&'a CallFrame> {
+        context.call_tree.frame(self.index, self.inner_index)
+    }
+
+    /// Account at `index`, or [`ParseError::MissingAccount`] naming `instruction`
+    pub fn account(&self, instruction: &'static str, index: usize) -> ParseResult<&Pubkey> {
+        self.accounts
+            .get(index)
+            .ok_or(ParseError::MissingAccount { instruction, index })
+    }
+}
+
+/// A typed event produced by a [`ProtocolParser`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "protocol", rename_all = "snake_case")]
+pub enum ProtocolEvent {
+    /// Native SOL activity of the System program
+    System(SystemEvent),
+    /// SPL Token or Token-2022 activity
+    Token(TokenEvent),
+    /// Token swap on a DEX or aggregator
+    Swap(SwapEvent),
+    /// SPL Governance proposal or vote activity
+    Governance(GovernanceEvent),
+    /// Instruction, event or error of an Anchor program decoded with its IDL
+    Anchor(AnchorEvent),
+    /// Event of a parser registered outside this crate
+    Custom { kind: String, data: serde_json::Value },
+}
+
+/// Trait for protocol-specific parsers
+pub trait ProtocolParser: Send + Sync {
+    /// Name recorded on events, logs and metrics
+    fn name(&self) -> &str;
+
+    /// Programs whose instructions this parser handles
+    fn program_ids(&self) -> Vec<Pubkey>;
+
+    /// Parse one instruction of a program listed by [`ProtocolParser::program_ids`].
+    ///
+    /// Inner instructions are also dispatched on their own, with the
+    /// instructions they invoked in turn as their `inner_instructions`, so a
+    /// parser only needs to look at `instruction.inner_instructions` when the
+    /// instruction's meaning depends on what it invoked. Its node in the
+    /// transaction's [`CallTree`], with the nested invocations below it and
+    /// their compute units, status and logs, is `instruction.frame(context)`.
+    fn parse(&self, instruction: &DecodedInstruction, context: &TransactionContext) -> ParseResult<Vec<ProtocolEvent>>;
+}
@@ -0,0 +1,346 @@
+//! Dispatch of decoded instructions to protocol parsers by program id
+
+use crate::core::metrics::{PARSER_ERRORS_TOTAL, PARSER_EVENTS_TOTAL, PARSER_UNKNOWN_INSTRUCTIONS_TOTAL};
+use crate::parsers::{
+    DecodedInstruction, DecodedTransaction, ProtocolEvent, ProtocolParser, TransactionContext,
+};
+use metrics::counter;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// An event together with the instruction it was parsed from
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedEvent {
+    /// Name of the parser that produced the event
+    pub parser: String,
+    pub program_id: Pubkey,
+    /// Index of the top-level instruction
+    pub instruction_index: usize,
+    /// Position within the top-level instruction's inner instructions, if inner
+    pub inner_index: Option<usize>,
+    pub event: ProtocolEvent,
+}
+
+/// The events parsed from one transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedTransaction {
+    pub context: TransactionContext,
+    pub events: Vec<ParsedEvent>,
+}
+
+/// Routes instructions to the [`ProtocolParser`] registered for their program.
+///
+/// Fallback parsers, such as an [`AnchorParser`](crate::parsers::AnchorParser),
+/// only see the programs no dedicated parser is registered for. Instructions
+/// of programs without any parser are counted per program id, so coverage gaps
+/// show up in [`ParserRegistry::unknown_programs`].
+#[derive(Default)]
+pub struct ParserRegistry {
+    parsers: HashMap<Pubkey, Arc<dyn ProtocolParser>>,
+    fallbacks: HashMap<Pubkey, Arc<dyn ProtocolParser>>,
+    unknown: Mutex<HashMap<Pubkey, u64>>,
+}
+
+impl std::fmt::Debug for ParserRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParserRegistry")
+            .field("programs", &self.parsers.keys().collect::<Vec<_>>())
+            .field("fallback_programs", &self.fallbacks.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl ParserRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `parser` for every program it lists, replacing any parser
+    /// previously registered for the same program
+    pub fn register(&mut self, parser: Arc<dyn ProtocolParser>) {
+        for program_id in parser.program_ids() {
+            if let Some(previous) = self.parsers.insert(program_id, parser.clone()) {
+                tracing::warn!(
+                    "Parser {} replaces {} for program {}",
+                    parser.name(),
+                    previous.name(),
+                    program_id
+                );
+            }
+        }
+    }
+
+    /// Register `parser` for every program it lists, used only for programs
+    /// without a parser registered with [`ParserRegistry::register`]
+    pub fn register_fallback(&mut self, parser: Arc<dyn ProtocolParser>) {
+        for program_id in parser.program_ids() {
+            if let Some(previous) = self.fallbacks.insert(program_id, parser.clone()) {
+                tracing::warn!(
+                    "Fallback parser {} replaces {} for program {}",
+                    parser.name(),
+                    previous.name(),
+                    program_id
+                );
+            }
+        }
+    }
+
+    /// Parser handling `program_id`: the registered one, else the fallback
+    pub fn parser_for(&self, program_id: &Pubkey) -> Option<&Arc<dyn ProtocolParser>> {
+        self.parsers.get(program_id).or_else(|| self.fallbacks.get(program_id))
+    }
+
+    /// Whether no parser has been registered
+    pub fn is_empty(&self) -> bool {
+        self.parsers.is_empty() && self.fallbacks.is_empty()
+    }
+
+    /// Parse a top-level instruction and each of its inner instructions.
+    ///
+    /// An inner instruction is handed to its parser with the instructions it
+    /// invoked attached as `inner_instructions`, found from the stack heights
+    /// of the instructions following it.
+    pub fn parse_instruction(&self, instruction: &DecodedInstruction, context: &TransactionContext) -> Vec<ParsedEvent> {
+        let mut events = self.dispatch(instruction, None, context);
+        let inner_instructions = &instruction.inner_instructions;
+        for (inner_index, inner) in inner_instructions.iter().enumerate() {
+            let invoked: Vec<_> = inner_instructions[inner_index + 1..]
+                .iter()
+                .take_while(|next| next.stack_height > inner.stack_height)
+                .cloned()
+                .collect();
+            if invoked.is_empty() {
+                events.extend(self.dispatch(inner, Some(inner_index), context));
+            } else {
+                let subtree = DecodedInstruction {
+                    inner_instructions: invoked,
+                    ..inner.clone()
+                };
+                events.extend(self.dispatch(&subtree, Some(inner_index), context));
+            }
+        }
+        events
+    }
+
+    /// Parse every instruction of `transaction`
+    pub fn parse_transaction(&self, transaction: &DecodedTransaction) -> ParsedTransaction {
+        let events = transaction
+            .instructions
+            .iter()
+            .flat_map(|instruction| self.parse_instruction(instruction, &transaction.context))
+            .collect();
+        ParsedTransaction {
+            context: transaction.context.clone(),
+            events,
+        }
+    }
+
+    /// Programs seen without a registered parser, most frequent first
+    pub fn unknown_programs(&self) -> Vec<(Pubkey, u64)> {
+        let mut unknown: Vec<_> = self.unknown.lock().unwrap().iter().map(|(k, v)| (*k, *v)).collect();
+        unknown.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        unknown
+    }
+
+    fn dispatch(
+        &self,
+        instruction: &DecodedInstruction,
+        inner_index: Option<usize>,
+        context: &TransactionContext,
+    ) -> Vec<ParsedEvent> {
+        let Some(parser) = self.parser_for(&instruction.program_id) else {
+            counter!(PARSER_UNKNOWN_INSTRUCTIONS_TOTAL, 1);
+            *self.unknown.lock().unwrap().entry(instruction.program_id).or_insert(0) += 1;
+            return Vec::new();
+        };
+
+        match parser.parse(instruction, context) {
+            Ok(events) => {
+                counter!(PARSER_EVENTS_TOTAL, events.len() as u64, "parser" => parser.name().to_string());
+                events
+                    .into_iter()
+                    .map(|event| ParsedEvent {
+                        parser: parser.name().to_string(),
+                        program_id: instruction.program_id,
+                        instruction_index: instruction.index,
+                        inner_index,
+                        event,
+                    })
+                    .collect()
+            }
+            Err(e) => {
+                counter!(PARSER_ERRORS_TOTAL, 1, "parser" => parser.name().to_string());
+                tracing::debug!(
+                    "Parser {} failed on instruction {} of {}: {}",
+                    parser.name(),
+                    instruction.index,
+                    context.signature,
+                    e
+                );
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::{ParseError, ParseResult};
+    use serde_json::json;
+
+    struct EchoParser(Pubkey);
+
+    impl ProtocolParser for EchoParser {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn program_ids(&self) -> Vec<Pubkey> {
+            vec![self.0]
+        }
+
+        fn parse(&self, instruction: &DecodedInstruction, _context: &TransactionContext) -> ParseResult<Vec<ProtocolEvent>> {
+            match instruction.data.first() {
+                Some(0) => Err(ParseError::UnknownInstruction("0".to_string())),
+                _ => Ok(vec![ProtocolEvent::Custom {
+                    kind: "echo".to_string(),
+                    data: json!(instruction.data),
+                }]),
+            }
+        }
+    }
+
+    fn instruction(program_id: Pubkey, data: Vec<u8>, inner: Vec<DecodedInstruction>) -> DecodedInstruction {
+        DecodedInstruction {
+            program_id,
+            accounts: vec![],
+            data,
+            index: 0,
+            inner_index: None,
+            stack_height: if inner.is_empty() { 2 } else { 1 },
+            inner_instructions: inner,
+        }
+    }
+
+    #[test]
+    fn test_dispatch_by_program_id() {
+        let known = Pubkey::new_unique();
+        let unknown = Pubkey::new_unique();
+        let mut registry = ParserRegistry::new();
+        registry.register(Arc::new(EchoParser(known)));
+        assert!(registry.parser_for(&known).is_some());
+
+        let ix = instruction(
+            unknown,
+            vec![],
+            vec![instruction(known, vec![7], vec![]), instruction(known, vec![0], vec![]), instruction(unknown, vec![], vec![])],
+        );
+        let events = registry.parse_instruction(&ix, &TransactionContext::default());
+
+        // The failing inner instruction produces no event
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].parser, "echo");
+        assert_eq!(events[0].inner_index, Some(0));
+        assert_eq!(events[0].event, ProtocolEvent::Custom { kind: "echo".to_string(), data: json!([7]) });
+        assert_eq!(registry.unknown_programs(), vec![(unknown, 2)]);
+    }
+
+    #[test]
+    fn test_inner_instruction_receives_subtree() {
+        struct DepthParser(Pubkey);
+
+        impl ProtocolParser for DepthParser {
+            fn name(&self) -> &str {
+                "depth"
+            }
+
+            fn program_ids(&self) -> Vec<Pubkey> {
+                vec![self.0]
+            }
+
+            fn parse(&self, instruction: &DecodedInstruction, _context: &TransactionContext) -> ParseResult<Vec<ProtocolEvent>> {
+                Ok(vec![ProtocolEvent::Custom {
+                    kind: "depth".to_string(),
+                    data: json!(instruction.inner_instructions.len()),
+                }])
+            }
+        }
+
+        let router = Pubkey::new_unique();
+        let dex = Pubkey::new_unique();
+        let at = |program_id, stack_height| DecodedInstruction {
+            stack_height,
+            ..instruction(program_id, vec![], vec![])
+        };
+        let ix = instruction(router, vec![], vec![at(dex, 2), at(router, 3), at(router, 3), at(dex, 2), at(router, 3)]);
+        let mut registry = ParserRegistry::new();
+        registry.register(Arc::new(DepthParser(dex)));
+
+        let invoked: Vec<_> = registry
+            .parse_instruction(&ix, &TransactionContext::default())
+            .into_iter()
+            .map(|event| (event.inner_index, event.event))
+            .collect();
+        assert_eq!(
+            invoked,
+            vec![
+                (Some(0), ProtocolEvent::Custom { kind: "depth".to_string(), data: json!(2) }),
+                (Some(3), ProtocolEvent::Custom { kind: "depth".to_string(), data: json!(1) }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_parser_replaces_existing() {
+        let program = Pubkey::new_unique();
+        let mut registry = ParserRegistry::new();
+        registry.register(Arc::new(EchoParser(program)));
+        registry.register(Arc::new(EchoParser(program)));
+        assert_eq!(registry.parsers.len(), 1);
+
+        let transaction = DecodedTransaction {
+            context: TransactionContext {
+                signature: "sig".to_string(),
+                ..Default::default()
+            },
+            instructions: vec![instruction(program, vec![1], vec![])],
+        };
+        let parsed = registry.parse_transaction(&transaction);
+        assert_eq!(parsed.context.signature, "sig");
+        assert_eq!(parsed.events.len(), 1);
+    }
+
+    #[test]
+    fn test_fallback_only_for_programs_without_parser() {
+        struct FallbackParser(Vec<Pubkey>);
+
+        impl ProtocolParser for FallbackParser {
+            fn name(&self) -> &str {
+                "fallback"
+            }
+
+            fn program_ids(&self) -> Vec<Pubkey> {
+                self.0.clone()
+            }
+
+            fn parse(&self, _instruction: &DecodedInstruction, _context: &TransactionContext) -> ParseResult<Vec<ProtocolEvent>> {
+                Ok(vec![ProtocolEvent::Custom { kind: "fallback".to_string(), data: json!(null) }])
+            }
+        }
+
+        let dedicated = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let mut registry = ParserRegistry::new();
+        registry.register_fallback(Arc::new(FallbackParser(vec![dedicated, other])));
+        registry.register(Arc::new(EchoParser(dedicated)));
+
+        assert_eq!(registry.parser_for(&dedicated).unwrap().name(), "echo");
+        assert_eq!(registry.parser_for(&other).unwrap().name(), "fallback");
+        let events = registry.parse_instruction(&instruction(other, vec![1], vec![]), &TransactionContext::default());
+        assert_eq!(events[0].parser, "fallback");
+        assert!(registry.unknown_programs().is_empty());
+    }
+}
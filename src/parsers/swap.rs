@@ -0,0 +1,507 @@
+//! DEX swap parsers for aggregators and AMMs
+
+use crate::models::{ProtocolInteraction, TokenAmount};
+use crate::parsers::anchor;
+use crate::parsers::token::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::parsers::{
+    DecodedInstruction, ParseResult, ParsedTransaction, ProtocolEvent, ProtocolParser, TokenEvent, TokenParser,
+    TransactionContext,
+};
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{pubkey, system_program};
+
+/// Programs that move funds on behalf of a swap without being a venue
+const PLUMBING_PROGRAMS: [Pubkey; 6] = [
+    TOKEN_PROGRAM_ID,
+    TOKEN_2022_PROGRAM_ID,
+    system_program::ID,
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"),
+    pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"),
+    pubkey!("Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo"),
+];
+
+/// Swap venues with a built-in parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dex {
+    /// Jupiter v6 aggregator
+    Jupiter,
+    /// Raydium AMM v4
+    RaydiumAmm,
+    /// Raydium concentrated liquidity
+    RaydiumClmm,
+    OrcaWhirlpool,
+    MeteoraDlmm,
+    /// Meteora dynamic AMM pools
+    MeteoraAmm,
+}
+
+impl Dex {
+    pub const ALL: [Dex; 6] = [
+        Dex::Jupiter,
+        Dex::RaydiumAmm,
+        Dex::RaydiumClmm,
+        Dex::OrcaWhirlpool,
+        Dex::MeteoraDlmm,
+        Dex::MeteoraAmm,
+    ];
+
+    /// Protocol name recorded on swaps and protocol interactions
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dex::Jupiter => "jupiter",
+            Dex::RaydiumAmm => "raydium_amm",
+            Dex::RaydiumClmm => "raydium_clmm",
+            Dex::OrcaWhirlpool => "orca_whirlpool",
+            Dex::MeteoraDlmm => "meteora_dlmm",
+            Dex::MeteoraAmm => "meteora_amm",
+        }
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        match self {
+            Dex::Jupiter => pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4"),
+            Dex::RaydiumAmm => pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8"),
+            Dex::RaydiumClmm => pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK"),
+            Dex::OrcaWhirlpool => pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc"),
+            Dex::MeteoraDlmm => pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9ot4Li6Uo"),
+            Dex::MeteoraAmm => pubkey!("Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB"),
+        }
+    }
+
+    pub fn from_program_id(program_id: &Pubkey) -> Option<Self> {
+        Self::ALL.into_iter().find(|dex| dex.program_id() == *program_id)
+    }
+
+    /// Swap instructions as (discriminator, pool account index)
+    fn swap_instructions(&self) -> Vec<(Vec<u8>, Option<usize>)> {
+        let anchor = |names: &[&str], pool: Option<usize>| -> Vec<(Vec<u8>, Option<usize>)> {
+            names.iter().map(|name| (anchor_discriminator(name).to_vec(), pool)).collect()
+        };
+        match self {
+            Dex::Jupiter => anchor(
+                &[
+                    "route",
+                    "route_with_token_ledger",
+                    "exact_out_route",
+                    "shared_accounts_route",
+                    "shared_accounts_route_with_token_ledger",
+                    "shared_accounts_exact_out_route",
+                ],
+                None,
+            ),
+            // SwapBaseIn and SwapBaseOut, with the AMM id as second account
+            Dex::RaydiumAmm => vec![(vec![9], Some(1)), (vec![11], Some(1))],
+            Dex::RaydiumClmm => [anchor(&["swap", "swap_v2"], Some(2)), anchor(&["swap_router_base_in"], None)].concat(),
+            Dex::OrcaWhirlpool => [
+                anchor(&["swap"], Some(2)),
+                anchor(&["swap_v2"], Some(4)),
+                anchor(&["two_hop_swap", "two_hop_swap_v2"], None),
+            ]
+            .concat(),
+            Dex::MeteoraDlmm => anchor(
+                &["swap", "swap2", "swap_exact_out", "swap_exact_out2", "swap_with_price_impact", "swap_with_price_impact2"],
+                Some(0),
+            ),
+            Dex::MeteoraAmm => anchor(&["swap"], Some(0)),
+        }
+    }
+}
+
+/// Discriminator of the Anchor instruction `name`
+fn anchor_discriminator(name: &str) -> [u8; 8] {
+    anchor::discriminator("global", name)
+}
+
+/// A swap normalized across venues.
+///
+/// Amounts are raw base units and are the user's net token flows within the
+/// swap instruction, so intermediate hops of a routed swap cancel out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapEvent {
+    /// Protocol name of the swap instruction's program
+    pub dex: String,
+    /// Signer whose tokens were swapped
+    pub user: String,
+    /// Pool the swap executed against, unknown for routed swaps
+    pub pool: Option<String>,
+    pub input_mint: String,
+    pub input_amount: u64,
+    pub input_decimals: Option<u8>,
+    pub output_mint: String,
+    pub output_amount: u64,
+    pub output_decimals: Option<u8>,
+    /// Venues the swap went through in execution order, by protocol name or
+    /// program id for venues without a parser
+    pub route: Vec<String>,
+    /// Whether another swap invoked this one, making it a venue leg of a
+    /// route that is already counted by the outer swap
+    #[serde(default)]
+    pub route_leg: bool,
+}
+
+/// Parser for the swap instructions of one [`Dex`].
+///
+/// Swaps are derived from the token transfers invoked by the swap
+/// instruction, so a swap routed by Jupiter produces a `jupiter` event for the
+/// whole route and one event per venue it went through, flagged as
+/// [`SwapEvent::route_leg`]. Other instructions of the program (liquidity,
+/// admin) produce no event.
+#[derive(Debug, Clone)]
+pub struct SwapParser {
+    dex: Dex,
+    instructions: Vec<(Vec<u8>, Option<usize>)>,
+}
+
+impl SwapParser {
+    pub fn new(dex: Dex) -> Self {
+        Self {
+            dex,
+            instructions: dex.swap_instructions(),
+        }
+    }
+
+    /// One parser per supported [`Dex`]
+    pub fn all() -> Vec<Self> {
+        Dex::ALL.into_iter().map(Self::new).collect()
+    }
+
+    pub fn dex(&self) -> Dex {
+        self.dex
+    }
+}
+
+impl ProtocolParser for SwapParser {
+    fn name(&self) -> &str {
+        self.dex.name()
+    }
+
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![self.dex.program_id()]
+    }
+
+    fn parse(&self, ix: &DecodedInstruction, context: &TransactionContext) -> ParseResult<Vec<ProtocolEvent>> {
+        let Some((_, pool_index)) = self
+            .instructions
+            .iter()
+            .find(|(discriminator, _)| ix.data.starts_with(discriminator))
+        else {
+            return Ok(Vec::new());
+        };
+
+        // A swap that failed before moving tokens, or whose flows do not
+        // single out one input and one output mint, has nothing to report
+        let Some(flows) = UserFlows::derive(ix, context) else {
+            return Ok(Vec::new());
+        };
+        let pool = match pool_index {
+            Some(index) => Some(ix.account("Swap", *index)?.to_string()),
+            None => None,
+        };
+
+        let mut route: Vec<String> = Vec::new();
+        for inner in &ix.inner_instructions {
+            if inner.program_id == ix.program_id || PLUMBING_PROGRAMS.contains(&inner.program_id) {
+                continue;
+            }
+            let venue = match Dex::from_program_id(&inner.program_id) {
+                Some(dex) => dex.name().to_string(),
+                None => inner.program_id.to_string(),
+            };
+            if route.last() != Some(&venue) {
+                route.push(venue);
+            }
+        }
+        if route.is_empty() {
+            route.push(self.dex.name().to_string());
+        }
+        let call_stack = context.call_tree.call_stack(ix.index, ix.inner_index);
+        let route_leg = call_stack
+            .iter()
+            .rev()
+            .skip(1)
+            .any(|frame| Dex::from_program_id(&frame.program_id).is_some());
+
+        Ok(vec![ProtocolEvent::Swap(SwapEvent {
+            dex: self.dex.name().to_string(),
+            user: flows.user,
+            pool,
+            input_mint: flows.input.0,
+            input_amount: flows.input.1,
+            input_decimals: flows.input.2,
+            output_mint: flows.output.0,
+            output_amount: flows.output.1,
+            output_decimals: flows.output.2,
+            route,
+            route_leg,
+        })])
+    }
+}
+
+/// Net token flows of the swapping user, as (mint, amount, decimals)
+struct UserFlows {
+    user: String,
+    input: (String, u64, Option<u8>),
+    output: (String, u64, Option<u8>),
+}
+
+impl UserFlows {
+    /// The user is the first transaction signer authorizing a transfer; the
+    /// input is the one mint they sent on net and the output the one mint
+    /// their accounts received on net. Amounts of different mints are not
+    /// comparable, so flows with several such mints are rejected.
+    fn derive(ix: &DecodedInstruction, context: &TransactionContext) -> Option<Self> {
+        let transfers: Vec<_> = ix
+            .inner_instructions
+            .iter()
+            .filter(|inner| inner.program_id == TOKEN_PROGRAM_ID || inner.program_id == TOKEN_2022_PROGRAM_ID)
+            .filter_map(|inner| TokenParser.parse(inner, context).ok())
+            .flatten()
+            .filter_map(|event| match event {
+                ProtocolEvent::Token(TokenEvent::Transfer {
+                    destination,
+                    authority,
+                    mint: Some(mint),
+                    amount,
+                    decimals,
+                    ..
+                }) => Some((destination, authority, mint, amount, decimals)),
+                _ => None,
+            })
+            .collect();
+
+        let signers: Vec<String> = context
+            .account_keys
+            .iter()
+            .take(context.num_required_signatures)
+            .map(|key| key.to_string())
+            .collect();
+        let user = transfers
+            .iter()
+            .map(|(_, authority, ..)| authority)
+            .find(|authority| signers.contains(authority))?
+            .clone();
+
+        let mut net: Vec<(String, i128, Option<u8>)> = Vec::new();
+        let mut add = |mint: &str, amount: i128, decimals: Option<u8>| match net.iter_mut().find(|(m, ..)| m == mint) {
+            Some(entry) => entry.1 += amount,
+            None => net.push((mint.to_string(), amount, decimals)),
+        };
+        for (destination, authority, mint, amount, decimals) in &transfers {
+            if *authority == user {
+                add(mint, -(*amount as i128), *decimals);
+            }
+            if owner_of(context, destination).as_deref() == Some(user.as_str()) {
+                add(mint, *amount as i128, *decimals);
+            }
+        }
+
+        Self::from_net(user, &net)
+    }
+
+    /// Flows from the user's net amount per mint, with intermediate mints of
+    /// a route netting to zero
+    fn from_net(user: String, net: &[(String, i128, Option<u8>)]) -> Option<Self> {
+        let inputs: Vec<_> = net.iter().filter(|(_, amount, _)| *amount < 0).collect();
+        let outputs: Vec<_> = net.iter().filter(|(_, amount, _)| *amount > 0).collect();
+        let ([input], [output]) = (inputs.as_slice(), outputs.as_slice()) else {
+            if inputs.len() > 1 || outputs.len() > 1 {
+                tracing::debug!("Ambiguous swap flows of {}: {:?}", user, net);
+            }
+            return None;
+        };
+        Some(Self {
+            input: (input.0.clone(), input.1.unsigned_abs() as u64, input.2),
+            output: (output.0.clone(), output.1 as u64, output.2),
+            user,
+        })
+    }
+}
+
+/// Owner of the token account `account`, from the status meta
+fn owner_of(context: &TransactionContext, account: &str) -> Option<String> {
+    let index = context.account_keys.iter().position(|key| key.to_string() == account)?;
+    context
+        .pre_token_balances
+        .iter()
+        .chain(&context.post_token_balances)
+        .find(|balance| balance.account_index == index)
+        .and_then(|balance| balance.owner.clone())
+}
+
+/// Protocol interaction rows for the swaps of a successful transaction.
+///
+/// `amount` is the input amount, with zero decimals when the input decimals
+/// are unknown. Venue legs of a route get `interaction_type = 'route_leg'`, so
+/// each route is counted once among the `swap` rows. Nothing is returned
+/// without a block time, since the rows are ordered and priced by it.
+pub fn swap_interactions(transaction: &ParsedTransaction) -> Vec<ProtocolInteraction> {
+    let context = &transaction.context;
+    let Some(timestamp) = context.block_time.and_then(|t| Utc.timestamp_opt(t, 0).single()) else {
+        return Vec::new();
+    };
+    if !context.success {
+        return Vec::new();
+    }
+
+    transaction
+        .events
+        .iter()
+        .enumerate()
+        .filter_map(|(index, parsed)| match &parsed.event {
+            ProtocolEvent::Swap(swap) => {
+                let amount = TokenAmount::new(swap.input_amount, swap.input_decimals.unwrap_or(0));
+                let interaction = ProtocolInteraction::new(
+                    swap.user.clone(),
+                    swap.dex.clone(),
+                    if swap.route_leg { "route_leg" } else { "swap" }.to_string(),
+                    amount,
+                    timestamp,
+                )
+                .with_source(context.signature.clone(), index as i32)
+                .with_details(serde_json::to_value(swap).ok()?);
+                Some(interaction)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::parse_fixture;
+    use crate::parsers::ParserRegistry;
+    use std::sync::Arc;
+
+    const USER: &str = "FTD2AUYnbYjZrmhga76FsjJS2d5zfFxdWwbfEjJLtHar";
+    const USDC: &str = "9hGdMK6RVJEcUVVBzBeJFQsS2mv7WMQ2zFd2JmpwLPFE";
+    const WSOL: &str = "So11111111111111111111111111111111111111112";
+    const BONK: &str = "B7ScJ3BvgitUPFxK8TiauFbSh6mMh4iKQKGgTMWdPg4T";
+
+    fn parse_route() -> ParsedTransaction {
+        let mut registry = ParserRegistry::new();
+        for parser in SwapParser::all() {
+            registry.register(Arc::new(parser));
+        }
+        parse_fixture("jupiter_route", &registry)
+    }
+
+    /// A swap of the route fixture, where only the aggregator swap has no pool
+    fn swap(dex: Dex, pool: Option<&str>, input: (&str, u64, u8), output: (&str, u64, u8), route: &[&str]) -> SwapEvent {
+        SwapEvent {
+            dex: dex.name().to_string(),
+            user: USER.to_string(),
+            pool: pool.map(str::to_string),
+            input_mint: input.0.to_string(),
+            input_amount: input.1,
+            input_decimals: Some(input.2),
+            output_mint: output.0.to_string(),
+            output_amount: output.1,
+            output_decimals: Some(output.2),
+            route: route.iter().map(|venue| venue.to_string()).collect(),
+            route_leg: pool.is_some(),
+        }
+    }
+
+    #[test]
+    fn test_routed_swap_and_venue_swaps() {
+        let parsed = parse_route();
+        let swaps: Vec<_> = parsed
+            .events
+            .iter()
+            .map(|parsed| match &parsed.event {
+                ProtocolEvent::Swap(swap) => (parsed.inner_index, swap.clone()),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+
+        // The Raydium deposit at the top level is not a swap
+        assert_eq!(
+            swaps,
+            vec![
+                (
+                    None,
+                    swap(
+                        Dex::Jupiter,
+                        None,
+                        (USDC, 100_000_000, 6),
+                        (BONK, 4_200_000_000_000, 5),
+                        &["raydium_amm", "orca_whirlpool"],
+                    )
+                ),
+                (
+                    Some(0),
+                    swap(
+                        Dex::RaydiumAmm,
+                        Some("2HtFujt2kh47uSSedfbbvLr42L1ygEnEdntd7eDsCKtK"),
+                        (USDC, 100_000_000, 6),
+                        (WSOL, 500_000_000, 9),
+                        &["raydium_amm"],
+                    )
+                ),
+                (
+                    Some(3),
+                    swap(
+                        Dex::OrcaWhirlpool,
+                        Some("FNQMAtznNL1cWMkqKURcyn6onwW1khMNs7CUH4q5FioG"),
+                        (WSOL, 500_000_000, 9),
+                        (BONK, 4_200_000_000_000, 5),
+                        &["orca_whirlpool"],
+                    )
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_swap_interactions() {
+        let mut parsed = parse_route();
+        let interactions = swap_interactions(&parsed);
+        assert_eq!(interactions.len(), 3);
+
+        let jupiter = &interactions[0];
+        assert_eq!(jupiter.wallet, USER);
+        assert_eq!(jupiter.protocol, "jupiter");
+        assert_eq!(jupiter.interaction_type, "swap");
+        assert_eq!(jupiter.amount.to_string(), "100");
+        assert_eq!(jupiter.timestamp.timestamp(), 1_700_000_000);
+        assert_eq!(jupiter.signature.as_deref(), Some(parsed.context.signature.as_str()));
+        assert_eq!(jupiter.event_index, Some(0));
+        assert_eq!(jupiter.details.as_ref().unwrap()["output_mint"], BONK);
+        assert_eq!(interactions[1].interaction_type, "route_leg");
+        assert_eq!(interactions[2].protocol, "orca_whirlpool");
+        assert_eq!(interactions[2].interaction_type, "route_leg");
+        assert_eq!(interactions[2].amount.to_string(), "0.5");
+
+        parsed.context.success = false;
+        assert!(swap_interactions(&parsed).is_empty());
+    }
+
+    #[test]
+    fn test_flows_per_mint() {
+        let flow = |mint: &str, amount: i128, decimals: u8| (mint.to_string(), amount, Some(decimals));
+
+        // The intermediate mint of a route nets to zero
+        let flows = UserFlows::from_net(
+            USER.to_string(),
+            &[flow(USDC, -100_000_000, 6), flow(WSOL, 0, 9), flow(BONK, 4_200_000_000_000, 5)],
+        )
+        .unwrap();
+        assert_eq!(flows.input, (USDC.to_string(), 100_000_000, Some(6)));
+        assert_eq!(flows.output, (BONK.to_string(), 4_200_000_000_000, Some(5)));
+
+        // Raw amounts of different mints are not compared to pick one
+        let two_outputs = [flow(USDC, -100_000_000, 6), flow(WSOL, 1_000_000, 9), flow(BONK, 4_200_000_000_000, 5)];
+        assert!(UserFlows::from_net(USER.to_string(), &two_outputs).is_none());
+        let two_inputs = [flow(USDC, -100_000_000, 6), flow(WSOL, -1_000_000, 9), flow(BONK, 4_200_000_000_000, 5)];
+        assert!(UserFlows::from_net(USER.to_string(), &two_inputs).is_none());
+    }
+
+    #[test]
+    fn test_anchor_discriminator() {
+        // sha256("global:swap")[..8], as listed in the Whirlpool IDL
+        assert_eq!(anchor_discriminator("swap"), [248, 198, 158, 145, 225, 117, 135, 200]);
+    }
+}
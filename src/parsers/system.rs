@@ -0,0 +1,406 @@
+//! System program parser and native SOL ledger
+
+use crate::parsers::{
+    DecodedInstruction, ParseError, ParseResult, ParsedTransaction, ProtocolEvent, ProtocolParser,
+    TransactionContext,
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::program_utils::limited_deserialize;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction::SystemInstruction;
+use solana_sdk::system_program;
+use std::collections::BTreeMap;
+
+/// Native SOL activity decoded from a System program instruction.
+///
+/// Addresses are base58 strings and amounts are in lamports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SystemEvent {
+    SolTransfer {
+        from: String,
+        to: String,
+        lamports: u64,
+        /// Seed `from` was derived with, for `TransferWithSeed`
+        seed: Option<String>,
+    },
+    AccountCreated {
+        funder: String,
+        account: String,
+        lamports: u64,
+        space: u64,
+        /// Program assigned as owner of the new account
+        owner: String,
+        seed: Option<String>,
+    },
+    Assign {
+        account: String,
+        owner: String,
+    },
+    Allocate {
+        account: String,
+        space: u64,
+    },
+    /// Durable nonce account operation
+    Nonce {
+        operation: NonceOperation,
+        nonce_account: String,
+        /// Authority signing the operation, or the new authority for
+        /// `initialize` and `authorize`
+        authority: Option<String>,
+    },
+    /// Transaction fee charged to the fee payer, derived from the status meta
+    FeeDebit {
+        payer: String,
+        lamports: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NonceOperation {
+    Initialize,
+    Advance,
+    Withdraw,
+    Authorize,
+    Upgrade,
+}
+
+/// Parser for the System program.
+///
+/// Nonce withdrawals produce both a [`SystemEvent::Nonce`] and the
+/// [`SystemEvent::SolTransfer`] they imply, so a [`SolLedger`] only has to
+/// look at transfers and account creations.
+#[derive(Debug, Default, Clone)]
+pub struct SystemParser;
+
+impl SystemParser {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl ProtocolParser for SystemParser {
+    fn name(&self) -> &str {
+        "system"
+    }
+
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![system_program::id()]
+    }
+
+    fn parse(&self, ix: &DecodedInstruction, _context: &TransactionContext) -> ParseResult<Vec<ProtocolEvent>> {
+        let instruction: SystemInstruction = limited_deserialize(&ix.data)
+            .map_err(|e| ParseError::UnknownInstruction(format!("system instruction: {}", e)))?;
+        let account = |name: &'static str, index: usize| ix.account(name, index).map(|key| key.to_string());
+
+        let events = match instruction {
+            SystemInstruction::Transfer { lamports } => vec![SystemEvent::SolTransfer {
+                from: account("Transfer", 0)?,
+                to: account("Transfer", 1)?,
+                lamports,
+                seed: None,
+            }],
+            SystemInstruction::TransferWithSeed { lamports, from_seed, .. } => vec![SystemEvent::SolTransfer {
+                from: account("TransferWithSeed", 0)?,
+                to: account("TransferWithSeed", 2)?,
+                lamports,
+                seed: Some(from_seed),
+            }],
+            SystemInstruction::CreateAccount { lamports, space, owner } => vec![SystemEvent::AccountCreated {
+                funder: account("CreateAccount", 0)?,
+                account: account("CreateAccount", 1)?,
+                lamports,
+                space,
+                owner: owner.to_string(),
+                seed: None,
+            }],
+            SystemInstruction::CreateAccountWithSeed {
+                seed,
+                lamports,
+                space,
+                owner,
+                ..
+            } => vec![SystemEvent::AccountCreated {
+                funder: account("CreateAccountWithSeed", 0)?,
+                account: account("CreateAccountWithSeed", 1)?,
+                lamports,
+                space,
+                owner: owner.to_string(),
+                seed: Some(seed),
+            }],
+            SystemInstruction::Assign { owner } | SystemInstruction::AssignWithSeed { owner, .. } => {
+                vec![SystemEvent::Assign {
+                    account: account("Assign", 0)?,
+                    owner: owner.to_string(),
+                }]
+            }
+            SystemInstruction::Allocate { space } | SystemInstruction::AllocateWithSeed { space, .. } => {
+                vec![SystemEvent::Allocate {
+                    account: account("Allocate", 0)?,
+                    space,
+                }]
+            }
+            SystemInstruction::InitializeNonceAccount(authority) => vec![SystemEvent::Nonce {
+                operation: NonceOperation::Initialize,
+                nonce_account: account("InitializeNonceAccount", 0)?,
+                authority: Some(authority.to_string()),
+            }],
+            SystemInstruction::AdvanceNonceAccount => vec![SystemEvent::Nonce {
+                operation: NonceOperation::Advance,
+                nonce_account: account("AdvanceNonceAccount", 0)?,
+                authority: Some(account("AdvanceNonceAccount", 2)?),
+            }],
+            SystemInstruction::WithdrawNonceAccount(lamports) => {
+                let nonce_account = account("WithdrawNonceAccount", 0)?;
+                vec![
+                    SystemEvent::Nonce {
+                        operation: NonceOperation::Withdraw,
+                        nonce_account: nonce_account.clone(),
+                        authority: Some(account("WithdrawNonceAccount", 4)?),
+                    },
+                    SystemEvent::SolTransfer {
+                        from: nonce_account,
+                        to: account("WithdrawNonceAccount", 1)?,
+                        lamports,
+                        seed: None,
+                    },
+                ]
+            }
+            SystemInstruction::AuthorizeNonceAccount(authority) => vec![SystemEvent::Nonce {
+                operation: NonceOperation::Authorize,
+                nonce_account: account("AuthorizeNonceAccount", 0)?,
+                authority: Some(authority.to_string()),
+            }],
+            SystemInstruction::UpgradeNonceAccount => vec![SystemEvent::Nonce {
+                operation: NonceOperation::Upgrade,
+                nonce_account: account("UpgradeNonceAccount", 0)?,
+                authority: None,
+            }],
+        };
+        Ok(events.into_iter().map(ProtocolEvent::System).collect())
+    }
+}
+
+/// Fee charged to the fee payer of `context`, if any.
+///
+/// Fees are charged whether or not the transaction succeeded.
+pub fn fee_debit(context: &TransactionContext) -> Option<SystemEvent> {
+    let payer = context.fee_payer()?;
+    (context.fee > 0).then(|| SystemEvent::FeeDebit {
+        payer: payer.to_string(),
+        lamports: context.fee,
+    })
+}
+
+/// An account whose derived SOL change does not match its on-chain balances
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerDiscrepancy {
+    pub account: String,
+    /// `post_balance - pre_balance` from the status meta
+    pub on_chain: i128,
+    /// Net change derived from parsed events
+    pub derived: i128,
+}
+
+/// Per-account native SOL changes of one transaction.
+///
+/// Built from the fee debit and the [`SystemEvent`]s of a parsed transaction.
+/// Events of failed transactions are ignored since only the fee is charged.
+/// Lamports moved by other programs (rent refunds on token account close,
+/// program-owned account debits) are not covered and show up as
+/// discrepancies.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SolLedger {
+    changes: BTreeMap<String, i128>,
+}
+
+impl SolLedger {
+    /// Derive the ledger of `transaction`
+    pub fn from_transaction(transaction: &ParsedTransaction) -> Self {
+        let mut ledger = Self::default();
+        if let Some(fee) = fee_debit(&transaction.context) {
+            ledger.apply(&fee);
+        }
+        if transaction.context.success {
+            for parsed in &transaction.events {
+                if let ProtocolEvent::System(event) = &parsed.event {
+                    ledger.apply(event);
+                }
+            }
+        }
+        ledger
+    }
+
+    /// Record the lamport movement of `event`
+    pub fn apply(&mut self, event: &SystemEvent) {
+        match event {
+            SystemEvent::SolTransfer { from, to, lamports, .. } => self.transfer(from, to, *lamports),
+            SystemEvent::AccountCreated {
+                funder,
+                account,
+                lamports,
+                ..
+            } => self.transfer(funder, account, *lamports),
+            SystemEvent::FeeDebit { payer, lamports } => self.add(payer, -(*lamports as i128)),
+            SystemEvent::Assign { .. } | SystemEvent::Allocate { .. } | SystemEvent::Nonce { .. } => {}
+        }
+    }
+
+    /// Net change of `account` in lamports
+    pub fn change(&self, account: &str) -> i128 {
+        self.changes.get(account).copied().unwrap_or(0)
+    }
+
+    /// Non-zero net changes by account
+    pub fn changes(&self) -> impl Iterator<Item = (&str, i128)> {
+        self.changes
+            .iter()
+            .filter(|(_, change)| **change != 0)
+            .map(|(account, change)| (account.as_str(), *change))
+    }
+
+    /// Accounts of `context` whose derived change differs from the status meta
+    pub fn discrepancies(&self, context: &TransactionContext) -> Vec<LedgerDiscrepancy> {
+        context
+            .account_keys
+            .iter()
+            .enumerate()
+            .filter_map(|(index, key)| {
+                let pre = *context.pre_balances.get(index)? as i128;
+                let post = *context.post_balances.get(index)? as i128;
+                let account = key.to_string();
+                let derived = self.change(&account);
+                (post - pre != derived).then_some(LedgerDiscrepancy {
+                    account,
+                    on_chain: post - pre,
+                    derived,
+                })
+            })
+            .collect()
+    }
+
+    fn transfer(&mut self, from: &str, to: &str, lamports: u64) {
+        self.add(from, -(lamports as i128));
+        self.add(to, lamports as i128);
+    }
+
+    fn add(&mut self, account: &str, lamports: i128) {
+        *self.changes.entry(account.to_string()).or_insert(0) += lamports;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::parse_fixture;
+    use crate::parsers::ParserRegistry;
+    use std::sync::Arc;
+
+    const PAYER: &str = "AWxggjuZRmWULwxwPeM6ZZxRtdDdekVq22mFRx2QbW7U";
+    const ALICE: &str = "9DF55pt5xPXmDGYADqvjQuF6e39getW2X3vBHMoohx5q";
+
+    fn parse(name: &str) -> ParsedTransaction {
+        let mut registry = ParserRegistry::new();
+        registry.register(Arc::new(SystemParser::new()));
+        parse_fixture(name, &registry)
+    }
+
+    #[test]
+    fn test_system_instructions() {
+        let parsed = parse("system_transfers");
+        let events: Vec<_> = parsed
+            .events
+            .iter()
+            .map(|parsed| match &parsed.event {
+                ProtocolEvent::System(event) => event.clone(),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                SystemEvent::Nonce {
+                    operation: NonceOperation::Advance,
+                    nonce_account: "7EV9tBGXuMhZ1KgRHy7ZyFVQWCkz3QuFtUhLNT87h99v".to_string(),
+                    authority: Some(PAYER.to_string()),
+                },
+                SystemEvent::SolTransfer {
+                    from: PAYER.to_string(),
+                    to: ALICE.to_string(),
+                    lamports: 1_000_000_000,
+                    seed: None,
+                },
+                SystemEvent::AccountCreated {
+                    funder: PAYER.to_string(),
+                    account: "FADVGe79mEz8ePHWM9cr6NNM74FATuYe1RABNJv7frzx".to_string(),
+                    lamports: 2_039_280,
+                    space: 165,
+                    owner: "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA".to_string(),
+                    seed: None,
+                },
+                SystemEvent::SolTransfer {
+                    from: "9mTTZ53om78EgZ9cUuf7iL8C5JMFW5DLqJbYgnDMxoyx".to_string(),
+                    to: "WH8gZT3semMTFw7wyDFRhHMTgKgnyJeTGs2Cak7P7C4".to_string(),
+                    lamports: 250_000_000,
+                    seed: Some("vault".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ledger_reconciles_with_balances() {
+        let parsed = parse("system_transfers");
+        let ledger = SolLedger::from_transaction(&parsed);
+
+        assert_eq!(
+            fee_debit(&parsed.context),
+            Some(SystemEvent::FeeDebit {
+                payer: PAYER.to_string(),
+                lamports: 10_000,
+            })
+        );
+        assert_eq!(ledger.change(PAYER), -(10_000 + 1_000_000_000 + 2_039_280));
+        assert_eq!(ledger.change(ALICE), 1_000_000_000);
+        assert_eq!(ledger.changes().count(), 5);
+        assert_eq!(ledger.discrepancies(&parsed.context), vec![]);
+    }
+
+    #[test]
+    fn test_failed_transaction_only_debits_fee() {
+        let parsed = parse("system_failed");
+        assert!(!parsed.context.success);
+        // Instructions are still parsed, but their effects were rolled back
+        assert_eq!(parsed.events.len(), 4);
+
+        let ledger = SolLedger::from_transaction(&parsed);
+        assert_eq!(ledger.changes().collect::<Vec<_>>(), vec![(PAYER, -10_000)]);
+        assert_eq!(ledger.discrepancies(&parsed.context), vec![]);
+
+        // A ledger that missed the rollback would not reconcile
+        let mut unreconciled = ledger.clone();
+        unreconciled.apply(&SystemEvent::SolTransfer {
+            from: PAYER.to_string(),
+            to: ALICE.to_string(),
+            lamports: 5,
+            seed: None,
+        });
+        assert_eq!(
+            unreconciled.discrepancies(&parsed.context),
+            vec![
+                LedgerDiscrepancy {
+                    account: PAYER.to_string(),
+                    on_chain: -10_000,
+                    derived: -10_005,
+                },
+                LedgerDiscrepancy {
+                    account: ALICE.to_string(),
+                    on_chain: 0,
+                    derived: 5,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,27 @@
+//! Helpers shared by the parser tests
+
+use crate::parsers::{decode_transaction, ParsedTransaction, ParserRegistry};
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::path::Path;
+
+/// The transaction fixture `name`, from `tests/fixtures/transactions`
+pub fn fixture(name: &str) -> EncodedConfirmedTransactionWithStatusMeta {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/transactions")
+        .join(format!("{}.json", name));
+    serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap()
+}
+
+/// Decode the transaction fixture `name` and parse it with `registry`
+pub fn parse_fixture(name: &str, registry: &ParserRegistry) -> ParsedTransaction {
+    parse_encoded(&fixture(name), registry)
+}
+
+/// Decode a `getTransaction` response and parse it with `registry`
+pub fn parse_encoded(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    registry: &ParserRegistry,
+) -> ParsedTransaction {
+    let decoded = decode_transaction(transaction.slot, transaction.block_time, &transaction.transaction).unwrap();
+    registry.parse_transaction(&decoded)
+}
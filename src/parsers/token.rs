@@ -0,0 +1,499 @@
+//! SPL Token and Token-2022 instruction parser
+
+use crate::parsers::{
+    DecodedInstruction, ParseError, ParseResult, ProtocolEvent, ProtocolParser, TransactionContext,
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::transfer_fee::instruction::TransferFeeInstruction;
+use spl_token_2022::instruction::{AuthorityType, TokenInstruction};
+use spl_token_2022::solana_program::program_option::COption;
+
+/// SPL Token program id
+pub const TOKEN_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+/// Token-2022 program id
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = spl_token_2022::ID;
+
+/// Token activity decoded from an SPL Token or Token-2022 instruction.
+///
+/// Addresses are base58 strings. Mints and decimals missing from the
+/// instruction itself are resolved from the transaction's token balances and
+/// are `None` only when the status meta does not cover the account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TokenEvent {
+    Transfer {
+        source: String,
+        destination: String,
+        authority: String,
+        mint: Option<String>,
+        amount: u64,
+        decimals: Option<u8>,
+        /// Fee withheld by a Token-2022 transfer fee, in base units
+        fee: Option<u64>,
+    },
+    MintTo {
+        mint: String,
+        account: String,
+        authority: String,
+        amount: u64,
+        decimals: Option<u8>,
+    },
+    Burn {
+        mint: String,
+        account: String,
+        authority: String,
+        amount: u64,
+        decimals: Option<u8>,
+    },
+    Approve {
+        account: String,
+        delegate: String,
+        owner: String,
+        mint: Option<String>,
+        amount: u64,
+        decimals: Option<u8>,
+    },
+    Revoke {
+        account: String,
+        owner: String,
+    },
+    InitializeMint {
+        mint: String,
+        decimals: u8,
+        mint_authority: String,
+        freeze_authority: Option<String>,
+    },
+    InitializeAccount {
+        account: String,
+        mint: String,
+        owner: String,
+    },
+    CloseAccount {
+        account: String,
+        destination: String,
+        owner: String,
+        mint: Option<String>,
+    },
+    SetAuthority {
+        /// Mint or token account whose authority changes
+        target: String,
+        authority_type: String,
+        authority: String,
+        new_authority: Option<String>,
+    },
+    /// A Token-2022 extension instruction without a dedicated event
+    Extension {
+        extension: String,
+        /// Extension-specific instruction discriminator
+        instruction: Option<u8>,
+        accounts: Vec<String>,
+    },
+}
+
+/// Parser for the SPL Token and Token-2022 programs.
+///
+/// Token-2022 is a superset of SPL Token, so both programs are decoded with
+/// the Token-2022 instruction layout. Instructions that move no tokens and
+/// change no authority (`SyncNative`, `AmountToUiAmount`, ...) produce no event.
+#[derive(Debug, Default, Clone)]
+pub struct TokenParser;
+
+impl TokenParser {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl ProtocolParser for TokenParser {
+    fn name(&self) -> &str {
+        "spl-token"
+    }
+
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID]
+    }
+
+    // The unchecked `Transfer` is deprecated but still widely used on chain
+    #[allow(deprecated)]
+    fn parse(&self, ix: &DecodedInstruction, context: &TransactionContext) -> ParseResult<Vec<ProtocolEvent>> {
+        let instruction = TokenInstruction::unpack(&ix.data)
+            .map_err(|e| ParseError::UnknownInstruction(format!("token instruction: {}", e)))?;
+        let account = |name: &'static str, index: usize| ix.account(name, index).map(|key| key.to_string());
+
+        let event = match instruction {
+            TokenInstruction::Transfer { amount } => {
+                let source = ix.account("Transfer", 0)?;
+                let destination = ix.account("Transfer", 1)?;
+                let (mint, decimals) = token_balance(context, source)
+                    .or_else(|| token_balance(context, destination))
+                    .unzip();
+                TokenEvent::Transfer {
+                    source: source.to_string(),
+                    destination: destination.to_string(),
+                    authority: account("Transfer", 2)?,
+                    mint,
+                    amount,
+                    decimals,
+                    fee: None,
+                }
+            }
+            TokenInstruction::TransferChecked { amount, decimals } => TokenEvent::Transfer {
+                source: account("TransferChecked", 0)?,
+                destination: account("TransferChecked", 2)?,
+                authority: account("TransferChecked", 3)?,
+                mint: Some(account("TransferChecked", 1)?),
+                amount,
+                decimals: Some(decimals),
+                fee: None,
+            },
+            TokenInstruction::TransferFeeExtension(TransferFeeInstruction::TransferCheckedWithFee {
+                amount,
+                decimals,
+                fee,
+            }) => TokenEvent::Transfer {
+                source: account("TransferCheckedWithFee", 0)?,
+                destination: account("TransferCheckedWithFee", 2)?,
+                authority: account("TransferCheckedWithFee", 3)?,
+                mint: Some(account("TransferCheckedWithFee", 1)?),
+                amount,
+                decimals: Some(decimals),
+                fee: Some(fee),
+            },
+            TokenInstruction::MintTo { amount } => TokenEvent::MintTo {
+                mint: account("MintTo", 0)?,
+                account: account("MintTo", 1)?,
+                authority: account("MintTo", 2)?,
+                amount,
+                decimals: balance_decimals(context, ix.account("MintTo", 1)?),
+            },
+            TokenInstruction::MintToChecked { amount, decimals } => TokenEvent::MintTo {
+                mint: account("MintToChecked", 0)?,
+                account: account("MintToChecked", 1)?,
+                authority: account("MintToChecked", 2)?,
+                amount,
+                decimals: Some(decimals),
+            },
+            TokenInstruction::Burn { amount } => TokenEvent::Burn {
+                account: account("Burn", 0)?,
+                mint: account("Burn", 1)?,
+                authority: account("Burn", 2)?,
+                amount,
+                decimals: balance_decimals(context, ix.account("Burn", 0)?),
+            },
+            TokenInstruction::BurnChecked { amount, decimals } => TokenEvent::Burn {
+                account: account("BurnChecked", 0)?,
+                mint: account("BurnChecked", 1)?,
+                authority: account("BurnChecked", 2)?,
+                amount,
+                decimals: Some(decimals),
+            },
+            TokenInstruction::Approve { amount } => {
+                let source = ix.account("Approve", 0)?;
+                TokenEvent::Approve {
+                    account: source.to_string(),
+                    delegate: account("Approve", 1)?,
+                    owner: account("Approve", 2)?,
+                    mint: token_balance(context, source).map(|(mint, _)| mint),
+                    amount,
+                    decimals: balance_decimals(context, source),
+                }
+            }
+            TokenInstruction::ApproveChecked { amount, decimals } => TokenEvent::Approve {
+                account: account("ApproveChecked", 0)?,
+                delegate: account("ApproveChecked", 2)?,
+                owner: account("ApproveChecked", 3)?,
+                mint: Some(account("ApproveChecked", 1)?),
+                amount,
+                decimals: Some(decimals),
+            },
+            TokenInstruction::Revoke => TokenEvent::Revoke {
+                account: account("Revoke", 0)?,
+                owner: account("Revoke", 1)?,
+            },
+            TokenInstruction::InitializeMint {
+                decimals,
+                mint_authority,
+                freeze_authority,
+            }
+            | TokenInstruction::InitializeMint2 {
+                decimals,
+                mint_authority,
+                freeze_authority,
+            } => TokenEvent::InitializeMint {
+                mint: account("InitializeMint", 0)?,
+                decimals,
+                mint_authority: mint_authority.to_string(),
+                freeze_authority: coption(freeze_authority),
+            },
+            TokenInstruction::InitializeAccount => TokenEvent::InitializeAccount {
+                account: account("InitializeAccount", 0)?,
+                mint: account("InitializeAccount", 1)?,
+                owner: account("InitializeAccount", 2)?,
+            },
+            TokenInstruction::InitializeAccount2 { owner } | TokenInstruction::InitializeAccount3 { owner } => {
+                TokenEvent::InitializeAccount {
+                    account: account("InitializeAccount", 0)?,
+                    mint: account("InitializeAccount", 1)?,
+                    owner: owner.to_string(),
+                }
+            }
+            TokenInstruction::CloseAccount => {
+                let closed = ix.account("CloseAccount", 0)?;
+                TokenEvent::CloseAccount {
+                    account: closed.to_string(),
+                    destination: account("CloseAccount", 1)?,
+                    owner: account("CloseAccount", 2)?,
+                    mint: token_balance(context, closed).map(|(mint, _)| mint),
+                }
+            }
+            TokenInstruction::SetAuthority {
+                authority_type,
+                new_authority,
+            } => TokenEvent::SetAuthority {
+                target: account("SetAuthority", 0)?,
+                authority_type: authority_type_name(&authority_type).to_string(),
+                authority: account("SetAuthority", 1)?,
+                new_authority: coption(new_authority),
+            },
+            TokenInstruction::TransferFeeExtension(_) => extension(ix, "transfer_fee", ix.data.get(1).copied()),
+            TokenInstruction::ConfidentialTransferExtension => extension(ix, "confidential_transfer", ix.data.get(1).copied()),
+            TokenInstruction::DefaultAccountStateExtension => extension(ix, "default_account_state", ix.data.get(1).copied()),
+            TokenInstruction::MemoTransferExtension => extension(ix, "memo_transfer", ix.data.get(1).copied()),
+            TokenInstruction::InterestBearingMintExtension => extension(ix, "interest_bearing_mint", ix.data.get(1).copied()),
+            TokenInstruction::CpiGuardExtension => extension(ix, "cpi_guard", ix.data.get(1).copied()),
+            TokenInstruction::TransferHookExtension => extension(ix, "transfer_hook", ix.data.get(1).copied()),
+            TokenInstruction::ConfidentialTransferFeeExtension => extension(ix, "confidential_transfer_fee", ix.data.get(1).copied()),
+            TokenInstruction::MetadataPointerExtension => extension(ix, "metadata_pointer", ix.data.get(1).copied()),
+            TokenInstruction::GroupPointerExtension => extension(ix, "group_pointer", ix.data.get(1).copied()),
+            TokenInstruction::GroupMemberPointerExtension => extension(ix, "group_member_pointer", ix.data.get(1).copied()),
+            TokenInstruction::InitializeMintCloseAuthority { .. } => extension(ix, "mint_close_authority", None),
+            TokenInstruction::InitializeNonTransferableMint => extension(ix, "non_transferable", None),
+            TokenInstruction::InitializePermanentDelegate { .. } => extension(ix, "permanent_delegate", None),
+            TokenInstruction::InitializeImmutableOwner => extension(ix, "immutable_owner", None),
+            _ => return Ok(Vec::new()),
+        };
+        Ok(vec![ProtocolEvent::Token(event)])
+    }
+}
+
+/// Mint and decimals of the token account `account`, from the status meta
+fn token_balance(context: &TransactionContext, account: &Pubkey) -> Option<(String, u8)> {
+    context
+        .pre_token_balance(account)
+        .or_else(|| context.post_token_balance(account))
+        .map(|balance| (balance.mint.clone(), balance.decimals))
+}
+
+fn balance_decimals(context: &TransactionContext, account: &Pubkey) -> Option<u8> {
+    token_balance(context, account).map(|(_, decimals)| decimals)
+}
+
+fn coption(value: COption<Pubkey>) -> Option<String> {
+    match value {
+        COption::Some(key) => Some(key.to_string()),
+        COption::None => None,
+    }
+}
+
+/// `instruction` is the extension's own discriminator following the tag;
+/// standalone extension initializers have none
+fn extension(ix: &DecodedInstruction, name: &str, instruction: Option<u8>) -> TokenEvent {
+    TokenEvent::Extension {
+        extension: name.to_string(),
+        instruction,
+        accounts: ix.accounts.iter().map(|key| key.to_string()).collect(),
+    }
+}
+
+fn authority_type_name(authority_type: &AuthorityType) -> &'static str {
+    match authority_type {
+        AuthorityType::MintTokens => "mint_tokens",
+        AuthorityType::FreezeAccount => "freeze_account",
+        AuthorityType::AccountOwner => "account_owner",
+        AuthorityType::CloseAccount => "close_account",
+        AuthorityType::TransferFeeConfig => "transfer_fee_config",
+        AuthorityType::WithheldWithdraw => "withheld_withdraw",
+        AuthorityType::CloseMint => "close_mint",
+        AuthorityType::InterestRate => "interest_rate",
+        AuthorityType::PermanentDelegate => "permanent_delegate",
+        AuthorityType::ConfidentialTransferMint => "confidential_transfer_mint",
+        AuthorityType::TransferHookProgramId => "transfer_hook_program_id",
+        AuthorityType::ConfidentialTransferFeeConfig => "confidential_transfer_fee_config",
+        AuthorityType::MetadataPointer => "metadata_pointer",
+        AuthorityType::GroupPointer => "group_pointer",
+        AuthorityType::GroupMemberPointer => "group_member_pointer",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::parse_fixture;
+    use crate::parsers::{ParsedTransaction, ParserRegistry};
+    use std::sync::Arc;
+
+    /// Parse the fixture `name` with a registry holding only the token parser
+    fn parse(name: &str) -> ParsedTransaction {
+        let mut registry = ParserRegistry::new();
+        registry.register(Arc::new(TokenParser::new()));
+        parse_fixture(name, &registry)
+    }
+
+    fn token_events(parsed: &ParsedTransaction) -> Vec<TokenEvent> {
+        parsed
+            .events
+            .iter()
+            .map(|parsed| match &parsed.event {
+                ProtocolEvent::Token(event) => event.clone(),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_spl_token_transfer_close_and_initialize() {
+        let parsed = parse("spl_token_transfer");
+        let owner = "67vHA8qZGCJKw1UNGUJZME4MwEWDRGWzp7MGvsut43A8";
+        let usdc = "9hGdMK6RVJEcUVVBzBeJFQsS2mv7WMQ2zFd2JmpwLPFE";
+        assert_eq!(parsed.context.slot, 250_000_001);
+        assert!(parsed.events.iter().all(|event| event.parser == "spl-token"));
+
+        assert_eq!(
+            token_events(&parsed),
+            vec![
+                // Unchecked transfer: mint and decimals come from the token balances
+                TokenEvent::Transfer {
+                    source: "4sAFqwXMzZFwSDjjHceT9txELSCNEbVd7iw7G6yBJs4s".to_string(),
+                    destination: "5tC4Qjk6b9xuP7a9B9FFs3m5Rto4siXsfqGt95zFJhf7".to_string(),
+                    authority: owner.to_string(),
+                    mint: Some(usdc.to_string()),
+                    amount: 1_250_000,
+                    decimals: Some(6),
+                    fee: None,
+                },
+                TokenEvent::InitializeAccount {
+                    account: "CxDLY9yvbq9dfEhgKPTH6DfuV8NNBBDKzm36XjfMmwSe".to_string(),
+                    mint: usdc.to_string(),
+                    owner: owner.to_string(),
+                },
+                TokenEvent::CloseAccount {
+                    account: "EyWLqSKAt2AJZep41YJrgX8QgaZSYbmx4vveAV3uSs89".to_string(),
+                    destination: owner.to_string(),
+                    owner: owner.to_string(),
+                    mint: Some(usdc.to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spl_token_mint_burn_approve_and_authority() {
+        let parsed = parse("spl_token_mint_burn");
+        let authority = "3BcvnbvZ7tn4ioGE88Nq3CpUmQCNTXn1PG3AMkhF5fob";
+        let account = "H5AL2Y9C39KYbkCDbv2JvXJtv6RHHYD5sjShG9h1ERG8";
+        let mint = "B7ScJ3BvgitUPFxK8TiauFbSh6mMh4iKQKGgTMWdPg4T";
+
+        assert_eq!(
+            token_events(&parsed),
+            vec![
+                TokenEvent::MintTo {
+                    mint: mint.to_string(),
+                    account: account.to_string(),
+                    authority: authority.to_string(),
+                    amount: 1_000_000_000,
+                    decimals: Some(5),
+                },
+                TokenEvent::Burn {
+                    mint: mint.to_string(),
+                    account: account.to_string(),
+                    authority: authority.to_string(),
+                    amount: 400_000,
+                    decimals: Some(5),
+                },
+                TokenEvent::Approve {
+                    account: account.to_string(),
+                    delegate: "8UopbHH1AeJB4b2pYYtK1d1dDVwGut9yqKjTVfvzU3f9".to_string(),
+                    owner: authority.to_string(),
+                    mint: Some(mint.to_string()),
+                    amount: 250_000,
+                    decimals: Some(5),
+                },
+                TokenEvent::Revoke {
+                    account: account.to_string(),
+                    owner: authority.to_string(),
+                },
+                TokenEvent::SetAuthority {
+                    target: mint.to_string(),
+                    authority_type: "mint_tokens".to_string(),
+                    authority: authority.to_string(),
+                    new_authority: Some("F9KCpkhGvAR2TCy6Cgj8JfdTpzeNcwkQhokQHkMn9Go1".to_string()),
+                },
+                TokenEvent::SetAuthority {
+                    target: account.to_string(),
+                    authority_type: "close_account".to_string(),
+                    authority: authority.to_string(),
+                    new_authority: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_token_2022_transfer_fee_and_extensions() {
+        let parsed = parse("token_2022_transfer_fee");
+        let alice = "3x9az88Dkbxa6tkKByxqEn7jBTJCJCD4dVvou49L24ET";
+        let alice_account = "3sPoxzgi2gQP646b7N5ajxboSDZAY4aF2xASdqjubKjX";
+        assert!(parsed.events.iter().all(|event| event.program_id == TOKEN_2022_PROGRAM_ID));
+
+        assert_eq!(
+            token_events(&parsed),
+            vec![
+                TokenEvent::InitializeMint {
+                    mint: "Auu3Xt8aFE2RFAYMRsmWz2iUFrQBQB9HCUjRaW9iFSHq".to_string(),
+                    decimals: 9,
+                    mint_authority: alice.to_string(),
+                    freeze_authority: None,
+                },
+                TokenEvent::Extension {
+                    extension: "memo_transfer".to_string(),
+                    instruction: Some(0),
+                    accounts: vec![alice_account.to_string(), alice.to_string()],
+                },
+                TokenEvent::Transfer {
+                    source: alice_account.to_string(),
+                    destination: "ExcKxo7gdpY8gt4ju1zSsZeEPFWCiSC3mQjgBbpJscSa".to_string(),
+                    authority: alice.to_string(),
+                    mint: Some("68gSUsLAHa2byJLwzNjtPUMgkKvLDViWaMkBZ5kx2rZ3".to_string()),
+                    amount: 10_000_000,
+                    decimals: Some(6),
+                    fee: Some(10_000),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event_serialization_and_unknown_data() {
+        let event = ProtocolEvent::Token(TokenEvent::Revoke {
+            account: "a".to_string(),
+            owner: "b".to_string(),
+        });
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({ "protocol": "token", "type": "revoke", "account": "a", "owner": "b" })
+        );
+
+        let ix = DecodedInstruction {
+            program_id: TOKEN_PROGRAM_ID,
+            accounts: vec![],
+            data: vec![255],
+            index: 0,
+            inner_index: None,
+            stack_height: 1,
+            inner_instructions: vec![],
+        };
+        let result = TokenParser::new().parse(&ix, &TransactionContext::default());
+        assert!(matches!(result, Err(ParseError::UnknownInstruction(_))));
+    }
+}
@@ -0,0 +1,324 @@
+//! Consensus prices from several price providers
+
+use crate::core::error::{Error, Result};
+use crate::core::metrics::{PRICE_OUTLIERS_TOTAL, PRICE_PROVIDER_ERRORS_TOTAL};
+use crate::models::amount::Decimal;
+use crate::models::UsdValue;
+use crate::prices::provider::{PriceProvider, PriceQuote, PriceStore};
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use metrics::counter;
+use rust_decimal::prelude::ToPrimitive;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Source recorded for aggregated prices
+pub const AGGREGATE_SOURCE: &str = "aggregate";
+
+/// Default largest relative deviation from the median a quote may have
+const DEFAULT_MAX_DEVIATION: f64 = 0.05;
+
+/// How accepted quotes are combined into one price
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AggregationMethod {
+    #[default]
+    Median,
+    /// Mean weighted by the USD volume of each quote, the median if no quote
+    /// reports a volume
+    VolumeWeighted,
+}
+
+/// Price agreed on by the providers of a [`PriceAggregator`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregatedPrice {
+    pub mint: String,
+    pub price_usd: UsdValue,
+    pub timestamp: DateTime<Utc>,
+    /// Quotes the price was computed from
+    pub quotes: Vec<PriceQuote>,
+    /// Quotes rejected as outliers
+    pub outliers: Vec<PriceQuote>,
+    /// Providers that failed, with the error
+    pub failed: Vec<(String, String)>,
+}
+
+/// Requested point in time
+#[derive(Debug, Clone, Copy)]
+enum Query {
+    Spot,
+    At(DateTime<Utc>),
+}
+
+/// Queries several [`PriceProvider`]s concurrently and combines their quotes.
+///
+/// Quotes deviating from the median by more than the maximum deviation are
+/// rejected before the rest are combined. Fallback providers are queried in
+/// order, one at a time, only when no primary provider returned a quote.
+/// With a store set, aggregated prices are persisted with the source
+/// [`AGGREGATE_SOURCE`].
+pub struct PriceAggregator {
+    providers: Vec<Arc<dyn PriceProvider>>,
+    fallbacks: Vec<Arc<dyn PriceProvider>>,
+    method: AggregationMethod,
+    max_deviation: f64,
+    store: Option<Arc<dyn PriceStore>>,
+}
+
+impl PriceAggregator {
+    pub fn new(providers: Vec<Arc<dyn PriceProvider>>) -> Self {
+        Self {
+            providers,
+            fallbacks: Vec::new(),
+            method: AggregationMethod::default(),
+            max_deviation: DEFAULT_MAX_DEVIATION,
+            store: None,
+        }
+    }
+
+    pub fn with_fallback(mut self, provider: Arc<dyn PriceProvider>) -> Self {
+        self.fallbacks.push(provider);
+        self
+    }
+
+    pub fn with_method(mut self, method: AggregationMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Largest relative deviation from the median, e.g. `0.05` for 5%
+    pub fn with_max_deviation(mut self, max_deviation: f64) -> Self {
+        self.max_deviation = max_deviation;
+        self
+    }
+
+    pub fn with_store(mut self, store: Arc<dyn PriceStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Current price of `mint`
+    pub async fn spot_price(&self, mint: &str) -> Result<AggregatedPrice> {
+        self.aggregate(mint, Query::Spot).await
+    }
+
+    /// Price of `mint` at `at`
+    pub async fn historical_price(&self, mint: &str, at: DateTime<Utc>) -> Result<AggregatedPrice> {
+        self.aggregate(mint, Query::At(at)).await
+    }
+
+    async fn aggregate(&self, mint: &str, query: Query) -> Result<AggregatedPrice> {
+        let mut failed = Vec::new();
+        let results = join_all(self.providers.iter().map(|provider| fetch(provider.as_ref(), mint, query))).await;
+        let mut quotes = collect(&self.providers, results, &mut failed);
+        for fallback in &self.fallbacks {
+            if !quotes.is_empty() {
+                break;
+            }
+            let result = fetch(fallback.as_ref(), mint, query).await;
+            quotes = collect(std::slice::from_ref(fallback), vec![result], &mut failed);
+        }
+        if quotes.is_empty() {
+            return Err(Error::pipeline(format!("No price source priced {}", mint)));
+        }
+
+        let mid = median(&quotes);
+        let (quotes, outliers): (Vec<_>, Vec<_>) = quotes
+            .into_iter()
+            .partition(|quote| deviation(quote.price_usd, mid).is_some_and(|d| d <= self.max_deviation));
+        for outlier in &outliers {
+            counter!(PRICE_OUTLIERS_TOTAL, 1, "provider" => outlier.source.clone());
+            warn!(
+                "Rejected {} price {} of {}, median is {}",
+                outlier.source, outlier.price_usd, mint, mid
+            );
+        }
+        if quotes.is_empty() {
+            return Err(Error::validation(format!("Price sources of {} disagree", mint)));
+        }
+
+        let price_usd = match self.method {
+            AggregationMethod::Median => median(&quotes),
+            AggregationMethod::VolumeWeighted => volume_weighted(&quotes).unwrap_or_else(|| median(&quotes)),
+        };
+        let timestamp = match query {
+            Query::Spot => quotes.iter().map(|quote| quote.timestamp).max().unwrap_or_else(Utc::now),
+            Query::At(at) => at,
+        };
+        let price = AggregatedPrice {
+            mint: mint.to_string(),
+            price_usd,
+            timestamp,
+            quotes,
+            outliers,
+            failed,
+        };
+        if let Some(store) = &self.store {
+            let row = PriceQuote::new(mint, price_usd, timestamp, AGGREGATE_SOURCE).to_history();
+            store.save_prices(&[row]).await?;
+        }
+        Ok(price)
+    }
+}
+
+async fn fetch(provider: &dyn PriceProvider, mint: &str, query: Query) -> Result<Option<PriceQuote>> {
+    match query {
+        Query::Spot => provider.spot_price(mint).await,
+        Query::At(at) => provider.historical_price(mint, at).await,
+    }
+}
+
+/// Usable quotes of `results`, recording failed providers in `failed`
+fn collect(
+    providers: &[Arc<dyn PriceProvider>],
+    results: Vec<Result<Option<PriceQuote>>>,
+    failed: &mut Vec<(String, String)>,
+) -> Vec<PriceQuote> {
+    let mut quotes = Vec::new();
+    for (provider, result) in providers.iter().zip(results) {
+        match result {
+            Ok(Some(quote)) if quote.price_usd.is_positive() => quotes.push(quote),
+            Ok(_) => {}
+            Err(e) => {
+                counter!(PRICE_PROVIDER_ERRORS_TOTAL, 1, "provider" => provider.name().to_string());
+                warn!("Price provider {} failed: {}", provider.name(), e);
+                failed.push((provider.name().to_string(), e.to_string()));
+            }
+        }
+    }
+    quotes
+}
+
+/// Median price of non-empty `quotes`
+fn median(quotes: &[PriceQuote]) -> UsdValue {
+    let mut prices: Vec<UsdValue> = quotes.iter().map(|quote| quote.price_usd).collect();
+    prices.sort();
+    let mid = prices.len() / 2;
+    if prices.len().is_multiple_of(2) {
+        (prices[mid - 1] + prices[mid]) / Decimal::TWO
+    } else {
+        prices[mid]
+    }
+}
+
+/// Relative deviation of `price` from a positive `mid`
+fn deviation(price: UsdValue, mid: UsdValue) -> Option<f64> {
+    if !mid.is_positive() {
+        return None;
+    }
+    (price - mid).abs().checked_ratio(mid)?.to_f64()
+}
+
+/// Volume-weighted mean price, `None` without volumes
+fn volume_weighted(quotes: &[PriceQuote]) -> Option<UsdValue> {
+    let (weighted, volume) = quotes
+        .iter()
+        .filter_map(|quote| quote.volume_usd.filter(UsdValue::is_positive).map(|v| (quote.price_usd, v)))
+        .fold((UsdValue::ZERO, Decimal::ZERO), |(weighted, total), (price, volume)| {
+            (weighted + price * volume.as_decimal(), total + volume.as_decimal())
+        });
+    (volume > Decimal::ZERO).then(|| weighted / volume)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::http::{CoinGeckoProvider, JupiterProvider};
+    use crate::prices::provider::{MockPriceProvider, MockPriceStore};
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const MINT: &str = "So11111111111111111111111111111111111111112";
+
+    fn usd(value: &str) -> UsdValue {
+        value.parse().unwrap()
+    }
+
+    async fn coingecko(price: f64, volume: f64) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/simple/token_price/solana"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                MINT: {"usd": price, "usd_24h_vol": volume, "last_updated_at": 1_700_000_000}
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn jupiter(price: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/price/v2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": {MINT: {"price": price}}})))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn unavailable() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_median_rejects_outliers_and_persists() {
+        let (first, second) = (coingecko(100.0, 1_000.0).await, coingecko(102.0, 3_000.0).await);
+        let (outlier, down) = (jupiter("150.0").await, unavailable().await);
+
+        let mut store = MockPriceStore::new();
+        store
+            .expect_save_prices()
+            .withf(|prices| prices.len() == 1 && prices[0].source == AGGREGATE_SOURCE && prices[0].price_usd == usd("101"))
+            .times(1)
+            .returning(|_| Ok(()));
+        let aggregator = PriceAggregator::new(vec![
+            Arc::new(CoinGeckoProvider::new(first.uri())),
+            Arc::new(CoinGeckoProvider::new(second.uri())),
+            Arc::new(JupiterProvider::new(outlier.uri())),
+            Arc::new(JupiterProvider::new(down.uri())),
+        ])
+        .with_store(Arc::new(store));
+
+        let price = aggregator.spot_price(MINT).await.unwrap();
+        assert_eq!(price.price_usd, usd("101"));
+        assert_eq!(price.quotes.len(), 2);
+        assert_eq!(price.outliers.len(), 1);
+        assert_eq!(price.outliers[0].price_usd, usd("150"));
+        assert_eq!(price.failed.len(), 1);
+        assert_eq!(price.failed[0].0, "jupiter");
+        assert_eq!(price.timestamp.timestamp(), 1_700_000_000);
+
+        let weighted = PriceAggregator::new(vec![
+            Arc::new(CoinGeckoProvider::new(first.uri())),
+            Arc::new(CoinGeckoProvider::new(second.uri())),
+        ])
+        .with_method(AggregationMethod::VolumeWeighted);
+        assert_eq!(weighted.spot_price(MINT).await.unwrap().price_usd, usd("101.5"));
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_when_sources_fail() {
+        let (down, backup) = (unavailable().await, jupiter("99.5").await);
+        let mut unused = MockPriceProvider::new();
+        unused.expect_spot_price().never();
+        unused.expect_historical_price().returning(|_, _| Ok(None));
+
+        let aggregator = PriceAggregator::new(vec![Arc::new(CoinGeckoProvider::new(down.uri()))])
+            .with_fallback(Arc::new(JupiterProvider::new(backup.uri())))
+            .with_fallback(Arc::new(unused));
+        let price = aggregator.spot_price(MINT).await.unwrap();
+        assert_eq!(price.price_usd, usd("99.5"));
+        assert_eq!(price.quotes[0].source, "jupiter");
+        assert_eq!(price.failed[0].0, "coingecko");
+
+        // Jupiter has no history, so nothing prices the past
+        let at = Utc::now();
+        let error = aggregator.historical_price(MINT, at).await;
+        assert!(error.is_err());
+    }
+}
@@ -0,0 +1,406 @@
+//! Prices derived from parsed DEX swaps
+
+use crate::core::error::{Error, Result};
+use crate::db::Database;
+use crate::models::amount::Decimal;
+use crate::models::{TokenAmount, UsdValue};
+use crate::parsers::swap::SwapEvent;
+use crate::prices::provider::{PriceProvider, PriceQuote, PriceStore};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+/// Source recorded for prices derived from swaps
+pub const DEX_VWAP_SOURCE: &str = "dex_vwap";
+
+/// Mint of wrapped SOL
+pub const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+/// Mint of USDC
+pub const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+/// Mint of USDT
+pub const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB";
+
+/// A swap and the block time of its transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapTrade {
+    pub timestamp: DateTime<Utc>,
+    pub swap: SwapEvent,
+}
+
+/// Storage of parsed swaps
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait SwapStore: Send + Sync {
+    /// Swaps selling or buying any of `mints` between `from` and `to`,
+    /// oldest first
+    async fn swaps(&self, mints: &[String], from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<SwapTrade>>;
+}
+
+#[async_trait]
+impl SwapStore for Database {
+    async fn swaps(&self, mints: &[String], from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<SwapTrade>> {
+        let interactions = self
+            .get_swaps(mints, from, to)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to load swaps: {}", e)))?;
+        Ok(interactions
+            .into_iter()
+            .filter_map(|interaction| {
+                let swap = serde_json::from_value(interaction.details?).ok()?;
+                Some(SwapTrade {
+                    timestamp: interaction.timestamp,
+                    swap,
+                })
+            })
+            .collect())
+    }
+}
+
+/// One swap of the priced mint against a quote mint, in UI units
+#[derive(Debug, Clone)]
+struct Fill<'a> {
+    /// USD per unit of the priced mint
+    price: UsdValue,
+    /// Units of the priced mint
+    amount: Decimal,
+    /// USD value of the quote side
+    volume: UsdValue,
+    trader: &'a str,
+}
+
+/// Volume-weighted average prices of swaps against SOL and USD stablecoins.
+///
+/// Swaps against a stablecoin are valued at one dollar per unit, swaps
+/// against SOL at the VWAP of SOL against the stablecoins over the same
+/// bucket. Venue legs of a routed swap are skipped, since the route is counted
+/// once by its outer swap.
+///
+/// A bucket only yields a price with enough trades and USD volume. Against
+/// manipulation, trades deviating from the bucket's median price are dropped,
+/// and buckets where a single trader makes up too large a share of the volume
+/// are rejected.
+pub struct DexPriceProvider {
+    store: Arc<dyn SwapStore>,
+    prices: Option<Arc<dyn PriceStore>>,
+    usd_quotes: Vec<String>,
+    bucket: Duration,
+    min_trades: usize,
+    min_volume_usd: UsdValue,
+    max_trade_deviation: f64,
+    max_trader_share: f64,
+}
+
+impl DexPriceProvider {
+    pub fn new(store: Arc<dyn SwapStore>) -> Self {
+        Self {
+            store,
+            prices: None,
+            usd_quotes: vec![USDC_MINT.to_string(), USDT_MINT.to_string()],
+            bucket: Duration::hours(1),
+            min_trades: 3,
+            min_volume_usd: UsdValue::from(1_000),
+            max_trade_deviation: 0.2,
+            max_trader_share: 0.5,
+        }
+    }
+
+    /// Persist series computed by [`vwap_series`](Self::vwap_series) in `store`
+    pub fn with_price_store(mut self, store: Arc<dyn PriceStore>) -> Self {
+        self.prices = Some(store);
+        self
+    }
+
+    /// Mints valued at one dollar per unit
+    pub fn with_usd_quotes(mut self, mints: Vec<String>) -> Self {
+        self.usd_quotes = mints;
+        self
+    }
+
+    /// Span of trades behind spot and historical prices
+    pub fn with_bucket(mut self, bucket: Duration) -> Self {
+        self.bucket = bucket;
+        self
+    }
+
+    /// Fewest trades and USD volume a bucket needs to be priced
+    pub fn with_min_liquidity(mut self, trades: usize, volume_usd: UsdValue) -> Self {
+        self.min_trades = trades;
+        self.min_volume_usd = volume_usd;
+        self
+    }
+
+    /// Largest relative deviation of a trade from the bucket median, and
+    /// largest share of the bucket volume a single trader may have
+    pub fn with_guards(mut self, max_trade_deviation: f64, max_trader_share: f64) -> Self {
+        self.max_trade_deviation = max_trade_deviation;
+        self.max_trader_share = max_trader_share;
+        self
+    }
+
+    /// VWAP of `mint` over swaps after `start` up to and including `end`,
+    /// `None` without enough liquidity
+    pub async fn vwap(&self, mint: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Option<PriceQuote>> {
+        let trades = self.trades(mint, start, end).await?;
+        Ok(self.bucket_vwap(mint, &trades, start, end))
+    }
+
+    /// VWAP of `mint` per `bucket` over `range`, stamped at the end of each
+    /// bucket. Buckets without enough liquidity are left out. Persisted with
+    /// the source [`DEX_VWAP_SOURCE`] when a price store is set.
+    pub async fn vwap_series(
+        &self,
+        mint: &str,
+        range: RangeInclusive<DateTime<Utc>>,
+        bucket: Duration,
+    ) -> Result<Vec<PriceQuote>> {
+        if bucket <= Duration::zero() {
+            return Err(Error::validation("Bucket must be positive"));
+        }
+        let (start, end) = (*range.start(), *range.end());
+        let trades = self.trades(mint, start, end).await?;
+        let mut series = Vec::new();
+        let mut bucket_start = start;
+        while bucket_start < end {
+            let bucket_end = (bucket_start + bucket).min(end);
+            series.extend(self.bucket_vwap(mint, &trades, bucket_start, bucket_end));
+            bucket_start = bucket_end;
+        }
+        if let Some(store) = &self.prices {
+            if !series.is_empty() {
+                let rows: Vec<_> = series.iter().map(PriceQuote::to_history).collect();
+                store.save_prices(&rows).await?;
+            }
+        }
+        Ok(series)
+    }
+
+    async fn trades(&self, mint: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<SwapTrade>> {
+        let mut mints = vec![mint.to_string()];
+        if mint != WSOL_MINT {
+            mints.push(WSOL_MINT.to_string());
+        }
+        self.store.swaps(&mints, start, end).await
+    }
+
+    fn bucket_vwap(&self, mint: &str, trades: &[SwapTrade], start: DateTime<Utc>, end: DateTime<Utc>) -> Option<PriceQuote> {
+        if self.usd_quotes.iter().any(|quote| quote == mint) {
+            return None;
+        }
+        let trades: Vec<&SwapEvent> = trades
+            .iter()
+            .filter(|trade| trade.timestamp > start && trade.timestamp <= end && !trade.swap.route_leg)
+            .map(|trade| &trade.swap)
+            .collect();
+        let mut quotes: HashMap<&str, UsdValue> =
+            self.usd_quotes.iter().map(|quote| (quote.as_str(), UsdValue::ONE)).collect();
+        if mint != WSOL_MINT {
+            if let Some((sol, _)) = self.guarded_vwap(fills(WSOL_MINT, &trades, &quotes)) {
+                quotes.insert(WSOL_MINT, sol);
+            }
+        }
+        let (price, volume) = self.guarded_vwap(fills(mint, &trades, &quotes))?;
+        Some(PriceQuote::new(mint, price, end, DEX_VWAP_SOURCE).with_volume(volume))
+    }
+
+    /// Price and USD volume of `fills` passing the liquidity filters and
+    /// manipulation guards
+    fn guarded_vwap(&self, fills: Vec<Fill>) -> Option<(UsdValue, UsdValue)> {
+        if fills.len() < self.min_trades.max(1) {
+            return None;
+        }
+        let mut prices: Vec<UsdValue> = fills.iter().map(|fill| fill.price).collect();
+        prices.sort();
+        let median = prices[prices.len() / 2];
+        let fills: Vec<Fill> = fills
+            .into_iter()
+            .filter(|fill| ratio((fill.price - median).abs(), median) <= self.max_trade_deviation)
+            .collect();
+        if fills.len() < self.min_trades.max(1) {
+            return None;
+        }
+
+        let volume: UsdValue = fills.iter().map(|fill| fill.volume).sum();
+        if volume < self.min_volume_usd {
+            return None;
+        }
+        let mut traders: HashMap<&str, UsdValue> = HashMap::new();
+        for fill in &fills {
+            *traders.entry(fill.trader).or_default() += fill.volume;
+        }
+        if traders.values().any(|traded| ratio(*traded, volume) > self.max_trader_share) {
+            return None;
+        }
+        let amount: Decimal = fills.iter().map(|fill| fill.amount).sum();
+        Some((volume / amount, volume))
+    }
+}
+
+/// `value` relative to a positive `total`
+fn ratio(value: UsdValue, total: UsdValue) -> f64 {
+    value.checked_ratio(total).and_then(|ratio| ratio.to_f64()).unwrap_or(f64::INFINITY)
+}
+
+/// Swaps of `mint` against a mint of `quotes`, valued at its USD price
+fn fills<'a>(mint: &str, trades: &[&'a SwapEvent], quotes: &HashMap<&str, UsdValue>) -> Vec<Fill<'a>> {
+    let ui = |amount: u64, decimals: Option<u8>| Some(TokenAmount::new(amount, decimals?).to_decimal());
+    trades
+        .iter()
+        .filter_map(|swap| {
+            let (amount, quote, quote_amount) = if swap.input_mint == mint {
+                (
+                    ui(swap.input_amount, swap.input_decimals)?,
+                    &swap.output_mint,
+                    ui(swap.output_amount, swap.output_decimals)?,
+                )
+            } else if swap.output_mint == mint {
+                (
+                    ui(swap.output_amount, swap.output_decimals)?,
+                    &swap.input_mint,
+                    ui(swap.input_amount, swap.input_decimals)?,
+                )
+            } else {
+                return None;
+            };
+            let volume = quotes.get(quote.as_str())?.checked_mul(quote_amount)?;
+            (amount > Decimal::ZERO && volume.is_positive()).then(|| Fill {
+                price: volume / amount,
+                amount,
+                volume,
+                trader: &swap.user,
+            })
+        })
+        .collect()
+}
+
+#[async_trait]
+impl PriceProvider for DexPriceProvider {
+    fn name(&self) -> &str {
+        DEX_VWAP_SOURCE
+    }
+
+    async fn spot_price(&self, mint: &str) -> Result<Option<PriceQuote>> {
+        let now = Utc::now();
+        self.vwap(mint, now - self.bucket, now).await
+    }
+
+    async fn historical_price(&self, mint: &str, at: DateTime<Utc>) -> Result<Option<PriceQuote>> {
+        self.vwap(mint, at - self.bucket, at).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::provider::MockPriceStore;
+    use crate::prices::test_utils::{at_minute, usd};
+
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    /// `user` sells `sold` of one mint for `bought` of another, in UI units
+    fn trade(minute: i64, user: &str, sold: (&str, f64, u8), bought: (&str, f64, u8)) -> SwapTrade {
+        let raw = |(_, amount, decimals): (&str, f64, u8)| (amount * 10f64.powi(decimals as i32)).round() as u64;
+        SwapTrade {
+            timestamp: at_minute(minute),
+            swap: SwapEvent {
+                dex: "raydium_amm".to_string(),
+                user: user.to_string(),
+                pool: Some("pool".to_string()),
+                input_mint: sold.0.to_string(),
+                input_amount: raw(sold),
+                input_decimals: Some(sold.2),
+                output_mint: bought.0.to_string(),
+                output_amount: raw(bought),
+                output_decimals: Some(bought.2),
+                route: vec!["raydium_amm".to_string()],
+                route_leg: false,
+            },
+        }
+    }
+
+    fn sol(amount: f64) -> (&'static str, f64, u8) {
+        (WSOL_MINT, amount, 9)
+    }
+
+    fn usdc(amount: f64) -> (&'static str, f64, u8) {
+        (USDC_MINT, amount, 6)
+    }
+
+    fn bonk(amount: f64) -> (&'static str, f64, u8) {
+        (BONK, amount, 5)
+    }
+
+    /// SOL at $100 and BONK at $0.00002, plus a manipulated BONK trade
+    fn trades() -> Vec<SwapTrade> {
+        // Venue leg of a route, already counted by the outer swap
+        let mut routed = trade(40, "h", bonk(1.0), usdc(1_000.0));
+        routed.swap.route_leg = true;
+        vec![
+            trade(5, "a", sol(10.0), usdc(990.0)),
+            trade(10, "b", usdc(1_010.0), sol(10.0)),
+            trade(15, "c", sol(10.0), usdc(1_000.0)),
+            trade(20, "d", sol(5.0), bonk(25_000_000.0)),
+            trade(25, "e", bonk(25_000_000.0), sol(5.0)),
+            trade(30, "f", bonk(50_000_000.0), usdc(1_000.0)),
+            trade(35, "g", sol(0.1), bonk(50_000.0)),
+            routed,
+        ]
+    }
+
+    fn provider(trades: Vec<SwapTrade>) -> DexPriceProvider {
+        let mut store = MockSwapStore::new();
+        store
+            .expect_swaps()
+            .withf(|mints, _, _| mints.contains(&WSOL_MINT.to_string()))
+            .returning(move |_, _, _| Ok(trades.clone()));
+        DexPriceProvider::new(Arc::new(store))
+    }
+
+    #[tokio::test]
+    async fn test_vwap_routes_through_sol_and_drops_outliers() {
+        let provider = provider(trades());
+        let quote = provider.historical_price(BONK, at_minute(60)).await.unwrap().unwrap();
+        assert_eq!(quote.source, DEX_VWAP_SOURCE);
+        assert_eq!(quote.timestamp, at_minute(60));
+        // 500 + 500 + 1000 USD for 100M BONK, the 10x trade of "g" is dropped
+        assert_eq!(quote.price_usd, usd("0.00002"));
+        assert_eq!(quote.volume_usd, Some(usd("2000")));
+
+        let sol = provider.historical_price(WSOL_MINT, at_minute(60)).await.unwrap().unwrap();
+        assert_eq!(sol.price_usd, usd("100"));
+        assert_eq!(provider.historical_price(USDC_MINT, at_minute(60)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_liquidity_filters_and_trader_share_guard() {
+        let thin = provider(trades()).with_min_liquidity(3, usd("5000"));
+        assert_eq!(thin.historical_price(BONK, at_minute(60)).await.unwrap(), None);
+
+        let mut whale = trades();
+        whale.push(trade(45, "f", bonk(200_000_000.0), usdc(4_000.0)));
+        assert_eq!(provider(whale).historical_price(BONK, at_minute(60)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_series_is_bucketed_and_persisted() {
+        let mut trades = trades();
+        trades.extend([
+            trade(65, "a", sol(10.0), usdc(1_100.0)),
+            trade(70, "b", usdc(1_100.0), sol(10.0)),
+            trade(75, "c", sol(10.0), usdc(1_100.0)),
+        ]);
+        let mut prices = MockPriceStore::new();
+        prices
+            .expect_save_prices()
+            .withf(|rows| rows.len() == 2 && rows.iter().all(|row| row.source == DEX_VWAP_SOURCE))
+            .times(1)
+            .returning(|_| Ok(()));
+        let provider = provider(trades).with_price_store(Arc::new(prices));
+
+        let series = provider.vwap_series(WSOL_MINT, at_minute(0)..=at_minute(120), Duration::hours(1)).await.unwrap();
+        let points: Vec<_> = series.iter().map(|quote| (quote.timestamp, quote.price_usd)).collect();
+        assert_eq!(points, vec![(at_minute(60), usd("100")), (at_minute(120), usd("110"))]);
+    }
+}
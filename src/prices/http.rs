@@ -0,0 +1,291 @@
+//! Price providers backed by public price APIs
+
+use crate::core::error::{Error, Result};
+use crate::models::UsdValue;
+use crate::prices::provider::{PriceProvider, PriceQuote};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Request timeout of the HTTP providers
+const DEFAULT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+fn http_client(timeout: std::time::Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .unwrap_or_default()
+}
+
+/// GET `request` and decode its JSON body, naming `source` in errors
+async fn get_json<T: DeserializeOwned>(source: &str, request: reqwest::RequestBuilder) -> Result<T> {
+    let response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| Error::pipeline(format!("{} request failed: {}", source, e)))?;
+    response
+        .json()
+        .await
+        .map_err(|e| Error::pipeline(format!("Invalid {} response: {}", source, e)))
+}
+
+/// CoinGecko token prices by Solana contract address
+pub struct CoinGeckoProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    /// How far before the requested time historical lookups search
+    window: Duration,
+}
+
+#[derive(Deserialize)]
+struct CoinGeckoSpot {
+    usd: Option<f64>,
+    usd_24h_vol: Option<f64>,
+    last_updated_at: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct CoinGeckoChart {
+    /// `[unix millis, price]` pairs, oldest first
+    prices: Vec<(f64, f64)>,
+    #[serde(default)]
+    total_volumes: Vec<(f64, f64)>,
+}
+
+impl CoinGeckoProvider {
+    pub const NAME: &'static str = "coingecko";
+
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            client: http_client(DEFAULT_TIMEOUT),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key: None,
+            window: Duration::hours(1),
+        }
+    }
+
+    /// Send `api_key` as the `x-cg-pro-api-key` header
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.client = http_client(timeout);
+        self
+    }
+
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.get(format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(key) => request.header("x-cg-pro-api-key", key),
+            None => request,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceProvider for CoinGeckoProvider {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    async fn spot_price(&self, mint: &str) -> Result<Option<PriceQuote>> {
+        let request = self.get("/simple/token_price/solana").query(&[
+            ("contract_addresses", mint),
+            ("vs_currencies", "usd"),
+            ("include_24hr_vol", "true"),
+            ("include_last_updated_at", "true"),
+        ]);
+        let prices: HashMap<String, CoinGeckoSpot> = get_json(Self::NAME, request).await?;
+        // Keys may come back lowercased
+        let Some(spot) = prices
+            .into_iter()
+            .find_map(|(key, spot)| key.eq_ignore_ascii_case(mint).then_some(spot))
+        else {
+            return Ok(None);
+        };
+        let Some(price) = spot.usd.and_then(UsdValue::from_f64) else {
+            return Ok(None);
+        };
+        let timestamp = spot
+            .last_updated_at
+            .and_then(|t| Utc.timestamp_opt(t, 0).single())
+            .unwrap_or_else(Utc::now);
+        let quote = PriceQuote::new(mint, price, timestamp, Self::NAME);
+        Ok(Some(match spot.usd_24h_vol.and_then(UsdValue::from_f64) {
+            Some(volume) => quote.with_volume(volume),
+            None => quote,
+        }))
+    }
+
+    async fn historical_price(&self, mint: &str, at: DateTime<Utc>) -> Result<Option<PriceQuote>> {
+        let request = self
+            .get(&format!("/coins/solana/contract/{}/market_chart/range", mint))
+            .query(&[
+                ("vs_currency", "usd".to_string()),
+                ("from", (at - self.window).timestamp().to_string()),
+                ("to", at.timestamp().to_string()),
+            ]);
+        let chart: CoinGeckoChart = get_json(Self::NAME, request).await?;
+        let at_millis = at.timestamp_millis() as f64;
+        let Some(index) = chart.prices.iter().rposition(|(time, _)| *time <= at_millis) else {
+            return Ok(None);
+        };
+        let (time, price) = chart.prices[index];
+        let (Some(timestamp), Some(price)) = (Utc.timestamp_millis_opt(time as i64).single(), UsdValue::from_f64(price))
+        else {
+            return Ok(None);
+        };
+        let quote = PriceQuote::new(mint, price, timestamp, Self::NAME);
+        Ok(Some(match chart.total_volumes.get(index).and_then(|(_, volume)| UsdValue::from_f64(*volume)) {
+            Some(volume) => quote.with_volume(volume),
+            None => quote,
+        }))
+    }
+}
+
+/// Jupiter price API, spot prices only
+pub struct JupiterProvider {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct JupiterPrices {
+    data: HashMap<String, Option<JupiterPrice>>,
+}
+
+#[derive(Deserialize)]
+struct JupiterPrice {
+    /// Decimal string
+    price: String,
+}
+
+impl JupiterProvider {
+    pub const NAME: &'static str = "jupiter";
+
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            client: http_client(DEFAULT_TIMEOUT),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.client = http_client(timeout);
+        self
+    }
+}
+
+#[async_trait]
+impl PriceProvider for JupiterProvider {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    async fn spot_price(&self, mint: &str) -> Result<Option<PriceQuote>> {
+        let request = self
+            .client
+            .get(format!("{}/price/v2", self.base_url))
+            .query(&[("ids", mint)]);
+        let mut prices: JupiterPrices = get_json(Self::NAME, request).await?;
+        let Some(Some(price)) = prices.data.remove(mint) else {
+            return Ok(None);
+        };
+        let price = price
+            .price
+            .parse()
+            .map_err(|_| Error::pipeline(format!("Invalid {} price {:?}", Self::NAME, price.price)))?;
+        Ok(Some(PriceQuote::new(mint, price, Utc::now(), Self::NAME)))
+    }
+
+    async fn historical_price(&self, _mint: &str, _at: DateTime<Utc>) -> Result<Option<PriceQuote>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn usd(value: &str) -> UsdValue {
+        value.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_coingecko_spot_and_historical() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/simple/token_price/solana"))
+            .and(query_param("contract_addresses", MINT))
+            .and(header("x-cg-pro-api-key", "secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                MINT.to_lowercase(): {"usd": 0.9998, "usd_24h_vol": 2_500_000.0, "last_updated_at": 1_700_000_000}
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/coins/solana/contract/{}/market_chart/range", MINT)))
+            .and(query_param("to", "1700000000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "prices": [[1_699_999_400_000.0, 1.001], [1_699_999_700_000.0, 1.002]],
+                "total_volumes": [[1_699_999_400_000.0, 10.0], [1_699_999_700_000.0, 20.0]]
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = CoinGeckoProvider::new(server.uri()).with_api_key("secret");
+        let spot = provider.spot_price(MINT).await.unwrap().unwrap();
+        assert_eq!((spot.price_usd, spot.volume_usd), (usd("0.9998"), Some(usd("2500000"))));
+        assert_eq!(spot.timestamp.timestamp(), 1_700_000_000);
+        assert_eq!(spot.source, "coingecko");
+
+        let at = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let historical = provider.historical_price(MINT, at).await.unwrap().unwrap();
+        assert_eq!((historical.price_usd, historical.volume_usd), (usd("1.002"), Some(usd("20"))));
+        assert_eq!(historical.timestamp.timestamp(), 1_699_999_700);
+    }
+
+    #[tokio::test]
+    async fn test_jupiter_spot_and_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/price/v2"))
+            .and(query_param("ids", MINT))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": {MINT: {"id": MINT, "type": "derivedPrice", "price": "1.0001"}}
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/price/v2"))
+            .and(query_param("ids", "unknown"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": {"unknown": null}})))
+            .mount(&server)
+            .await;
+
+        let provider = JupiterProvider::new(server.uri());
+        assert_eq!(provider.spot_price(MINT).await.unwrap().unwrap().price_usd, usd("1.0001"));
+        assert_eq!(provider.spot_price("unknown").await.unwrap(), None);
+        assert_eq!(provider.historical_price(MINT, Utc::now()).await.unwrap(), None);
+
+        // Unmatched requests get a 404
+        let error = CoinGeckoProvider::new(server.uri()).spot_price(MINT).await.unwrap_err();
+        assert!(error.to_string().contains("coingecko request failed"));
+    }
+}
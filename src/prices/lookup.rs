@@ -0,0 +1,387 @@
+//! Point-in-time price lookups and candles from `price_history`
+
+use crate::core::error::{Error, Result};
+use crate::db::Database;
+use crate::models::amount::Decimal;
+use crate::models::{PriceHistory, UsdValue};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+/// Default largest distance between a requested time and the price used
+const DEFAULT_MAX_STALENESS: i64 = 3_600;
+
+/// Stored prices of a mint between two times
+pub type PriceRange = (String, DateTime<Utc>, DateTime<Utc>);
+
+/// Read access to `price_history`
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait PriceHistoryStore: Send + Sync {
+    /// Prices of every `(mint, from, to)` range, ordered by mint and time,
+    /// limited to `sources` unless it is empty
+    async fn prices_in(&self, ranges: &[PriceRange], sources: &[String]) -> Result<Vec<PriceHistory>>;
+}
+
+#[async_trait]
+impl PriceHistoryStore for Database {
+    async fn prices_in(&self, ranges: &[PriceRange], sources: &[String]) -> Result<Vec<PriceHistory>> {
+        self.get_price_history(ranges, sources)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to load prices: {}", e)))
+    }
+}
+
+/// How a price at a point in time is derived from the stored prices
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PriceMode {
+    /// Latest price at or before the requested time
+    #[default]
+    NearestBefore,
+    /// Linear interpolation between the prices around the requested time,
+    /// the latest price before it when there is none after
+    Linear,
+    /// Time-weighted average over the window ending at the requested time
+    Twap(Duration),
+}
+
+/// Price of a mint at a requested time
+#[derive(Debug, Clone, PartialEq)]
+pub struct PricePoint {
+    pub mint: String,
+    /// Requested time
+    pub at: DateTime<Utc>,
+    pub price_usd: UsdValue,
+    /// Source of the stored price nearest to `at` among those used
+    pub source: String,
+    /// Time of that stored price
+    pub observed_at: DateTime<Utc>,
+    /// Distance between `at` and `observed_at`
+    pub staleness: Duration,
+}
+
+/// Open, high, low and close price and USD volume of one interval
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub open: UsdValue,
+    pub high: UsdValue,
+    pub low: UsdValue,
+    pub close: UsdValue,
+    /// Sum of the volumes reported with the prices, zero if none were
+    pub volume_usd: UsdValue,
+    /// Stored prices in the interval
+    pub samples: usize,
+}
+
+/// Looks up prices at arbitrary times from `price_history`.
+///
+/// A price is only returned when a stored price lies within the maximum
+/// staleness of the requested time. Lookups can be restricted to some
+/// sources, which is advisable for interpolation and candles since sources
+/// are not mixed on purpose.
+pub struct PriceLookup {
+    store: Arc<dyn PriceHistoryStore>,
+    mode: PriceMode,
+    max_staleness: Duration,
+    sources: Vec<String>,
+}
+
+impl PriceLookup {
+    pub fn new(store: Arc<dyn PriceHistoryStore>) -> Self {
+        Self {
+            store,
+            mode: PriceMode::default(),
+            max_staleness: Duration::seconds(DEFAULT_MAX_STALENESS),
+            sources: Vec::new(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: PriceMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_max_staleness(mut self, max_staleness: Duration) -> Self {
+        self.max_staleness = max_staleness;
+        self
+    }
+
+    /// Only use prices of `sources`
+    pub fn with_sources(mut self, sources: Vec<String>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// How far before and after a requested time stored prices are used
+    pub fn window(&self) -> (Duration, Duration) {
+        match self.mode {
+            PriceMode::NearestBefore => (self.max_staleness, Duration::zero()),
+            PriceMode::Linear => (self.max_staleness, self.max_staleness),
+            PriceMode::Twap(window) => (window + self.max_staleness, Duration::zero()),
+        }
+    }
+
+    /// Price of `mint` at `at`, `None` without a fresh enough stored price
+    pub async fn price_at(&self, mint: &str, at: DateTime<Utc>) -> Result<Option<PricePoint>> {
+        Ok(self.prices_at(&[(mint.to_string(), at)]).await?.pop().flatten())
+    }
+
+    /// Prices of every `(mint, at)` request, in request order, loaded with a
+    /// single query
+    pub async fn prices_at(&self, requests: &[(String, DateTime<Utc>)]) -> Result<Vec<Option<PricePoint>>> {
+        if requests.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (lookback, lookahead) = self.window();
+        let mut spans: BTreeMap<&str, (DateTime<Utc>, DateTime<Utc>)> = BTreeMap::new();
+        for (mint, at) in requests {
+            let span = spans.entry(mint).or_insert((*at, *at));
+            span.0 = span.0.min(*at);
+            span.1 = span.1.max(*at);
+        }
+        let ranges: Vec<PriceRange> = spans
+            .into_iter()
+            .map(|(mint, (first, last))| (mint.to_string(), first - lookback, last + lookahead))
+            .collect();
+
+        let rows = self.store.prices_in(&ranges, &self.sources).await?;
+        let mut prices: HashMap<&str, Vec<&PriceHistory>> = HashMap::new();
+        for row in &rows {
+            prices.entry(&row.token_mint).or_default().push(row);
+        }
+        for rows in prices.values_mut() {
+            rows.sort_by_key(|row| row.timestamp);
+        }
+        Ok(requests
+            .iter()
+            .map(|(mint, at)| self.resolve(prices.get(mint.as_str())?, mint, *at))
+            .collect())
+    }
+
+    /// Candles of `mint` per `interval` over `range`, starting at its start.
+    /// Intervals without stored prices are left out.
+    pub async fn candles(
+        &self,
+        mint: &str,
+        range: RangeInclusive<DateTime<Utc>>,
+        interval: Duration,
+    ) -> Result<Vec<Candle>> {
+        let step = interval.num_milliseconds();
+        if step <= 0 {
+            return Err(Error::validation("Candle interval must be at least a millisecond"));
+        }
+        let (start, end) = (*range.start(), *range.end());
+        let mut rows = self.store.prices_in(&[(mint.to_string(), start, end)], &self.sources).await?;
+        rows.sort_by_key(|row| row.timestamp);
+
+        let mut candles: Vec<(i64, Candle)> = Vec::new();
+        for row in rows.iter().filter(|row| row.token_mint == mint) {
+            let index = (row.timestamp - start).num_milliseconds().div_euclid(step);
+            let volume = row.volume_usd.unwrap_or_default();
+            match candles.last_mut() {
+                Some((last, candle)) if *last == index => {
+                    candle.high = candle.high.max(row.price_usd);
+                    candle.low = candle.low.min(row.price_usd);
+                    candle.close = row.price_usd;
+                    candle.volume_usd += volume;
+                    candle.samples += 1;
+                }
+                _ => {
+                    let candle_start = start + Duration::milliseconds(step * index);
+                    candles.push((
+                        index,
+                        Candle {
+                            start: candle_start,
+                            end: candle_start + interval,
+                            open: row.price_usd,
+                            high: row.price_usd,
+                            low: row.price_usd,
+                            close: row.price_usd,
+                            volume_usd: volume,
+                            samples: 1,
+                        },
+                    ));
+                }
+            }
+        }
+        Ok(candles.into_iter().map(|(_, candle)| candle).collect())
+    }
+
+    /// Price at `at` from `prices` of `mint`, sorted by time
+    fn resolve(&self, prices: &[&PriceHistory], mint: &str, at: DateTime<Utc>) -> Option<PricePoint> {
+        let split = prices.partition_point(|price| price.timestamp <= at);
+        let before = split
+            .checked_sub(1)
+            .map(|index| prices[index])
+            .filter(|price| at - price.timestamp <= self.max_staleness);
+        let point = |price_usd: UsdValue, observed: &PriceHistory| PricePoint {
+            mint: mint.to_string(),
+            at,
+            price_usd,
+            source: observed.source.clone(),
+            observed_at: observed.timestamp,
+            staleness: (at - observed.timestamp).abs(),
+        };
+
+        match self.mode {
+            PriceMode::NearestBefore => before.map(|price| point(price.price_usd, price)),
+            PriceMode::Linear => {
+                let after = prices
+                    .get(split)
+                    .filter(|price| price.timestamp - at <= self.max_staleness);
+                match (before, after) {
+                    (Some(before), Some(after)) => {
+                        let span = Decimal::from((after.timestamp - before.timestamp).num_milliseconds());
+                        let weight = Decimal::from((at - before.timestamp).num_milliseconds()) / span;
+                        let price = before.price_usd + (after.price_usd - before.price_usd) * weight;
+                        let nearest = if weight <= Decimal::new(5, 1) { before } else { after };
+                        Some(point(price, nearest))
+                    }
+                    (Some(before), None) => Some(point(before.price_usd, before)),
+                    _ => None,
+                }
+            }
+            PriceMode::Twap(window) => {
+                let last = before?;
+                let start = at - window;
+                // The price in effect at the window start, if stored, opens it
+                let first = prices[..split].partition_point(|price| price.timestamp <= start);
+                let window_prices = &prices[first.saturating_sub(1)..split];
+                let mut time = window_prices[0].timestamp.max(start);
+                let mut level = window_prices[0].price_usd;
+                let (mut weighted, mut total) = (UsdValue::ZERO, Decimal::ZERO);
+                for price in &window_prices[1..] {
+                    let span = Decimal::from((price.timestamp - time).num_milliseconds());
+                    weighted += level * span;
+                    total += span;
+                    time = price.timestamp;
+                    level = price.price_usd;
+                }
+                let span = Decimal::from((at - time).num_milliseconds());
+                weighted += level * span;
+                total += span;
+                let price = if total > Decimal::ZERO { weighted / total } else { level };
+                Some(point(price, last))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::test_utils::{at_minute, price, usd};
+
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    /// An aggregate price of `mint` at `minute` with its volume
+    fn aggregate(mint: &str, minute: i64, price_usd: &str, volume: Option<&str>) -> PriceHistory {
+        let mut price = price(mint, at_minute(minute), price_usd, "aggregate");
+        price.volume_usd = volume.map(usd);
+        price
+    }
+
+    /// SOL at 100 from minute 0, 110 from minute 10 and 90 from minute 20
+    fn lookup(mode: PriceMode) -> PriceLookup {
+        let rows = [
+            aggregate(SOL, 0, "100", Some("5")),
+            aggregate(SOL, 10, "110", Some("1")),
+            aggregate(SOL, 20, "90", None),
+            aggregate(BONK, 5, "0.00002", None),
+        ];
+        let mut store = MockPriceHistoryStore::new();
+        store
+            .expect_prices_in()
+            .times(1)
+            .returning(move |ranges, _| {
+                Ok(rows
+                    .iter()
+                    .filter(|row| {
+                        ranges
+                            .iter()
+                            .any(|(mint, from, to)| row.token_mint == *mint && row.timestamp >= *from && row.timestamp <= *to)
+                    })
+                    .cloned()
+                    .collect())
+            });
+        PriceLookup::new(Arc::new(store))
+            .with_mode(mode)
+            .with_max_staleness(Duration::minutes(15))
+    }
+
+    #[tokio::test]
+    async fn test_price_modes_and_staleness() {
+        let nearest = lookup(PriceMode::NearestBefore).price_at(SOL, at_minute(14)).await.unwrap().unwrap();
+        assert_eq!((nearest.price_usd, nearest.observed_at), (usd("110"), at_minute(10)));
+        assert_eq!(nearest.staleness, Duration::minutes(4));
+        assert_eq!(nearest.source, "aggregate");
+        assert_eq!(lookup(PriceMode::NearestBefore).price_at(SOL, at_minute(40)).await.unwrap(), None);
+
+        let linear = lookup(PriceMode::Linear).price_at(SOL, at_minute(14)).await.unwrap().unwrap();
+        assert_eq!(linear.price_usd, usd("102"));
+        assert_eq!(linear.observed_at, at_minute(10));
+        // Past the last price it holds the latest one
+        let held = lookup(PriceMode::Linear).price_at(SOL, at_minute(25)).await.unwrap().unwrap();
+        assert_eq!(held.price_usd, usd("90"));
+
+        // 5 minutes at 100, 10 at 110, 5 at 90
+        let twap = lookup(PriceMode::Twap(Duration::minutes(20)))
+            .price_at(SOL, at_minute(25))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(twap.price_usd, usd("102.5"));
+        assert_eq!(twap.observed_at, at_minute(20));
+    }
+
+    #[tokio::test]
+    async fn test_batch_lookup_uses_one_query() {
+        let requests = vec![
+            (SOL.to_string(), at_minute(5)),
+            (BONK.to_string(), at_minute(6)),
+            (SOL.to_string(), at_minute(21)),
+            ("unknown".to_string(), at_minute(5)),
+        ];
+        let points = lookup(PriceMode::NearestBefore).prices_at(&requests).await.unwrap();
+        let prices: Vec<_> = points.iter().map(|point| point.as_ref().map(|point| point.price_usd)).collect();
+        assert_eq!(prices, vec![Some(usd("100")), Some(usd("0.00002")), Some(usd("90")), None]);
+    }
+
+    #[tokio::test]
+    async fn test_candles() {
+        let candles = lookup(PriceMode::NearestBefore)
+            .candles(SOL, at_minute(0)..=at_minute(30), Duration::minutes(15))
+            .await
+            .unwrap();
+        assert_eq!(
+            candles,
+            vec![
+                Candle {
+                    start: at_minute(0),
+                    end: at_minute(15),
+                    open: usd("100"),
+                    high: usd("110"),
+                    low: usd("100"),
+                    close: usd("110"),
+                    volume_usd: usd("6"),
+                    samples: 2,
+                },
+                Candle {
+                    start: at_minute(15),
+                    end: at_minute(30),
+                    open: usd("90"),
+                    high: usd("90"),
+                    low: usd("90"),
+                    close: usd("90"),
+                    volume_usd: UsdValue::ZERO,
+                    samples: 1,
+                },
+            ]
+        );
+    }
+}
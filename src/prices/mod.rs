@@ -0,0 +1,29 @@
+//! Token prices
+//!
+//! A [`PriceProvider`] reports spot and historical USD prices of a mint. A
+//! [`PriceAggregator`] queries several providers concurrently, rejects
+//! outlying quotes, combines the rest by median or volume and persists the
+//! result in `price_history` through a [`PriceStore`]. A [`DexPriceProvider`]
+//! derives prices of long-tail tokens from parsed swaps, an
+//! [`OraclePriceProvider`] reads Pyth and Switchboard accounts. A
+//! [`PriceLookup`] answers point-in-time and candle queries from the stored
+//! prices, which a [`UsdValuer`] uses to value transfers, swaps and balance
+//! changes at block time.
+
+pub mod aggregator;
+pub mod dex;
+pub mod http;
+pub mod lookup;
+pub mod oracle;
+pub mod provider;
+#[cfg(test)]
+mod test_utils;
+pub mod valuation;
+
+pub use aggregator::{AggregatedPrice, AggregationMethod, PriceAggregator, AGGREGATE_SOURCE};
+pub use dex::{DexPriceProvider, SwapStore, SwapTrade, DEX_VWAP_SOURCE};
+pub use http::{CoinGeckoProvider, JupiterProvider};
+pub use lookup::{Candle, PriceHistoryStore, PriceLookup, PriceMode, PricePoint};
+pub use oracle::{Oracle, OraclePrice, OraclePriceProvider, OracleStore};
+pub use provider::{PriceProvider, PriceQuote, PriceStore};
+pub use valuation::{usd_valuations, RevaluingPriceStore, UsdValuer, ValuationStore};
@@ -0,0 +1,638 @@
+//! Prices read from Pyth and Switchboard oracle accounts
+
+use crate::core::error::{Error, Result};
+use crate::core::metrics::ORACLE_REJECTED_PRICES_TOTAL;
+use crate::db::Database;
+use crate::models::amount::Decimal;
+use crate::models::{OracleUpdate, UsdValue};
+use crate::parsers::anchor;
+use crate::prices::provider::{PriceProvider, PriceQuote, PriceStore};
+use crate::rpc::client::AccountClientTrait;
+use async_trait::async_trait;
+use borsh::BorshDeserialize;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use metrics::counter;
+use rust_decimal::prelude::ToPrimitive;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Magic number of legacy Pyth accounts
+const PYTH_MAGIC: u32 = 0xa1b2_c3d4;
+/// Account type of legacy Pyth price accounts
+const PYTH_PRICE_ACCOUNT: u32 = 3;
+/// Aggregate status of a legacy Pyth price that is trading
+const PYTH_STATUS_TRADING: u32 = 1;
+
+/// Offsets into a legacy Pyth price account
+const PYTH_EXPONENT: usize = 20;
+const PYTH_TIMESTAMP: usize = 96;
+const PYTH_AGG_PRICE: usize = 208;
+const PYTH_AGG_CONF: usize = 216;
+const PYTH_AGG_STATUS: usize = 224;
+const PYTH_AGG_PUB_SLOT: usize = 232;
+
+/// Offsets into the latest confirmed round of a Switchboard V2 aggregator
+const SWITCHBOARD_NUM_SUCCESS: usize = 341;
+const SWITCHBOARD_ROUND_SLOT: usize = 350;
+const SWITCHBOARD_ROUND_TIMESTAMP: usize = 358;
+const SWITCHBOARD_RESULT: usize = 366;
+const SWITCHBOARD_STD_DEVIATION: usize = 386;
+
+/// Default oldest price accepted, relative to the requested time
+const DEFAULT_MAX_AGE: i64 = 60;
+/// Default largest confidence interval accepted, relative to the price
+const DEFAULT_MAX_CONFIDENCE: f64 = 0.02;
+
+/// Oracle program an account belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Oracle {
+    Pyth,
+    Switchboard,
+}
+
+impl Oracle {
+    /// Name recorded as the price source
+    pub fn name(&self) -> &'static str {
+        match self {
+            Oracle::Pyth => "pyth",
+            Oracle::Switchboard => "switchboard",
+        }
+    }
+}
+
+/// `PriceUpdateV2` account of the Pyth receiver program
+#[derive(BorshDeserialize)]
+struct PriceUpdateV2 {
+    _write_authority: [u8; 32],
+    verification_level: VerificationLevel,
+    message: PriceFeedMessage,
+    posted_slot: u64,
+}
+
+#[derive(BorshDeserialize, PartialEq, Eq)]
+enum VerificationLevel {
+    Partial { _num_signatures: u8 },
+    Full,
+}
+
+#[derive(BorshDeserialize)]
+struct PriceFeedMessage {
+    _feed_id: [u8; 32],
+    price: i64,
+    conf: u64,
+    exponent: i32,
+    publish_time: i64,
+    _prev_publish_time: i64,
+    _ema_price: i64,
+    _ema_conf: u64,
+}
+
+/// A price decoded from an oracle account, as `price * 10^exponent` with the
+/// confidence interval in the same units
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub oracle: Oracle,
+    pub price: i128,
+    pub confidence: u128,
+    pub exponent: i32,
+    pub publish_time: DateTime<Utc>,
+    /// Slot the price was published at
+    pub slot: u64,
+}
+
+impl OraclePrice {
+    /// Decode a legacy Pyth price account, a Pyth `PriceUpdateV2` account or
+    /// a Switchboard V2 aggregator. `None` for other accounts, legacy Pyth
+    /// prices that are not trading, partially verified Pyth updates and
+    /// aggregators without a confirmed round.
+    ///
+    /// `PriceUpdateV2` accounts do not record the publish slot; the slot they
+    /// were posted at is used instead.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() >= 8 && data[..8] == anchor::discriminator("account", "PriceUpdateV2") {
+            return Self::decode_price_update(data);
+        }
+        if data.len() >= 8 && data[..8] == anchor::discriminator("account", "AggregatorAccountData") {
+            return Self::decode_switchboard(data);
+        }
+        Self::decode_pyth(data)
+    }
+
+    fn decode_pyth(data: &[u8]) -> Option<Self> {
+        if read::<4>(data, 0).map(u32::from_le_bytes)? != PYTH_MAGIC
+            || read::<4>(data, 8).map(u32::from_le_bytes)? != PYTH_PRICE_ACCOUNT
+            || read::<4>(data, PYTH_AGG_STATUS).map(u32::from_le_bytes)? != PYTH_STATUS_TRADING
+        {
+            return None;
+        }
+        Some(Self {
+            oracle: Oracle::Pyth,
+            price: read::<8>(data, PYTH_AGG_PRICE).map(i64::from_le_bytes)? as i128,
+            confidence: read::<8>(data, PYTH_AGG_CONF).map(u64::from_le_bytes)? as u128,
+            exponent: read::<4>(data, PYTH_EXPONENT).map(i32::from_le_bytes)?,
+            publish_time: timestamp(read::<8>(data, PYTH_TIMESTAMP).map(i64::from_le_bytes)?)?,
+            slot: read::<8>(data, PYTH_AGG_PUB_SLOT).map(u64::from_le_bytes)?,
+        })
+    }
+
+    fn decode_price_update(data: &[u8]) -> Option<Self> {
+        let update = PriceUpdateV2::deserialize(&mut &data[8..]).ok()?;
+        if update.verification_level != VerificationLevel::Full {
+            return None;
+        }
+        let message = update.message;
+        Some(Self {
+            oracle: Oracle::Pyth,
+            price: message.price as i128,
+            confidence: message.conf as u128,
+            exponent: message.exponent,
+            publish_time: timestamp(message.publish_time)?,
+            slot: update.posted_slot,
+        })
+    }
+
+    fn decode_switchboard(data: &[u8]) -> Option<Self> {
+        if read::<4>(data, SWITCHBOARD_NUM_SUCCESS).map(u32::from_le_bytes)? == 0 {
+            return None;
+        }
+        let (price, scale) = decimal(data, SWITCHBOARD_RESULT)?;
+        let (deviation, deviation_scale) = decimal(data, SWITCHBOARD_STD_DEVIATION)?;
+        // Express the deviation in the units of the result
+        let shift = scale as i64 - deviation_scale as i64;
+        let factor = 10u128.checked_pow(shift.unsigned_abs() as u32)?;
+        let deviation = deviation.unsigned_abs();
+        let confidence = if shift >= 0 {
+            deviation.checked_mul(factor)?
+        } else {
+            deviation / factor
+        };
+        Some(Self {
+            oracle: Oracle::Switchboard,
+            price,
+            confidence,
+            exponent: -(i32::try_from(scale).ok()?),
+            publish_time: timestamp(read::<8>(data, SWITCHBOARD_ROUND_TIMESTAMP).map(i64::from_le_bytes)?)?,
+            slot: read::<8>(data, SWITCHBOARD_ROUND_SLOT).map(u64::from_le_bytes)?,
+        })
+    }
+
+    /// Exact price, `None` if it does not fit a decimal
+    pub fn price_usd(&self) -> Option<UsdValue> {
+        scaled(self.price, self.exponent)
+    }
+
+    /// Exact confidence interval, `None` if it does not fit a decimal
+    pub fn confidence_usd(&self) -> Option<UsdValue> {
+        scaled(i128::try_from(self.confidence).ok()?, self.exponent)
+    }
+}
+
+/// `mantissa * 10^exponent`
+fn scaled(mantissa: i128, exponent: i32) -> Option<UsdValue> {
+    if exponent <= 0 {
+        return Decimal::try_from_i128_with_scale(mantissa, exponent.unsigned_abs()).ok().map(UsdValue::new);
+    }
+    let mut value = Decimal::try_from_i128_with_scale(mantissa, 0).ok()?;
+    for _ in 0..exponent {
+        value = value.checked_mul(Decimal::TEN)?;
+    }
+    Some(UsdValue::new(value))
+}
+
+fn read<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    data.get(offset..offset + N)?.try_into().ok()
+}
+
+fn timestamp(seconds: i64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(seconds, 0).single()
+}
+
+/// Switchboard decimal: an `i128` mantissa and a `u32` scale
+fn decimal(data: &[u8], offset: usize) -> Option<(i128, u32)> {
+    Some((
+        read::<16>(data, offset).map(i128::from_le_bytes)?,
+        read::<4>(data, offset + 16).map(u32::from_le_bytes)?,
+    ))
+}
+
+/// Storage of raw oracle account updates
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait OracleStore: Send + Sync {
+    async fn save_updates(&self, updates: &[OracleUpdate]) -> Result<()>;
+
+    /// Latest update of `account` published at or before `at`
+    async fn latest_update(&self, account: &str, at: DateTime<Utc>) -> Result<Option<OracleUpdate>>;
+
+    /// Updates of `account` published between `from` and `to`, oldest first
+    async fn updates(&self, account: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<OracleUpdate>>;
+}
+
+#[async_trait]
+impl OracleStore for Database {
+    async fn save_updates(&self, updates: &[OracleUpdate]) -> Result<()> {
+        self.insert_oracle_updates(updates)
+            .await
+            .map(|_| ())
+            .map_err(|e| Error::pipeline(format!("Failed to persist oracle updates: {}", e)))
+    }
+
+    async fn latest_update(&self, account: &str, at: DateTime<Utc>) -> Result<Option<OracleUpdate>> {
+        self.latest_oracle_update(account, at)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to load oracle update: {}", e)))
+    }
+
+    async fn updates(&self, account: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<OracleUpdate>> {
+        self.get_oracle_updates(account, from, to)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to load oracle updates: {}", e)))
+    }
+}
+
+/// Price source reading Pyth and Switchboard accounts directly.
+///
+/// Each mint is priced from one oracle account. Prices published longer
+/// than the maximum age before the requested time, or with a confidence
+/// interval wider than the maximum share of the price, are rejected. With an
+/// [`OracleStore`], the raw account data read by [`poll`](Self::poll) is kept
+/// per slot, so historical prices are decoded from the latest stored update.
+/// Quotes carry the oracle as source and the account, slot and confidence as
+/// provenance.
+pub struct OraclePriceProvider {
+    client: Arc<dyn AccountClientTrait>,
+    feeds: BTreeMap<String, Pubkey>,
+    store: Option<Arc<dyn OracleStore>>,
+    prices: Option<Arc<dyn PriceStore>>,
+    max_age: Duration,
+    max_confidence: f64,
+}
+
+impl OraclePriceProvider {
+    pub fn new(client: Arc<dyn AccountClientTrait>) -> Self {
+        Self {
+            client,
+            feeds: BTreeMap::new(),
+            store: None,
+            prices: None,
+            max_age: Duration::seconds(DEFAULT_MAX_AGE),
+            max_confidence: DEFAULT_MAX_CONFIDENCE,
+        }
+    }
+
+    /// Price `mint` from the oracle account `feed`
+    pub fn with_feed(mut self, mint: impl Into<String>, feed: Pubkey) -> Self {
+        self.feeds.insert(mint.into(), feed);
+        self
+    }
+
+    pub fn with_store(mut self, store: Arc<dyn OracleStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Persist prices read by [`poll`](Self::poll) and
+    /// [`reconstruct`](Self::reconstruct) in `store`
+    pub fn with_price_store(mut self, store: Arc<dyn PriceStore>) -> Self {
+        self.prices = Some(store);
+        self
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Largest confidence interval relative to the price, e.g. `0.01` for 1%
+    pub fn with_max_confidence(mut self, max_confidence: f64) -> Self {
+        self.max_confidence = max_confidence;
+        self
+    }
+
+    /// Read every feed with `getMultipleAccounts`, store the raw updates and
+    /// return the prices that pass the checks. Rejected prices are logged.
+    pub async fn poll(&self) -> Result<Vec<PriceQuote>> {
+        let feeds: Vec<Pubkey> = self.feeds.values().copied().collect();
+        let accounts = self
+            .client
+            .get_multiple_accounts(&feeds)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to fetch oracle accounts: {}", e)))?;
+        let now = Utc::now();
+        let mut updates = Vec::new();
+        let mut quotes = Vec::new();
+        for ((mint, feed), account) in self.feeds.iter().zip(accounts) {
+            let Some(account) = account else {
+                warn!("Oracle account {} of {} does not exist", feed, mint);
+                continue;
+            };
+            let Some(price) = OraclePrice::decode(&account.data) else {
+                warn!("Oracle account {} of {} could not be decoded", feed, mint);
+                continue;
+            };
+            updates.push(OracleUpdate {
+                account: feed.to_string(),
+                oracle: price.oracle.name().to_string(),
+                slot: price.slot as i64,
+                publish_time: price.publish_time,
+                data: account.data,
+                created_at: now,
+            });
+            match self.check(mint, feed, &price, now) {
+                Ok(quote) => quotes.push(quote),
+                Err(e) => warn!("{}", e),
+            }
+        }
+        if let Some(store) = &self.store {
+            store.save_updates(&updates).await?;
+        }
+        self.save(&quotes).await?;
+        Ok(quotes)
+    }
+
+    /// Decode the stored updates of `mint`'s feed over `range` into prices,
+    /// skipping low-confidence ones
+    pub async fn reconstruct(&self, mint: &str, range: RangeInclusive<DateTime<Utc>>) -> Result<Vec<PriceQuote>> {
+        let (Some(feed), Some(store)) = (self.feeds.get(mint), &self.store) else {
+            return Ok(Vec::new());
+        };
+        let updates = store.updates(&feed.to_string(), *range.start(), *range.end()).await?;
+        let quotes: Vec<PriceQuote> = updates
+            .iter()
+            .filter_map(|update| OraclePrice::decode(&update.data))
+            .filter_map(|price| self.check(mint, feed, &price, price.publish_time).ok())
+            .collect();
+        self.save(&quotes).await?;
+        Ok(quotes)
+    }
+
+    async fn save(&self, quotes: &[PriceQuote]) -> Result<()> {
+        match &self.prices {
+            Some(store) if !quotes.is_empty() => {
+                let rows: Vec<_> = quotes.iter().map(PriceQuote::to_history).collect();
+                store.save_prices(&rows).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Quote of `price` if it is fresh at `at` and confident enough
+    fn check(&self, mint: &str, feed: &Pubkey, price: &OraclePrice, at: DateTime<Utc>) -> Result<PriceQuote> {
+        let oracle = price.oracle.name();
+        let reject = |reason: &'static str, message: String| {
+            counter!(ORACLE_REJECTED_PRICES_TOTAL, 1, "oracle" => oracle, "reason" => reason);
+            Err(Error::validation(message))
+        };
+        if at - price.publish_time > self.max_age {
+            return reject(
+                "stale",
+                format!("Stale {} price of {}: published {}, wanted {}", oracle, mint, price.publish_time, at),
+            );
+        }
+        let (Some(value), Some(confidence)) = (price.price_usd(), price.confidence_usd()) else {
+            return reject(
+                "range",
+                format!("Unrepresentable {} price of {}: {}e{}", oracle, mint, price.price, price.exponent),
+            );
+        };
+        let relative = confidence.checked_ratio(value).and_then(|ratio| ratio.to_f64());
+        if !value.is_positive() || relative.is_none_or(|ratio| ratio > self.max_confidence) {
+            return reject(
+                "confidence",
+                format!("Low-confidence {} price of {}: {} ± {}", oracle, mint, value, confidence),
+            );
+        }
+        Ok(PriceQuote::new(mint, value, price.publish_time, oracle).with_provenance(
+            feed.to_string(),
+            price.slot,
+            confidence,
+        ))
+    }
+}
+
+#[async_trait]
+impl PriceProvider for OraclePriceProvider {
+    fn name(&self) -> &str {
+        "oracle"
+    }
+
+    async fn spot_price(&self, mint: &str) -> Result<Option<PriceQuote>> {
+        let Some(feed) = self.feeds.get(mint) else {
+            return Ok(None);
+        };
+        let account = self
+            .client
+            .get_account(feed)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to fetch oracle account {}: {}", feed, e)))?;
+        let Some(account) = account else {
+            return Ok(None);
+        };
+        let price = OraclePrice::decode(&account.data)
+            .ok_or_else(|| Error::validation(format!("Undecodable oracle account {}", feed)))?;
+        self.check(mint, feed, &price, Utc::now()).map(Some)
+    }
+
+    async fn historical_price(&self, mint: &str, at: DateTime<Utc>) -> Result<Option<PriceQuote>> {
+        let (Some(feed), Some(store)) = (self.feeds.get(mint), &self.store) else {
+            return Ok(None);
+        };
+        let Some(update) = store.latest_update(&feed.to_string(), at).await? else {
+            return Ok(None);
+        };
+        let price = OraclePrice::decode(&update.data)
+            .ok_or_else(|| Error::validation(format!("Undecodable oracle update {} at {}", feed, update.slot)))?;
+        self.check(mint, feed, &price, at).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::provider::MockPriceStore;
+    use crate::rpc::client::MockAccountClientTrait;
+    use borsh::BorshSerialize;
+    use solana_sdk::account::Account;
+
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+
+    /// Legacy Pyth price account publishing `price * 10^-8` at `time`
+    fn pyth(price: i64, conf: u64, time: i64, status: u32) -> Vec<u8> {
+        let mut data = vec![0u8; 3312];
+        let mut put = |offset: usize, bytes: &[u8]| data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        put(0, &PYTH_MAGIC.to_le_bytes());
+        put(4, &2u32.to_le_bytes());
+        put(8, &PYTH_PRICE_ACCOUNT.to_le_bytes());
+        put(PYTH_EXPONENT, &(-8i32).to_le_bytes());
+        put(PYTH_TIMESTAMP, &time.to_le_bytes());
+        put(PYTH_AGG_PRICE, &price.to_le_bytes());
+        put(PYTH_AGG_CONF, &conf.to_le_bytes());
+        put(PYTH_AGG_STATUS, &status.to_le_bytes());
+        put(PYTH_AGG_PUB_SLOT, &250_000_000u64.to_le_bytes());
+        data
+    }
+
+    fn price_update(price: i64, conf: u64, time: i64, full: bool) -> Vec<u8> {
+        let mut data = anchor::discriminator("account", "PriceUpdateV2").to_vec();
+        data.extend([7; 32]);
+        if full {
+            data.push(1);
+        } else {
+            data.extend([0, 3]);
+        }
+        data.extend([1; 32]);
+        (price, conf, -8i32, time, time - 1, price, conf).serialize(&mut data).unwrap();
+        data.extend(260_000_000u64.to_le_bytes());
+        data
+    }
+
+    fn switchboard(mantissa: i128, scale: u32, deviation: i128, deviation_scale: u32, time: i64) -> Vec<u8> {
+        let mut data = vec![0u8; 3851];
+        data[..8].copy_from_slice(&anchor::discriminator("account", "AggregatorAccountData"));
+        let mut put = |offset: usize, bytes: &[u8]| data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        put(SWITCHBOARD_NUM_SUCCESS, &3u32.to_le_bytes());
+        put(SWITCHBOARD_ROUND_SLOT, &270_000_000u64.to_le_bytes());
+        put(SWITCHBOARD_ROUND_TIMESTAMP, &time.to_le_bytes());
+        put(SWITCHBOARD_RESULT, &mantissa.to_le_bytes());
+        put(SWITCHBOARD_RESULT + 16, &scale.to_le_bytes());
+        put(SWITCHBOARD_STD_DEVIATION, &deviation.to_le_bytes());
+        put(SWITCHBOARD_STD_DEVIATION + 16, &deviation_scale.to_le_bytes());
+        data
+    }
+
+    fn account(data: Vec<u8>) -> Account {
+        Account {
+            lamports: 1,
+            data,
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn test_decode_oracle_accounts() {
+        let legacy = OraclePrice::decode(&pyth(15_012_345_678, 7_000_000, 1_700_000_000, 1)).unwrap();
+        assert_eq!(
+            legacy,
+            OraclePrice {
+                oracle: Oracle::Pyth,
+                price: 15_012_345_678,
+                confidence: 7_000_000,
+                exponent: -8,
+                publish_time: timestamp(1_700_000_000).unwrap(),
+                slot: 250_000_000,
+            }
+        );
+        assert_eq!(legacy.price_usd(), Some("150.12345678".parse().unwrap()));
+        assert_eq!(OraclePrice::decode(&pyth(1, 1, 1_700_000_000, 2)), None);
+
+        let pull = OraclePrice::decode(&price_update(15_000_000_000, 1, 1_700_000_100, true)).unwrap();
+        assert_eq!((pull.price, pull.exponent, pull.slot), (15_000_000_000, -8, 260_000_000));
+        assert_eq!(OraclePrice::decode(&price_update(1, 1, 1, false)), None);
+
+        // 150.25 with a deviation of 0.05
+        let aggregator = OraclePrice::decode(&switchboard(15_025, 2, 5, 2, 1_700_000_200)).unwrap();
+        assert_eq!(aggregator.oracle, Oracle::Switchboard);
+        assert_eq!((aggregator.price, aggregator.confidence, aggregator.exponent), (15_025, 5, -2));
+        let rescaled = OraclePrice::decode(&switchboard(15_025, 2, 50, 3, 1_700_000_200)).unwrap();
+        assert_eq!(rescaled.confidence, 5);
+
+        assert_eq!(OraclePrice::decode(&[0; 64]), None);
+    }
+
+    #[tokio::test]
+    async fn test_spot_price_rejects_stale_and_uncertain_prices() {
+        let (fresh, stale, wide) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let now = Utc::now().timestamp();
+        let mut client = MockAccountClientTrait::new();
+        client.expect_get_account().returning(move |key| {
+            Ok(Some(account(if *key == fresh {
+                switchboard(15_025, 2, 5, 2, now - 5)
+            } else if *key == stale {
+                pyth(15_000_000_000, 1_000_000, now - 600, 1)
+            } else {
+                pyth(15_000_000_000, 900_000_000, now, 1)
+            })))
+        });
+        let provider = OraclePriceProvider::new(Arc::new(client))
+            .with_feed(SOL, fresh)
+            .with_feed("stale", stale)
+            .with_feed("wide", wide);
+
+        let quote = provider.spot_price(SOL).await.unwrap().unwrap();
+        assert_eq!(quote.source, "switchboard");
+        assert_eq!(quote.price_usd.to_string(), "150.25");
+        assert_eq!(quote.feed, Some(fresh.to_string()));
+        assert_eq!(quote.slot, Some(270_000_000));
+        assert_eq!(quote.confidence.unwrap().to_string(), "0.05");
+
+        let error = provider.spot_price("stale").await.unwrap_err();
+        assert!(error.to_string().contains("Stale pyth price"));
+        let error = provider.spot_price("wide").await.unwrap_err();
+        assert!(error.to_string().contains("Low-confidence"));
+        assert_eq!(provider.spot_price("unknown").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_poll_records_updates_for_historical_prices() {
+        let feed = Pubkey::new_unique();
+        let now = Utc::now().timestamp();
+        let mut client = MockAccountClientTrait::new();
+        client
+            .expect_get_multiple_accounts()
+            .returning(move |_| Ok(vec![Some(account(pyth(15_000_000_000, 1_000_000, now, 1)))]));
+
+        let mut store = MockOracleStore::new();
+        store
+            .expect_save_updates()
+            .withf(move |updates| {
+                updates.len() == 1
+                    && updates[0].account == feed.to_string()
+                    && updates[0].oracle == "pyth"
+                    && updates[0].slot == 250_000_000
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let stored = OracleUpdate {
+            account: feed.to_string(),
+            oracle: "pyth".to_string(),
+            slot: 240_000_000,
+            publish_time: timestamp(1_700_000_000).unwrap(),
+            data: pyth(14_000_000_000, 1_000_000, 1_700_000_000, 1),
+            created_at: Utc::now(),
+        };
+        store
+            .expect_latest_update()
+            .returning(move |_, _| Ok(Some(stored.clone())));
+        let mut prices = MockPriceStore::new();
+        prices
+            .expect_save_prices()
+            .withf(move |rows| {
+                rows.len() == 1
+                    && rows[0].source == "pyth"
+                    && rows[0].feed == Some(feed.to_string())
+                    && rows[0].slot == Some(250_000_000)
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let provider = OraclePriceProvider::new(Arc::new(client))
+            .with_feed(SOL, feed)
+            .with_store(Arc::new(store))
+            .with_price_store(Arc::new(prices));
+        let quotes = provider.poll().await.unwrap();
+        assert_eq!(quotes[0].price_usd, UsdValue::from(150));
+
+        let at = timestamp(1_700_000_030).unwrap();
+        let historical = provider.historical_price(SOL, at).await.unwrap().unwrap();
+        assert_eq!(historical.price_usd, UsdValue::from(140));
+        assert_eq!(historical.timestamp, timestamp(1_700_000_000).unwrap());
+        // Too long after the last stored update
+        let later = timestamp(1_700_001_000).unwrap();
+        assert!(provider.historical_price(SOL, later).await.is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! Price provider abstraction and price storage
+
+use crate::core::error::{Error, Result};
+use crate::db::Database;
+use crate::models::{PriceHistory, UsdValue};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+/// USD price of a mint reported by one source
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceQuote {
+    pub mint: String,
+    pub price_usd: UsdValue,
+    /// Traded volume in USD behind the price, if the source reports one
+    pub volume_usd: Option<UsdValue>,
+    /// When the source observed the price
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    /// Account the price was read from, for on-chain sources
+    pub feed: Option<String>,
+    /// Slot the price was published at, for on-chain sources
+    pub slot: Option<u64>,
+    /// Confidence interval in USD, if the source reports one
+    pub confidence: Option<UsdValue>,
+}
+
+impl PriceQuote {
+    pub fn new(mint: impl Into<String>, price_usd: UsdValue, timestamp: DateTime<Utc>, source: impl Into<String>) -> Self {
+        Self {
+            mint: mint.into(),
+            price_usd,
+            volume_usd: None,
+            timestamp,
+            source: source.into(),
+            feed: None,
+            slot: None,
+            confidence: None,
+        }
+    }
+
+    pub fn with_volume(mut self, volume_usd: UsdValue) -> Self {
+        self.volume_usd = Some(volume_usd);
+        self
+    }
+
+    /// Record the on-chain account, slot and confidence behind the price
+    pub fn with_provenance(mut self, feed: impl Into<String>, slot: u64, confidence: UsdValue) -> Self {
+        self.feed = Some(feed.into());
+        self.slot = Some(slot);
+        self.confidence = Some(confidence);
+        self
+    }
+
+    /// `price_history` row of this quote
+    pub fn to_history(&self) -> PriceHistory {
+        let mut history = PriceHistory::new(self.mint.clone(), self.price_usd, self.timestamp, self.source.clone());
+        history.feed = self.feed.clone();
+        history.slot = self.slot.map(|slot| slot as i64);
+        history.confidence = self.confidence;
+        history.volume_usd = self.volume_usd;
+        history
+    }
+}
+
+/// A source of USD token prices
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait PriceProvider: Send + Sync {
+    /// Name recorded as the source of its quotes
+    fn name(&self) -> &str;
+
+    /// Current price of `mint`, `None` if the source does not price it
+    async fn spot_price(&self, mint: &str) -> Result<Option<PriceQuote>>;
+
+    /// Price of `mint` at or shortly before `at`, `None` if the source has
+    /// no price for that time
+    async fn historical_price(&self, mint: &str, at: DateTime<Utc>) -> Result<Option<PriceQuote>>;
+}
+
+/// Storage of prices
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait PriceStore: Send + Sync {
+    async fn save_prices(&self, prices: &[PriceHistory]) -> Result<()>;
+}
+
+#[async_trait]
+impl PriceStore for Database {
+    async fn save_prices(&self, prices: &[PriceHistory]) -> Result<()> {
+        self.insert_price_history(prices)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to persist prices: {}", e)))
+    }
+}
@@ -0,0 +1,26 @@
+//! Helpers shared by the price tests
+
+use crate::models::{PriceHistory, UsdValue};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+/// Unix time the test timelines start at
+pub const BASE_TIME: i64 = 1_700_000_000;
+
+pub fn usd(value: &str) -> UsdValue {
+    value.parse().unwrap()
+}
+
+/// `seconds` after [`BASE_TIME`]
+pub fn at(seconds: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(BASE_TIME + seconds, 0).unwrap()
+}
+
+/// `minute` minutes after [`BASE_TIME`]
+pub fn at_minute(minute: i64) -> DateTime<Utc> {
+    at(0) + Duration::minutes(minute)
+}
+
+/// A `source` price of `mint` at `timestamp`
+pub fn price(mint: &str, timestamp: DateTime<Utc>, price_usd: &str, source: &str) -> PriceHistory {
+    PriceHistory::new(mint.to_string(), usd(price_usd), timestamp, source.to_string())
+}
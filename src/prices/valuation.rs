@@ -0,0 +1,508 @@
+//! USD value at block time of the amounts moved by transactions
+
+use crate::balances::{balance_changes, NATIVE_MINT};
+use crate::core::error::{Error, Result};
+use crate::core::indexer::{Enricher, ParsedBatch};
+use crate::core::metrics::USD_VALUATIONS_UNPRICED_TOTAL;
+use crate::db::Database;
+use crate::models::{PriceHistory, RawAmount, UsdValuation};
+use crate::parsers::{ParsedEvent, ParsedTransaction, ProtocolEvent, SystemEvent, TokenEvent, TransactionContext};
+use crate::prices::dex::WSOL_MINT;
+use crate::prices::lookup::{PriceLookup, PriceRange};
+use crate::prices::provider::PriceStore;
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use metrics::counter;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Decimals of native SOL amounts, in lamports
+const NATIVE_DECIMALS: u8 = 9;
+
+/// Storage of USD valuations
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait ValuationStore: Send + Sync {
+    /// Store `valuations`, keeping stored ones priced more freshly
+    async fn save_valuations(&self, valuations: &[UsdValuation]) -> Result<()>;
+
+    /// Valuations of every `(mint, from, to)` range
+    async fn valuations_in(&self, ranges: &[PriceRange]) -> Result<Vec<UsdValuation>>;
+}
+
+#[async_trait]
+impl ValuationStore for Database {
+    async fn save_valuations(&self, valuations: &[UsdValuation]) -> Result<()> {
+        self.insert_usd_valuations(valuations)
+            .await
+            .map(|_| ())
+            .map_err(|e| Error::pipeline(format!("Failed to persist USD valuations: {}", e)))
+    }
+
+    async fn valuations_in(&self, ranges: &[PriceRange]) -> Result<Vec<UsdValuation>> {
+        self.get_usd_valuations(ranges)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to load USD valuations: {}", e)))
+    }
+}
+
+/// Unpriced valuation rows for the token transfers, swaps and balance changes
+/// of a transaction with a block time.
+///
+/// Transfers and swaps are only taken from successful transactions. As in
+/// [`DexPriceProvider`](crate::prices::dex::DexPriceProvider), a routed swap is
+/// valued once by its outer swap, so venue legs are skipped, and so are the
+/// transfers a swap makes, which its input and output already cover. The
+/// balance changes restate the same flows per account, so sums must not mix
+/// `balance_change` rows with the other kinds. Amounts whose mint or decimals
+/// neither the event nor the token balances of the status meta tell are left
+/// out.
+pub fn usd_valuations(transaction: &ParsedTransaction) -> Vec<UsdValuation> {
+    let context = &transaction.context;
+    let Some(timestamp) = context.block_time.and_then(|t| Utc.timestamp_opt(t, 0).single()) else {
+        return Vec::new();
+    };
+    let decimals: HashMap<&str, u8> = context
+        .pre_token_balances
+        .iter()
+        .chain(&context.post_token_balances)
+        .map(|balance| (balance.mint.as_str(), balance.decimals))
+        .chain([(NATIVE_MINT, NATIVE_DECIMALS)])
+        .collect();
+    let mut valuations = Vec::new();
+    let mut push = |kind: &str, item: String, mint: &str, amount: RawAmount, event_decimals: Option<u8>| {
+        if let Some(decimals) = event_decimals.or_else(|| decimals.get(mint).copied()) {
+            valuations.push(UsdValuation::new(
+                context.signature.clone(),
+                kind,
+                item,
+                mint.to_string(),
+                amount,
+                decimals,
+                timestamp,
+            ));
+        }
+    };
+
+    if context.success {
+        let swap_frames: Vec<(usize, Option<usize>)> = transaction
+            .events
+            .iter()
+            .filter(|parsed| matches!(parsed.event, ProtocolEvent::Swap(_)))
+            .map(|parsed| (parsed.instruction_index, parsed.inner_index))
+            .collect();
+        let within_swap = |parsed: &ParsedEvent| {
+            let stack = context.call_tree.call_stack(parsed.instruction_index, parsed.inner_index);
+            stack
+                .iter()
+                .rev()
+                .skip(1)
+                .any(|frame| swap_frames.contains(&(frame.instruction_index, frame.inner_index)))
+        };
+
+        for (index, parsed) in transaction.events.iter().enumerate() {
+            match &parsed.event {
+                ProtocolEvent::System(SystemEvent::SolTransfer { .. }) | ProtocolEvent::Token(TokenEvent::Transfer { .. })
+                    if within_swap(parsed) => {}
+                ProtocolEvent::Swap(swap) if swap.route_leg => {}
+                ProtocolEvent::System(SystemEvent::SolTransfer { lamports, .. }) => {
+                    push("transfer", index.to_string(), NATIVE_MINT, RawAmount::from(*lamports), None);
+                }
+                ProtocolEvent::Token(TokenEvent::Transfer {
+                    source,
+                    destination,
+                    mint,
+                    amount,
+                    decimals,
+                    ..
+                }) => {
+                    let mint = mint
+                        .clone()
+                        .or_else(|| account_mint(context, source))
+                        .or_else(|| account_mint(context, destination));
+                    if let Some(mint) = mint {
+                        push("transfer", index.to_string(), &mint, RawAmount::from(*amount), *decimals);
+                    }
+                }
+                ProtocolEvent::Swap(swap) => {
+                    let (input, output) = (RawAmount::from(swap.input_amount), RawAmount::from(swap.output_amount));
+                    push("swap_input", index.to_string(), &swap.input_mint, input, swap.input_decimals);
+                    push("swap_output", index.to_string(), &swap.output_mint, output, swap.output_decimals);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for change in balance_changes(transaction).changes {
+        push("balance_change", change.account, &change.mint, change.delta, None);
+    }
+    valuations
+}
+
+/// Mint of the token account `account`, from the status meta
+fn account_mint(context: &TransactionContext, account: &str) -> Option<String> {
+    let index = context.account_keys.iter().position(|key| key.to_string() == account)?;
+    context
+        .pre_token_balances
+        .iter()
+        .chain(&context.post_token_balances)
+        .find(|balance| balance.account_index == index)
+        .map(|balance| balance.mint.clone())
+}
+
+/// Mint whose price values amounts of `mint`: wrapped SOL for native SOL
+fn price_mint(mint: &str) -> &str {
+    if mint == NATIVE_MINT {
+        WSOL_MINT
+    } else {
+        mint
+    }
+}
+
+/// Values token amounts in USD at block time from the stored prices.
+///
+/// As an [`Enricher`] it attaches a valuation to every token transfer, swap
+/// leg and balance change of a batch, which the database sink stores in
+/// `usd_valuations`, priced or not. Each value records the source, time and
+/// staleness of the price used. Amounts without a price within the lookup's
+/// maximum staleness are kept unpriced so [`UsdValuer::revalue`] can fill them
+/// in once prices arrive.
+pub struct UsdValuer {
+    lookup: PriceLookup,
+}
+
+impl UsdValuer {
+    pub fn new(lookup: PriceLookup) -> Self {
+        Self { lookup }
+    }
+
+    /// Price `valuations` at their block time with a single price query,
+    /// returning how many got a price
+    pub async fn value(&self, valuations: &mut [UsdValuation]) -> Result<usize> {
+        let requests: Vec<(String, DateTime<Utc>)> = valuations
+            .iter()
+            .map(|valuation| (price_mint(&valuation.mint).to_string(), valuation.timestamp))
+            .collect();
+        let points = self.lookup.prices_at(&requests).await?;
+        let mut priced = 0;
+        for (valuation, point) in valuations.iter_mut().zip(points) {
+            match point {
+                Some(point) => {
+                    valuation.set_price(point.price_usd, point.source, point.observed_at);
+                    priced += 1;
+                }
+                None => counter!(USD_VALUATIONS_UNPRICED_TOTAL, 1, "kind" => valuation.kind.clone()),
+            }
+        }
+        Ok(priced)
+    }
+
+    /// Price the valuations stored in `store` within `ranges` again and store
+    /// those that got a fresher price, returning how many did
+    pub async fn revalue(&self, store: &dyn ValuationStore, ranges: &[PriceRange]) -> Result<usize> {
+        let mut valuations = store.valuations_in(ranges).await?;
+        if valuations.is_empty() {
+            return Ok(0);
+        }
+        let before: Vec<Option<i64>> = valuations.iter().map(|valuation| valuation.staleness_seconds).collect();
+        self.value(&mut valuations).await?;
+        let improved: Vec<UsdValuation> = valuations
+            .into_iter()
+            .zip(before)
+            .filter(|(valuation, before)| match (valuation.staleness_seconds, before) {
+                (Some(after), Some(before)) => after < *before,
+                (Some(_), None) => true,
+                (None, _) => false,
+            })
+            .map(|(valuation, _)| valuation)
+            .collect();
+        if !improved.is_empty() {
+            store.save_valuations(&improved).await?;
+        }
+        Ok(improved.len())
+    }
+
+    /// Revalue the stored valuations that newly stored `prices` are within
+    /// reach of
+    pub async fn revalue_prices(&self, store: &dyn ValuationStore, prices: &[PriceHistory]) -> Result<usize> {
+        let (lookback, lookahead) = self.lookup.window();
+        let mut spans: BTreeMap<&str, (DateTime<Utc>, DateTime<Utc>)> = BTreeMap::new();
+        for price in prices {
+            let span = spans.entry(&price.token_mint).or_insert((price.timestamp, price.timestamp));
+            span.0 = span.0.min(price.timestamp);
+            span.1 = span.1.max(price.timestamp);
+        }
+        let mut ranges: Vec<PriceRange> = Vec::new();
+        for (mint, (first, last)) in spans {
+            // A price at t serves requests from t - lookahead to t + lookback
+            let (from, to) = (first - lookahead, last + lookback);
+            if mint == WSOL_MINT {
+                ranges.push((NATIVE_MINT.to_string(), from, to));
+            }
+            ranges.push((mint.to_string(), from, to));
+        }
+        if ranges.is_empty() {
+            return Ok(0);
+        }
+        self.revalue(store, &ranges).await
+    }
+}
+
+#[async_trait]
+impl Enricher for UsdValuer {
+    fn name(&self) -> &str {
+        "usd_value"
+    }
+
+    async fn enrich(&self, batch: &mut ParsedBatch) -> Result<()> {
+        // Kept even if pricing fails, so they can be revalued later
+        batch.valuations = batch.transactions.iter().flat_map(usd_valuations).collect();
+        self.value(&mut batch.valuations).await?;
+        Ok(())
+    }
+}
+
+/// [`PriceStore`] revaluing the stored valuations a saved batch of prices
+/// reaches, so amounts valued before their prices arrived, or at staler
+/// ones, pick them up. Use it as the store of price providers and backfills.
+pub struct RevaluingPriceStore {
+    prices: Arc<dyn PriceStore>,
+    valuer: Arc<UsdValuer>,
+    valuations: Arc<dyn ValuationStore>,
+}
+
+impl RevaluingPriceStore {
+    pub fn new(prices: Arc<dyn PriceStore>, valuer: Arc<UsdValuer>, valuations: Arc<dyn ValuationStore>) -> Self {
+        Self {
+            prices,
+            valuer,
+            valuations,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceStore for RevaluingPriceStore {
+    async fn save_prices(&self, prices: &[PriceHistory]) -> Result<()> {
+        self.prices.save_prices(prices).await?;
+        // The prices are stored; a failed revaluation only delays the values
+        if let Err(e) = self.valuer.revalue_prices(self.valuations.as_ref(), prices).await {
+            warn!("Failed to revalue after storing {} prices: {}", prices.len(), e);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::parse_fixture;
+    use crate::parsers::{ParserRegistry, SwapEvent, SwapParser, TokenBalance, TokenParser};
+    use crate::prices::dex::USDC_MINT;
+    use crate::prices::lookup::MockPriceHistoryStore;
+    use crate::prices::provider::MockPriceStore;
+    use crate::prices::test_utils::{at, price, usd, BASE_TIME};
+    use chrono::Duration;
+    use solana_sdk::pubkey::Pubkey;
+
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    fn event(event: ProtocolEvent) -> ParsedEvent {
+        ParsedEvent {
+            parser: "test".to_string(),
+            program_id: Pubkey::new_unique(),
+            instruction_index: 0,
+            inner_index: None,
+            event,
+        }
+    }
+
+    fn usdc(account_index: usize, amount: u64) -> TokenBalance {
+        TokenBalance {
+            account_index,
+            mint: USDC_MINT.to_string(),
+            owner: None,
+            program_id: None,
+            amount,
+            decimals: 6,
+        }
+    }
+
+    /// Sends 1 SOL, moves 2 USDC between token accounts and swaps 2 USDC
+    /// for BONK
+    fn transaction() -> ParsedTransaction {
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let context = TransactionContext {
+            signature: "sig".to_string(),
+            slot: 1,
+            block_time: Some(BASE_TIME),
+            success: true,
+            fee: 5_000,
+            account_keys: keys.clone(),
+            num_static_keys: 4,
+            num_required_signatures: 1,
+            pre_balances: vec![10_000_000_000, 0, 2_039_280, 2_039_280],
+            post_balances: vec![8_999_995_000, 1_000_000_000, 2_039_280, 2_039_280],
+            pre_token_balances: vec![usdc(2, 5_000_000), usdc(3, 0)],
+            post_token_balances: vec![usdc(2, 3_000_000), usdc(3, 2_000_000)],
+            ..Default::default()
+        };
+        let events = vec![
+            event(ProtocolEvent::System(SystemEvent::SolTransfer {
+                from: keys[0].to_string(),
+                to: keys[1].to_string(),
+                lamports: 1_000_000_000,
+                seed: None,
+            })),
+            event(ProtocolEvent::Token(TokenEvent::Transfer {
+                source: keys[2].to_string(),
+                destination: keys[3].to_string(),
+                authority: keys[0].to_string(),
+                mint: None,
+                amount: 2_000_000,
+                decimals: None,
+                fee: None,
+            })),
+            event(ProtocolEvent::Swap(SwapEvent {
+                dex: "raydium".to_string(),
+                user: keys[0].to_string(),
+                pool: None,
+                input_mint: USDC_MINT.to_string(),
+                input_amount: 2_000_000,
+                input_decimals: Some(6),
+                output_mint: BONK.to_string(),
+                output_amount: 100_000_000,
+                output_decimals: Some(5),
+                route: Vec::new(),
+                route_leg: false,
+            })),
+        ];
+        ParsedTransaction { context, events }
+    }
+
+    /// Lookup over `rows`, expecting a single query
+    fn valuer(rows: Vec<PriceHistory>) -> UsdValuer {
+        let mut store = MockPriceHistoryStore::new();
+        store.expect_prices_in().times(1).returning(move |ranges, _| {
+            Ok(rows
+                .iter()
+                .filter(|row| {
+                    ranges
+                        .iter()
+                        .any(|(mint, from, to)| row.token_mint == *mint && row.timestamp >= *from && row.timestamp <= *to)
+                })
+                .cloned()
+                .collect())
+        });
+        UsdValuer::new(PriceLookup::new(Arc::new(store)).with_max_staleness(Duration::minutes(30)))
+    }
+
+    #[tokio::test]
+    async fn test_values_transfers_swaps_and_balance_changes() {
+        let mut batch = ParsedBatch {
+            transactions: vec![transaction()],
+            ..Default::default()
+        };
+        let valuer = valuer(vec![
+            price(WSOL_MINT, at(-60), "150", "pyth"),
+            price(USDC_MINT, at(-600), "1", "coingecko"),
+            price(USDC_MINT, at(60), "0.9", "coingecko"),
+        ]);
+        valuer.enrich(&mut batch).await.unwrap();
+
+        let find = |kind: &str, mint: &str| {
+            batch
+                .valuations
+                .iter()
+                .find(|valuation| valuation.kind == kind && valuation.mint == mint)
+                .unwrap()
+        };
+        assert_eq!(batch.valuations.len(), 8);
+        let sol = find("transfer", NATIVE_MINT);
+        assert_eq!((sol.item.as_str(), sol.value_usd), ("0", Some(usd("150"))));
+        assert_eq!(sol.price_source.as_deref(), Some("pyth"));
+        assert_eq!(sol.staleness_seconds, Some(60));
+
+        // Mint and decimals come from the token balances
+        let transfer = find("transfer", USDC_MINT);
+        assert_eq!((transfer.item.as_str(), transfer.value_usd), ("1", Some(usd("2"))));
+        assert_eq!(transfer.price_timestamp, Some(at(-600)));
+        assert_eq!(find("swap_input", USDC_MINT).value_usd, Some(usd("2")));
+        let bonk = find("swap_output", BONK);
+        assert_eq!((bonk.price_usd, bonk.staleness_seconds), (None, None));
+
+        let payer = batch
+            .valuations
+            .iter()
+            .find(|valuation| valuation.kind == "balance_change" && valuation.amount < RawAmount(-1_000_000_000))
+            .unwrap();
+        assert_eq!(payer.value_usd, Some(usd("-150.00075")));
+        let changes = batch.valuations.iter().filter(|valuation| valuation.kind == "balance_change");
+        assert_eq!(changes.filter(|valuation| valuation.value_usd.is_some()).count(), 4);
+    }
+
+    #[test]
+    fn test_values_a_route_once() {
+        let mut registry = ParserRegistry::new();
+        registry.register(Arc::new(TokenParser::new()));
+        for parser in SwapParser::all() {
+            registry.register(Arc::new(parser));
+        }
+        let parsed = parse_fixture("jupiter_route", &registry);
+
+        // Only the Jupiter swap is valued; its venue legs and the transfers
+        // they make are not
+        let kinds: Vec<_> = usd_valuations(&parsed)
+            .into_iter()
+            .filter(|valuation| valuation.kind != "balance_change")
+            .map(|valuation| (valuation.kind, valuation.mint))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("swap_input".to_string(), "9hGdMK6RVJEcUVVBzBeJFQsS2mv7WMQ2zFd2JmpwLPFE".to_string()),
+                ("swap_output".to_string(), "B7ScJ3BvgitUPFxK8TiauFbSh6mMh4iKQKGgTMWdPg4T".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revalues_with_fresher_prices() {
+        let mut stale = UsdValuation::new("a".to_string(), "transfer", "0".to_string(), BONK.to_string(), RawAmount(100_000), 5, at(0));
+        stale.set_price(usd("0.00001"), "dex_vwap".to_string(), at(-1_500));
+        let mut fresh = UsdValuation::new("b".to_string(), "transfer", "0".to_string(), BONK.to_string(), RawAmount(100_000), 5, at(600));
+        fresh.set_price(usd("0.00003"), "dex_vwap".to_string(), at(590));
+        let unpriced = UsdValuation::new("c".to_string(), "swap_input", "1".to_string(), BONK.to_string(), RawAmount(100_000), 5, at(300));
+
+        let mut valuations = MockValuationStore::new();
+        valuations
+            .expect_valuations_in()
+            .withf(|ranges| ranges.len() == 1 && ranges[0] == (BONK.to_string(), at(-30), at(1_770)))
+            .times(1)
+            .returning(move |_| Ok(vec![stale.clone(), fresh.clone(), unpriced.clone()]));
+        valuations
+            .expect_save_valuations()
+            .withf(|saved| {
+                saved.len() == 2
+                    && saved.iter().all(|valuation| valuation.price_usd == Some(usd("0.00002")))
+                    && saved[0].signature == "a"
+                    && saved[0].staleness_seconds == Some(30)
+                    && saved[1].signature == "c"
+                    && saved[1].value_usd == Some(usd("0.00002"))
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let mut prices = MockPriceStore::new();
+        prices.expect_save_prices().times(1).returning(|_| Ok(()));
+
+        let new_price = price(BONK, at(-30), "0.00002", "aggregate");
+        let store = RevaluingPriceStore::new(
+            Arc::new(prices),
+            Arc::new(valuer(vec![new_price.clone()])),
+            Arc::new(valuations),
+        );
+        store.save_prices(&[new_price]).await.unwrap();
+    }
+}
@@ -1,15 +1,26 @@
 use crate::rpc::rate_limit::RpcRateLimiter;
 use std::sync::Arc;
 use std::time::Duration;
-use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_client::{RpcClient, RpcClientConfig};
 use solana_sdk::commitment_config::CommitmentConfig;
 use crate::rpc::config::RpcConfig;
 use crate::rpc::health::HealthMonitor;
 use tokio::sync::RwLock;
 use crate::rpc::error::RpcError;
+use crate::rpc::sender::{count_bytes, CountingHttpSender};
+use serde_json::json;
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::{RpcBlockConfig, RpcTransactionConfig};
+use solana_client::rpc_response::{Response, RpcKeyedAccount};
+use solana_transaction_status::{TransactionDetails, UiTransactionEncoding};
 use std::time::Instant;
+use tracing::field;
 use url;
 
+/// Keys accepted by a single `getMultipleAccounts` request
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
 #[cfg_attr(test, mockall::automock)]
 #[async_trait::async_trait]
 pub trait RpcClientTrait: Send + Sync {
@@ -24,6 +35,82 @@ pub trait RpcClientTrait: Send + Sync {
         &self,
         signature: &str,
     ) -> Result<crate::models::transaction::Transaction, crate::rpc::error::RpcError>;
+
+    /// Transaction with its full status meta (balances, inner instructions
+    /// and logs), as needed by the parsers
+    async fn get_encoded_transaction(
+        &self,
+        signature: &str,
+    ) -> Result<solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta, RpcError>;
+}
+
+/// Token accounts and SOL balance of a wallet, used by balance validation and
+/// snapshots
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait TokenAccountClientTrait: Send + Sync {
+    /// Lamports held by `pubkey`, with the slot they were read at
+    async fn get_balance(&self, pubkey: &solana_sdk::pubkey::Pubkey) -> Result<Response<u64>, RpcError>;
+
+    /// Accounts of `program_id` owned by `owner`, with the slot they were read at
+    async fn get_token_accounts_by_owner(
+        &self,
+        owner: &solana_sdk::pubkey::Pubkey,
+        program_id: &solana_sdk::pubkey::Pubkey,
+    ) -> Result<Response<Vec<(solana_sdk::pubkey::Pubkey, solana_sdk::account::Account)>>, RpcError>;
+}
+
+/// Block-range access used by `fetcher::BlockFetcher`
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait BlockClientTrait: Send + Sync {
+    async fn get_blocks(&self, start_slot: u64, end_slot: Option<u64>) -> Result<Vec<u64>, RpcError>;
+
+    async fn get_blocks_with_limit(&self, start_slot: u64, limit: usize) -> Result<Vec<u64>, RpcError>;
+
+    async fn get_block(
+        &self,
+        slot: u64,
+    ) -> Result<solana_transaction_status::EncodedConfirmedBlock, RpcError>;
+}
+
+/// Account data access used by resolvers and registries
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait AccountClientTrait: Send + Sync {
+    /// Account at `pubkey`, `None` if it does not exist
+    async fn get_account(
+        &self,
+        pubkey: &solana_sdk::pubkey::Pubkey,
+    ) -> Result<Option<solana_sdk::account::Account>, RpcError>;
+
+    /// Accounts at `pubkeys` in request order, `None` for missing accounts
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[solana_sdk::pubkey::Pubkey],
+    ) -> Result<Vec<Option<solana_sdk::account::Account>>, RpcError>;
+}
+
+/// Run a blocking RPC call without stalling other tasks on a multi-threaded runtime
+fn blocking<R>(f: impl FnOnce() -> R) -> R {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(f)
+        }
+        _ => f(),
+    }
+}
+
+/// Blocking client for `url`, counting the bytes of its requests
+fn rpc_client(url: &str) -> RpcClient {
+    RpcClient::new_sender(CountingHttpSender::new(url), RpcClientConfig::with_commitment(CommitmentConfig::confirmed()))
+}
+
+/// Slot or signature a request refers to, attached to its trace spans
+#[derive(Debug, Clone, Default)]
+struct RequestTarget {
+    slot: Option<u64>,
+    signature: Option<String>,
 }
 
 /// Client for interacting with Solana RPC endpoints
@@ -40,6 +127,9 @@ pub struct SolanaRpcClient {
     current_endpoint_idx: usize,
     /// Store the endpoint URL as a String for &str return
     current_endpoint_url: String,
+    /// Host metrics sampler, kept across health checks so CPU usage is
+    /// measured between them
+    sampler: crate::core::system::SystemSampler,
 }
 
 /// Manual Debug implementation to skip the `client` field,
@@ -52,6 +142,7 @@ impl std::fmt::Debug for SolanaRpcClient {
             .field("rate_limiter", &self.rate_limiter)
             .field("current_endpoint_idx", &self.current_endpoint_idx)
             .field("current_endpoint_url", &self.current_endpoint_url)
+            .field("sampler", &self.sampler)
             .finish()
     }
 }
@@ -81,10 +172,7 @@ impl SolanaRpcClient {
         // Initialize rate limiter
         let rate_limiter = RpcRateLimiter::new(&config.rate_limit)?;
         
-        let client = RpcClient::new_with_commitment(
-            endpoint_url.clone(),
-            CommitmentConfig::confirmed(),
-        );
+        let client = rpc_client(&endpoint_url);
 
         Ok(Self {
             config,
@@ -93,6 +181,7 @@ impl SolanaRpcClient {
             client: Arc::new(RwLock::new(client)),
             current_endpoint_idx: 0,
             current_endpoint_url: endpoint_url,
+            sampler: crate::core::system::SystemSampler::default(),
         })
     }
     
@@ -114,43 +203,81 @@ impl SolanaRpcClient {
             .ok_or_else(|| RpcError::InvalidEndpoint(next_idx))?;
         let url = endpoint.url.clone();
         // Update the client to use the new endpoint
-        let new_client = RpcClient::new_with_commitment(url.clone(), CommitmentConfig::confirmed());
+        let new_client = rpc_client(&url);
         *self.client.write().await = new_client;
         // Update current endpoint tracking
         // (Assume these are atomic or only used for display)
         // If not, wrap in a Mutex or RwLock as needed
         // self.current_endpoint_idx = next_idx;
         // self.current_endpoint_url = url;
-        tracing::info!("Switched to endpoint {}: {}", next_idx, url);
+        tracing::info!("Switched to endpoint {}: {}", next_idx, endpoint.redacted_url());
         Ok(())
     }
 
-    async fn with_retry<F, T>(&self, operation: &str, mut f: F) -> std::result::Result<T, RpcError>
+    async fn with_retry<F, T>(
+        &self,
+        request: RpcRequest,
+        target: RequestTarget,
+        mut f: F,
+    ) -> std::result::Result<T, RpcError>
     where
-        F: FnMut() -> std::result::Result<T, RpcError>,
+        F: FnMut(&RpcClient) -> std::result::Result<T, RpcError>,
     {
+        let operation = request.to_string();
         let mut attempts = 0;
         let max_attempts = self.config.retry.max_retries;
         let mut last_error = None;
-        let start_time = Instant::now();
-        let mut endpoint_switches = 0;
 
         while attempts < max_attempts {
             // Wait for rate limit permit
             self.rate_limiter.wait_for_permit().await;
 
-            match f() {
+            let endpoint_idx = self.health_monitor.get_current_endpoint().await;
+            let endpoint = self
+                .config
+                .endpoints
+                .get(endpoint_idx)
+                .map(|e| e.redacted_url())
+                .unwrap_or_else(|| "unknown".to_string());
+            let span = tracing::info_span!(
+                "rpc.attempt",
+                rpc.method = %operation,
+                rpc.endpoint = %endpoint,
+                attempt = attempts + 1,
+                slot = field::Empty,
+                signature = field::Empty,
+                error.class = field::Empty,
+            );
+            if let Some(slot) = target.slot {
+                span.record("slot", slot);
+            }
+            if let Some(signature) = &target.signature {
+                span.record("signature", signature.as_str());
+            }
+
+            let attempt_start = Instant::now();
+            let (outcome, bytes_sent, bytes_received) = {
+                let client = self.client.read().await;
+                span.in_scope(|| blocking(|| count_bytes(|| f(&client))))
+            };
+            match outcome {
                 Ok(result) => {
                     // Record success
-                    let endpoint_idx = self.health_monitor.get_current_endpoint().await;
-                    let response_time_ms = start_time.elapsed().as_millis() as u64;
-                    self.health_monitor.record_success(endpoint_idx, response_time_ms, 0).await.unwrap_or(());
+                    let latency = attempt_start.elapsed();
+                    self.health_monitor
+                        .record_request_success(endpoint_idx, &operation, latency, bytes_sent, bytes_received)
+                        .await
+                        .unwrap_or(());
                     return Ok(result);
                 }
                 Err(e) => {
                     // Record failure
-                    let endpoint_idx = self.health_monitor.get_current_endpoint().await;
-                    self.health_monitor.record_failure(endpoint_idx).await.unwrap_or(());
+                    let latency = attempt_start.elapsed();
+                    span.record("error.class", e.class());
+                    self.health_monitor
+                        .record_request_failure(endpoint_idx, &operation, latency, bytes_sent, &e)
+                        .await
+                        .unwrap_or(());
                     if !e.is_retryable() {
                         return Err(e.with_context(format!("{} failed", operation)));
                     }
@@ -160,12 +287,10 @@ impl SolanaRpcClient {
                     if let Err(switch_err) = self.switch_to_next_healthy_endpoint().await {
                         tracing::error!("All endpoints failed: {}", switch_err);
                         return Err(RpcError::AllEndpointsFailed(format!("{} failed after {} attempts, all endpoints unhealthy", operation, attempts)));
-                    } else {
-                        endpoint_switches += 1;
                     }
                     // Calculate backoff duration
                     let backoff = Duration::from_millis(
-                        self.config.retry.retry_delay_ms * 2u64.pow(attempts as u32)
+                        self.config.retry.retry_delay_ms * 2u64.pow(attempts)
                     );
                     tracing::warn!("Retrying {} (attempt {}), switching endpoint, backoff {:?}", operation, attempts, backoff);
                     tokio::time::sleep(backoff).await;
@@ -176,24 +301,192 @@ impl SolanaRpcClient {
         Err(last_error.unwrap_or_else(|| RpcError::Internal(format!("{} failed after {} attempts", operation, attempts))))
     }
 
+    /// Block at `slot` with full JSON-encoded transactions.
+    ///
+    /// Requests `maxSupportedTransactionVersion: 0`, without which nodes
+    /// reject blocks holding any versioned transaction.
     pub async fn get_block(&self, slot: u64) -> std::result::Result<solana_transaction_status::EncodedConfirmedBlock, RpcError> {
-        self.with_retry("get_block", || {
-            let client = self.client.blocking_read();
-            client.get_block(slot)
-                .map_err(|e| RpcError::RequestFailed(e)
-                    .with_context(format!("Failed to get block at slot {}", slot)))
+        let config = RpcBlockConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            transaction_details: Some(TransactionDetails::Full),
+            rewards: Some(true),
+            commitment: None,
+            max_supported_transaction_version: Some(0),
+        };
+        let target = RequestTarget { slot: Some(slot), ..Default::default() };
+        self.with_retry(RpcRequest::GetBlock, target, |client| {
+            client
+                .get_block_with_config(slot, config)
+                .map(Into::into)
+                .map_err(|e| {
+                    let error = RpcError::from(e);
+                    if error.is_skipped_slot() {
+                        RpcError::SlotSkipped(slot)
+                    } else {
+                        error
+                    }
+                })
         }).await
     }
 
+    /// Transaction `signature` with its status meta, JSON encoded.
+    ///
+    /// Like [`SolanaRpcClient::get_block`], accepts versioned transactions.
+    pub async fn get_transaction(
+        &self,
+        signature: &str,
+    ) -> std::result::Result<solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta, RpcError> {
+        let parsed = signature
+            .parse::<solana_sdk::signature::Signature>()
+            .map_err(|e| RpcError::Internal(format!("Invalid signature {}: {}", signature, e)))?;
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            commitment: None,
+            max_supported_transaction_version: Some(0),
+        };
+        let target = RequestTarget { signature: Some(signature.to_string()), ..Default::default() };
+        self.with_retry(RpcRequest::GetTransaction, target, |client| {
+            client.get_transaction_with_config(&parsed, config).map_err(RpcError::from)
+        }).await
+    }
+
+    /// Signatures of up to `limit` transactions of `address` before
+    /// `before`, newest first
+    pub async fn get_signatures_for_address(
+        &self,
+        address: &solana_sdk::pubkey::Pubkey,
+        before: Option<String>,
+        limit: usize,
+    ) -> std::result::Result<Vec<String>, RpcError> {
+        let before = before
+            .map(|signature| {
+                signature
+                    .parse::<solana_sdk::signature::Signature>()
+                    .map_err(|e| RpcError::Internal(format!("Invalid signature {}: {}", signature, e)))
+            })
+            .transpose()?;
+        self.with_retry(RpcRequest::GetSignaturesForAddress, RequestTarget::default(), |client| {
+            let config = GetConfirmedSignaturesForAddress2Config {
+                before,
+                limit: Some(limit),
+                ..Default::default()
+            };
+            client
+                .get_signatures_for_address_with_config(address, config)
+                .map(|statuses| statuses.into_iter().map(|status| status.signature).collect())
+                .map_err(RpcError::from)
+        }).await
+    }
+
+    /// Slots of the confirmed blocks in `[start_slot, end_slot]`
+    pub async fn get_blocks(
+        &self,
+        start_slot: u64,
+        end_slot: Option<u64>,
+    ) -> std::result::Result<Vec<u64>, RpcError> {
+        let target = RequestTarget { slot: Some(start_slot), ..Default::default() };
+        self.with_retry(RpcRequest::GetBlocks, target, |client| {
+            client.get_blocks(start_slot, end_slot).map_err(RpcError::from)
+        }).await
+    }
+
+    /// Slots of up to `limit` confirmed blocks starting at `start_slot`
+    pub async fn get_blocks_with_limit(
+        &self,
+        start_slot: u64,
+        limit: usize,
+    ) -> std::result::Result<Vec<u64>, RpcError> {
+        let target = RequestTarget { slot: Some(start_slot), ..Default::default() };
+        self.with_retry(RpcRequest::GetBlocksWithLimit, target, |client| {
+            client.get_blocks_with_limit(start_slot, limit).map_err(RpcError::from)
+        }).await
+    }
+
+    /// Account at `pubkey`, `None` if it does not exist
+    pub async fn get_account(
+        &self,
+        pubkey: &solana_sdk::pubkey::Pubkey,
+    ) -> std::result::Result<Option<solana_sdk::account::Account>, RpcError> {
+        self.with_retry(RpcRequest::GetAccountInfo, RequestTarget::default(), |client| {
+            client
+                .get_account_with_commitment(pubkey, client.commitment())
+                .map(|response| response.value)
+                .map_err(RpcError::from)
+        })
+        .await
+    }
+
+    /// Accounts at `pubkeys`, requested in chunks of at most
+    /// `MAX_MULTIPLE_ACCOUNTS` keys
+    pub async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[solana_sdk::pubkey::Pubkey],
+    ) -> std::result::Result<Vec<Option<solana_sdk::account::Account>>, RpcError> {
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            accounts.extend(
+                self.with_retry(RpcRequest::GetMultipleAccounts, RequestTarget::default(), |client| {
+                    client.get_multiple_accounts(chunk).map_err(RpcError::from)
+                })
+                .await?,
+            );
+        }
+        Ok(accounts)
+    }
+
+    /// Lamports held by `pubkey`, with the slot they were read at
+    pub async fn get_balance(
+        &self,
+        pubkey: &solana_sdk::pubkey::Pubkey,
+    ) -> std::result::Result<Response<u64>, RpcError> {
+        self.with_retry(RpcRequest::GetBalance, RequestTarget::default(), |client| {
+            client
+                .get_balance_with_commitment(pubkey, client.commitment())
+                .map_err(RpcError::from)
+        })
+        .await
+    }
+
+    /// Accounts of `program_id` owned by `owner`, decoded from base64, with
+    /// the slot they were read at
+    pub async fn get_token_accounts_by_owner(
+        &self,
+        owner: &solana_sdk::pubkey::Pubkey,
+        program_id: &solana_sdk::pubkey::Pubkey,
+    ) -> std::result::Result<Response<Vec<(solana_sdk::pubkey::Pubkey, solana_sdk::account::Account)>>, RpcError> {
+        let params = json!([owner.to_string(), { "programId": program_id.to_string() }, { "encoding": "base64" }]);
+        let response: Response<Vec<RpcKeyedAccount>> = self
+            .with_retry(RpcRequest::GetTokenAccountsByOwner, RequestTarget::default(), |client| {
+                client
+                    .send(RpcRequest::GetTokenAccountsByOwner, params.clone())
+                    .map_err(RpcError::from)
+            })
+            .await?;
+        let mut accounts = Vec::with_capacity(response.value.len());
+        for keyed in response.value {
+            let pubkey = keyed
+                .pubkey
+                .parse()
+                .map_err(|_| RpcError::Internal(format!("Invalid token account key {}", keyed.pubkey)))?;
+            let account = keyed
+                .account
+                .decode()
+                .ok_or_else(|| RpcError::Internal(format!("Undecodable token account {}", keyed.pubkey)))?;
+            accounts.push((pubkey, account));
+        }
+        Ok(Response {
+            context: response.context,
+            value: accounts,
+        })
+    }
+
     pub async fn get_signature_status(
         &self,
         signature: &solana_sdk::signature::Signature,
     ) -> std::result::Result<Option<std::result::Result<(), solana_sdk::transaction::TransactionError>>, RpcError> {
-        self.with_retry("get_signature_status", || {
-            let client = self.client.blocking_read();
-            client.get_signature_status(signature)
-                .map_err(|e| RpcError::RequestFailed(e)
-                    .with_context(format!("Failed to get status for signature {}", signature)))
+        let target = RequestTarget { signature: Some(signature.to_string()), ..Default::default() };
+        self.with_retry(RpcRequest::GetSignatureStatuses, target, |client| {
+            client.get_signature_status(signature).map_err(RpcError::from)
         }).await
     }
 
@@ -228,28 +521,95 @@ impl SolanaRpcClient {
     }
 }
 
+#[async_trait::async_trait]
+impl RpcClientTrait for SolanaRpcClient {
+    async fn get_signatures_for_address(
+        &self,
+        address: &solana_sdk::pubkey::Pubkey,
+        before: Option<String>,
+        limit: usize,
+    ) -> Result<Vec<String>, RpcError> {
+        SolanaRpcClient::get_signatures_for_address(self, address, before, limit).await
+    }
+
+    async fn get_transaction(&self, signature: &str) -> Result<crate::models::transaction::Transaction, RpcError> {
+        let encoded = SolanaRpcClient::get_transaction(self, signature).await?;
+        crate::models::transaction::Transaction::from_block_transaction(encoded.slot, encoded.block_time, &encoded.transaction)
+            .ok_or_else(|| RpcError::Internal(format!("Transaction {} has no signature", signature)))
+    }
+
+    async fn get_encoded_transaction(
+        &self,
+        signature: &str,
+    ) -> Result<solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta, RpcError> {
+        SolanaRpcClient::get_transaction(self, signature).await
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockClientTrait for SolanaRpcClient {
+    async fn get_blocks(&self, start_slot: u64, end_slot: Option<u64>) -> Result<Vec<u64>, RpcError> {
+        SolanaRpcClient::get_blocks(self, start_slot, end_slot).await
+    }
+
+    async fn get_blocks_with_limit(&self, start_slot: u64, limit: usize) -> Result<Vec<u64>, RpcError> {
+        SolanaRpcClient::get_blocks_with_limit(self, start_slot, limit).await
+    }
+
+    async fn get_block(
+        &self,
+        slot: u64,
+    ) -> Result<solana_transaction_status::EncodedConfirmedBlock, RpcError> {
+        SolanaRpcClient::get_block(self, slot).await
+    }
+}
+
+#[async_trait::async_trait]
+impl AccountClientTrait for SolanaRpcClient {
+    async fn get_account(
+        &self,
+        pubkey: &solana_sdk::pubkey::Pubkey,
+    ) -> Result<Option<solana_sdk::account::Account>, RpcError> {
+        SolanaRpcClient::get_account(self, pubkey).await
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[solana_sdk::pubkey::Pubkey],
+    ) -> Result<Vec<Option<solana_sdk::account::Account>>, RpcError> {
+        SolanaRpcClient::get_multiple_accounts(self, pubkeys).await
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenAccountClientTrait for SolanaRpcClient {
+    async fn get_balance(&self, pubkey: &solana_sdk::pubkey::Pubkey) -> Result<Response<u64>, RpcError> {
+        SolanaRpcClient::get_balance(self, pubkey).await
+    }
+
+    async fn get_token_accounts_by_owner(
+        &self,
+        owner: &solana_sdk::pubkey::Pubkey,
+        program_id: &solana_sdk::pubkey::Pubkey,
+    ) -> Result<Response<Vec<(solana_sdk::pubkey::Pubkey, solana_sdk::account::Account)>>, RpcError> {
+        SolanaRpcClient::get_token_accounts_by_owner(self, owner, program_id).await
+    }
+}
+
 #[async_trait::async_trait]
 impl crate::core::traits::Client for SolanaRpcClient {
     fn config(&self) -> &dyn crate::core::traits::Config {
         &*self.config
     }
     async fn is_healthy(&self) -> crate::core::error::Result<bool> {
-        // Use health_monitor or stub
-        Ok(true)
+        Ok(self.health_monitor.component_health().await.status.is_available())
     }
     fn current_endpoint(&self) -> &str {
         // Return the first enabled endpoint as a string reference
         self.config.endpoints.iter().find(|e| e.enabled).map(|e| e.url.as_str()).unwrap_or("")
     }
     async fn get_metrics(&self) -> crate::core::error::Result<crate::core::traits::ClientMetrics> {
-        // Stub: return default metrics
-        Ok(crate::core::traits::ClientMetrics {
-            successful_requests: 0,
-            failed_requests: 0,
-            avg_response_time_ms: 0.0,
-            current_rps: 0.0,
-            bytes_transferred: 0,
-        })
+        Ok(self.health_monitor.client_metrics().await)
     }
 }
 
@@ -264,16 +624,22 @@ impl crate::core::traits::HealthCheck for SolanaRpcClient {
     }
 
     async fn get_health_details(&self) -> crate::core::error::Result<crate::core::traits::HealthDetails> {
-        // Stub: return default details
+        let component = self.health_monitor.component_health().await;
         Ok(crate::core::traits::HealthDetails {
-            status: crate::core::traits::HealthStatus::Healthy,
-            components: vec![],
-            metrics: crate::core::traits::SystemMetrics {
-                cpu_usage: 0.0,
-                memory_usage: 0,
-                disk_usage: 0,
-                network_usage: 0,
-            },
+            status: component.status.clone(),
+            components: vec![component],
+            metrics: self.sampler.sample(),
         })
     }
+}
+
+#[async_trait::async_trait]
+impl crate::core::traits::HealthComponent for SolanaRpcClient {
+    fn name(&self) -> &str {
+        "rpc"
+    }
+
+    async fn component_health(&self) -> crate::core::traits::ComponentHealth {
+        self.health_monitor.component_health().await
+    }
 } 
\ No newline at end of file
@@ -1,5 +1,5 @@
 use thiserror::Error;
-use solana_client::client_error::ClientError;
+use solana_client::client_error::{ClientError, ClientErrorKind};
 
 #[derive(Error, Debug)]
 pub enum RpcError {
@@ -13,7 +13,7 @@ pub enum RpcError {
     InvalidEndpoint(usize),
 
     #[error("Request failed: {0}")]
-    RequestFailed(#[from] ClientError),
+    RequestFailed(Box<ClientError>),
 
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
@@ -38,8 +38,16 @@ pub enum RpcError {
 
     #[error("All endpoints failed: {0}")]
     AllEndpointsFailed(String),
+
+    #[error("Slot {0} was skipped or is missing from the ledger")]
+    SlotSkipped(u64),
 }
 
+/// JSON-RPC error: slot was skipped, or missing due to a ledger jump to a recent snapshot
+pub const JSON_RPC_SERVER_ERROR_SLOT_SKIPPED: i64 = -32007;
+/// JSON-RPC error: slot was skipped, or missing in long-term storage
+pub const JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED: i64 = -32009;
+
 impl RpcError {
     pub fn is_retryable(&self) -> bool {
         matches!(
@@ -51,6 +59,28 @@ impl RpcError {
         )
     }
 
+    /// JSON-RPC error code returned by the node, if any
+    pub fn rpc_code(&self) -> Option<i64> {
+        match self {
+            RpcError::RequestFailed(e) => match e.kind() {
+                ClientErrorKind::RpcError(solana_client::rpc_request::RpcError::RpcResponseError { code, .. }) => {
+                    Some(*code)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether the requested slot has no block because it was skipped
+    pub fn is_skipped_slot(&self) -> bool {
+        matches!(self, RpcError::SlotSkipped(_))
+            || matches!(
+                self.rpc_code(),
+                Some(JSON_RPC_SERVER_ERROR_SLOT_SKIPPED | JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED)
+            )
+    }
+
     pub fn is_circuit_breaker(&self) -> bool {
         matches!(self, RpcError::CircuitBreakerOpen(_))
     }
@@ -74,6 +104,34 @@ impl RpcError {
     pub fn is_connection_error(&self) -> bool {
         matches!(self, RpcError::ConnectionError(_))
     }
+
+    /// Coarse error class used as a metrics label
+    pub fn class(&self) -> &'static str {
+        match self {
+            RpcError::InvalidConfig(_) => "invalid_config",
+            RpcError::NoEnabledEndpoints => "no_enabled_endpoints",
+            RpcError::InvalidEndpoint(_) => "invalid_endpoint",
+            RpcError::RequestFailed(e) => match e.kind() {
+                ClientErrorKind::Io(_) => "io",
+                ClientErrorKind::Reqwest(e) if e.is_timeout() => "timeout",
+                ClientErrorKind::Reqwest(_) => "http",
+                ClientErrorKind::RpcError(_) => "rpc",
+                ClientErrorKind::SerdeJson(_) => "serialization",
+                ClientErrorKind::SigningError(_) => "signing",
+                ClientErrorKind::TransactionError(_) => "transaction",
+                ClientErrorKind::Custom(_) => "custom",
+            },
+            RpcError::RateLimitExceeded => "rate_limited",
+            RpcError::Timeout => "timeout",
+            RpcError::RetryLimitExceeded => "retry_limit",
+            RpcError::HealthCheckFailed => "health_check",
+            RpcError::Internal(_) => "internal",
+            RpcError::ConnectionError(_) => "connection",
+            RpcError::CircuitBreakerOpen(_) => "circuit_breaker",
+            RpcError::AllEndpointsFailed(_) => "all_endpoints_failed",
+            RpcError::SlotSkipped(_) => "slot_skipped",
+        }
+    }
 }
 
 impl From<std::io::Error> for RpcError {
@@ -82,6 +140,12 @@ impl From<std::io::Error> for RpcError {
     }
 }
 
+impl From<ClientError> for RpcError {
+    fn from(err: ClientError) -> Self {
+        RpcError::RequestFailed(Box::new(err))
+    }
+}
+
 impl From<reqwest::Error> for RpcError {
     fn from(err: reqwest::Error) -> Self {
         if err.is_timeout() {
@@ -89,7 +153,7 @@ impl From<reqwest::Error> for RpcError {
         } else if err.is_connect() {
             RpcError::ConnectionError(err.to_string())
         } else {
-            RpcError::RequestFailed(ClientError::from(err))
+            RpcError::from(ClientError::from(err))
         }
     }
 }
@@ -97,12 +161,11 @@ impl From<reqwest::Error> for RpcError {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use solana_client::client_error::ClientErrorKind;
 
     #[test]
     fn test_retryable_errors() {
         let retryable_errors = vec![
-            RpcError::RequestFailed(ClientError::from(ClientErrorKind::Custom("test".to_string()))),
+            RpcError::from(ClientError::from(ClientErrorKind::Custom("test".to_string()))),
             RpcError::RateLimitExceeded,
             RpcError::Timeout,
             RpcError::ConnectionError("test".to_string()),
@@ -134,6 +197,36 @@ mod tests {
         assert!(!error.is_retryable());
     }
 
+    #[test]
+    fn test_error_class() {
+        assert_eq!(RpcError::Timeout.class(), "timeout");
+        assert_eq!(RpcError::RateLimitExceeded.class(), "rate_limited");
+        assert_eq!(
+            RpcError::from(ClientError::from(ClientErrorKind::Custom("test".to_string()))).class(),
+            "custom"
+        );
+    }
+
+    #[test]
+    fn test_skipped_slot_detection() {
+        let response_error = |code| {
+            RpcError::from(ClientError::from(ClientErrorKind::RpcError(
+                solana_client::rpc_request::RpcError::RpcResponseError {
+                    code,
+                    message: "Slot 42 was skipped".to_string(),
+                    data: solana_client::rpc_request::RpcResponseErrorData::Empty,
+                },
+            )))
+        };
+        assert_eq!(response_error(-32007).rpc_code(), Some(-32007));
+        assert!(response_error(JSON_RPC_SERVER_ERROR_SLOT_SKIPPED).is_skipped_slot());
+        assert!(response_error(JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED).is_skipped_slot());
+        assert!(!response_error(-32004).is_skipped_slot());
+        assert!(RpcError::SlotSkipped(42).is_skipped_slot());
+        assert!(!RpcError::SlotSkipped(42).is_retryable());
+        assert_eq!(RpcError::Timeout.rpc_code(), None);
+    }
+
     // #[test]
     // fn test_error_with_context() {
     //     let error = RpcError::RequestFailed(ClientError::IoError(std::io::Error::new(
@@ -1,10 +1,21 @@
-use crate::core::traits::HealthStatus;
+use crate::core::metrics::{
+    RPC_BYTES_RECEIVED_TOTAL, RPC_BYTES_SENT_TOTAL, RPC_CURRENT_RPS, RPC_ERRORS_TOTAL,
+    RPC_REQUESTS_TOTAL, RPC_REQUEST_DURATION_SECONDS,
+};
+use crate::core::traits::{ClientMetrics, ComponentHealth, HealthStatus, MethodMetrics};
 use crate::rpc::config::RpcConfig;
 use crate::rpc::error::RpcError;
+use crate::rpc::metrics::{LatencyHistogram, MethodStats, RpsWindow};
+use metrics::{counter, gauge, histogram};
+use serde_json::json;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::time::{Duration, Instant};
 
+/// Method label used by the legacy recording API, which carries no method name
+const UNKNOWN_METHOD: &str = "unknown";
+
 /// Statistics for an endpoint
 #[derive(Debug, Clone)]
 pub struct EndpointStats {
@@ -14,14 +25,32 @@ pub struct EndpointStats {
     pub failed_requests: u64,
     /// Average response time in milliseconds
     pub avg_response_time_ms: f64,
+    /// Median response time in milliseconds
+    pub p50_response_time_ms: f64,
+    /// 95th percentile response time in milliseconds
+    pub p95_response_time_ms: f64,
+    /// 99th percentile response time in milliseconds
+    pub p99_response_time_ms: f64,
     /// Current requests per second
     pub current_rps: f64,
     /// Total bytes transferred
     pub total_bytes_transferred: u64,
+    /// Bytes sent in request payloads
+    pub bytes_sent: u64,
+    /// Bytes received in response payloads
+    pub bytes_received: u64,
+    /// Failed requests keyed by error class
+    pub errors_by_class: HashMap<String, u64>,
+    /// Per-method accounting keyed by RPC method name
+    pub methods: HashMap<String, MethodStats>,
     /// Last successful request timestamp
     pub last_success: Option<Instant>,
     /// Last failed request timestamp
     pub last_failure: Option<Instant>,
+    /// Latency distribution of all requests
+    latency: LatencyHistogram,
+    /// Sliding window used for `current_rps`
+    window: RpsWindow,
 }
 
 impl Default for EndpointStats {
@@ -30,10 +59,96 @@ impl Default for EndpointStats {
             successful_requests: 0,
             failed_requests: 0,
             avg_response_time_ms: 0.0,
+            p50_response_time_ms: 0.0,
+            p95_response_time_ms: 0.0,
+            p99_response_time_ms: 0.0,
             current_rps: 0.0,
             total_bytes_transferred: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+            errors_by_class: HashMap::new(),
+            methods: HashMap::new(),
             last_success: None,
             last_failure: None,
+            latency: LatencyHistogram::default(),
+            window: RpsWindow::default(),
+        }
+    }
+}
+
+impl EndpointStats {
+    /// Latency distribution of all requests to this endpoint
+    pub fn latency(&self) -> &LatencyHistogram {
+        &self.latency
+    }
+
+    fn record_success(&mut self, method: &str, latency: Duration, bytes_sent: u64, bytes_received: u64) {
+        let now = Instant::now();
+        self.successful_requests += 1;
+        self.bytes_sent += bytes_sent;
+        self.bytes_received += bytes_received;
+        self.total_bytes_transferred += bytes_sent + bytes_received;
+        self.last_success = Some(now);
+        self.latency.record(latency);
+        self.window.record(now);
+        self.methods
+            .entry(method.to_string())
+            .or_default()
+            .record_success(latency, bytes_sent, bytes_received);
+        self.refresh(now);
+    }
+
+    fn record_failure(&mut self, method: &str, latency: Duration, bytes_sent: u64, error: Option<&RpcError>) {
+        let now = Instant::now();
+        self.failed_requests += 1;
+        self.bytes_sent += bytes_sent;
+        self.total_bytes_transferred += bytes_sent;
+        self.last_failure = Some(now);
+        self.window.record(now);
+        if let Some(error) = error {
+            *self.errors_by_class.entry(error.class().to_string()).or_insert(0) += 1;
+            self.latency.record(latency);
+            self.methods
+                .entry(method.to_string())
+                .or_default()
+                .record_failure(latency, bytes_sent, error);
+        } else {
+            self.methods.entry(method.to_string()).or_default().failed_requests += 1;
+        }
+        self.refresh(now);
+    }
+
+    /// Whether the most recent request to this endpoint failed
+    pub fn is_failing(&self) -> bool {
+        match (self.last_success, self.last_failure) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(success), Some(failure)) => failure > success,
+        }
+    }
+
+    /// Recompute the derived fields (averages, percentiles, rate) as of `now`
+    fn refresh(&mut self, now: Instant) {
+        self.avg_response_time_ms = self.latency.mean_ms();
+        self.p50_response_time_ms = self.latency.quantile_ms(0.50);
+        self.p95_response_time_ms = self.latency.quantile_ms(0.95);
+        self.p99_response_time_ms = self.latency.quantile_ms(0.99);
+        self.current_rps = self.window.rate(now);
+    }
+}
+
+impl From<&MethodStats> for MethodMetrics {
+    fn from(stats: &MethodStats) -> Self {
+        Self {
+            successful_requests: stats.successful_requests,
+            failed_requests: stats.failed_requests,
+            avg_response_time_ms: stats.latency.mean_ms(),
+            p50_response_time_ms: stats.latency.quantile_ms(0.50),
+            p95_response_time_ms: stats.latency.quantile_ms(0.95),
+            p99_response_time_ms: stats.latency.quantile_ms(0.99),
+            bytes_sent: stats.bytes_sent,
+            bytes_received: stats.bytes_received,
+            errors_by_class: stats.errors_by_class.clone(),
         }
     }
 }
@@ -92,41 +207,124 @@ impl HealthMonitor {
     
     /// Get statistics for all endpoints
     pub async fn get_stats(&self) -> Result<Vec<EndpointStats>, RpcError> {
-        Ok(self.stats.read().await.clone())
-    }
-    
-    /// Record a successful request
-    pub async fn record_success(&self, endpoint_idx: usize, response_time_ms: u64, bytes_transferred: u64) -> Result<(), RpcError> {
         let mut stats = self.stats.write().await;
-        if endpoint_idx >= stats.len() {
-            return Err(RpcError::InvalidEndpoint(endpoint_idx));
+        let now = Instant::now();
+        for endpoint_stats in stats.iter_mut() {
+            endpoint_stats.refresh(now);
         }
+        Ok(stats.clone())
+    }
 
-        let stats = &mut stats[endpoint_idx];
-        stats.successful_requests += 1;
-        stats.total_bytes_transferred += bytes_transferred;
-        stats.last_success = Some(Instant::now());
+    /// Aggregate metrics across all endpoints
+    pub async fn client_metrics(&self) -> ClientMetrics {
+        let stats = self.get_stats().await.unwrap_or_default();
 
-        // Update average response time
-        let total_requests = stats.successful_requests + stats.failed_requests;
-        stats.avg_response_time_ms = (stats.avg_response_time_ms * (total_requests - 1) as f64 + response_time_ms as f64) / total_requests as f64;
+        let mut latency = LatencyHistogram::default();
+        let mut methods: HashMap<String, MethodStats> = HashMap::new();
+        let mut metrics = ClientMetrics::default();
+        for endpoint_stats in &stats {
+            metrics.successful_requests += endpoint_stats.successful_requests;
+            metrics.failed_requests += endpoint_stats.failed_requests;
+            metrics.current_rps += endpoint_stats.current_rps;
+            metrics.bytes_sent += endpoint_stats.bytes_sent;
+            metrics.bytes_received += endpoint_stats.bytes_received;
+            metrics.bytes_transferred += endpoint_stats.total_bytes_transferred;
+            for (class, count) in &endpoint_stats.errors_by_class {
+                *metrics.errors_by_class.entry(class.clone()).or_insert(0) += count;
+            }
+            for (method, method_stats) in &endpoint_stats.methods {
+                methods.entry(method.clone()).or_default().merge(method_stats);
+            }
+            latency.merge(&endpoint_stats.latency);
+        }
 
-        Ok(())
+        metrics.avg_response_time_ms = latency.mean_ms();
+        metrics.p50_response_time_ms = latency.quantile_ms(0.50);
+        metrics.p95_response_time_ms = latency.quantile_ms(0.95);
+        metrics.p99_response_time_ms = latency.quantile_ms(0.99);
+        metrics.methods = methods
+            .iter()
+            .map(|(method, stats)| (method.clone(), MethodMetrics::from(stats)))
+            .collect();
+        metrics
+    }
+    
+    /// Record a successful request
+    pub async fn record_success(&self, endpoint_idx: usize, response_time_ms: u64, bytes_transferred: u64) -> Result<(), RpcError> {
+        self.record_request_success(
+            endpoint_idx,
+            UNKNOWN_METHOD,
+            Duration::from_millis(response_time_ms),
+            0,
+            bytes_transferred,
+        )
+        .await
     }
     
     /// Record a failed request
     pub async fn record_failure(&self, endpoint_idx: usize) -> Result<(), RpcError> {
         let mut stats = self.stats.write().await;
-        if endpoint_idx >= stats.len() {
-            return Err(RpcError::InvalidEndpoint(endpoint_idx));
-        }
+        let stats = stats.get_mut(endpoint_idx).ok_or(RpcError::InvalidEndpoint(endpoint_idx))?;
+        stats.record_failure(UNKNOWN_METHOD, Duration::ZERO, 0, None);
+        Ok(())
+    }
+
+    /// Record a successful attempt of `method` against an endpoint
+    pub async fn record_request_success(
+        &self,
+        endpoint_idx: usize,
+        method: &str,
+        latency: Duration,
+        bytes_sent: u64,
+        bytes_received: u64,
+    ) -> Result<(), RpcError> {
+        let endpoint = self.endpoint_label(endpoint_idx).ok_or(RpcError::InvalidEndpoint(endpoint_idx))?;
+        let mut stats = self.stats.write().await;
+        let stats = &mut stats[endpoint_idx];
+        stats.record_success(method, latency, bytes_sent, bytes_received);
+
+        let labels = [("endpoint", endpoint), ("method", method.to_string())];
+        counter!(RPC_REQUESTS_TOTAL, 1, &labels);
+        counter!(RPC_BYTES_SENT_TOTAL, bytes_sent, &labels);
+        counter!(RPC_BYTES_RECEIVED_TOTAL, bytes_received, &labels);
+        histogram!(RPC_REQUEST_DURATION_SECONDS, latency.as_secs_f64(), &labels);
+        gauge!(RPC_CURRENT_RPS, stats.current_rps, "endpoint" => labels[0].1.clone());
+        Ok(())
+    }
 
+    /// Record a failed attempt of `method` against an endpoint
+    pub async fn record_request_failure(
+        &self,
+        endpoint_idx: usize,
+        method: &str,
+        latency: Duration,
+        bytes_sent: u64,
+        error: &RpcError,
+    ) -> Result<(), RpcError> {
+        let endpoint = self.endpoint_label(endpoint_idx).ok_or(RpcError::InvalidEndpoint(endpoint_idx))?;
+        let mut stats = self.stats.write().await;
         let stats = &mut stats[endpoint_idx];
-        stats.failed_requests += 1;
-        stats.last_failure = Some(Instant::now());
+        stats.record_failure(method, latency, bytes_sent, Some(error));
 
+        let labels = [("endpoint", endpoint), ("method", method.to_string())];
+        counter!(RPC_REQUESTS_TOTAL, 1, &labels);
+        counter!(RPC_BYTES_SENT_TOTAL, bytes_sent, &labels);
+        counter!(
+            RPC_ERRORS_TOTAL,
+            1,
+            "endpoint" => labels[0].1.clone(),
+            "method" => labels[1].1.clone(),
+            "class" => error.class()
+        );
+        histogram!(RPC_REQUEST_DURATION_SECONDS, latency.as_secs_f64(), &labels);
+        gauge!(RPC_CURRENT_RPS, stats.current_rps, "endpoint" => labels[0].1.clone());
         Ok(())
     }
+
+    /// Redacted endpoint URL used as the `endpoint` metrics label
+    fn endpoint_label(&self, endpoint_idx: usize) -> Option<String> {
+        self.config.endpoints.get(endpoint_idx).map(|e| e.redacted_url())
+    }
     
     /// Get the current endpoint index
     pub async fn get_current_endpoint(&self) -> usize {
@@ -140,6 +338,53 @@ impl HealthMonitor {
         Ok(())
     }
 
+    /// Health of the enabled endpoints as a single component.
+    ///
+    /// An endpoint counts as failing when its most recent request failed; idle
+    /// endpoints count as up. Some failing endpoints degrade the component, all
+    /// of them failing makes it unhealthy.
+    pub async fn component_health(&self) -> ComponentHealth {
+        let stats = self.get_stats().await.unwrap_or_default();
+        let enabled: Vec<_> = self
+            .config
+            .endpoints
+            .iter()
+            .zip(stats.iter())
+            .filter(|(endpoint, _)| endpoint.enabled)
+            .collect();
+        let failing = enabled.iter().filter(|(_, stats)| stats.is_failing()).count();
+
+        let status = if enabled.is_empty() {
+            HealthStatus::Unhealthy(Some("no enabled endpoints".to_string()))
+        } else if failing == enabled.len() {
+            HealthStatus::Unhealthy(Some(format!("all {} endpoints failing", failing)))
+        } else if failing > 0 {
+            HealthStatus::Degraded(Some(format!("{} of {} endpoints failing", failing, enabled.len())))
+        } else {
+            HealthStatus::Healthy
+        };
+
+        let endpoints: Vec<_> = enabled
+            .iter()
+            .map(|(endpoint, stats)| {
+                json!({
+                    "url": endpoint.redacted_url(),
+                    "healthy": !stats.is_failing(),
+                    "successful_requests": stats.successful_requests,
+                    "failed_requests": stats.failed_requests,
+                    "p95_response_time_ms": stats.p95_response_time_ms,
+                    "current_rps": stats.current_rps,
+                })
+            })
+            .collect();
+
+        ComponentHealth {
+            name: "rpc".to_string(),
+            status,
+            metrics: Some(json!({ "endpoints": endpoints })),
+        }
+    }
+
     /// Returns the index of the next healthy endpoint, or an error if none are healthy.
     pub async fn next_healthy_endpoint(&self) -> Result<usize, RpcError> {
         let stats = self.stats.read().await;
@@ -217,6 +462,30 @@ mod tests {
         assert_eq!(monitor.get_current_endpoint().await, 1);
     }
 
+    #[tokio::test]
+    async fn test_component_health() {
+        let mut config = create_test_config();
+        config.endpoints[1].url = "https://mainnet.helius-rpc.com/?api-key=secret-key".to_string();
+        let monitor = HealthMonitor::new(Arc::new(config));
+
+        // Idle endpoints count as up
+        assert_eq!(monitor.component_health().await.status, HealthStatus::Healthy);
+
+        monitor.record_failure(0).await.unwrap();
+        let health = monitor.component_health().await;
+        assert_eq!(health.status, HealthStatus::Degraded(Some("1 of 2 endpoints failing".to_string())));
+        let metrics = health.metrics.unwrap();
+        assert_eq!(metrics["endpoints"][0]["healthy"], false);
+        assert_eq!(metrics["endpoints"][1]["url"], "https://mainnet.helius-rpc.com");
+
+        monitor.record_failure(1).await.unwrap();
+        assert!(!monitor.component_health().await.status.is_available());
+
+        monitor.record_success(0, 10, 100).await.unwrap();
+        monitor.record_success(1, 10, 100).await.unwrap();
+        assert_eq!(monitor.component_health().await.status, HealthStatus::Healthy);
+    }
+
     #[tokio::test]
     async fn test_stats_recording() {
         let config = Arc::new(create_test_config());
@@ -234,6 +503,14 @@ mod tests {
         assert!(stats[0].avg_response_time_ms > 0.0);
     }
 
+    #[test]
+    fn test_endpoint_label_redacts_api_key() {
+        let mut config = create_test_config();
+        config.endpoints[0].url = "https://mainnet.helius-rpc.com/?api-key=secret-key".to_string();
+        let monitor = HealthMonitor::new(Arc::new(config));
+        assert_eq!(monitor.endpoint_label(0).unwrap(), "https://mainnet.helius-rpc.com");
+    }
+
     #[tokio::test]
     async fn test_invalid_endpoint() {
         let config = Arc::new(create_test_config());
@@ -243,4 +520,34 @@ mod tests {
         assert!(monitor.record_success(2, 100, 1000).await.is_err());
         assert!(monitor.record_failure(2).await.is_err());
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_per_method_accounting() {
+        let config = Arc::new(create_test_config());
+        let monitor = HealthMonitor::new(config);
+
+        monitor.record_request_success(0, "getBlock", Duration::from_millis(20), 50, 500).await.unwrap();
+        monitor.record_request_success(1, "getBlock", Duration::from_millis(40), 50, 700).await.unwrap();
+        monitor.record_request_failure(0, "getSlot", Duration::from_millis(5), 40, &RpcError::Timeout).await.unwrap();
+
+        let stats = monitor.get_stats().await.unwrap();
+        assert_eq!(stats[0].successful_requests, 1);
+        assert_eq!(stats[0].failed_requests, 1);
+        assert_eq!(stats[0].bytes_sent, 90);
+        assert_eq!(stats[0].bytes_received, 500);
+        assert_eq!(stats[0].errors_by_class.get("timeout"), Some(&1));
+        assert_eq!(stats[0].methods["getSlot"].failed_requests, 1);
+        assert!(stats[0].current_rps > 0.0);
+        assert!(stats[0].p99_response_time_ms >= stats[0].p50_response_time_ms);
+
+        let metrics = monitor.client_metrics().await;
+        assert_eq!(metrics.successful_requests, 2);
+        assert_eq!(metrics.failed_requests, 1);
+        assert_eq!(metrics.bytes_sent, 140);
+        assert_eq!(metrics.bytes_received, 1200);
+        assert_eq!(metrics.bytes_transferred, 1340);
+        assert_eq!(metrics.methods["getBlock"].successful_requests, 2);
+        assert_eq!(metrics.methods["getSlot"].errors_by_class.get("timeout"), Some(&1));
+        assert!(metrics.p95_response_time_ms > 0.0);
+    }
+}
@@ -0,0 +1,272 @@
+//! Request accounting primitives for RPC endpoints
+//!
+//! These types back the per-endpoint [`EndpointStats`](crate::rpc::health::EndpointStats)
+//! and the aggregate [`ClientMetrics`](crate::core::traits::ClientMetrics).
+
+use crate::rpc::error::RpcError;
+use std::collections::{HashMap, VecDeque};
+use tokio::time::{Duration, Instant};
+
+/// Upper bounds (in milliseconds) of the latency histogram buckets.
+/// Samples above the last bound fall into an overflow bucket.
+pub const LATENCY_BUCKETS_MS: [f64; 14] = [
+    1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 30000.0,
+];
+
+/// Default width of the sliding window used for requests-per-second
+pub const DEFAULT_RPS_WINDOW: Duration = Duration::from_secs(10);
+
+/// Fixed-bucket latency histogram with quantile estimation
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    /// Sample count per bucket, with one extra overflow bucket
+    counts: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    /// Number of recorded samples
+    count: u64,
+    /// Sum of all samples in milliseconds
+    sum_ms: f64,
+    /// Largest recorded sample in milliseconds
+    max_ms: f64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; LATENCY_BUCKETS_MS.len() + 1],
+            count: 0,
+            sum_ms: 0.0,
+            max_ms: 0.0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    /// Record a latency sample
+    pub fn record(&mut self, latency: Duration) {
+        let ms = latency.as_secs_f64() * 1000.0;
+        let idx = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.counts[idx] += 1;
+        self.count += 1;
+        self.sum_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    /// Merge another histogram into this one
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other_count;
+        }
+        self.count += other.count;
+        self.sum_ms += other.sum_ms;
+        self.max_ms = self.max_ms.max(other.max_ms);
+    }
+
+    /// Number of recorded samples
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Mean latency in milliseconds
+    pub fn mean_ms(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.sum_ms / self.count as f64
+    }
+
+    /// Estimate the latency at quantile `q` (0.0..=1.0) in milliseconds.
+    ///
+    /// The estimate interpolates linearly inside the bucket holding the
+    /// requested rank and never exceeds the largest observed sample.
+    pub fn quantile_ms(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let rank = (q.clamp(0.0, 1.0) * self.count as f64).max(1.0);
+        let mut cumulative = 0u64;
+        for (idx, count) in self.counts.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            let previous = cumulative;
+            cumulative += count;
+            if cumulative as f64 >= rank {
+                let lower = if idx == 0 { 0.0 } else { LATENCY_BUCKETS_MS[idx - 1] };
+                let upper = LATENCY_BUCKETS_MS.get(idx).copied().unwrap_or(self.max_ms);
+                let fraction = (rank - previous as f64) / *count as f64;
+                return (lower + (upper - lower) * fraction).min(self.max_ms);
+            }
+        }
+        self.max_ms
+    }
+}
+
+/// Sliding window request counter used to compute requests per second
+#[derive(Debug, Clone)]
+pub struct RpsWindow {
+    /// Window width
+    window: Duration,
+    /// Request timestamps inside the window, oldest first
+    timestamps: VecDeque<Instant>,
+}
+
+impl Default for RpsWindow {
+    fn default() -> Self {
+        Self::new(DEFAULT_RPS_WINDOW)
+    }
+}
+
+impl RpsWindow {
+    /// Create a new window of the given width
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            timestamps: VecDeque::new(),
+        }
+    }
+
+    /// Record a request at `now`
+    pub fn record(&mut self, now: Instant) {
+        self.timestamps.push_back(now);
+        self.evict(now);
+    }
+
+    /// Requests per second observed over the window ending at `now`
+    pub fn rate(&self, now: Instant) -> f64 {
+        let in_window = self
+            .timestamps
+            .iter()
+            .filter(|ts| now.duration_since(**ts) < self.window)
+            .count();
+        in_window as f64 / self.window.as_secs_f64()
+    }
+
+    fn evict(&mut self, now: Instant) {
+        while let Some(oldest) = self.timestamps.front() {
+            if now.duration_since(*oldest) >= self.window {
+                self.timestamps.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+/// Accounting for a single RPC method
+#[derive(Debug, Clone, Default)]
+pub struct MethodStats {
+    /// Number of successful attempts
+    pub successful_requests: u64,
+    /// Number of failed attempts
+    pub failed_requests: u64,
+    /// Bytes sent in request payloads
+    pub bytes_sent: u64,
+    /// Bytes received in response payloads
+    pub bytes_received: u64,
+    /// Failed attempts keyed by error class (see [`RpcError::class`])
+    pub errors_by_class: HashMap<String, u64>,
+    /// Latency distribution of all attempts
+    pub latency: LatencyHistogram,
+}
+
+impl MethodStats {
+    /// Record a successful attempt
+    pub fn record_success(&mut self, latency: Duration, bytes_sent: u64, bytes_received: u64) {
+        self.successful_requests += 1;
+        self.bytes_sent += bytes_sent;
+        self.bytes_received += bytes_received;
+        self.latency.record(latency);
+    }
+
+    /// Record a failed attempt
+    pub fn record_failure(&mut self, latency: Duration, bytes_sent: u64, error: &RpcError) {
+        self.failed_requests += 1;
+        self.bytes_sent += bytes_sent;
+        *self.errors_by_class.entry(error.class().to_string()).or_insert(0) += 1;
+        self.latency.record(latency);
+    }
+
+    /// Merge another method's accounting into this one
+    pub fn merge(&mut self, other: &MethodStats) {
+        self.successful_requests += other.successful_requests;
+        self.failed_requests += other.failed_requests;
+        self.bytes_sent += other.bytes_sent;
+        self.bytes_received += other.bytes_received;
+        for (class, count) in &other.errors_by_class {
+            *self.errors_by_class.entry(class.clone()).or_insert(0) += count;
+        }
+        self.latency.merge(&other.latency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_quantiles() {
+        let mut histogram = LatencyHistogram::default();
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+
+        assert_eq!(histogram.count(), 100);
+        assert!((histogram.mean_ms() - 50.5).abs() < 1e-9);
+
+        let p50 = histogram.quantile_ms(0.5);
+        let p95 = histogram.quantile_ms(0.95);
+        let p99 = histogram.quantile_ms(0.99);
+        assert!(p50 > 25.0 && p50 <= 50.0, "p50 = {}", p50);
+        assert!(p95 > 50.0 && p95 <= 100.0, "p95 = {}", p95);
+        assert!(p99 >= p95 && p99 <= 100.0, "p99 = {}", p99);
+    }
+
+    #[test]
+    fn test_histogram_overflow_bucket() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(Duration::from_secs(45));
+        assert_eq!(histogram.quantile_ms(0.99), 45_000.0);
+    }
+
+    #[test]
+    fn test_histogram_merge() {
+        let mut a = LatencyHistogram::default();
+        let mut b = LatencyHistogram::default();
+        a.record(Duration::from_millis(10));
+        b.record(Duration::from_millis(30));
+        a.merge(&b);
+        assert_eq!(a.count(), 2);
+        assert!((a.mean_ms() - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rps_window_eviction() {
+        let mut window = RpsWindow::new(Duration::from_secs(2));
+        let start = Instant::now();
+        for _ in 0..4 {
+            window.record(start);
+        }
+        assert_eq!(window.rate(start), 2.0);
+
+        let later = start + Duration::from_secs(3);
+        window.record(later);
+        assert_eq!(window.rate(later), 0.5);
+    }
+
+    #[test]
+    fn test_method_stats_error_classes() {
+        let mut stats = MethodStats::default();
+        stats.record_success(Duration::from_millis(5), 10, 100);
+        stats.record_failure(Duration::from_millis(5), 10, &RpcError::Timeout);
+        stats.record_failure(Duration::from_millis(5), 10, &RpcError::Timeout);
+
+        assert_eq!(stats.successful_requests, 1);
+        assert_eq!(stats.failed_requests, 2);
+        assert_eq!(stats.bytes_sent, 30);
+        assert_eq!(stats.bytes_received, 100);
+        assert_eq!(stats.errors_by_class.get("timeout"), Some(&2));
+    }
+}
@@ -8,7 +8,9 @@ pub mod client;
 pub mod config;
 pub mod error;
 pub mod health;
+pub mod metrics;
 pub mod rate_limit;
+pub mod sender;
 
 pub type RpcClientError = Error;
 
@@ -16,6 +18,7 @@ pub use client::SolanaRpcClient;
 pub use config::{EndpointConfig, RpcConfig, RateLimitConfig};
 pub use error::RpcError;
 pub use health::{HealthMonitor, EndpointStats};
+pub use metrics::{LatencyHistogram, MethodStats, RpsWindow};
 pub use rate_limit::RpcRateLimiter;
 
 pub type Result<T> = std::result::Result<T, RpcClientError>;
@@ -126,4 +129,22 @@ mod tests {
         health_monitor.switch_endpoint().await.unwrap();
         assert_eq!(health_monitor.get_current_endpoint().await, 1);
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_client_metrics_reflect_recorded_requests() {
+        use crate::core::traits::Client;
+
+        let client = SolanaRpcClient::new(RpcConfig::default()).unwrap();
+        client
+            .health_monitor()
+            .record_request_success(0, "getBlock", Duration::from_millis(30), 64, 1024)
+            .await
+            .unwrap();
+
+        let metrics = client.get_metrics().await.unwrap();
+        assert_eq!(metrics.successful_requests, 1);
+        assert_eq!(metrics.bytes_transferred, 1088);
+        assert!(metrics.avg_response_time_ms >= 30.0);
+        assert!(metrics.current_rps > 0.0);
+    }
+}
@@ -0,0 +1,198 @@
+//! HTTP transport of the RPC client, counting the bytes of each request and
+//! response body.
+//!
+//! The blocking `RpcClient` runs each request on a current-thread runtime
+//! inside the calling thread, so the counts are kept per thread and read back
+//! by [`count_bytes`] around the call.
+
+use async_trait::async_trait;
+use reqwest::header::{CONTENT_TYPE, RETRY_AFTER};
+use reqwest::StatusCode;
+use solana_client::client_error::Result as ClientResult;
+use solana_client::rpc_custom_error::{
+    NodeUnhealthyErrorData, JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+    JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+};
+use solana_client::rpc_request::{RpcError, RpcRequest, RpcResponseErrorData};
+use solana_client::rpc_response::RpcSimulateTransactionResult;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// Timeout of a single HTTP request, as for the default `HttpSender`
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Attempts left after an HTTP 429 before the error is returned
+const TOO_MANY_REQUESTS_RETRIES: usize = 5;
+
+thread_local! {
+    /// Request and response body bytes exchanged on this thread
+    static TRANSFERRED: Cell<(u64, u64)> = const { Cell::new((0, 0)) };
+}
+
+fn add_transferred(sent: u64, received: u64) {
+    TRANSFERRED.with(|transferred| {
+        let (total_sent, total_received) = transferred.get();
+        transferred.set((total_sent + sent, total_received + received));
+    });
+}
+
+/// Run `f`, returning its result with the request and response body bytes
+/// its requests exchanged through a [`CountingHttpSender`]
+pub fn count_bytes<R>(f: impl FnOnce() -> R) -> (R, u64, u64) {
+    let outer = TRANSFERRED.with(|transferred| transferred.replace((0, 0)));
+    let result = f();
+    let (sent, received) = TRANSFERRED.with(|transferred| transferred.replace(outer));
+    add_transferred(sent, received);
+    (result, sent, received)
+}
+
+/// JSON-RPC over HTTP, like `solana_client::http_sender::HttpSender`, that
+/// counts the body bytes it sends and receives
+pub struct CountingHttpSender {
+    client: reqwest::Client,
+    url: String,
+    request_id: AtomicU64,
+    stats: RwLock<RpcTransportStats>,
+}
+
+impl CountingHttpSender {
+    pub fn new(url: impl ToString) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .pool_idle_timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("build rpc http client"),
+            url: url.to_string(),
+            request_id: AtomicU64::new(0),
+            stats: RwLock::new(RpcTransportStats::default()),
+        }
+    }
+
+    async fn send_counted(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+        rate_limited: &mut Duration,
+    ) -> ClientResult<serde_json::Value> {
+        let request_id = self.request_id.fetch_add(1, Ordering::Relaxed);
+        let body = request.build_request_json(request_id, params).to_string();
+
+        let mut retries = TOO_MANY_REQUESTS_RETRIES;
+        loop {
+            add_transferred(body.len() as u64, 0);
+            let response = self
+                .client
+                .post(&self.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                if response.status() == StatusCode::TOO_MANY_REQUESTS && retries > 0 {
+                    let delay = response
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok()?.parse::<u64>().ok())
+                        .filter(|&seconds| seconds < 120)
+                        .map_or(Duration::from_millis(500), Duration::from_secs);
+                    retries -= 1;
+                    tracing::debug!("Too many requests, {} retries left, pausing for {:?}", retries, delay);
+                    tokio::time::sleep(delay).await;
+                    *rate_limited += delay;
+                    continue;
+                }
+                return Err(response.error_for_status().unwrap_err().into());
+            }
+
+            let bytes = response.bytes().await?;
+            add_transferred(0, bytes.len() as u64);
+            let mut json: serde_json::Value = serde_json::from_slice(&bytes)?;
+            if json["error"].is_object() {
+                return Err(response_error(&json["error"]).into());
+            }
+            return Ok(json["result"].take());
+        }
+    }
+}
+
+/// Error of a JSON-RPC response `error` object
+fn response_error(error: &serde_json::Value) -> RpcError {
+    let (Some(code), Some(message)) = (error["code"].as_i64(), error["message"].as_str()) else {
+        return RpcError::RpcRequestError(format!("Failed to deserialize RPC error response: {}", error));
+    };
+    let data = match code {
+        JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE => {
+            serde_json::from_value::<RpcSimulateTransactionResult>(error["data"].clone())
+                .map(RpcResponseErrorData::SendTransactionPreflightFailure)
+                .unwrap_or(RpcResponseErrorData::Empty)
+        }
+        JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY => serde_json::from_value::<NodeUnhealthyErrorData>(error["data"].clone())
+            .map(|data| RpcResponseErrorData::NodeUnhealthy { num_slots_behind: data.num_slots_behind })
+            .unwrap_or(RpcResponseErrorData::Empty),
+        _ => RpcResponseErrorData::Empty,
+    };
+    RpcError::RpcResponseError { code, message: message.to_string(), data }
+}
+
+#[async_trait]
+impl RpcSender for CountingHttpSender {
+    async fn send(&self, request: RpcRequest, params: serde_json::Value) -> ClientResult<serde_json::Value> {
+        let start = Instant::now();
+        let mut rate_limited = Duration::ZERO;
+        let result = self.send_counted(request, params, &mut rate_limited).await;
+
+        let mut stats = self.stats.write().unwrap();
+        stats.request_count += 1;
+        stats.elapsed_time += start.elapsed();
+        stats.rate_limited_time += rate_limited;
+        result
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.stats.read().unwrap().clone()
+    }
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_client::rpc_client::{RpcClient, RpcClientConfig};
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_counts_body_bytes() {
+        let server = MockServer::start().await;
+        let body = r#"{"jsonrpc":"2.0","result":42,"id":0}"#;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+
+        let client = RpcClient::new_sender(
+            CountingHttpSender::new(server.uri()),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        );
+        let (slot, sent, received) =
+            count_bytes(|| client.send::<u64>(RpcRequest::GetSlot, serde_json::json!([])).unwrap());
+        assert_eq!(slot, 42);
+        let request = RpcRequest::GetSlot.build_request_json(0, serde_json::json!([])).to_string();
+        assert_eq!((sent, received), (request.len() as u64, body.len() as u64));
+    }
+
+    #[test]
+    fn test_response_error() {
+        let error = response_error(&serde_json::json!({ "code": -32007, "message": "Slot 5 was skipped" }));
+        assert!(matches!(error, RpcError::RpcResponseError { code: -32007, .. }));
+    }
+}
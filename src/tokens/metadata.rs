@@ -0,0 +1,112 @@
+//! Metaplex Token Metadata account decoding
+
+use borsh::BorshDeserialize;
+use solana_sdk::pubkey::Pubkey;
+
+/// Program id of the Metaplex Token Metadata program
+pub const METADATA_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bWgbk8G1");
+
+/// Account key tag of `MetadataV1` accounts
+const METADATA_V1: u8 = 4;
+
+/// Address of the metadata account of `mint`
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Leading fields of a `MetadataV1` account, up to the URI
+#[derive(BorshDeserialize)]
+struct MetadataHeader {
+    key: u8,
+    update_authority: [u8; 32],
+    mint: [u8; 32],
+    name: String,
+    symbol: String,
+    uri: String,
+}
+
+/// Display metadata of a mint, as stored by the Metaplex program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    pub mint: Pubkey,
+    pub update_authority: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+impl Metadata {
+    /// Decode a metadata account, `None` if `data` is not a `MetadataV1`
+    /// account.
+    ///
+    /// The program pads names, symbols and URIs with NUL bytes up to a fixed
+    /// length; padding and control characters are stripped.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let header = MetadataHeader::deserialize(&mut &data[..]).ok()?;
+        if header.key != METADATA_V1 {
+            return None;
+        }
+        Some(Self {
+            mint: Pubkey::new_from_array(header.mint),
+            update_authority: Pubkey::new_from_array(header.update_authority),
+            name: clean(&header.name),
+            symbol: clean(&header.symbol),
+            uri: clean(&header.uri),
+        })
+    }
+}
+
+fn clean(value: &str) -> String {
+    value.chars().filter(|c| !c.is_control()).collect::<String>().trim().to_string()
+}
+
+#[cfg(test)]
+pub(crate) fn encode(mint: &Pubkey, name: &str, symbol: &str, uri: &str) -> Vec<u8> {
+    let padded = |value: &str, len: usize| {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.resize(len, 0);
+        let mut field = (len as u32).to_le_bytes().to_vec();
+        field.extend(bytes);
+        field
+    };
+    let mut data = vec![METADATA_V1];
+    data.extend([9; 32]);
+    data.extend(mint.to_bytes());
+    data.extend(padded(name, 32));
+    data.extend(padded(symbol, 10));
+    data.extend(padded(uri, 200));
+    data.extend(500u16.to_le_bytes());
+    data.push(0);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_strips_padding() {
+        let mint = Pubkey::new_unique();
+        let metadata = Metadata::decode(&encode(&mint, "USD Coin", "USDC", "https://example.org/usdc.json")).unwrap();
+        assert_eq!(metadata.mint, mint);
+        assert_eq!(metadata.name, "USD Coin");
+        assert_eq!(metadata.symbol, "USDC");
+        assert_eq!(metadata.uri, "https://example.org/usdc.json");
+
+        let mut other = encode(&mint, "USD Coin", "USDC", "");
+        other[0] = 6;
+        assert_eq!(Metadata::decode(&other), None);
+        assert_eq!(Metadata::decode(&[METADATA_V1, 1, 2]), None);
+    }
+
+    #[test]
+    fn test_metadata_address_is_deterministic() {
+        let mint = Pubkey::new_unique();
+        assert_eq!(metadata_address(&mint), metadata_address(&mint));
+        assert_ne!(metadata_address(&mint), metadata_address(&Pubkey::new_unique()));
+    }
+}
@@ -0,0 +1,12 @@
+//! Token metadata resolution
+//!
+//! A [`TokenRegistry`] resolves the decimals of a mint from its mint account
+//! and its name, symbol and URI from the Metaplex metadata account derived
+//! from it, merges entries of an offline token list and caches the results,
+//! persisting them through a [`TokenStore`].
+
+pub mod metadata;
+pub mod registry;
+
+pub use metadata::{metadata_address, Metadata, METADATA_PROGRAM_ID};
+pub use registry::{TokenList, TokenListEntry, TokenRegistry, TokenStore};
@@ -0,0 +1,399 @@
+//! Cached mint decimals and display metadata
+
+use crate::core::error::{Error, Result};
+use crate::db::Database;
+use crate::models::TokenInfo;
+use crate::parsers::token::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::rpc::client::AccountClientTrait;
+use crate::tokens::metadata::{metadata_address, Metadata};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::StateWithExtensions;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+/// Chain id of mainnet-beta in token lists
+const MAINNET_CHAIN_ID: u64 = 101;
+
+/// Token entry of an offline token list
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenListEntry {
+    pub address: String,
+    pub decimals: u8,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    #[serde(rename = "logoURI")]
+    pub logo_uri: Option<String>,
+    pub chain_id: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TokenListFile {
+    /// Solana Labs token-list format
+    Wrapped { tokens: Vec<TokenListEntry> },
+    /// Plain array, as served by Jupiter
+    Plain(Vec<TokenListEntry>),
+}
+
+/// Offline token list keyed by mint
+#[derive(Debug, Clone, Default)]
+pub struct TokenList {
+    entries: HashMap<String, TokenListEntry>,
+}
+
+impl TokenList {
+    /// Parse a token list in the Solana Labs (`{"tokens": [...]}`) or plain
+    /// array format. Entries of other chains are skipped.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let entries = match serde_json::from_str(json)? {
+            TokenListFile::Wrapped { tokens } => tokens,
+            TokenListFile::Plain(tokens) => tokens,
+        };
+        Ok(entries.into_iter().collect())
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn get(&self, mint: &str) -> Option<&TokenListEntry> {
+        self.entries.get(mint)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl FromIterator<TokenListEntry> for TokenList {
+    fn from_iter<I: IntoIterator<Item = TokenListEntry>>(iter: I) -> Self {
+        let entries = iter
+            .into_iter()
+            .filter(|entry| entry.chain_id.is_none_or(|chain| chain == MAINNET_CHAIN_ID))
+            .map(|entry| (entry.address.clone(), entry))
+            .collect();
+        Self { entries }
+    }
+}
+
+/// Persistent storage of resolved tokens
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    async fn load_tokens(&self, mints: &[String]) -> Result<Vec<TokenInfo>>;
+    async fn save_tokens(&self, tokens: &[TokenInfo]) -> Result<()>;
+}
+
+#[async_trait]
+impl TokenStore for Database {
+    async fn load_tokens(&self, mints: &[String]) -> Result<Vec<TokenInfo>> {
+        self.get_tokens(mints)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to load tokens: {}", e)))
+    }
+
+    async fn save_tokens(&self, tokens: &[TokenInfo]) -> Result<()> {
+        self.upsert_tokens(tokens)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to persist tokens: {}", e)))
+    }
+}
+
+/// Resolves mints to their decimals and display metadata.
+///
+/// Lookups go through an in-memory cache, then the [`TokenStore`], then RPC:
+/// mint accounts and Metaplex metadata accounts are fetched together with
+/// `getMultipleAccounts`. Decimals always come from the mint account when it
+/// exists. Name, symbol and logo of the token list take precedence over
+/// on-chain metadata, which anyone can set to an existing token's symbol.
+pub struct TokenRegistry<C> {
+    client: Arc<C>,
+    store: Option<Arc<dyn TokenStore>>,
+    token_list: TokenList,
+    cache: RwLock<HashMap<String, TokenInfo>>,
+}
+
+impl<C: AccountClientTrait> TokenRegistry<C> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            store: None,
+            token_list: TokenList::default(),
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Persist resolved tokens and look up tokens resolved in earlier runs
+    pub fn with_store(mut self, store: Arc<dyn TokenStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn with_token_list(mut self, token_list: TokenList) -> Self {
+        self.token_list = token_list;
+        self
+    }
+
+    /// Cached token of `mint`, without any lookup
+    pub fn cached(&self, mint: &str) -> Option<TokenInfo> {
+        self.cache.read().unwrap().get(mint).cloned()
+    }
+
+    /// Drop `mint` from the cache so the next lookup fetches it again
+    pub fn invalidate(&self, mint: &str) {
+        self.cache.write().unwrap().remove(mint);
+    }
+
+    pub async fn get(&self, mint: &str) -> Result<Option<TokenInfo>> {
+        Ok(self.resolve(&[mint.to_string()]).await?.remove(mint))
+    }
+
+    /// Tokens of `mints`, keyed by mint. Mints with no mint account and no
+    /// token list entry are left out.
+    pub async fn resolve(&self, mints: &[String]) -> Result<HashMap<String, TokenInfo>> {
+        let mut resolved = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut missing: Vec<String> = {
+            let cache = self.cache.read().unwrap();
+            mints
+                .iter()
+                .filter(|mint| seen.insert(mint.as_str()))
+                .filter(|mint| match cache.get(mint.as_str()) {
+                    Some(token) => {
+                        resolved.insert(token.mint.clone(), token.clone());
+                        false
+                    }
+                    None => true,
+                })
+                .cloned()
+                .collect()
+        };
+
+        if let (Some(store), false) = (&self.store, missing.is_empty()) {
+            let stored = store.load_tokens(&missing).await?;
+            missing.retain(|mint| !stored.iter().any(|token| &token.mint == mint));
+            self.remember(&mut resolved, stored);
+        }
+        if missing.is_empty() {
+            return Ok(resolved);
+        }
+
+        let fetched = self.fetch(&missing).await?;
+        if let (Some(store), false) = (&self.store, fetched.is_empty()) {
+            store.save_tokens(&fetched).await?;
+        }
+        self.remember(&mut resolved, fetched);
+        Ok(resolved)
+    }
+
+    fn remember(&self, resolved: &mut HashMap<String, TokenInfo>, tokens: Vec<TokenInfo>) {
+        let mut cache = self.cache.write().unwrap();
+        for token in tokens {
+            cache.insert(token.mint.clone(), token.clone());
+            resolved.insert(token.mint.clone(), token);
+        }
+    }
+
+    async fn fetch(&self, mints: &[String]) -> Result<Vec<TokenInfo>> {
+        let mut keys = Vec::with_capacity(mints.len() * 2);
+        for mint in mints {
+            keys.push(Pubkey::from_str(mint).map_err(|_| Error::validation(format!("Invalid mint {}", mint)))?);
+        }
+        let metadata_keys: Vec<Pubkey> = keys.iter().map(metadata_address).collect();
+        keys.extend(metadata_keys);
+        let mut accounts = self
+            .client
+            .get_multiple_accounts(&keys)
+            .await
+            .map_err(|e| Error::pipeline(format!("Failed to fetch token accounts: {}", e)))?;
+        let metadata_accounts = accounts.split_off(mints.len());
+
+        let mut tokens = Vec::new();
+        for ((mint, mint_account), metadata_account) in mints.iter().zip(accounts).zip(metadata_accounts) {
+            let listed = self.token_list.get(mint);
+            let decimals = match (mint_account.as_ref().and_then(mint_decimals), listed) {
+                (Some(decimals), Some(entry)) if decimals != entry.decimals => {
+                    warn!(
+                        "Token list has {} decimals for {}, mint account has {}",
+                        entry.decimals, mint, decimals
+                    );
+                    decimals
+                }
+                (Some(decimals), _) => decimals,
+                (None, Some(entry)) => entry.decimals,
+                (None, None) => continue,
+            };
+
+            let mut token = TokenInfo::new(mint.clone(), decimals);
+            let metadata = metadata_account
+                .and_then(|account| Metadata::decode(&account.data))
+                .filter(|metadata| &metadata.mint.to_string() == mint);
+            if let Some(metadata) = metadata {
+                token.name = Some(metadata.name).filter(|name| !name.is_empty());
+                token.symbol = Some(metadata.symbol).filter(|symbol| !symbol.is_empty());
+                token.uri = Some(metadata.uri).filter(|uri| !uri.is_empty());
+                token.source = "metaplex".to_string();
+            }
+            if let Some(entry) = listed {
+                token.name = entry.name.clone().or(token.name);
+                token.symbol = entry.symbol.clone().or(token.symbol);
+                token.logo_uri = entry.logo_uri.clone();
+                token.source = "token_list".to_string();
+            }
+            token.updated_at = Utc::now();
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+}
+
+/// Decimals of a mint account owned by either token program
+fn mint_decimals(account: &Account) -> Option<u8> {
+    if account.owner != TOKEN_PROGRAM_ID && account.owner != TOKEN_2022_PROGRAM_ID {
+        return None;
+    }
+    StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&account.data)
+        .ok()
+        .map(|mint| mint.base.decimals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::client::MockAccountClientTrait;
+    use crate::tokens::metadata::{encode, METADATA_PROGRAM_ID};
+    use solana_sdk::program_option::COption;
+    use solana_sdk::program_pack::Pack;
+
+    const TOKEN_LIST: &str = r#"{
+        "name": "test list",
+        "tokens": [
+            {"chainId": 101, "address": "BONK_MINT", "symbol": "BONK", "name": "Bonk", "decimals": 5, "logoURI": "https://example.org/bonk.png"},
+            {"chainId": 103, "address": "DEVNET_MINT", "symbol": "DEV", "name": "Devnet", "decimals": 9}
+        ]
+    }"#;
+
+    fn account(owner: Pubkey, data: Vec<u8>) -> Account {
+        Account {
+            lamports: 1_461_600,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn mint_account(decimals: u8) -> Account {
+        let mint = spl_token_2022::state::Mint {
+            mint_authority: COption::None,
+            supply: 1_000_000,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        };
+        let mut data = vec![0; spl_token_2022::state::Mint::LEN];
+        mint.pack_into_slice(&mut data);
+        account(TOKEN_PROGRAM_ID, data)
+    }
+
+    #[test]
+    fn test_token_list_formats() {
+        let list = TokenList::from_json(TOKEN_LIST).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list.get("BONK_MINT").unwrap().logo_uri.as_deref(), Some("https://example.org/bonk.png"));
+        assert!(list.get("DEVNET_MINT").is_none());
+
+        let plain = TokenList::from_json(r#"[{"address": "A", "symbol": "A", "decimals": 2}]"#).unwrap();
+        assert_eq!(plain.get("A").unwrap().decimals, 2);
+        assert!(TokenList::from_json(r#"{"tokens": 1}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolves_from_chain_and_token_list() {
+        let usdc = Pubkey::new_unique();
+        let bonk = Pubkey::new_unique();
+        let unknown = Pubkey::new_unique();
+        let list: TokenList = vec![TokenListEntry {
+            address: bonk.to_string(),
+            decimals: 5,
+            name: Some("Bonk".to_string()),
+            symbol: Some("BONK".to_string()),
+            logo_uri: Some("https://example.org/bonk.png".to_string()),
+            chain_id: Some(101),
+        }]
+        .into_iter()
+        .collect();
+
+        let mut client = MockAccountClientTrait::new();
+        client
+            .expect_get_multiple_accounts()
+            .withf(move |keys| keys.len() == 6 && keys[..3] == [usdc, bonk, unknown] && keys[3] == metadata_address(&usdc))
+            .times(1)
+            .returning(move |_| {
+                Ok(vec![
+                    Some(mint_account(6)),
+                    Some(mint_account(5)),
+                    None,
+                    Some(account(METADATA_PROGRAM_ID, encode(&usdc, "USD Coin", "USDC", "https://example.org/usdc.json"))),
+                    Some(account(METADATA_PROGRAM_ID, encode(&bonk, "Bonk Inu", "BONK2", ""))),
+                    None,
+                ])
+            });
+        let mut store = MockTokenStore::new();
+        store.expect_load_tokens().times(1).returning(|_| Ok(Vec::new()));
+        store
+            .expect_save_tokens()
+            .withf(|tokens| tokens.len() == 2)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let registry = TokenRegistry::new(Arc::new(client))
+            .with_store(Arc::new(store))
+            .with_token_list(list);
+        let mints = [usdc.to_string(), bonk.to_string(), unknown.to_string(), usdc.to_string()];
+        let tokens = registry.resolve(&mints).await.unwrap();
+        assert_eq!(tokens.len(), 2);
+
+        let usdc_info = &tokens[&usdc.to_string()];
+        assert_eq!(usdc_info.decimals, 6);
+        assert_eq!(usdc_info.symbol.as_deref(), Some("USDC"));
+        assert_eq!(usdc_info.uri.as_deref(), Some("https://example.org/usdc.json"));
+        assert_eq!(usdc_info.source, "metaplex");
+
+        let bonk_info = &tokens[&bonk.to_string()];
+        assert_eq!(bonk_info.symbol.as_deref(), Some("BONK"));
+        assert_eq!(bonk_info.name.as_deref(), Some("Bonk"));
+        assert_eq!(bonk_info.uri, None);
+        assert_eq!(bonk_info.source, "token_list");
+
+        // Served from the cache, the mocks expect a single call each
+        assert_eq!(registry.get(&usdc.to_string()).await.unwrap().unwrap().decimals, 6);
+        assert!(registry.cached(&bonk.to_string()).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_stored_tokens_skip_rpc() {
+        let mint = Pubkey::new_unique().to_string();
+        let stored = TokenInfo::new(mint.clone(), 9);
+        let mut store = MockTokenStore::new();
+        store.expect_load_tokens().returning(move |_| Ok(vec![stored.clone()]));
+        let mut client = MockAccountClientTrait::new();
+        client.expect_get_multiple_accounts().never();
+
+        let registry = TokenRegistry::new(Arc::new(client)).with_store(Arc::new(store));
+        assert_eq!(registry.get(&mint).await.unwrap().unwrap().decimals, 9);
+        assert!(registry.get("not a mint").await.is_err());
+    }
+}
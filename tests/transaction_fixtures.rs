@@ -0,0 +1,48 @@
+use solana_rpc_client::balances::balance_changes;
+use solana_rpc_client::parsers::{decode_transaction, ParserRegistry, TokenParser};
+use solana_sdk::signature::Signature;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/transactions");
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Signature a recorded fixture is named after, `<name>_<signature>.json`
+fn recorded_signature(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    let (_, signature) = stem.rsplit_once('_')?;
+    Signature::from_str(signature).ok().map(|_| signature.to_string())
+}
+
+#[test]
+fn test_recorded_fixtures_match_their_signature() {
+    for path in fixtures() {
+        let Some(signature) = recorded_signature(&path) else { continue };
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["transaction"]["signatures"][0], signature.as_str(), "{}", path.display());
+    }
+}
+
+#[test]
+fn test_fixtures_balance_invariants() {
+    let mut registry = ParserRegistry::new();
+    registry.register(Arc::new(TokenParser::new()));
+
+    for path in fixtures() {
+        let fixture: EncodedConfirmedTransactionWithStatusMeta =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let decoded = decode_transaction(fixture.slot, fixture.block_time, &fixture.transaction).unwrap();
+        let changes = balance_changes(&registry.parse_transaction(&decoded));
+        assert_eq!(changes.violations, vec![], "{}", path.display());
+    }
+}